@@ -0,0 +1,145 @@
+//! Today's totals kept up to date as sessions start, record input and are
+//! saved, so the dashboard doesn't rescan stored sessions on every frame.
+
+use crate::types::{Action, Session};
+use chrono::{DateTime, Local, NaiveDate};
+use std::collections::{BTreeMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// Window the actions-per-minute rate is measured over.
+const APM_WINDOW: Duration = Duration::from_secs(60);
+
+/// Today's totals at one point in time, from [`LiveAggregates::snapshot`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricsSnapshot {
+    pub date: NaiveDate,
+    /// Saved sessions started today plus the running one.
+    pub tracked_today_secs: i64,
+    pub sessions_today: usize,
+    /// Tracked time per task, with the same sessions as `tracked_today_secs`.
+    pub task_secs: BTreeMap<String, i64>,
+    /// Key presses (not releases), shortcuts and mouse button presses in
+    /// the last minute.
+    pub apm: usize,
+}
+
+/// Running totals for the current day. Sessions count towards the day they
+/// started on; the running one only from midnight if it started earlier.
+#[derive(Debug, Default)]
+pub struct LiveAggregates {
+    date: Option<NaiveDate>,
+    sessions: usize,
+    task_secs: BTreeMap<String, i64>,
+    running: Option<(String, DateTime<Local>)>,
+    actions: VecDeque<Instant>,
+}
+
+impl LiveAggregates {
+    /// Starts from the stored `sessions`, counting the ones started today.
+    pub fn seed(sessions: &[Session]) -> Self {
+        let mut aggregates = Self::default();
+        for session in sessions {
+            aggregates.session_saved(session);
+        }
+        aggregates
+    }
+
+    pub fn session_started(&mut self, task_name: &str, start: DateTime<Local>) {
+        self.running = Some((task_name.to_string(), start));
+    }
+
+    /// The running session stopped; it counts again once it's saved.
+    pub fn session_ended(&mut self) {
+        self.running = None;
+        self.actions.clear();
+    }
+
+    pub fn session_saved(&mut self, session: &Session) {
+        let today = self.roll_over();
+        if session.started_at().map(|t| t.date_naive()) != Some(today) {
+            return;
+        }
+        self.sessions += 1;
+        *self.task_secs.entry(session.task_name.clone()).or_default() +=
+            session.duration().map_or(0, |d| d.num_seconds().max(0));
+    }
+
+    /// Takes a stored session back out of the totals, when it's deleted or
+    /// about to be saved again with changes.
+    pub fn session_removed(&mut self, session: &Session) {
+        let today = self.roll_over();
+        if session.started_at().map(|t| t.date_naive()) != Some(today) {
+            return;
+        }
+        self.sessions = self.sessions.saturating_sub(1);
+        if let Some(secs) = self.task_secs.get_mut(&session.task_name) {
+            *secs -= session.duration().map_or(0, |d| d.num_seconds().max(0));
+            if *secs <= 0 {
+                self.task_secs.remove(&session.task_name);
+            }
+        }
+    }
+
+    /// Counts `action`, recorded at `now`, towards the APM rate.
+    pub fn recorded(&mut self, action: &Action, now: Instant) {
+        let counts = match action {
+            Action::KeyPress { keys, .. } => !keys.is_empty(),
+            Action::Shortcut { .. } | Action::MouseDown { .. } => true,
+            _ => false,
+        };
+        if counts {
+            self.actions.push_back(now);
+        }
+        while self
+            .actions
+            .front()
+            .is_some_and(|&t| now.duration_since(t) > APM_WINDOW)
+        {
+            self.actions.pop_front();
+        }
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let now = Local::now();
+        let today = now.date_naive();
+        let mut snapshot = MetricsSnapshot {
+            date: today,
+            tracked_today_secs: 0,
+            sessions_today: 0,
+            task_secs: BTreeMap::new(),
+            apm: 0,
+        };
+        if self.date == Some(today) {
+            snapshot.sessions_today = self.sessions;
+            snapshot.task_secs = self.task_secs.clone();
+        }
+
+        if let Some((task, start)) = &self.running {
+            let midnight = today
+                .and_hms_opt(0, 0, 0)
+                .and_then(|t| t.and_local_timezone(Local).earliest())
+                .unwrap_or(now);
+            let secs = (now - (*start).max(midnight)).num_seconds().max(0);
+            *snapshot.task_secs.entry(task.clone()).or_default() += secs;
+            snapshot.apm = self
+                .actions
+                .iter()
+                .filter(|t| t.elapsed() <= APM_WINDOW)
+                .count();
+        }
+        snapshot.tracked_today_secs = snapshot.task_secs.values().sum();
+        snapshot
+    }
+
+    /// Clears the totals if the day changed since they were last touched,
+    /// returning today's date.
+    fn roll_over(&mut self) -> NaiveDate {
+        let today = Local::now().date_naive();
+        if self.date != Some(today) {
+            self.date = Some(today);
+            self.sessions = 0;
+            self.task_secs.clear();
+        }
+        today
+    }
+}
@@ -0,0 +1,725 @@
+use crate::config::{CalendarConfig, ScreenConfig};
+use crate::ide;
+use crate::keystroke;
+use crate::types::{Action, Session};
+use chrono::{DateTime, Datelike, Days, FixedOffset, NaiveDate};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DailyTotal {
+    pub date: NaiveDate,
+    pub sessions: usize,
+    pub tracked_secs: i64,
+    pub events: usize,
+}
+
+/// Totals for one week, as laid out by [`CalendarConfig`].
+#[derive(Debug, Clone, Serialize)]
+pub struct WeeklyTotal {
+    /// First day of the week.
+    pub week_start: NaiveDate,
+    pub sessions: usize,
+    pub tracked_secs: i64,
+    /// Part of `tracked_secs` on days outside the workweek.
+    pub off_day_secs: i64,
+    /// Workdays with any tracked time.
+    pub workdays_tracked: usize,
+}
+
+/// Totals for one machine in a merged dataset.
+#[derive(Debug, Clone, Serialize)]
+pub struct MachineTotal {
+    pub machine_id: String,
+    pub sessions: usize,
+    pub tracked_secs: i64,
+    pub events: usize,
+}
+
+/// A session event with the time since the one before it.
+#[derive(Debug, Clone, Serialize)]
+pub struct TimedEvent {
+    #[serde(flatten)]
+    pub action: Action,
+    /// Milliseconds since the previous event; `None` for the first event or
+    /// when either timestamp can't be parsed.
+    pub delta_ms: Option<i64>,
+    /// The trial the event fell in (see [`trial_ids`]).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trial_id: Option<String>,
+}
+
+/// Time tracked against one task name across stored sessions.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TaskTime {
+    pub today_secs: i64,
+    pub total_secs: i64,
+    pub sessions: usize,
+}
+
+/// Totals for one task over a set of sessions.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TaskSummary {
+    pub task_name: String,
+    pub sessions: usize,
+    pub tracked_secs: i64,
+    pub keystrokes: usize,
+    pub mouse_moves: usize,
+}
+
+/// Time one app had keyboard focus over a set of sessions.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AppTime {
+    pub app: String,
+    pub focused_secs: i64,
+}
+
+/// Tracked time against a task's budget, day by day.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BurnDown {
+    pub planned_secs: i64,
+    pub tracked_secs: i64,
+    /// Cumulative tracked time at the end of each day with sessions, oldest
+    /// first.
+    pub days: Vec<(NaiveDate, i64)>,
+    /// When the budget runs out at the average daily pace since the first
+    /// session. `None` if nothing is tracked yet or it's already used up.
+    pub projected_completion: Option<NaiveDate>,
+}
+
+impl BurnDown {
+    pub fn remaining_secs(&self) -> i64 {
+        self.planned_secs - self.tracked_secs
+    }
+}
+
+/// Mouse button presses in a session and how long they were held.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ClickStats {
+    pub clicks: usize,
+    /// Mean hold time over the recorded releases.
+    pub avg_hold_ms: Option<i64>,
+}
+
+impl ClickStats {
+    /// e.g. `"12 (average hold 95 ms)"`.
+    pub fn describe(&self) -> String {
+        match self.avg_hold_ms {
+            Some(ms) => format!("{} (average hold {} ms)", self.clicks, ms),
+            None => self.clicks.to_string(),
+        }
+    }
+}
+
+/// How a session's break reminders were followed.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BreakCompliance {
+    pub reminders: usize,
+    /// Full breaks taken while a reminder was pending.
+    pub breaks_after_reminder: usize,
+    pub breaks: usize,
+    pub micro_breaks: usize,
+}
+
+/// A stretch of a session typed under one keyboard layout.
+#[derive(Debug, Clone, Serialize)]
+pub struct LayoutSegment {
+    pub layout: String,
+    pub start: DateTime<FixedOffset>,
+    pub end: DateTime<FixedOffset>,
+    pub key_events: usize,
+}
+
+/// A mouse move in the coordinates of the window that had focus.
+#[derive(Debug, Clone, Serialize)]
+pub struct WindowMove {
+    pub timestamp: String,
+    pub window: u64,
+    pub coords: (i32, i32),
+}
+
+/// A file reported by the IDE plugin endpoint during a session.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FileActivity {
+    pub path: String,
+    pub opens: usize,
+    pub saves: usize,
+}
+
+/// Custom event kinds marking where a trial within a session starts and
+/// ends. Both carry the trial's `trial_id`.
+pub const TRIAL_START_KIND: &str = "trial_start";
+pub const TRIAL_END_KIND: &str = "trial_end";
+/// Custom event kinds marking where recording was paused and resumed. The
+/// resume carries `paused_secs`.
+pub const PAUSE_KIND: &str = "pause";
+pub const RESUME_KIND: &str = "resume";
+
+/// One trial marked within a session, from its start marker to its end
+/// marker, or to the session end if it was never ended.
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct TrialSummary {
+    pub trial_id: String,
+    pub start: String,
+    pub end: Option<String>,
+    pub duration_ms: Option<i64>,
+    /// Events between the markers, not counting the markers themselves.
+    pub events: usize,
+    pub key_presses: usize,
+    pub mouse_moves: usize,
+    pub clicks: usize,
+}
+
+/// Number of recorded events (mouse moves plus all other actions).
+pub fn event_count(session: &Session) -> usize {
+    session.actions.len() + session.mouse_path.len()
+}
+
+/// Milliseconds from the start of `session` to `timestamp`; 0 when either
+/// can't be parsed or `timestamp` is before the start.
+pub fn offset_ms(session: &Session, timestamp: &str) -> i64 {
+    DateTime::parse_from_rfc3339(&session.start_time)
+        .ok()
+        .zip(DateTime::parse_from_rfc3339(timestamp).ok())
+        .map_or(0, |(start, at)| (at - start).num_milliseconds().max(0))
+}
+
+/// A time within a session as `+M:SS.mmm`.
+pub fn format_offset(ms: i64) -> String {
+    format!("+{}:{:02}.{:03}", ms / 60_000, ms / 1000 % 60, ms % 1000)
+}
+
+/// Slices in [`event_density`].
+pub const DENSITY_BUCKETS: usize = 16;
+
+/// Events per minute in each of [`DENSITY_BUCKETS`] equal slices of the
+/// session, for a sparkline of how busy it was. Empty for sessions that are
+/// still running or have no duration.
+pub fn event_density(session: &Session) -> Vec<u32> {
+    let (Some(start), Some(duration)) = (session.started_at(), session.duration()) else {
+        return Vec::new();
+    };
+    let span_ms = duration.num_milliseconds();
+    if span_ms <= 0 {
+        return Vec::new();
+    }
+    let mut counts = vec![0u64; DENSITY_BUCKETS];
+    for action in session.ordered_actions() {
+        if let Ok(at) = DateTime::parse_from_rfc3339(action.timestamp()) {
+            let offset = (at.timestamp_millis() - start.timestamp_millis()).clamp(0, span_ms - 1);
+            counts[(offset * DENSITY_BUCKETS as i64 / span_ms) as usize] += 1;
+        }
+    }
+    let bucket_ms = span_ms as f64 / DENSITY_BUCKETS as f64;
+    counts
+        .into_iter()
+        .map(|count| (count as f64 * 60_000.0 / bucket_ms).round() as u32)
+        .collect()
+}
+
+/// Total length of the recorded mouse path in pixels.
+pub fn mouse_distance_px(session: &Session) -> f64 {
+    let mut last: Option<(i32, i32)> = None;
+    let mut distance = 0.0;
+    for point in session.mouse_path.iter() {
+        if let Some((x, y)) = last {
+            let (dx, dy) = ((point.coords.0 - x) as f64, (point.coords.1 - y) as f64);
+            distance += (dx * dx + dy * dy).sqrt();
+        }
+        last = Some(point.coords);
+    }
+    distance
+}
+
+/// Mouse distance formatted in the configured physical unit, or in pixels
+/// if the screen size isn't configured.
+pub fn format_mouse_distance(session: &Session, screen: &ScreenConfig) -> String {
+    let px = mouse_distance_px(session);
+    match screen.physical_distance(px) {
+        Some(distance) => format!("{:.1} {}", distance, screen.unit.label()),
+        None => format!("{:.0} px", px),
+    }
+}
+
+/// The session's events in recording order with the sub-second gaps
+/// between them.
+pub fn timed_events(session: &Session) -> Vec<TimedEvent> {
+    let actions = session.ordered_actions();
+    let trials = trial_ids(&actions);
+    let mut previous: Option<DateTime<FixedOffset>> = None;
+    actions
+        .into_iter()
+        .zip(trials)
+        .map(|(action, trial_id)| {
+            let at = DateTime::parse_from_rfc3339(action.timestamp()).ok();
+            let delta_ms = previous
+                .zip(at)
+                .map(|(previous, at)| (at - previous).num_milliseconds());
+            previous = at;
+            TimedEvent {
+                action,
+                delta_ms,
+                trial_id,
+            }
+        })
+        .collect()
+}
+
+/// The trial each of `actions` (in recording order) fell in, `None`
+/// outside trials. Markers belong to the trial they start or end.
+pub fn trial_ids(actions: &[Action]) -> Vec<Option<String>> {
+    let mut current: Option<String> = None;
+    actions
+        .iter()
+        .map(|action| match trial_marker(action) {
+            Some((true, id)) => {
+                current = Some(id.to_string());
+                current.clone()
+            }
+            Some((false, id)) => {
+                current = None;
+                Some(id.to_string())
+            }
+            None => current.clone(),
+        })
+        .collect()
+}
+
+/// Per-trial totals for the trials marked in `session`, in the order they
+/// started.
+pub fn trial_summaries(session: &Session) -> Vec<TrialSummary> {
+    let mut trials: Vec<TrialSummary> = Vec::new();
+    let mut open = false;
+    for action in session.ordered_actions() {
+        match trial_marker(&action) {
+            Some((true, id)) => {
+                trials.push(TrialSummary {
+                    trial_id: id.to_string(),
+                    start: action.timestamp().to_string(),
+                    ..Default::default()
+                });
+                open = true;
+            }
+            Some((false, _)) => {
+                if let Some(trial) = trials.last_mut().filter(|_| open) {
+                    trial.end = Some(action.timestamp().to_string());
+                }
+                open = false;
+            }
+            None => {
+                let Some(trial) = trials.last_mut().filter(|_| open) else {
+                    continue;
+                };
+                trial.events += 1;
+                match action {
+                    Action::KeyPress { .. } => trial.key_presses += 1,
+                    Action::MouseMove { .. } => trial.mouse_moves += 1,
+                    Action::MouseDown { .. } => trial.clicks += 1,
+                    _ => {}
+                }
+            }
+        }
+    }
+    for trial in &mut trials {
+        if trial.end.is_none() {
+            trial.end = session.end_time.clone();
+        }
+        let start = DateTime::parse_from_rfc3339(&trial.start).ok();
+        let end = trial
+            .end
+            .as_deref()
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok());
+        trial.duration_ms = start
+            .zip(end)
+            .map(|(start, end)| (end - start).num_milliseconds());
+    }
+    trials
+}
+
+/// `(is_start, trial_id)` for a trial start or end marker.
+pub(crate) fn trial_marker(action: &Action) -> Option<(bool, &str)> {
+    let Action::Custom { kind, payload, .. } = action else {
+        return None;
+    };
+    let start = match kind.as_str() {
+        TRIAL_START_KIND => true,
+        TRIAL_END_KIND => false,
+        _ => return None,
+    };
+    Some((start, payload.get("trial_id")?.as_str()?))
+}
+
+/// Per-day totals keyed on each session's local start date, oldest first.
+pub fn daily_totals<'a>(sessions: impl IntoIterator<Item = &'a Session>) -> Vec<DailyTotal> {
+    let mut days: BTreeMap<NaiveDate, DailyTotal> = BTreeMap::new();
+    for session in sessions {
+        let Some(date) = session.started_at().map(|t| t.date_naive()) else {
+            continue;
+        };
+        let total = days.entry(date).or_insert(DailyTotal {
+            date,
+            sessions: 0,
+            tracked_secs: 0,
+            events: 0,
+        });
+        total.sessions += 1;
+        total.tracked_secs += session.duration().map_or(0, |d| d.num_seconds());
+        total.events += event_count(session);
+    }
+    days.into_values().collect()
+}
+
+/// Per-week totals, oldest first. Sessions count towards the day they
+/// started on.
+pub fn weekly_totals<'a>(
+    sessions: impl IntoIterator<Item = &'a Session>,
+    calendar: &CalendarConfig,
+) -> Vec<WeeklyTotal> {
+    let mut weeks: BTreeMap<NaiveDate, WeeklyTotal> = BTreeMap::new();
+    for day in daily_totals(sessions) {
+        let week_start = calendar.week_start(day.date);
+        let week = weeks.entry(week_start).or_insert(WeeklyTotal {
+            week_start,
+            sessions: 0,
+            tracked_secs: 0,
+            off_day_secs: 0,
+            workdays_tracked: 0,
+        });
+        week.sessions += day.sessions;
+        week.tracked_secs += day.tracked_secs;
+        if !calendar.is_workday(day.date.weekday()) {
+            week.off_day_secs += day.tracked_secs;
+        } else if day.tracked_secs > 0 {
+            week.workdays_tracked += 1;
+        }
+    }
+    weeks.into_values().collect()
+}
+
+/// Per-machine totals, sorted by machine id. Untagged sessions are grouped
+/// under `"local"`.
+pub fn machine_totals(sessions: &[Session]) -> Vec<MachineTotal> {
+    let mut machines: BTreeMap<&str, MachineTotal> = BTreeMap::new();
+    for session in sessions {
+        let machine_id = session.machine_id.as_deref().unwrap_or("local");
+        let total = machines.entry(machine_id).or_insert(MachineTotal {
+            machine_id: machine_id.to_string(),
+            sessions: 0,
+            tracked_secs: 0,
+            events: 0,
+        });
+        total.sessions += 1;
+        total.tracked_secs += session.duration().map_or(0, |d| d.num_seconds());
+        total.events += event_count(session);
+    }
+    machines.into_values().collect()
+}
+
+/// Time tracked against `task_name` on `today` (by session start date) and
+/// in total.
+pub fn task_time<'a>(
+    sessions: impl IntoIterator<Item = &'a Session>,
+    task_name: &str,
+    today: NaiveDate,
+) -> TaskTime {
+    let mut time = TaskTime::default();
+    for session in sessions.into_iter().filter(|s| s.task_name == task_name) {
+        let secs = session.duration().map_or(0, |d| d.num_seconds());
+        time.sessions += 1;
+        time.total_secs += secs;
+        if session
+            .started_at()
+            .is_some_and(|t| t.date_naive() == today)
+        {
+            time.today_secs += secs;
+        }
+    }
+    time
+}
+
+pub fn click_stats(session: &Session) -> ClickStats {
+    let mut stats = ClickStats::default();
+    let mut holds = Vec::new();
+    for action in &session.actions {
+        match action {
+            Action::MouseDown { .. } => stats.clicks += 1,
+            Action::MouseUp { hold_ms, .. } => holds.push(*hold_ms),
+            _ => {}
+        }
+    }
+    if !holds.is_empty() {
+        stats.avg_hold_ms = Some(holds.iter().sum::<i64>() / holds.len() as i64);
+    }
+    stats
+}
+
+/// Mouse moves and clicks by the cursor shape they happened under, e.g.
+/// how much pointing was over text or links. Events before the first
+/// recorded shape aren't counted.
+pub fn cursor_shapes(session: &Session) -> BTreeMap<String, usize> {
+    let mut shape = None;
+    let mut counts = BTreeMap::new();
+    for action in session.ordered_actions() {
+        match action {
+            Action::CursorChange { shape: s, .. } => shape = Some(s),
+            Action::MouseMove { .. } | Action::MouseDown { .. } => {
+                if let Some(shape) = &shape {
+                    *counts.entry(shape.clone()).or_insert(0) += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    counts
+}
+
+/// Burn-down of `planned_hours` against the sessions of `task_name`, with
+/// the completion projected from `today`.
+pub fn burn_down<'a>(
+    sessions: impl IntoIterator<Item = &'a Session>,
+    task_name: &str,
+    planned_hours: f64,
+    today: NaiveDate,
+) -> BurnDown {
+    let mut burn = BurnDown {
+        planned_secs: (planned_hours * 3600.0).round() as i64,
+        ..Default::default()
+    };
+    for day in daily_totals(sessions.into_iter().filter(|s| s.task_name == task_name)) {
+        burn.tracked_secs += day.tracked_secs;
+        burn.days.push((day.date, burn.tracked_secs));
+    }
+    if let Some(&(first, _)) = burn.days.first() {
+        let elapsed_days = (today - first).num_days().max(0) + 1;
+        let per_day = burn.tracked_secs as f64 / elapsed_days as f64;
+        if burn.remaining_secs() > 0 && per_day > 0.0 {
+            let days_left = (burn.remaining_secs() as f64 / per_day).ceil() as u64;
+            burn.projected_completion = today.checked_add_days(Days::new(days_left));
+        }
+    }
+    burn
+}
+
+/// Per-task totals over `sessions`, most tracked time first.
+pub fn task_summaries<'a>(sessions: impl IntoIterator<Item = &'a Session>) -> Vec<TaskSummary> {
+    let mut tasks: BTreeMap<&str, TaskSummary> = BTreeMap::new();
+    for session in sessions {
+        let summary = tasks
+            .entry(&session.task_name)
+            .or_insert_with(|| TaskSummary {
+                task_name: session.task_name.clone(),
+                ..Default::default()
+            });
+        summary.sessions += 1;
+        summary.tracked_secs += session.duration().map_or(0, |d| d.num_seconds());
+        summary.keystrokes += keystroke::keystrokes(session).len();
+        summary.mouse_moves += session.mouse_path.len();
+    }
+    let mut summaries: Vec<TaskSummary> = tasks.into_values().collect();
+    summaries.sort_by_key(|s| std::cmp::Reverse(s.tracked_secs));
+    summaries
+}
+
+/// Focused time per app over `sessions`, most first. Each focus change
+/// counts until the next one or the session end; windows without a
+/// recorded app aren't counted.
+pub fn app_times<'a>(sessions: impl IntoIterator<Item = &'a Session>) -> Vec<AppTime> {
+    let mut apps: BTreeMap<String, i64> = BTreeMap::new();
+    for session in sessions {
+        let Some(end) = session
+            .end_time
+            .as_deref()
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        else {
+            continue;
+        };
+        let mut focused: Option<(&str, DateTime<FixedOffset>)> = None;
+        for action in &session.actions {
+            let Action::FocusChange { timestamp, app, .. } = action else {
+                continue;
+            };
+            let Ok(at) = DateTime::parse_from_rfc3339(timestamp) else {
+                continue;
+            };
+            if let Some((app, since)) = focused.take() {
+                *apps.entry(app.to_string()).or_default() += (at - since).num_seconds().max(0);
+            }
+            focused = app.as_deref().map(|app| (app, at));
+        }
+        if let Some((app, since)) = focused {
+            *apps.entry(app.to_string()).or_default() += (end - since).num_seconds().max(0);
+        }
+    }
+    let mut times: Vec<AppTime> = apps
+        .into_iter()
+        .map(|(app, focused_secs)| AppTime { app, focused_secs })
+        .collect();
+    times.sort_by_key(|t| std::cmp::Reverse(t.focused_secs));
+    times
+}
+
+/// Days with at least `min_tracked_secs` of tracked time, oldest first.
+pub fn active_days(sessions: &[Session], min_tracked_secs: i64) -> Vec<NaiveDate> {
+    daily_totals(sessions)
+        .into_iter()
+        .filter(|total| total.tracked_secs > 0 && total.tracked_secs >= min_tracked_secs)
+        .map(|total| total.date)
+        .collect()
+}
+
+/// Consecutive active days ending on `today`, or on the day before if
+/// `today` isn't active yet. `days` must be sorted.
+pub fn current_streak(days: &[NaiveDate], today: NaiveDate) -> u32 {
+    let mut day = today;
+    if days.binary_search(&day).is_err() {
+        day = day - Days::new(1);
+    }
+    let mut streak = 0;
+    while days.binary_search(&day).is_ok() {
+        streak += 1;
+        day = day - Days::new(1);
+    }
+    streak
+}
+
+/// Longest run of consecutive active days. `days` must be sorted.
+pub fn longest_streak(days: &[NaiveDate]) -> u32 {
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for &day in days {
+        run = match previous {
+            Some(prev) if prev + Days::new(1) == day => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        previous = Some(day);
+    }
+    longest
+}
+
+/// Splits a session at its recorded layout changes. Time before the first
+/// change isn't attributed to any layout; the last segment runs to the
+/// session end.
+pub fn layout_segments(session: &Session) -> Vec<LayoutSegment> {
+    let Some(session_end) = session
+        .end_time
+        .as_deref()
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+    else {
+        return Vec::new();
+    };
+
+    let mut segments: Vec<LayoutSegment> = Vec::new();
+    for action in &session.actions {
+        match action {
+            Action::LayoutChange { timestamp, layout } => {
+                let Ok(at) = DateTime::parse_from_rfc3339(timestamp) else {
+                    continue;
+                };
+                if let Some(last) = segments.last_mut() {
+                    last.end = at;
+                }
+                segments.push(LayoutSegment {
+                    layout: layout.clone(),
+                    start: at,
+                    end: session_end,
+                    key_events: 0,
+                });
+            }
+            Action::KeyPress { .. } => {
+                if let Some(last) = segments.last_mut() {
+                    last.key_events += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    segments
+}
+
+/// Mouse moves translated into the focused window's local coordinates,
+/// using the bounds recorded with each focus change. Moves before the first
+/// change with bounds, or outside the focused window, are left out.
+pub fn window_moves(session: &Session) -> Vec<WindowMove> {
+    let mut focused = None;
+    let mut moves = Vec::new();
+    for action in session.ordered_actions() {
+        match action {
+            Action::FocusChange { window, bounds, .. } => {
+                focused = bounds.map(|bounds| (window, bounds));
+            }
+            Action::MouseMove { timestamp, coords } => {
+                let Some((window, bounds)) = focused else {
+                    continue;
+                };
+                if let Some(coords) = bounds.to_local(coords) {
+                    moves.push(WindowMove {
+                        timestamp,
+                        window,
+                        coords,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    moves
+}
+
+pub fn break_compliance(session: &Session) -> BreakCompliance {
+    let mut compliance = BreakCompliance::default();
+    for action in &session.actions {
+        match action {
+            Action::BreakReminder { kind, .. } if kind == "break" => compliance.reminders += 1,
+            Action::BreakTaken { kind, reminded, .. } => {
+                if kind == "break" {
+                    compliance.breaks += 1;
+                    if *reminded {
+                        compliance.breaks_after_reminder += 1;
+                    }
+                } else {
+                    compliance.micro_breaks += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    compliance
+}
+
+/// Files opened or saved in the IDE during `session`, most saved first.
+pub fn ide_files(session: &Session) -> Vec<FileActivity> {
+    let mut files: Vec<FileActivity> = Vec::new();
+    for action in &session.actions {
+        let Action::Custom { kind, payload, .. } = action else {
+            continue;
+        };
+        let (Some(event), Some(path)) = (
+            payload.get("event").and_then(|e| e.as_str()),
+            payload.get("path").and_then(|p| p.as_str()),
+        ) else {
+            continue;
+        };
+        if kind != ide::EVENT_KIND || !matches!(event, "file_open" | "file_save") {
+            continue;
+        }
+        let index = match files.iter().position(|f| f.path == path) {
+            Some(i) => i,
+            None => {
+                files.push(FileActivity {
+                    path: path.to_string(),
+                    ..Default::default()
+                });
+                files.len() - 1
+            }
+        };
+        if event == "file_save" {
+            files[index].saves += 1;
+        } else {
+            files[index].opens += 1;
+        }
+    }
+    files.sort_by(|a, b| b.saves.cmp(&a.saves).then(b.opens.cmp(&a.opens)));
+    files
+}
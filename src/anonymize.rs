@@ -0,0 +1,188 @@
+//! Anonymized exports for sharing study data. Task names, machine ids, app
+//! names, window titles and the other strings integrations attach to custom
+//! events are replaced with pseudonyms such as `task_3fa2b1c09d`, taken from
+//! a salted hash so the same value gets the same pseudonym in every export.
+//! The salt and the mapping back to the original values stay in the data
+//! directory and are never written next to the export.
+
+use crate::types::{Action, Session};
+use anyhow::{Context, Result};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Random salt, created on first use. Deleting it changes every pseudonym.
+pub const SALT_FILE: &str = "anonymization_salt.txt";
+/// Pseudonym -> original value, added to by every anonymized export.
+pub const MAPPING_FILE: &str = "anonymization_map.csv";
+/// Custom event fields kept as they are: they name what happened rather
+/// than where, e.g. the IDE's `file_save`.
+const KEPT_FIELDS: [&str; 2] = ["event", "alert"];
+
+/// The metadata field that identifies a person. Other fields, like condition
+/// and group, are kept for analysis.
+pub const PARTICIPANT_FIELD: &str = "participant_id";
+
+/// One pseudonym and the value it stands for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pseudonym {
+    /// What was replaced: `task`, `machine`, `app`, `title`, or a custom
+    /// event field.
+    pub kind: String,
+    pub original: String,
+}
+
+#[derive(Debug)]
+pub struct Pseudonymizer {
+    salt: String,
+    mapping: BTreeMap<String, Pseudonym>,
+}
+
+impl Pseudonymizer {
+    pub fn new(salt: impl Into<String>) -> Self {
+        Self {
+            salt: salt.into(),
+            mapping: BTreeMap::new(),
+        }
+    }
+
+    /// Uses the salt in `dir`, creating it on first use, and the mapping
+    /// already there.
+    pub fn load(dir: &Path) -> Result<Self> {
+        let salt_path = dir.join(SALT_FILE);
+        let salt = match fs::read_to_string(&salt_path) {
+            Ok(salt) => salt.trim().to_string(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let mut bytes = [0u8; 32];
+                getrandom::getrandom(&mut bytes).context("generating an anonymization salt")?;
+                let salt: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+                fs::write(&salt_path, &salt)?;
+                salt
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut pseudonymizer = Self::new(salt);
+        let mapping_path = dir.join(MAPPING_FILE);
+        if mapping_path.exists() {
+            let mut reader = csv::Reader::from_path(&mapping_path)?;
+            for record in reader.records() {
+                let record = record?;
+                if let (Some(pseudonym), Some(kind), Some(original)) =
+                    (record.get(0), record.get(1), record.get(2))
+                {
+                    pseudonymizer.mapping.insert(
+                        pseudonym.to_string(),
+                        Pseudonym {
+                            kind: kind.to_string(),
+                            original: original.to_string(),
+                        },
+                    );
+                }
+            }
+        }
+        Ok(pseudonymizer)
+    }
+
+    /// `<kind>_<first 10 hex digits of sha256(salt, kind, value)>`.
+    pub fn pseudonym(&mut self, kind: &str, value: &str) -> String {
+        let mut hasher = Sha256::new();
+        for part in [self.salt.as_str(), kind, value] {
+            hasher.update(part.as_bytes());
+            // Separates the parts, so ("ab", "c") and ("a", "bc") differ.
+            hasher.update([0]);
+        }
+        let digest: String = hasher.finalize()[..5]
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        let pseudonym = format!("{}_{}", kind, digest);
+        self.mapping.insert(
+            pseudonym.clone(),
+            Pseudonym {
+                kind: kind.to_string(),
+                original: value.to_string(),
+            },
+        );
+        pseudonym
+    }
+
+    /// `session` with identifying strings pseudonymized. Notes are free
+    /// text that can't be pseudonymized as a whole, so they're left out.
+    pub fn anonymize(&mut self, mut session: Session) -> Session {
+        session.task_name = self.pseudonym("task", &session.task_name);
+        session.machine_id = session
+            .machine_id
+            .take()
+            .map(|machine| self.pseudonym("machine", &machine));
+        session.notes = None;
+        if let Some(participant) = session.metadata.get_mut(PARTICIPANT_FIELD) {
+            *participant = self.pseudonym("participant", participant);
+        }
+        if let Some(consent) = &mut session.consent {
+            consent.participant_id = self.pseudonym("participant", &consent.participant_id);
+        }
+        for action in &mut session.actions {
+            match action {
+                Action::TaskSwitch { from, to, .. } => {
+                    *from = self.pseudonym("task", from);
+                    *to = self.pseudonym("task", to);
+                }
+                Action::FocusChange { title, app, .. } => {
+                    if let Some(title) = title {
+                        *title = self.pseudonym("title", title);
+                    }
+                    if let Some(app) = app {
+                        *app = self.pseudonym("app", app);
+                    }
+                }
+                Action::Custom { payload, .. } => self.anonymize_value(None, payload),
+                _ => {}
+            }
+        }
+        session
+    }
+
+    /// Pseudonymizes every string in `value` except those under
+    /// [`KEPT_FIELDS`], using the field name as the kind.
+    fn anonymize_value(&mut self, field: Option<&str>, value: &mut Value) {
+        match value {
+            Value::String(text) if !field.is_some_and(|f| KEPT_FIELDS.contains(&f)) => {
+                *text = self.pseudonym(field.unwrap_or("value"), text);
+            }
+            Value::Array(items) => {
+                for item in items {
+                    self.anonymize_value(field, item);
+                }
+            }
+            Value::Object(fields) => {
+                for (name, item) in fields.iter_mut() {
+                    self.anonymize_value(Some(name), item);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn mapping(&self) -> &BTreeMap<String, Pseudonym> {
+        &self.mapping
+    }
+
+    /// Writes every pseudonym handed out so far, including by earlier
+    /// exports, as `pseudonym,kind,original` rows.
+    pub fn write_mapping(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let tmp = path.with_extension("csv.tmp");
+        let mut writer = csv::Writer::from_path(&tmp)?;
+        writer.write_record(["pseudonym", "kind", "original"])?;
+        for (pseudonym, entry) in &self.mapping {
+            writer.write_record([pseudonym, &entry.kind, &entry.original])?;
+        }
+        writer.flush()?;
+        drop(writer);
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,330 @@
+use crate::analysis;
+use crate::config::Config;
+use crate::handle::MonitorHandle;
+use crate::metrics;
+use crate::types::Session;
+use anyhow::Result;
+use chrono::NaiveDate;
+use serde::Serialize;
+use serde_json::json;
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    thread,
+};
+
+const DEFAULT_PAGE_SIZE: usize = 500;
+
+#[derive(Serialize)]
+struct SessionSummary<'a> {
+    session_id: &'a str,
+    task_name: &'a str,
+    start_time: &'a str,
+    end_time: Option<&'a str>,
+    duration_secs: Option<i64>,
+    events: usize,
+    notes: Option<&'a str>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    metadata: &'a BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    metrics: BTreeMap<String, f64>,
+}
+
+/// Starts the local query API on `127.0.0.1:port` in a background thread.
+///
+/// Endpoints (JSON responses):
+/// - `GET /sessions?task=&since=YYYY-MM-DD&until=YYYY-MM-DD`
+/// - `GET /sessions/<id>/events?offset=&limit=`
+/// - `GET /aggregates/daily?task=&since=&until=`
+/// - `GET /live`: whether the monitor is recording, the task, its status
+///   line and today's live metrics, when started with a `monitor`
+/// - `POST /trial`: starts a timed trial of the current task, when started
+///   with a `monitor`
+/// - `POST /trial/start?id=` and `POST /trial/end`: mark a trial within the
+///   session being recorded, when started with a `monitor`
+/// - `POST /sync?label=`: records a sync marker and returns it, when
+///   started with a `monitor`
+///
+/// Requests from web pages are refused; see [`is_local_request`].
+pub fn spawn(port: u16, monitor: Option<MonitorHandle>) -> Result<thread::JoinHandle<()>> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    println!("✓ Query API listening on http://127.0.0.1:{}", port);
+
+    Ok(thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if let Err(e) = handle_connection(stream, port, monitor.as_ref()) {
+                eprintln!("API request failed: {}", e);
+            }
+        }
+    }))
+}
+
+/// Whether a request could only have come from a local program rather than
+/// a web page: its `Host` must be this port on the loopback address, which
+/// DNS rebinding can't fake, and POSTs, which pages can send without
+/// reading the reply, mustn't carry the `Origin` browsers add.
+pub fn is_local_request(port: u16, method: &str, host: Option<&str>, origin: Option<&str>) -> bool {
+    let host_ok = host.is_some_and(|host| {
+        [format!("127.0.0.1:{}", port), format!("localhost:{}", port)]
+            .iter()
+            .any(|allowed| host.eq_ignore_ascii_case(allowed))
+    });
+    host_ok && !(method == "POST" && origin.is_some())
+}
+
+/// The `Host` and `Origin` headers of a request, read up to the blank line
+/// that ends them.
+pub(crate) fn read_headers(
+    reader: &mut impl BufRead,
+    mut other: impl FnMut(&str, &str),
+) -> Result<(Option<String>, Option<String>)> {
+    let (mut host, mut origin) = (None, None);
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        if let Some((name, value)) = header.split_once(':') {
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("host") {
+                host = Some(value.to_string());
+            } else if name.eq_ignore_ascii_case("origin") {
+                origin = Some(value.to_string());
+            } else {
+                other(name, value);
+            }
+        }
+        header.clear();
+    }
+    Ok((host, origin))
+}
+
+fn handle_connection(
+    mut stream: TcpStream,
+    port: u16,
+    monitor: Option<&MonitorHandle>,
+) -> Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    // None of the endpoints take a body.
+    let (host, origin) = read_headers(&mut reader, |_, _| {})?;
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let target = parts.next().unwrap_or_default();
+
+    let (status, body) = if !is_local_request(port, method, host.as_deref(), origin.as_deref()) {
+        (
+            403,
+            json!({ "error": "only local programs may use the API" }),
+        )
+    } else if method != "GET" && method != "POST" {
+        (405, json!({ "error": "only GET and POST are supported" }))
+    } else {
+        let routed = if method == "POST" {
+            route_post(target, monitor)
+        } else {
+            route(target, monitor)
+        };
+        match routed {
+            Ok(Some(body)) => (200, body),
+            Ok(None) => (404, json!({ "error": "not found" })),
+            Err(e) => (400, json!({ "error": e.to_string() })),
+        }
+    };
+
+    let body = body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason(status),
+        body.len(),
+        body
+    )?;
+    Ok(())
+}
+
+fn route(target: &str, monitor: Option<&MonitorHandle>) -> Result<Option<serde_json::Value>> {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let params = parse_query(query);
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+    let value = match segments.as_slice() {
+        ["sessions"] => {
+            let sessions = filtered_sessions(&params)?;
+            let config = Config::load().unwrap_or_default();
+            let summaries: Vec<_> = sessions.iter().map(|s| summarize(s, &config)).collect();
+            serde_json::to_value(summaries)?
+        }
+        ["sessions", id, "events"] => {
+            let id = percent_decode(id);
+            let sessions = Config::load()?.stored_sessions()?;
+            let Some(session) = sessions.iter().find(|s| s.session_id == id) else {
+                return Ok(None);
+            };
+
+            let offset = parse_param(&params, "offset")?.unwrap_or(0);
+            let limit = parse_param(&params, "limit")?.unwrap_or(DEFAULT_PAGE_SIZE);
+            let events = analysis::timed_events(session);
+            let page: Vec<_> = events.iter().skip(offset).take(limit).collect();
+            json!({
+                "session_id": session.session_id,
+                "total": events.len(),
+                "offset": offset,
+                "limit": limit,
+                "events": page,
+            })
+        }
+        ["aggregates", "daily"] => {
+            let sessions = filtered_sessions(&params)?;
+            serde_json::to_value(analysis::daily_totals(&sessions))?
+        }
+        ["live"] => {
+            let Some(monitor) = monitor else {
+                return Ok(None);
+            };
+            let live = monitor.live_state()?;
+            json!({
+                "running": live.running,
+                "task_name": live.task_name,
+                "status": live.status,
+                "tracked_today_secs": live.metrics.tracked_today_secs,
+                "sessions_today": live.metrics.sessions_today,
+                "task_secs": live.metrics.task_secs,
+                "apm": live.metrics.apm,
+            })
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(value))
+}
+
+fn route_post(target: &str, monitor: Option<&MonitorHandle>) -> Result<Option<serde_json::Value>> {
+    let Some(monitor) = monitor else {
+        return Ok(None);
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let params = parse_query(query);
+    let value = match path.trim_matches('/') {
+        "trial" => {
+            let (started, status) = monitor.start_trial()?;
+            if !started {
+                anyhow::bail!(status);
+            }
+            json!({ "status": status })
+        }
+        "trial/start" => {
+            let trial_id = monitor.mark_trial_start(params.get("id").cloned())?;
+            json!({ "trial_id": trial_id })
+        }
+        "bug-report" => {
+            let (started, status) = monitor.start_bug_report()?;
+            if !started {
+                anyhow::bail!(status);
+            }
+            json!({ "status": status })
+        }
+        "trial/end" => json!({ "trial_id": monitor.mark_trial_end()? }),
+        "sync" => serde_json::to_value(monitor.sync_marker(params.get("label").cloned())?)?,
+        "buffer/save" => json!({ "session_id": monitor.save_ring_buffer()? }),
+        _ => return Ok(None),
+    };
+    Ok(Some(value))
+}
+
+fn filtered_sessions(params: &HashMap<String, String>) -> Result<Vec<Session>> {
+    let since = parse_date(params, "since")?;
+    let until = parse_date(params, "until")?;
+    let task = params.get("task");
+
+    Ok(Config::load()?
+        .stored_sessions()?
+        .into_iter()
+        .filter(|s| task.is_none_or(|t| &s.task_name == t))
+        .filter(|s| {
+            let date = s.started_at().map(|t| t.date_naive());
+            since.is_none_or(|d| date >= Some(d)) && until.is_none_or(|d| date <= Some(d))
+        })
+        .collect())
+}
+
+fn summarize<'a>(session: &'a Session, config: &Config) -> SessionSummary<'a> {
+    SessionSummary {
+        session_id: &session.session_id,
+        task_name: &session.task_name,
+        start_time: &session.start_time,
+        end_time: session.end_time.as_deref(),
+        duration_secs: session.duration().map(|d| d.num_seconds()),
+        events: analysis::event_count(session),
+        notes: session.notes.as_deref(),
+        metadata: &session.metadata,
+        metrics: metrics::session_metrics(config, session),
+    }
+}
+
+fn parse_param(params: &HashMap<String, String>, name: &str) -> Result<Option<usize>> {
+    params
+        .get(name)
+        .map(|v| {
+            v.parse()
+                .map_err(|_| anyhow::anyhow!("invalid {}: {}", name, v))
+        })
+        .transpose()
+}
+
+fn parse_date(params: &HashMap<String, String>, name: &str) -> Result<Option<NaiveDate>> {
+    params
+        .get(name)
+        .map(|v| {
+            NaiveDate::parse_from_str(v, "%Y-%m-%d")
+                .map_err(|_| anyhow::anyhow!("invalid {} (expected YYYY-MM-DD): {}", name, v))
+        })
+        .transpose()
+}
+
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect()
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Error",
+    }
+}
@@ -0,0 +1,73 @@
+//! App usage alerts: "you've been in Slack for 20 minutes". Tracks how long
+//! focus has stayed within each configured [`AppAlert`]'s apps and fires
+//! once per continuous stretch.
+
+use crate::config::AppAlert;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// An alert whose threshold was just reached.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppAlertEvent {
+    pub alert: String,
+    /// The focused app when it fired.
+    pub app: String,
+    pub focused_for: Duration,
+}
+
+#[derive(Debug, Default)]
+pub struct AppAlertTracker {
+    /// When focus entered each alert's apps, and whether it has fired
+    /// since.
+    stretches: HashMap<String, (Instant, bool)>,
+}
+
+impl AppAlertTracker {
+    /// Forgets every stretch, e.g. when a session starts.
+    pub fn reset(&mut self) {
+        self.stretches.clear();
+    }
+
+    /// A fired alert that focus is still within, and for how long, if any.
+    pub fn due(&self, now: Instant) -> Option<(&str, Duration)> {
+        self.stretches
+            .iter()
+            .filter(|(_, (_, fired))| *fired)
+            .map(|(name, (since, _))| (name.as_str(), now.duration_since(*since)))
+            .max_by_key(|(_, focused_for)| *focused_for)
+    }
+
+    /// Updates the stretches with the currently focused `app` and returns
+    /// the alerts that reached their threshold.
+    pub fn tick(
+        &mut self,
+        now: Instant,
+        app: Option<&str>,
+        alerts: &[AppAlert],
+    ) -> Vec<AppAlertEvent> {
+        let mut events = Vec::new();
+        for alert in alerts {
+            let Some(app) = app.filter(|app| alert.matches(app)) else {
+                self.stretches.remove(&alert.name);
+                continue;
+            };
+            let (since, fired) = self
+                .stretches
+                .entry(alert.name.clone())
+                .or_insert((now, false));
+            let focused_for = now.duration_since(*since);
+            if !*fired && focused_for >= Duration::from_secs(alert.minutes * 60) {
+                *fired = true;
+                events.push(AppAlertEvent {
+                    alert: alert.name.clone(),
+                    app: app.to_string(),
+                    focused_for,
+                });
+            }
+        }
+        // Alerts removed from the config while a stretch was running.
+        self.stretches
+            .retain(|name, _| alerts.iter().any(|alert| alert.name == *name));
+        events
+    }
+}
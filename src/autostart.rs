@@ -0,0 +1,197 @@
+//! Launching the GUI at login: an autostart `.desktop` entry on Linux, a
+//! launch agent on macOS and a `Run` registry value on Windows. Unlike
+//! `service`, nothing is started now and nothing is restarted on exit.
+//!
+//! The app is started in the directory autostart was enabled from, so its
+//! data files stay in the same place.
+
+use crate::service::{self, Launch};
+use anyhow::{bail, Result};
+
+const NAME: &str = service::SERVICE_NAME;
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::NAME;
+    use crate::service::{config_dir, Launch};
+    use anyhow::Result;
+    use std::{fs, path::PathBuf};
+
+    fn entry_path() -> Result<PathBuf> {
+        Ok(config_dir()?
+            .join("autostart")
+            .join(format!("{}.desktop", NAME)))
+    }
+
+    pub(super) fn enable(launch: &Launch) -> Result<()> {
+        let path = entry_path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(
+            &path,
+            format!(
+                "[Desktop Entry]\n\
+                 Type=Application\n\
+                 Name=Desktop Activity Monitor\n\
+                 Exec=\"{}\"\n\
+                 Path={}\n\
+                 X-GNOME-Autostart-enabled=true\n",
+                launch.exe.display(),
+                launch.working_dir.display()
+            ),
+        )?;
+        Ok(())
+    }
+
+    pub(super) fn disable() -> Result<()> {
+        let path = entry_path()?;
+        if path.exists() {
+            fs::remove_file(&path)?;
+        }
+        Ok(())
+    }
+
+    pub(super) fn is_enabled() -> bool {
+        // Desktops hide an entry rather than delete it when it's turned off
+        // in their own settings.
+        entry_path()
+            .and_then(|path| Ok(fs::read_to_string(path)?))
+            .is_ok_and(|entry| !entry.lines().any(|line| line.trim() == "Hidden=true"))
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::NAME;
+    use crate::service::{escape_xml as escape, launch_agent_path, Launch};
+    use anyhow::Result;
+    use std::{fs, path::PathBuf};
+
+    const LABEL: &str = "com.desk-monitor.app";
+
+    fn plist_path() -> Result<PathBuf> {
+        launch_agent_path(LABEL)
+    }
+
+    pub(super) fn enable(launch: &Launch) -> Result<()> {
+        let path = plist_path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // Only loaded at the next login; loading it now would open a second
+        // window.
+        fs::write(
+            &path,
+            format!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{}</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>{}</string>
+    </array>
+    <key>WorkingDirectory</key>
+    <string>{}</string>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>StandardErrorPath</key>
+    <string>/tmp/{}-app.log</string>
+</dict>
+</plist>
+"#,
+                LABEL,
+                escape(&launch.exe.to_string_lossy()),
+                escape(&launch.working_dir.to_string_lossy()),
+                NAME
+            ),
+        )?;
+        Ok(())
+    }
+
+    pub(super) fn disable() -> Result<()> {
+        let path = plist_path()?;
+        if path.exists() {
+            fs::remove_file(&path)?;
+        }
+        Ok(())
+    }
+
+    pub(super) fn is_enabled() -> bool {
+        plist_path().is_ok_and(|path| path.exists())
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::NAME;
+    use crate::service::{run, Launch};
+    use anyhow::Result;
+    use std::process::Command;
+
+    const RUN_KEY: &str = r"HKCU\Software\Microsoft\Windows\CurrentVersion\Run";
+
+    pub(super) fn enable(launch: &Launch) -> Result<()> {
+        let command = launch.windows_command(&format!("start \"\" \"{}\"", launch.exe.display()));
+        run(Command::new("reg").args([
+            "add", RUN_KEY, "/v", NAME, "/t", "REG_SZ", "/d", &command, "/f",
+        ]))
+    }
+
+    pub(super) fn disable() -> Result<()> {
+        if is_enabled() {
+            run(Command::new("reg").args(["delete", RUN_KEY, "/v", NAME, "/f"]))?;
+        }
+        Ok(())
+    }
+
+    pub(super) fn is_enabled() -> bool {
+        Command::new("reg")
+            .args(["query", RUN_KEY, "/v", NAME])
+            .output()
+            .is_ok_and(|output| output.status.success())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+mod platform {
+    use crate::service::Launch;
+    use anyhow::{bail, Result};
+
+    pub(super) fn enable(_launch: &Launch) -> Result<()> {
+        bail!("launching at login isn't supported on this platform")
+    }
+
+    pub(super) fn disable() -> Result<()> {
+        Ok(())
+    }
+
+    pub(super) fn is_enabled() -> bool {
+        false
+    }
+}
+
+/// Launches the GUI at login from the current directory. Refused while the
+/// login service is installed, since both would record the same input.
+pub fn enable() -> Result<()> {
+    if service::is_installed() {
+        bail!(
+            "the background service is installed; run `desk-monitor uninstall` first, \
+             or both would record"
+        );
+    }
+    platform::enable(&Launch::current()?)
+}
+
+pub fn disable() -> Result<()> {
+    platform::disable()
+}
+
+/// Whether the GUI is currently registered to launch at login, including
+/// by hand or from another copy of the app.
+pub fn is_enabled() -> bool {
+    platform::is_enabled()
+}
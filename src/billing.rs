@@ -0,0 +1,249 @@
+//! Invoices: the sessions recorded for a client's task over a date range,
+//! billed at an hourly rate, as CSV and as printable HTML.
+
+use crate::config::{BillingConfig, RoundingMode};
+use crate::integrity;
+use crate::types::Session;
+use anyhow::Result;
+use chrono::NaiveDate;
+use std::fs;
+use std::path::Path;
+
+/// One billed session.
+#[derive(Debug, Clone)]
+pub struct InvoiceLine {
+    pub date: NaiveDate,
+    /// Local start time, `HH:MM`.
+    pub start: String,
+    pub session_id: String,
+    /// The session's notes, or the task name without any.
+    pub description: String,
+    pub raw_secs: i64,
+    pub billed_secs: i64,
+    /// Rounded to cents, so the lines add up to the total.
+    pub amount: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Invoice {
+    pub client: String,
+    pub from: NaiveDate,
+    pub until: NaiveDate,
+    pub hourly_rate: f64,
+    pub currency: String,
+    pub lines: Vec<InvoiceLine>,
+}
+
+impl Invoice {
+    pub fn billed_secs(&self) -> i64 {
+        self.lines.iter().map(|line| line.billed_secs).sum()
+    }
+
+    pub fn total(&self) -> f64 {
+        cents(self.lines.iter().map(|line| line.amount).sum())
+    }
+
+    /// `invoice_<client>_<from>_<until>`, with anything but letters and
+    /// digits in the client name replaced by `_`.
+    pub fn file_stem(&self) -> String {
+        let client: String = self
+            .client
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '_' })
+            .collect();
+        format!("invoice_{}_{}_{}", client, self.from, self.until)
+    }
+}
+
+/// Bills the finished sessions of task `client` started between `from` and
+/// `until` (inclusive, local dates), oldest first.
+pub fn invoice<'a>(
+    sessions: impl IntoIterator<Item = &'a Session>,
+    client: &str,
+    from: NaiveDate,
+    until: NaiveDate,
+    hourly_rate: f64,
+    config: &BillingConfig,
+) -> Invoice {
+    let mut lines: Vec<InvoiceLine> = sessions
+        .into_iter()
+        .filter(|s| s.task_name == client)
+        .filter_map(|session| {
+            let start = session.started_at()?;
+            let date = start.date_naive();
+            if date < from || date > until {
+                return None;
+            }
+            let raw_secs = session.duration()?.num_seconds();
+            let billed_secs = billed_secs(raw_secs, config);
+            Some(InvoiceLine {
+                date,
+                start: start.format("%H:%M").to_string(),
+                session_id: session.session_id.clone(),
+                description: session
+                    .notes
+                    .clone()
+                    .unwrap_or_else(|| session.task_name.clone()),
+                raw_secs,
+                billed_secs,
+                amount: cents(billed_secs as f64 / 3600.0 * hourly_rate),
+            })
+        })
+        .collect();
+    lines.sort_by(|a, b| (a.date, &a.start).cmp(&(b.date, &b.start)));
+    Invoice {
+        client: client.to_string(),
+        from,
+        until,
+        hourly_rate,
+        currency: config.currency.clone(),
+        lines,
+    }
+}
+
+/// Whether `config` bills anything other than the exact tracked time.
+pub fn rounds(config: &BillingConfig) -> bool {
+    config.round_to_minutes > 0 || config.minimum_minutes > 0
+}
+
+/// Billed time for a session of `raw_secs`: rounded to the configured
+/// increment, then raised to the minimum.
+pub fn billed_secs(raw_secs: i64, config: &BillingConfig) -> i64 {
+    let step = i64::from(config.round_to_minutes) * 60;
+    let rounded = if step == 0 {
+        raw_secs
+    } else {
+        match config.rounding {
+            RoundingMode::Nearest => (raw_secs + step / 2) / step * step,
+            RoundingMode::Up => (raw_secs + step - 1) / step * step,
+            RoundingMode::Down => raw_secs / step * step,
+        }
+    };
+    rounded.max(i64::from(config.minimum_minutes) * 60)
+}
+
+fn cents(amount: f64) -> f64 {
+    (amount * 100.0).round() / 100.0
+}
+
+fn format_hm(secs: i64) -> String {
+    format!("{}:{:02}", secs / 3600, (secs % 3600) / 60)
+}
+
+/// One row per session and a total row.
+pub fn write_csv(invoice: &Invoice, path: impl AsRef<Path>) -> Result<()> {
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record([
+        "date",
+        "start",
+        "session_id",
+        "description",
+        "raw_duration",
+        "billed_duration",
+        "billed_hours",
+        "rate",
+        "amount",
+    ])?;
+    let rate = format!("{:.2}", invoice.hourly_rate);
+    for line in &invoice.lines {
+        writer.write_record([
+            line.date.to_string(),
+            line.start.clone(),
+            line.session_id.clone(),
+            line.description.clone(),
+            format_hm(line.raw_secs),
+            format_hm(line.billed_secs),
+            format!("{:.2}", line.billed_secs as f64 / 3600.0),
+            rate.clone(),
+            format!("{:.2}", line.amount),
+        ])?;
+    }
+    let raw_secs: i64 = invoice.lines.iter().map(|line| line.raw_secs).sum();
+    writer.write_record([
+        "Total".to_string(),
+        String::new(),
+        String::new(),
+        String::new(),
+        format_hm(raw_secs),
+        format_hm(invoice.billed_secs()),
+        format!("{:.2}", invoice.billed_secs() as f64 / 3600.0),
+        String::new(),
+        format!("{:.2}", invoice.total()),
+    ])?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Self-contained page meant for printing to PDF from a browser.
+pub fn to_html(invoice: &Invoice) -> String {
+    let mut rows = String::new();
+    for line in &invoice.lines {
+        rows.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td class=\"num\">{}</td>\
+             <td class=\"num\">{:.2}</td></tr>\n",
+            line.date,
+            line.start,
+            escape(&line.description),
+            format_hm(line.billed_secs),
+            line.amount
+        ));
+    }
+    // Text from the sessions goes in last, so braces in it aren't taken for
+    // placeholders.
+    INVOICE_TEMPLATE
+        .replace("{from}", &invoice.from.to_string())
+        .replace("{until}", &invoice.until.to_string())
+        .replace("{rate}", &format!("{:.2}", invoice.hourly_rate))
+        .replace("{hours}", &format_hm(invoice.billed_secs()))
+        .replace("{total}", &format!("{:.2}", invoice.total()))
+        .replace("{currency}", &escape(&invoice.currency))
+        .replace("{client}", &escape(&invoice.client))
+        .replace("{rows}", &rows)
+}
+
+/// Writes `<file_stem>.csv` and `<file_stem>.html` to `out_dir`, each with a
+/// `.sha256` checksum manifest.
+pub fn write_invoice(invoice: &Invoice, out_dir: &Path) -> Result<()> {
+    fs::create_dir_all(out_dir)?;
+    let stem = invoice.file_stem();
+    let csv = out_dir.join(format!("{}.csv", stem));
+    write_csv(invoice, &csv)?;
+    integrity::write_manifest(csv)?;
+    let html = out_dir.join(format!("{}.html", stem));
+    fs::write(&html, to_html(invoice))?;
+    integrity::write_manifest(html)?;
+    Ok(())
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+const INVOICE_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Invoice: {client}</title>
+<style>
+body { font-family: system-ui, sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; width: 100%; }
+th, td { padding: 4px 12px; border-bottom: 1px solid #ddd; text-align: left; }
+.num { text-align: right; }
+tfoot td { font-weight: bold; border-top: 2px solid #222; }
+</style>
+</head>
+<body>
+<h1>Invoice: {client}</h1>
+<p>{from} to {until} at {rate} {currency}/hour</p>
+<table>
+<thead><tr><th>Date</th><th>Start</th><th>Description</th><th class="num">Hours</th><th class="num">Amount ({currency})</th></tr></thead>
+<tbody>
+{rows}</tbody>
+<tfoot><tr><td colspan="3">Total</td><td class="num">{hours}</td><td class="num">{total}</td></tr></tfoot>
+</table>
+</body>
+</html>
+"#;
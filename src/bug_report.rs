@@ -0,0 +1,185 @@
+//! Bug-report capture: a short recording for reproducing a UI bug that
+//! also takes periodic screenshots and follows window focus, then bundles
+//! everything into a ZIP with a step-by-step reproduction timeline.
+//!
+//! The bundle holds `timeline.md`, `summary.md`, `events.json` (the events
+//! as served by the query API) and `screenshots/NNNN.png`.
+
+use crate::analysis;
+use crate::config::Config;
+use crate::export;
+use crate::integrity;
+use crate::screenshot::Screenshot;
+use crate::types::{Action, Session};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+/// Custom event kind screenshots are recorded under, with the file name
+/// in the bundle as `file`.
+pub const SCREENSHOT_KIND: &str = "screenshot";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BugReportConfig {
+    /// How long a capture runs before it stops and bundles itself.
+    pub duration_secs: u64,
+    /// Take screenshots during a capture. Off, a bundle only has the
+    /// events.
+    pub screenshots: bool,
+    pub screenshot_interval_secs: u64,
+    /// Screenshots wider than this are shrunk to keep bundles small. 0
+    /// keeps them full size.
+    pub screenshot_max_width: u32,
+    pub directory: String,
+}
+
+impl Default for BugReportConfig {
+    fn default() -> Self {
+        Self {
+            duration_secs: 120,
+            screenshots: true,
+            screenshot_interval_secs: 5,
+            screenshot_max_width: 1280,
+            directory: "bug_reports".to_string(),
+        }
+    }
+}
+
+/// A capture in progress.
+#[derive(Debug)]
+pub struct BugReport {
+    ends: Instant,
+    next_screenshot: Instant,
+    /// File names in the bundle and PNG data, in the order taken.
+    screenshots: Vec<(String, Vec<u8>)>,
+}
+
+impl BugReport {
+    pub fn new(config: &BugReportConfig) -> Self {
+        let now = Instant::now();
+        Self {
+            ends: now + Duration::from_secs(config.duration_secs),
+            next_screenshot: now,
+            screenshots: Vec::new(),
+        }
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.ends.saturating_duration_since(Instant::now())
+    }
+
+    pub fn is_over(&self) -> bool {
+        Instant::now() >= self.ends
+    }
+
+    /// Whether it's time for the next screenshot; if so, the one after is
+    /// scheduled `interval` from now.
+    pub fn screenshot_due(&mut self, interval: Duration) -> bool {
+        let now = Instant::now();
+        if now < self.next_screenshot {
+            return false;
+        }
+        self.next_screenshot = now + interval;
+        true
+    }
+
+    /// Keeps `screenshot` for the bundle and returns its file name there.
+    pub fn add_screenshot(&mut self, screenshot: &Screenshot) -> Result<String> {
+        let file = format!("screenshots/{:04}.png", self.screenshots.len() + 1);
+        self.screenshots.push((file.clone(), screenshot.to_png()?));
+        Ok(file)
+    }
+
+    /// Writes the bundle for `session`, the capture's saved session, to
+    /// `path`, along with a `.sha256` checksum manifest.
+    pub fn write_bundle(&self, session: &Session, config: &Config, path: &Path) -> Result<()> {
+        let mut zip = ZipWriter::new(BufWriter::new(File::create(path)?));
+        let text = SimpleFileOptions::default();
+        // PNGs are compressed already.
+        let image = text.compression_method(zip::CompressionMethod::Stored);
+        zip.start_file("timeline.md", text)?;
+        zip.write_all(timeline(session).as_bytes())?;
+        zip.start_file("summary.md", text)?;
+        zip.write_all(export::session_markdown(session, config).as_bytes())?;
+        let events = serde_json::to_string_pretty(&analysis::timed_events(session))?;
+        zip.start_file("events.json", text)?;
+        zip.write_all(events.as_bytes())?;
+        for (file, png) in &self.screenshots {
+            zip.start_file(file, image)?;
+            zip.write_all(png)?;
+        }
+        zip.finish()?.flush()?;
+        integrity::write_manifest(path)?;
+        Ok(())
+    }
+}
+
+/// Markdown list of the steps in `session`, each with its time since the
+/// start. Runs of mouse moves are collapsed to where the pointer ended up,
+/// and screenshots are shown inline.
+pub fn timeline(session: &Session) -> String {
+    let actions = session.ordered_actions();
+    let mut md = format!("# Reproduction steps: {}\n\n", session.task_name);
+    let mut step = 0;
+    for (i, action) in actions.iter().enumerate() {
+        let next_is_move = matches!(actions.get(i + 1), Some(Action::MouseMove { .. }));
+        let Some(description) = describe(action, next_is_move) else {
+            continue;
+        };
+        let offset = analysis::offset_ms(session, action.timestamp());
+        step += 1;
+        md.push_str(&format!(
+            "{}. `{}` {}\n",
+            step,
+            analysis::format_offset(offset),
+            description
+        ));
+    }
+    if step == 0 {
+        md.push_str("Nothing was recorded.\n");
+    }
+    md
+}
+
+/// One timeline step for `action`, or `None` for ones that don't make a
+/// step of their own.
+fn describe(action: &Action, next_is_move: bool) -> Option<String> {
+    let description = match action {
+        Action::MouseMove { coords, .. } if !next_is_move => {
+            format!("Moved the mouse to ({}, {})", coords.0, coords.1)
+        }
+        Action::KeyPress { keys, .. } if !keys.is_empty() => {
+            format!("Pressed {}", keys.join("+"))
+        }
+        Action::MouseDown { button, coords, .. } => {
+            format!("Clicked {} at ({}, {})", button, coords.0, coords.1)
+        }
+        Action::Shortcut { chord, name, .. } => format!("Shortcut {} ({})", name, chord),
+        Action::FocusChange {
+            window,
+            bounds: Some(b),
+            ..
+        } => format!(
+            "Focused window {} at ({}, {}) {}x{}",
+            window, b.x, b.y, b.width, b.height
+        ),
+        Action::FocusChange {
+            title: Some(title), ..
+        } => format!("Focused {:?}", title),
+        Action::FocusChange { window, .. } => format!("Focused window {}", window),
+        Action::TaskSwitch { to, .. } => format!("Switched to task {}", to),
+        Action::Custom { kind, payload, .. } if kind == SCREENSHOT_KIND => {
+            let file = payload.get("file")?.as_str()?;
+            format!("Screenshot ![{}]({})", file, file)
+        }
+        Action::Custom { kind, payload, .. } => format!("{} {}", kind, payload),
+        _ => return None,
+    };
+    Some(description)
+}
@@ -0,0 +1,101 @@
+//! Suggesting an idle threshold from the pauses between input events over a
+//! day of recording, instead of guessing one.
+
+use crate::types::{Action, Session};
+use chrono::{DateTime, NaiveDate};
+
+/// Upper bounds, in seconds, of the histogram buckets; a last bucket holds
+/// longer pauses.
+pub const BUCKET_BOUNDS: [u64; 9] = [2, 5, 10, 20, 30, 60, 120, 300, 600];
+/// Pauses needed before a threshold is suggested.
+pub const MIN_PAUSES: usize = 100;
+/// Gaps shorter than this are typing or pointing rather than pauses.
+const MIN_PAUSE_MS: i64 = 1000;
+/// The suggestion leaves this share of pauses below the threshold.
+const PERCENTILE: f64 = 0.95;
+
+/// Pauses between input events on one day.
+#[derive(Debug, Clone, Default)]
+pub struct PauseDistribution {
+    pub day: Option<NaiveDate>,
+    pub pauses: usize,
+    /// Pauses per bucket of [`BUCKET_BOUNDS`], plus one for longer ones.
+    pub counts: Vec<usize>,
+    /// Seconds that [`PERCENTILE`] of pauses are shorter than, once there
+    /// are [`MIN_PAUSES`].
+    pub suggested_secs: Option<u64>,
+}
+
+impl PauseDistribution {
+    /// Label for bucket `i`, e.g. `"10-20s"`.
+    pub fn bucket_label(i: usize) -> String {
+        let lower = if i == 0 { 1 } else { BUCKET_BOUNDS[i - 1] };
+        match BUCKET_BOUNDS.get(i) {
+            Some(upper) => format!("{}-{}s", lower, upper),
+            None => format!("{}s+", lower),
+        }
+    }
+}
+
+/// Distribution of the pauses between key presses, shortcuts, mouse moves
+/// and clicks in the sessions started on the latest day with any.
+pub fn latest_day_pauses(sessions: &[Session]) -> PauseDistribution {
+    let Some(day) = sessions
+        .iter()
+        .filter_map(|s| s.started_at().map(|t| t.date_naive()))
+        .max()
+    else {
+        return PauseDistribution::default();
+    };
+
+    let mut pauses_ms = Vec::new();
+    for session in sessions
+        .iter()
+        .filter(|s| s.started_at().is_some_and(|t| t.date_naive() == day))
+    {
+        let mut times: Vec<i64> = session
+            .ordered_actions()
+            .iter()
+            .filter(|action| {
+                matches!(
+                    action,
+                    Action::KeyPress { .. }
+                        | Action::Shortcut { .. }
+                        | Action::MouseMove { .. }
+                        | Action::MouseDown { .. }
+                        | Action::MouseUp { .. }
+                )
+            })
+            .filter_map(|action| DateTime::parse_from_rfc3339(action.timestamp()).ok())
+            .map(|t| t.timestamp_millis())
+            .collect();
+        times.sort_unstable();
+        pauses_ms.extend(
+            times
+                .windows(2)
+                .map(|pair| pair[1] - pair[0])
+                .filter(|&gap| gap >= MIN_PAUSE_MS),
+        );
+    }
+
+    let mut counts = vec![0; BUCKET_BOUNDS.len() + 1];
+    for &gap in &pauses_ms {
+        let secs = (gap / 1000) as u64;
+        counts[BUCKET_BOUNDS
+            .iter()
+            .position(|&bound| secs < bound)
+            .unwrap_or(BUCKET_BOUNDS.len())] += 1;
+    }
+
+    pauses_ms.sort_unstable();
+    let suggested_secs = (pauses_ms.len() >= MIN_PAUSES).then(|| {
+        let i = ((pauses_ms.len() as f64 * PERCENTILE) as usize).min(pauses_ms.len() - 1);
+        (pauses_ms[i] as u64).div_ceil(1000)
+    });
+    PauseDistribution {
+        day: Some(day),
+        pauses: pauses_ms.len(),
+        counts,
+        suggested_secs,
+    }
+}
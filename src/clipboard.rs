@@ -0,0 +1,65 @@
+//! Clipboard changes: something was copied or cut. Only that it happened is
+//! recorded, never what was copied.
+
+/// Custom event kind clipboard changes are recorded under.
+pub const EVENT_KIND: &str = "clipboard";
+
+/// How many times the clipboard changed since the last call, where
+/// supported.
+///
+/// On Linux this counts XFixes notifications that an application took the
+/// `CLIPBOARD` selection, which it does on each copy or cut. The first call
+/// starts listening and counts nothing.
+#[cfg(target_os = "linux")]
+pub fn changes() -> Option<u32> {
+    use std::cell::Cell;
+    use x11::{xfixes, xlib};
+
+    // Not exported by the `x11` crate; from `X11/extensions/Xfixes.h`.
+    const SELECTION_NOTIFY: i32 = 0;
+    const SET_SELECTION_OWNER_NOTIFY_MASK: std::os::raw::c_ulong = 1;
+
+    thread_local! {
+        static DISPLAY: Cell<*mut xlib::Display> = const { Cell::new(std::ptr::null_mut()) };
+        static NOTIFY_EVENT: Cell<i32> = const { Cell::new(0) };
+    }
+
+    DISPLAY.with(|display| unsafe {
+        if display.get().is_null() {
+            let dpy = xlib::XOpenDisplay(std::ptr::null());
+            if dpy.is_null() {
+                return None;
+            }
+            let (mut event_base, mut error_base) = (0, 0);
+            if xfixes::XFixesQueryExtension(dpy, &mut event_base, &mut error_base) == 0 {
+                xlib::XCloseDisplay(dpy);
+                return None;
+            }
+            let clipboard = xlib::XInternAtom(dpy, c"CLIPBOARD".as_ptr(), xlib::False);
+            xfixes::XFixesSelectSelectionInput(
+                dpy,
+                xlib::XDefaultRootWindow(dpy),
+                clipboard,
+                SET_SELECTION_OWNER_NOTIFY_MASK,
+            );
+            NOTIFY_EVENT.set(event_base + SELECTION_NOTIFY);
+            display.set(dpy);
+        }
+        let dpy = display.get();
+
+        let mut changes = 0;
+        while xlib::XPending(dpy) > 0 {
+            let mut event: xlib::XEvent = std::mem::zeroed();
+            xlib::XNextEvent(dpy, &mut event);
+            if event.get_type() == NOTIFY_EVENT.get() {
+                changes += 1;
+            }
+        }
+        Some(changes)
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn changes() -> Option<u32> {
+    None
+}
@@ -0,0 +1,181 @@
+use crate::config::Config;
+use crate::metrics;
+use crate::storage::{ROLLUPS_FILE, SESSIONS_FILE, SESSION_HEADERS};
+use crate::types::{Action, Session};
+use anyhow::Result;
+use chrono::{DateTime, Duration, Local};
+use csv::{ReaderBuilder, WriterBuilder};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fs::{self, OpenOptions},
+};
+
+/// Per-minute activity totals kept after raw mouse moves are dropped.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MinuteRollup {
+    pub session_id: String,
+    pub task_name: String,
+    /// Local minute, formatted `YYYY-MM-DDTHH:MM`.
+    pub minute: String,
+    pub mouse_moves: u64,
+    pub mouse_distance_px: f64,
+    pub key_events: u64,
+    pub other_events: u64,
+    /// Mouse button presses, also counted in `other_events`.
+    pub clicks: u64,
+    /// Configured derived metrics for the minute, as `name=value;...`.
+    pub derived: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CompactionReport {
+    pub sessions_compacted: usize,
+    pub mouse_moves_dropped: usize,
+    pub rollups_written: usize,
+}
+
+/// Rolls up and drops raw mouse moves from sessions that started more than
+/// `retention_days` ago, evaluating the configured metrics for each minute.
+/// Must not run while an `ActivityMonitor` holds the sessions file open,
+/// since the file is rewritten in place.
+pub fn compact(retention_days: i64, config: &Config) -> Result<CompactionReport> {
+    let cutoff = Local::now() - Duration::days(retention_days);
+    let mut report = CompactionReport::default();
+
+    let mut reader = ReaderBuilder::new()
+        .flexible(true)
+        .from_path(SESSIONS_FILE)?;
+    let tmp_path = format!("{}.tmp", SESSIONS_FILE);
+    // Older rows may have fewer columns than the current header.
+    let mut writer = WriterBuilder::new().flexible(true).from_path(&tmp_path)?;
+    writer.write_record(SESSION_HEADERS)?;
+
+    let mut rollups = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let session = Session::from_csv_record(&record)
+            .filter(|s| !s.mouse_path.is_empty())
+            .filter(|s| s.started_at().is_some_and(|t| t < cutoff));
+
+        // Rows that aren't compacted are copied through untouched.
+        let Some(mut session) = session else {
+            writer.write_record(&record)?;
+            continue;
+        };
+
+        for mut rollup in minute_rollups(&session) {
+            rollup.derived = metrics::evaluate(
+                &config.metrics,
+                &metrics::minute_variables(&rollup, &config.screen),
+            )
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join(";");
+            rollups.push(rollup);
+        }
+        report.sessions_compacted += 1;
+        report.mouse_moves_dropped += session.mouse_path.len();
+        session.mouse_path = Default::default();
+        writer.write_record(session.to_csv_record())?;
+    }
+    writer.flush()?;
+    drop(writer);
+
+    if report.sessions_compacted == 0 {
+        fs::remove_file(&tmp_path)?;
+        return Ok(report);
+    }
+
+    // Write rollups before replacing the sessions file so a failure can't
+    // lose the statistics for moves that are about to be dropped.
+    append_rollups(&rollups)?;
+    report.rollups_written = rollups.len();
+    fs::rename(&tmp_path, SESSIONS_FILE)?;
+    Ok(report)
+}
+
+pub fn minute_rollups(session: &Session) -> Vec<MinuteRollup> {
+    let mut minutes: BTreeMap<String, MinuteRollup> = BTreeMap::new();
+
+    let mut last: Option<(i32, i32)> = None;
+    for point in session.mouse_path.iter() {
+        let rollup = minute_bucket(&mut minutes, session, point.timestamp);
+        rollup.mouse_moves += 1;
+        if let Some((x, y)) = last {
+            let (dx, dy) = ((point.coords.0 - x) as f64, (point.coords.1 - y) as f64);
+            rollup.mouse_distance_px += (dx * dx + dy * dy).sqrt();
+        }
+        last = Some(point.coords);
+    }
+
+    for action in &session.actions {
+        let (timestamp, is_key) = match action {
+            Action::KeyPress { timestamp, .. } => (timestamp, true),
+            Action::MouseMove { timestamp, .. }
+            | Action::MouseDown { timestamp, .. }
+            | Action::MouseUp { timestamp, .. }
+            | Action::MouseClick { timestamp, .. }
+            | Action::CursorChange { timestamp, .. }
+            | Action::TaskSwitch { timestamp, .. }
+            | Action::Shortcut { timestamp, .. }
+            | Action::ClockChange { timestamp, .. }
+            | Action::LayoutChange { timestamp, .. }
+            | Action::CaptureInterrupted { timestamp, .. }
+            | Action::CaptureGap { timestamp, .. }
+            | Action::BreakReminder { timestamp, .. }
+            | Action::BreakTaken { timestamp, .. }
+            | Action::FocusChange { timestamp, .. }
+            | Action::Redacted { timestamp, .. }
+            | Action::Custom { timestamp, .. } => (timestamp, false),
+        };
+        let Ok(timestamp) = DateTime::parse_from_rfc3339(timestamp) else {
+            continue;
+        };
+        let rollup = minute_bucket(&mut minutes, session, timestamp.with_timezone(&Local));
+        if is_key {
+            rollup.key_events += 1;
+        } else {
+            rollup.other_events += 1;
+        }
+        // Counted like `analysis::click_stats`.
+        if matches!(action, Action::MouseDown { .. }) {
+            rollup.clicks += 1;
+        }
+    }
+
+    minutes.into_values().collect()
+}
+
+fn minute_bucket<'a>(
+    minutes: &'a mut BTreeMap<String, MinuteRollup>,
+    session: &Session,
+    timestamp: DateTime<Local>,
+) -> &'a mut MinuteRollup {
+    let minute = timestamp.format("%Y-%m-%dT%H:%M").to_string();
+    minutes
+        .entry(minute.clone())
+        .or_insert_with(|| MinuteRollup {
+            session_id: session.session_id.clone(),
+            task_name: session.task_name.clone(),
+            minute,
+            ..Default::default()
+        })
+}
+
+fn append_rollups(rollups: &[MinuteRollup]) -> Result<()> {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(ROLLUPS_FILE)?;
+    let is_new = file.metadata()?.len() == 0;
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(is_new)
+        .from_writer(file);
+    for rollup in rollups {
+        writer.serialize(rollup)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,778 @@
+use crate::bug_report::BugReportConfig;
+use crate::consent::ConsentConfig;
+use crate::contribute::ContributeConfig;
+use crate::ide::IdeConfig;
+use crate::lsl::LslConfig;
+use crate::metrics::DerivedMetric;
+use crate::policy::{self, Policy};
+use crate::privacy::PrivacyConfig;
+use crate::quality::QualityConfig;
+use crate::ring_buffer::RingBufferConfig;
+use crate::shortcuts::ShortcutMap;
+use crate::spool::SpoolConfig;
+use crate::storage::{self, CsvStorage, FolderStorage, Storage, SESSIONS_FILE};
+use crate::sync::SyncConfig;
+use crate::types::Session;
+use crate::updates::UpdateConfig;
+use anyhow::{bail, Result};
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const CONFIG_FILE: &str = "monitor_config.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Ask "What did you accomplish?" when a session is stopped.
+    pub prompt_session_notes: bool,
+    /// Seconds to count down after Start before recording, so there's time
+    /// to switch to another app. 0 starts immediately.
+    pub start_countdown_secs: u64,
+    /// Minimize the window once recording starts.
+    pub minimize_on_start: bool,
+    /// Turn on the desktop's Do Not Disturb mode while recording.
+    pub do_not_disturb: bool,
+    /// Folder sessions, the detailed log and summaries are written to,
+    /// set by "Choose new location…". Empty for the working directory.
+    pub data_dir: String,
+    pub daily_export: DailyExportConfig,
+    pub archive: ArchiveConfig,
+    pub spool: SpoolConfig,
+    pub schedule: ScheduleConfig,
+    pub capture: CaptureConfig,
+    pub api: ApiConfig,
+    pub ide: IdeConfig,
+    pub lsl: LslConfig,
+    pub shortcuts: ShortcutMap,
+    pub diagnostics: DiagnosticsConfig,
+    pub compaction: CompactionConfig,
+    pub templates: Vec<SessionTemplate>,
+    pub stats: StatsConfig,
+    pub calendar: CalendarConfig,
+    pub wellness: WellnessConfig,
+    pub screen: ScreenConfig,
+    pub privacy: PrivacyConfig,
+    pub quality: QualityConfig,
+    /// Derived metrics added to rollups, exports and reports.
+    pub metrics: Vec<DerivedMetric>,
+    /// Planned hours per task, burned down under Reports.
+    pub budgets: Vec<TaskBudget>,
+    pub billing: BillingConfig,
+    /// Alerts on long continuous stretches in an app or group of apps.
+    pub app_alerts: Vec<AppAlert>,
+    pub updates: UpdateConfig,
+    pub consent: ConsentConfig,
+    pub metadata: MetadataConfig,
+    pub trials: TrialConfig,
+    pub sync: SyncConfig,
+    pub ring_buffer: RingBufferConfig,
+    pub bug_report: BugReportConfig,
+    pub contribute: ContributeConfig,
+    /// Name of the profile last switched to, recorded on each session.
+    /// Empty when none is in use.
+    pub active_profile: String,
+    pub profiles: Vec<ConfigProfile>,
+    /// The administrator's policy, applied by `load` and never saved.
+    #[serde(skip)]
+    pub policy: Policy,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            prompt_session_notes: true,
+            start_countdown_secs: 0,
+            minimize_on_start: false,
+            do_not_disturb: false,
+            data_dir: String::new(),
+            daily_export: DailyExportConfig::default(),
+            archive: ArchiveConfig::default(),
+            spool: SpoolConfig::default(),
+            schedule: ScheduleConfig::default(),
+            capture: CaptureConfig::default(),
+            api: ApiConfig::default(),
+            ide: IdeConfig::default(),
+            lsl: LslConfig::default(),
+            shortcuts: ShortcutMap::default(),
+            diagnostics: DiagnosticsConfig::default(),
+            compaction: CompactionConfig::default(),
+            templates: SessionTemplate::defaults(),
+            stats: StatsConfig::default(),
+            calendar: CalendarConfig::default(),
+            wellness: WellnessConfig::default(),
+            screen: ScreenConfig::default(),
+            privacy: PrivacyConfig::default(),
+            quality: QualityConfig::default(),
+            metrics: Vec::new(),
+            budgets: Vec::new(),
+            billing: BillingConfig::default(),
+            app_alerts: Vec::new(),
+            updates: UpdateConfig::default(),
+            consent: ConsentConfig::default(),
+            metadata: MetadataConfig::default(),
+            trials: TrialConfig::default(),
+            sync: SyncConfig::default(),
+            ring_buffer: RingBufferConfig::default(),
+            bug_report: BugReportConfig::default(),
+            contribute: ContributeConfig::default(),
+            active_profile: String::new(),
+            profiles: Vec::new(),
+            policy: Policy::default(),
+        }
+    }
+}
+
+/// Named per-session overrides of the global settings, picked before a
+/// session starts. Unset fields fall back to the global config.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionTemplate {
+    pub name: String,
+    /// Pre-fills the task name when the template is selected.
+    pub task_name: Option<String>,
+    pub capture: Option<CaptureConfig>,
+    pub prompt_session_notes: Option<bool>,
+}
+
+impl SessionTemplate {
+    fn defaults() -> Vec<Self> {
+        vec![
+            SessionTemplate {
+                name: "Deep work".to_string(),
+                capture: Some(CaptureConfig {
+                    mouse_sample_rate: 10,
+                    ..Default::default()
+                }),
+                prompt_session_notes: Some(true),
+                ..Default::default()
+            },
+            SessionTemplate {
+                name: "Usability test".to_string(),
+                capture: Some(CaptureConfig::default()),
+                prompt_session_notes: Some(false),
+                ..Default::default()
+            },
+        ]
+    }
+}
+
+/// A named set of capture, privacy and export settings to switch between
+/// without restarting, e.g. a strictly redacted "Study" profile and an
+/// unredacted "Personal" one.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfigProfile {
+    pub name: String,
+    pub capture: CaptureConfig,
+    pub privacy: PrivacyConfig,
+    pub daily_export: DailyExportConfig,
+}
+
+/// Fields describing the subject of an experiment, e.g. `participant_id`,
+/// `condition` and `group`, filled in before recording and stored on each
+/// session.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MetadataConfig {
+    /// Field names, in the order they're asked for.
+    pub fields: Vec<String>,
+    /// Current values by field, kept for the next session. Set in the
+    /// window or with `desk-monitor meta`.
+    pub values: BTreeMap<String, String>,
+    /// Refuse to record until every field has a value.
+    pub required: bool,
+}
+
+impl MetadataConfig {
+    /// The values sessions are labelled with: non-empty ones, trimmed.
+    pub fn session_values(&self) -> BTreeMap<String, String> {
+        self.values
+            .iter()
+            .filter(|(_, value)| !value.trim().is_empty())
+            .map(|(field, value)| (field.clone(), value.trim().to_string()))
+            .collect()
+    }
+
+    /// The first field without a value, if any.
+    pub fn missing(&self) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|field| self.values.get(*field).is_none_or(|v| v.trim().is_empty()))
+            .map(String::as_str)
+    }
+}
+
+/// Hours planned for a task, e.g. a freelance estimate being billed
+/// against.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TaskBudget {
+    pub task_name: String,
+    pub planned_hours: f64,
+}
+
+/// Alert once focus has stayed on any of `apps` for `minutes` in a row
+/// while recording. `apps` are application names as the window system
+/// reports them (`WM_CLASS` on Linux), compared ignoring case; `name`
+/// labels the alert, e.g. a category like "Social".
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppAlert {
+    pub name: String,
+    pub apps: Vec<String>,
+    pub minutes: u64,
+}
+
+impl AppAlert {
+    pub fn matches(&self, app: &str) -> bool {
+        self.apps.iter().any(|a| a.trim().eq_ignore_ascii_case(app))
+    }
+}
+
+/// Automatic start/stop rules, checked by `scheduler::spawn_schedule`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScheduleConfig {
+    pub enabled: bool,
+    /// Minutes before an automatic start or stop to show a prompt, with the
+    /// option to skip it. 0 turns the prompt off.
+    pub notify_minutes: u32,
+    pub rules: Vec<ScheduleRule>,
+}
+
+impl Default for ScheduleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            notify_minutes: 5,
+            rules: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScheduleAction {
+    Start,
+    Stop,
+}
+
+/// Start or stop recording at `time` (`HH:MM`) on each of `days`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScheduleRule {
+    pub action: ScheduleAction,
+    pub time: String,
+    pub days: Vec<Weekday>,
+    /// Task to record when starting; empty keeps the current task name.
+    pub task: String,
+}
+
+impl Default for ScheduleRule {
+    fn default() -> Self {
+        Self {
+            action: ScheduleAction::Start,
+            time: "09:00".to_string(),
+            days: vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+            ],
+            task: String::new(),
+        }
+    }
+}
+
+/// Which event types the capture loop records. Checked on every tick, so
+/// changes apply to a running session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaptureConfig {
+    pub keyboard: bool,
+    pub mouse_moves: bool,
+    /// Mouse button presses and releases, with how long each was held,
+    /// other than the scroll wheel's.
+    pub mouse_buttons: bool,
+    /// Scroll wheel steps, recorded as presses of the `scroll_up` and
+    /// `scroll_down` buttons where the platform reports them that way.
+    pub scrolls: bool,
+    /// Record 1 in N mouse moves (key events are always kept). Applied when
+    /// a session starts and stored on it.
+    pub mouse_sample_rate: u32,
+    /// Record which window has keyboard focus when it changes.
+    pub focus_changes: bool,
+    /// Record that something was copied or cut to the clipboard (never
+    /// what), where supported.
+    pub clipboard: bool,
+    /// Also record the focused window's position and size with each focus
+    /// change.
+    pub window_geometry: bool,
+    /// Also record the focused window's title with each focus change,
+    /// after the redaction rules, where supported.
+    pub window_titles: bool,
+    /// Record the cursor shape (text beam, pointer, resize...) when it
+    /// changes under the moving or clicking pointer, where supported.
+    pub cursor_shapes: bool,
+    /// Record the element (role, name, app) under the pointer with each
+    /// mouse button press, where supported.
+    pub click_targets: bool,
+    /// Drop key events while the monitor's own window has focus and mouse
+    /// moves and clicks while the pointer is over it.
+    pub ignore_self: bool,
+}
+
+impl CaptureConfig {
+    /// Whether presses of mouse `button` (a `button_pressed` index) are
+    /// recorded: the scroll wheel's under `scrolls`, the rest under
+    /// `mouse_buttons`.
+    pub fn records_button(&self, button: usize) -> bool {
+        if crate::monitor::is_scroll(button) {
+            self.scrolls
+        } else {
+            self.mouse_buttons
+        }
+    }
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        Self {
+            keyboard: true,
+            mouse_moves: true,
+            mouse_buttons: true,
+            scrolls: true,
+            mouse_sample_rate: 1,
+            focus_changes: false,
+            clipboard: true,
+            window_geometry: false,
+            window_titles: false,
+            cursor_shapes: false,
+            click_targets: false,
+            ignore_self: true,
+        }
+    }
+}
+
+/// Input is polled `active_poll_hz` times a second while it's changing and
+/// `idle_poll_hz` once it has been still for a couple of seconds.
+/// Low-overhead mode: when the monitor's own CPU use exceeds the threshold,
+/// input is polled at most every `throttled_poll_ms`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiagnosticsConfig {
+    pub active_poll_hz: u32,
+    pub idle_poll_hz: u32,
+    pub low_overhead_mode: bool,
+    pub cpu_threshold_percent: f32,
+    pub throttled_poll_ms: u64,
+}
+
+impl Default for DiagnosticsConfig {
+    fn default() -> Self {
+        Self {
+            active_poll_hz: 60,
+            idle_poll_hz: 10,
+            low_overhead_mode: false,
+            cpu_threshold_percent: 5.0,
+            throttled_poll_ms: 100,
+        }
+    }
+}
+
+/// Startup maintenance that replaces raw mouse moves older than
+/// `retention_days` with per-minute rollups.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompactionConfig {
+    pub enabled: bool,
+    pub retention_days: i64,
+}
+
+impl Default for CompactionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            retention_days: 30,
+        }
+    }
+}
+
+/// Streak and badge rules for the dashboard stats.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatsConfig {
+    /// Minimum tracked time for a day to count towards a streak.
+    pub streak_min_minutes: u32,
+}
+
+impl Default for StatsConfig {
+    fn default() -> Self {
+        Self {
+            streak_min_minutes: 30,
+        }
+    }
+}
+
+/// How weeks are laid out in reports and the heat map.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CalendarConfig {
+    pub first_day_of_week: Weekday,
+    /// Days normally worked; time tracked on other days is reported as
+    /// outside the workweek.
+    pub workweek: Vec<Weekday>,
+}
+
+impl Default for CalendarConfig {
+    fn default() -> Self {
+        Self {
+            first_day_of_week: Weekday::Mon,
+            workweek: vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+            ],
+        }
+    }
+}
+
+impl CalendarConfig {
+    /// The seven weekdays, starting with `first_day_of_week`.
+    pub fn weekdays(&self) -> impl Iterator<Item = Weekday> {
+        std::iter::successors(Some(self.first_day_of_week), |day| Some(day.succ())).take(7)
+    }
+
+    /// First day of the week containing `date`.
+    pub fn week_start(&self, date: NaiveDate) -> NaiveDate {
+        date - Days::new(u64::from(date.weekday().days_since(self.first_day_of_week)))
+    }
+
+    pub fn is_workday(&self, day: Weekday) -> bool {
+        self.workweek.contains(&day)
+    }
+}
+
+/// Defaults for `invoice` exports.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BillingConfig {
+    /// Used when `invoice` isn't given `--rate`.
+    pub hourly_rate: f64,
+    pub currency: String,
+    /// Each session's billed time is rounded to a multiple of this many
+    /// minutes, in the `rounding` direction. 0 bills the exact time.
+    pub round_to_minutes: u32,
+    pub rounding: RoundingMode,
+    /// Sessions shorter than this (after rounding) are billed as this long.
+    pub minimum_minutes: u32,
+}
+
+impl Default for BillingConfig {
+    fn default() -> Self {
+        Self {
+            hourly_rate: 0.0,
+            currency: "USD".to_string(),
+            round_to_minutes: 0,
+            rounding: RoundingMode::Nearest,
+            minimum_minutes: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RoundingMode {
+    #[default]
+    Nearest,
+    Up,
+    Down,
+}
+
+/// Break reminders during long stretches of continuous activity.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WellnessConfig {
+    pub enabled: bool,
+    /// Remind after this much activity without a full break.
+    pub remind_after_minutes: u64,
+    /// Idle time that counts as a full break.
+    pub break_minutes: u64,
+    pub micro_breaks: bool,
+    /// Suggest a micro-break after this much activity without one.
+    pub micro_break_every_minutes: u64,
+    /// Idle time that counts as a micro-break.
+    pub micro_break_secs: u64,
+}
+
+impl Default for WellnessConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            remind_after_minutes: 50,
+            break_minutes: 5,
+            micro_breaks: true,
+            micro_break_every_minutes: 20,
+            micro_break_secs: 20,
+        }
+    }
+}
+
+/// Physical size of the screen, so mouse distance can be compared across
+/// machines with different resolutions. Set `dpi`, or the screen's width in
+/// both pixels and millimetres.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScreenConfig {
+    pub dpi: Option<f64>,
+    pub width_px: Option<u32>,
+    pub width_mm: Option<f64>,
+    pub unit: DistanceUnit,
+}
+
+impl ScreenConfig {
+    /// Pixels per inch, if the screen is configured.
+    pub fn dpi(&self) -> Option<f64> {
+        self.dpi
+            .or_else(|| Some(self.width_px? as f64 * 25.4 / self.width_mm?))
+            .filter(|dpi| dpi.is_finite() && *dpi > 0.0)
+    }
+
+    /// Converts a distance in pixels to `unit`.
+    pub fn physical_distance(&self, px: f64) -> Option<f64> {
+        let inches = px / self.dpi()?;
+        Some(match self.unit {
+            DistanceUnit::Cm => inches * 2.54,
+            DistanceUnit::In => inches,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DistanceUnit {
+    #[default]
+    Cm,
+    In,
+}
+
+impl DistanceUnit {
+    pub fn label(self) -> &'static str {
+        match self {
+            DistanceUnit::Cm => "cm",
+            DistanceUnit::In => "in",
+        }
+    }
+}
+
+/// Local HTTP query API over stored sessions. Only binds to 127.0.0.1.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiConfig {
+    pub enabled: bool,
+    pub port: u16,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 7878,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    Xlsx,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+            ExportFormat::Xlsx => "xlsx",
+        }
+    }
+}
+
+/// Fixed-length recordings for lab protocols: started by a hotkey, the API
+/// or the window, they stop by themselves after `duration_secs` and export
+/// the session to `directory`. Also the hotkey marking trials within a
+/// session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrialConfig {
+    pub duration_secs: u64,
+    /// Chord that starts a trial from any app, e.g. `Ctrl+Alt+T`, watched
+    /// for while nothing is being recorded. Empty turns it off.
+    pub hotkey: String,
+    pub directory: String,
+    pub format: ExportFormat,
+    /// Chord that starts a trial within the current session, or ends the
+    /// open one, while recording. Empty turns it off.
+    pub mark_hotkey: String,
+}
+
+impl Default for TrialConfig {
+    fn default() -> Self {
+        Self {
+            duration_secs: 300,
+            hotkey: String::new(),
+            directory: "trials".to_string(),
+            format: ExportFormat::Csv,
+            mark_hotkey: String::new(),
+        }
+    }
+}
+
+/// Exports the previous day's sessions once a day for nightly ETL jobs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DailyExportConfig {
+    pub enabled: bool,
+    /// Local time of day in `HH:MM` after which the export runs.
+    pub time: String,
+    pub directory: String,
+    pub format: ExportFormat,
+}
+
+impl Default for DailyExportConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            time: "00:30".to_string(),
+            directory: "exports".to_string(),
+            format: ExportFormat::Csv,
+        }
+    }
+}
+
+/// Keeps every saved session in its own file, in a folder per ISO week
+/// (see [`crate::storage::FolderStorage`]), instead of in
+/// `monitoring_sessions.csv`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArchiveConfig {
+    pub enabled: bool,
+    /// Under [`Config::data_dir`].
+    pub directory: String,
+}
+
+impl Default for ArchiveConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            directory: "data".to_string(),
+        }
+    }
+}
+
+impl Config {
+    /// Loads the config file, falling back to defaults if it doesn't exist,
+    /// with the system policy applied if there is one.
+    pub fn load() -> Result<Self> {
+        let mut config: Self = match fs::read_to_string(CONFIG_FILE) {
+            Ok(contents) => serde_json::from_str(&contents)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(e.into()),
+        };
+        if let Some(policy) = Policy::load(&policy::system_path())? {
+            policy.apply(&mut config);
+        }
+        Ok(config)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        fs::write(CONFIG_FILE, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// `monitoring_sessions.csv` in [`Config::data_dir`].
+    pub fn sessions_path(&self) -> PathBuf {
+        Path::new(&self.data_dir).join(SESSIONS_FILE)
+    }
+
+    /// Where sessions are stored: the week folders when
+    /// [`ArchiveConfig::enabled`], otherwise [`Config::sessions_path`].
+    /// `storage::load_dataset` reads either.
+    pub fn sessions_source(&self) -> PathBuf {
+        if self.archive.enabled {
+            Path::new(&self.data_dir).join(&self.archive.directory)
+        } else {
+            self.sessions_path()
+        }
+    }
+
+    /// Opens the storage sessions are saved to.
+    pub fn open_storage(&self) -> Result<Box<dyn Storage>> {
+        Ok(if self.archive.enabled {
+            Box::new(FolderStorage::new(self.sessions_source()))
+        } else {
+            Box::new(CsvStorage::open(self.sessions_path())?)
+        })
+    }
+
+    /// Every stored session, oldest first, without opening the storage for
+    /// writing. Nothing stored yet is no sessions.
+    pub fn stored_sessions(&self) -> Result<Vec<Session>> {
+        if self.archive.enabled {
+            FolderStorage::new(self.sessions_source()).load()
+        } else if self.sessions_path().exists() {
+            storage::load_sessions(self.sessions_path())
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    /// Saves the current settings as profile `name`, replacing one with
+    /// that name, and makes it the active profile.
+    pub fn save_profile(&mut self, name: &str) {
+        let profile = ConfigProfile {
+            name: name.to_string(),
+            capture: self.capture.clone(),
+            privacy: self.privacy.clone(),
+            daily_export: self.daily_export.clone(),
+        };
+        match self.profiles.iter_mut().find(|p| p.name == name) {
+            Some(existing) => *existing = profile,
+            None => self.profiles.push(profile),
+        }
+        self.active_profile = name.to_string();
+    }
+
+    /// Applies profile `name`'s settings. Changes made since the last
+    /// switch are saved to the profile that was active first.
+    pub fn switch_profile(&mut self, name: &str) -> Result<()> {
+        let Some(profile) = self.profiles.iter().find(|p| p.name == name).cloned() else {
+            bail!("no profile named \"{}\"", name);
+        };
+        if self.profiles.iter().any(|p| p.name == self.active_profile) {
+            self.save_profile(&self.active_profile.clone());
+        }
+        self.capture = profile.capture;
+        self.privacy = profile.privacy;
+        self.daily_export = profile.daily_export;
+        self.active_profile = profile.name;
+        self.policy.clone().apply(self);
+        Ok(())
+    }
+
+    /// Deletes profile `name`, leaving the current settings as they are.
+    pub fn remove_profile(&mut self, name: &str) {
+        self.profiles.retain(|p| p.name != name);
+        if self.active_profile == name {
+            self.active_profile.clear();
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! Participant consent for research use. When required, recording can't
+//! start until the participant has accepted the current version of the
+//! consent text; each acceptance is appended to [`CONSENT_FILE`] and attached
+//! to every session recorded under it.
+
+use crate::types::format_timestamp;
+use anyhow::Result;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::OpenOptions;
+use std::path::Path;
+
+/// Every acceptance, oldest first. Only ever appended to.
+pub const CONSENT_FILE: &str = "consent_log.csv";
+
+/// Off by default. Studies usually set it through the policy file so
+/// participants can't turn it off.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConsentConfig {
+    pub required: bool,
+    /// Shown on the consent screen.
+    pub text: String,
+    /// Changing it asks everyone to consent again.
+    pub version: String,
+}
+
+impl Default for ConsentConfig {
+    fn default() -> Self {
+        Self {
+            required: false,
+            text: "This app records your keyboard and mouse activity while you \
+                   record a task, for research. Key names, mouse positions and \
+                   task names are stored on this computer."
+                .to_string(),
+            version: "1".to_string(),
+        }
+    }
+}
+
+impl ConsentConfig {
+    /// SHA-256 of the text, logged so an edit made without changing the
+    /// version can be spotted.
+    pub fn text_sha256(&self) -> String {
+        format!("{:x}", Sha256::digest(self.text.as_bytes()))
+    }
+}
+
+/// One acceptance of the consent text.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConsentRecord {
+    pub version: String,
+    pub text_sha256: String,
+    pub accepted_at: String,
+    pub participant_id: String,
+}
+
+/// Every acceptance logged in `path`, oldest first.
+pub fn load(path: impl AsRef<Path>) -> Result<Vec<ConsentRecord>> {
+    let path = path.as_ref();
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let mut reader = csv::Reader::from_path(path)?;
+    let records = reader.deserialize().collect::<Result<Vec<_>, _>>()?;
+    Ok(records)
+}
+
+/// The latest acceptance of `config`'s current version, if any.
+pub fn accepted(path: impl AsRef<Path>, config: &ConsentConfig) -> Result<Option<ConsentRecord>> {
+    Ok(load(path)?
+        .into_iter()
+        .rev()
+        .find(|record| record.version == config.version))
+}
+
+/// Logs that `participant_id` accepted `config`'s text now.
+pub fn accept(
+    path: impl AsRef<Path>,
+    config: &ConsentConfig,
+    participant_id: &str,
+) -> Result<ConsentRecord> {
+    let path = path.as_ref();
+    let record = ConsentRecord {
+        version: config.version.clone(),
+        text_sha256: config.text_sha256(),
+        accepted_at: format_timestamp(Local::now()),
+        participant_id: participant_id.trim().to_string(),
+    };
+    let is_new = !path.exists();
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(is_new)
+        .from_writer(file);
+    writer.serialize(&record)?;
+    writer.flush()?;
+    Ok(record)
+}
@@ -0,0 +1,191 @@
+//! Opt-in contributions to a public research dataset. Every few days the
+//! sessions recorded since the last contribution are summed into a handful
+//! of totals (no events, task names, ids or times of day), shown in full
+//! for review, and only sent to the configured endpoint once accepted.
+//!
+//! Each contribution sent is kept in `directory` exactly as it was sent,
+//! which is also how the next one knows which days it covers.
+
+use crate::analysis;
+use crate::keystroke;
+use crate::types::Session;
+use crate::updates::{self, CURRENT_VERSION};
+use anyhow::{bail, Result};
+use chrono::{Days, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+/// Off by default, so nothing leaves the machine unless asked to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContributeConfig {
+    pub enabled: bool,
+    /// URL contributions are POSTed to as JSON.
+    pub endpoint: String,
+    /// Whole days each contribution covers.
+    pub interval_days: u32,
+    /// Where contributions are kept once sent.
+    pub directory: String,
+}
+
+impl Default for ContributeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoint: String::new(),
+            interval_days: 7,
+            directory: "contributions".to_string(),
+        }
+    }
+}
+
+/// Totals over whole days of sessions, as sent.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Contribution {
+    pub format: &'static str,
+    pub version: u32,
+    pub app_version: &'static str,
+    /// First and last day covered.
+    pub from: NaiveDate,
+    pub until: NaiveDate,
+    pub sessions: usize,
+    pub tracked_minutes: i64,
+    pub keystrokes: usize,
+    pub clicks: usize,
+    pub mouse_moves: usize,
+    pub mouse_distance_px: u64,
+}
+
+impl Contribution {
+    /// Totals over `sessions`, which are taken to cover `from` to `until`.
+    pub fn new<'a>(
+        sessions: impl IntoIterator<Item = &'a Session>,
+        from: NaiveDate,
+        until: NaiveDate,
+    ) -> Self {
+        let mut contribution = Self {
+            format: "desk-monitor-contribution",
+            version: 1,
+            app_version: CURRENT_VERSION,
+            from,
+            until,
+            sessions: 0,
+            tracked_minutes: 0,
+            keystrokes: 0,
+            clicks: 0,
+            mouse_moves: 0,
+            mouse_distance_px: 0,
+        };
+        let mut tracked_ms = 0;
+        let mut distance = 0.0;
+        for session in sessions {
+            contribution.sessions += 1;
+            tracked_ms += session.duration().map_or(0, |d| d.num_milliseconds());
+            contribution.keystrokes += keystroke::keystrokes(session).len();
+            contribution.clicks += analysis::click_stats(session).clicks;
+            contribution.mouse_moves += session.mouse_path.len();
+            distance += analysis::mouse_distance_px(session);
+        }
+        contribution.tracked_minutes = tracked_ms / 60_000;
+        contribution.mouse_distance_px = distance.round() as u64;
+        contribution
+    }
+
+    /// The request body: what's previewed is exactly what's sent and kept.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    fn file_name(&self) -> String {
+        format!("contribution_{}.json", self.until.format("%Y-%m-%d"))
+    }
+}
+
+/// Last day covered by a contribution already sent, from the files kept in
+/// `directory`.
+pub fn last_sent(config: &ContributeConfig) -> Option<NaiveDate> {
+    fs::read_dir(&config.directory)
+        .ok()?
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name().into_string().ok()?;
+            let day = name.strip_prefix("contribution_")?.strip_suffix(".json")?;
+            NaiveDate::parse_from_str(day, "%Y-%m-%d").ok()
+        })
+        .max()
+}
+
+/// The contribution to offer `today`, if contributing is on, at least
+/// `interval_days` whole days have passed since the last one (or, for the
+/// first, its days are over) and sessions were recorded on them. Today is
+/// left for the next one, since it isn't over.
+pub fn pending(
+    config: &ContributeConfig,
+    sessions: &[Session],
+    today: NaiveDate,
+) -> Option<Contribution> {
+    if !config.enabled || config.endpoint.trim().is_empty() || config.interval_days == 0 {
+        return None;
+    }
+    let until = today.pred_opt()?;
+    let from = match last_sent(config) {
+        Some(last) => last.succ_opt()?,
+        None => until.checked_sub_days(Days::new(u64::from(config.interval_days) - 1))?,
+    };
+    if (until - from).num_days() + 1 < i64::from(config.interval_days) {
+        return None;
+    }
+    let covered: Vec<&Session> = sessions
+        .iter()
+        .filter(|session| {
+            session
+                .started_at()
+                .is_some_and(|t| (from..=until).contains(&t.date_naive()))
+        })
+        .collect();
+    if covered.is_empty() {
+        return None;
+    }
+    Some(Contribution::new(covered, from, until))
+}
+
+/// POSTs `contribution` to the endpoint, then keeps it in `directory`.
+/// Only contributions that were sent are kept, so a failed one is offered
+/// again. Returns the path kept.
+pub fn send(config: &ContributeConfig, contribution: &Contribution) -> Result<PathBuf> {
+    let endpoint = config.endpoint.trim();
+    if !endpoint.starts_with("https://") && !endpoint.starts_with("http://") {
+        bail!("the contribution endpoint must be an http(s) URL");
+    }
+    fs::create_dir_all(&config.directory)?;
+    let path = Path::new(&config.directory).join(contribution.file_name());
+    let body = path.with_extension("json.sending");
+    fs::write(&body, contribution.to_json()?)?;
+    let sent = updates::curl(&[
+        "--header",
+        "Content-Type: application/json",
+        "--data-binary",
+        &format!("@{}", body.display()),
+        endpoint,
+    ]);
+    if let Err(e) = sent {
+        let _ = fs::remove_file(&body);
+        return Err(e);
+    }
+    fs::rename(&body, &path)?;
+    Ok(path)
+}
+
+/// Sends in the background, delivering one result.
+pub fn spawn_send(
+    config: ContributeConfig,
+    contribution: Contribution,
+) -> Receiver<Result<PathBuf>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = sender.send(send(&config, &contribution));
+    });
+    receiver
+}
@@ -0,0 +1,211 @@
+//! Crash reports: a panic hook that writes what was going on (the panic,
+//! a backtrace, the last status line and a summary of the settings) to
+//! `crash_reports/` before the app goes away, so the next launch can offer
+//! to open it.
+//!
+//! The settings summary only has switches and numbers: no task names,
+//! redaction patterns, endpoints, metadata or profile names.
+
+use crate::config::Config;
+use crate::portable;
+use crate::types::format_timestamp;
+use anyhow::Result;
+use chrono::Local;
+use std::backtrace::Backtrace;
+use std::cell::Cell;
+use std::fs;
+use std::panic::{self, UnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Where reports are written, relative to the data directory.
+pub const DIRECTORY: &str = "crash_reports";
+/// Holds the file name of the newest report already offered.
+const SEEN_FILE: &str = "last_seen";
+
+static STATUS: Mutex<String> = Mutex::new(String::new());
+static CONFIG_SUMMARY: Mutex<String> = Mutex::new(String::new());
+
+thread_local! {
+    /// Set while [`catch_expected`] runs, so the hook leaves its panics be.
+    static EXPECTED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Runs `f`, catching a panic the caller handles (a lost input device,
+/// say) without it being reported as a crash.
+pub fn catch_expected<R>(f: impl FnOnce() -> R + UnwindSafe) -> std::thread::Result<R> {
+    let outer = EXPECTED.replace(true);
+    let result = panic::catch_unwind(f);
+    EXPECTED.set(outer);
+    result
+}
+
+/// Keeps `status` as the last status line for a report, with the task
+/// name left out.
+pub fn record_status(status: &str, task_name: &str) {
+    let status = match task_name.trim() {
+        "" => status.to_string(),
+        task => status.replace(task, "[task]"),
+    };
+    *STATUS.lock().unwrap_or_else(|e| e.into_inner()) = status;
+}
+
+/// Keeps a summary of `config` for a report.
+pub fn record_config(config: &Config) {
+    *CONFIG_SUMMARY.lock().unwrap_or_else(|e| e.into_inner()) = config_summary(config);
+}
+
+fn config_summary(config: &Config) -> String {
+    let capture = &config.capture;
+    [
+        format!(
+            "capture: keyboard={} mouse_moves={} mouse_buttons={} scrolls={} \
+             mouse_sample_rate={} focus_changes={} clipboard={} window_geometry={} \
+             window_titles={} cursor_shapes={} click_targets={} ignore_self={}",
+            capture.keyboard,
+            capture.mouse_moves,
+            capture.mouse_buttons,
+            capture.scrolls,
+            capture.mouse_sample_rate,
+            capture.focus_changes,
+            capture.clipboard,
+            capture.window_geometry,
+            capture.window_titles,
+            capture.cursor_shapes,
+            capture.click_targets,
+            capture.ignore_self
+        ),
+        format!(
+            "privacy: redaction_rules={} hide_keys={} mouse_grid_px={}",
+            config.privacy.redact.len(),
+            config.privacy.hide_keys,
+            config.privacy.mouse_grid_px
+        ),
+        format!(
+            "api: enabled={} port={}",
+            config.api.enabled, config.api.port
+        ),
+        format!(
+            "daily_export: enabled={} format={}",
+            config.daily_export.enabled,
+            config.daily_export.format.extension()
+        ),
+        format!(
+            "schedule: enabled={} rules={}",
+            config.schedule.enabled,
+            config.schedule.rules.len()
+        ),
+        format!(
+            "lsl={} ring_buffer_secs={} contribute={} update_checks={}",
+            config.lsl.enabled,
+            config.ring_buffer.seconds,
+            config.contribute.enabled,
+            config.updates.check_at_startup
+        ),
+        format!(
+            "profiles={} profile_active={} templates={} portable={}",
+            config.profiles.len(),
+            !config.active_profile.is_empty(),
+            config.templates.len(),
+            portable::is_active()
+        ),
+    ]
+    .join("\n")
+}
+
+/// The report for a panic with `message` at `location`.
+pub fn report(message: &str, location: &str, thread: &str, backtrace: &str) -> String {
+    let status = STATUS.lock().map(|s| s.clone()).unwrap_or_default();
+    let config = CONFIG_SUMMARY.lock().map(|s| s.clone()).unwrap_or_default();
+    format!(
+        "desk-monitor {} crashed at {}\n\
+         Platform: {} {}\n\
+         Thread: {}\n\
+         Panic: {}\n\
+         Location: {}\n\
+         Last status: {}\n\
+         \n\
+         Settings:\n{}\n\
+         \n\
+         Backtrace:\n{}\n",
+        crate::updates::CURRENT_VERSION,
+        format_timestamp(Local::now()),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        thread,
+        message,
+        location,
+        status,
+        config,
+        backtrace
+    )
+}
+
+/// Writes `report` to a new timestamped file in `dir`.
+pub fn write_report(dir: &Path, report: &str) -> Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let stamp = Local::now().format("%Y%m%d_%H%M%S");
+    let mut path = dir.join(format!("crash_{}.txt", stamp));
+    // Panics on several threads at once get a file each.
+    let mut n = 1;
+    while path.exists() {
+        n += 1;
+        path = dir.join(format!("crash_{}_{}.txt", stamp, n));
+    }
+    fs::write(&path, report)?;
+    Ok(path)
+}
+
+/// Writes a report to [`DIRECTORY`] on any panic outside
+/// [`catch_expected`], then carries on as the previous hook would (printing
+/// the panic). Call [`record_config`] and [`record_status`] to fill it in.
+pub fn install(config: &Config) {
+    record_config(config);
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if EXPECTED.get() {
+            previous(info);
+            return;
+        }
+        let payload = info.payload();
+        let message = payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("(no message)");
+        let location = info
+            .location()
+            .map_or_else(|| "unknown".to_string(), ToString::to_string);
+        let thread = std::thread::current();
+        let backtrace = Backtrace::force_capture().to_string();
+        let report = report(
+            message,
+            &location,
+            thread.name().unwrap_or("unnamed"),
+            &backtrace,
+        );
+        match write_report(Path::new(DIRECTORY), &report) {
+            Ok(path) => eprintln!("Crash report written to {}", path.display()),
+            Err(e) => eprintln!("Error writing crash report: {}", e),
+        }
+        previous(info);
+    }));
+}
+
+/// The newest report in `dir` that hasn't been offered yet.
+pub fn unseen(dir: &Path) -> Option<PathBuf> {
+    let seen = fs::read_to_string(dir.join(SEEN_FILE)).unwrap_or_default();
+    let newest = fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| name.starts_with("crash_") && name.ends_with(".txt"))
+        .max()?;
+    (newest.as_str() > seen.trim()).then(|| dir.join(newest))
+}
+
+/// Notes `report` as offered, so [`unseen`] skips it and older ones.
+pub fn mark_seen(dir: &Path, report: &Path) -> Result<()> {
+    let name = report.file_name().unwrap_or_default().to_string_lossy();
+    fs::write(dir.join(SEEN_FILE), name.as_bytes())?;
+    Ok(())
+}
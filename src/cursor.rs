@@ -0,0 +1,78 @@
+/// Shape of the mouse cursor, where supported: `"text"`, `"pointer"`,
+/// `"arrow"`, `"resize_ew"`, `"resize_ns"`, `"resize_nwse"`,
+/// `"resize_nesw"`, `"move"`, `"wait"`, `"crosshair"`, `"not_allowed"` or
+/// `"other"` for anything else.
+///
+/// On Linux this is the name the focused application gave the cursor, read
+/// with XFixes. Cursors set from an image rather than by name, which some
+/// toolkits and games do, come out as `"other"`.
+#[cfg(target_os = "linux")]
+pub fn current_shape() -> Option<&'static str> {
+    use std::cell::Cell;
+    use std::ffi::CStr;
+    use x11::{xfixes, xlib};
+
+    thread_local! {
+        static DISPLAY: Cell<*mut xlib::Display> = const { Cell::new(std::ptr::null_mut()) };
+    }
+
+    DISPLAY.with(|display| unsafe {
+        if display.get().is_null() {
+            display.set(xlib::XOpenDisplay(std::ptr::null()));
+        }
+        let dpy = display.get();
+        if dpy.is_null() {
+            return None;
+        }
+        let (mut event_base, mut error_base) = (0, 0);
+        if xfixes::XFixesQueryExtension(dpy, &mut event_base, &mut error_base) == 0 {
+            return None;
+        }
+
+        let image = xfixes::XFixesGetCursorImage(dpy);
+        if image.is_null() {
+            return None;
+        }
+        let name = (*image).name;
+        let shape = if name.is_null() {
+            "other"
+        } else {
+            shape_for_name(&CStr::from_ptr(name).to_string_lossy())
+        };
+        xlib::XFree(image.cast());
+        Some(shape)
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn current_shape() -> Option<&'static str> {
+    None
+}
+
+/// Maps X cursor font and CSS cursor names, which cursor themes use, to a
+/// shape.
+#[cfg(target_os = "linux")]
+fn shape_for_name(name: &str) -> &'static str {
+    match name {
+        "left_ptr" | "default" | "arrow" | "top_left_arrow" => "arrow",
+        "xterm" | "text" | "ibeam" | "vertical-text" => "text",
+        "hand" | "hand1" | "hand2" | "pointer" | "pointing_hand" => "pointer",
+        "sb_h_double_arrow" | "h_double_arrow" | "ew-resize" | "col-resize" | "e-resize"
+        | "w-resize" | "left_side" | "right_side" | "split_h" => "resize_ew",
+        "sb_v_double_arrow" | "v_double_arrow" | "ns-resize" | "row-resize" | "n-resize"
+        | "s-resize" | "top_side" | "bottom_side" | "split_v" => "resize_ns",
+        "nwse-resize"
+        | "nw-resize"
+        | "se-resize"
+        | "top_left_corner"
+        | "bottom_right_corner"
+        | "size_fdiag" => "resize_nwse",
+        "nesw-resize" | "ne-resize" | "sw-resize" | "top_right_corner" | "bottom_left_corner"
+        | "size_bdiag" => "resize_nesw",
+        "fleur" | "move" | "all-scroll" | "grab" | "grabbing" | "size_all" => "move",
+        "watch" | "wait" | "progress" | "left_ptr_watch" => "wait",
+        "crosshair" | "cross" | "tcross" => "crosshair",
+        "not-allowed" | "no-drop" | "forbidden" | "crossed_circle" => "not_allowed",
+        _ => "other",
+    }
+}
@@ -0,0 +1,62 @@
+//! Sessions as polars `DataFrame`s, one row per recorded event in the order
+//! it happened, so analysis in Rust can start from stored sessions without
+//! parsing exports by hand. Needs the `polars` feature.
+
+use crate::analysis;
+use crate::storage::Storage;
+use crate::types::Session;
+use anyhow::Result;
+use polars::prelude::*;
+
+/// Columns of the frames built here.
+pub const COLUMNS: [&str; 6] = [
+    "session_id",
+    "task_name",
+    "timestamp",
+    "offset_ms",
+    "kind",
+    "payload",
+];
+
+impl Session {
+    /// The session's events as a frame with [`COLUMNS`]. `kind` and
+    /// `payload` are those of `Action::kind_and_payload`; `offset_ms` is
+    /// the time since the session started.
+    pub fn to_dataframe(&self) -> Result<DataFrame> {
+        events(std::slice::from_ref(self))
+    }
+}
+
+/// Every stored session's events in one frame, oldest session first.
+pub fn query_dataframe(storage: &dyn Storage) -> Result<DataFrame> {
+    events(&storage.load()?)
+}
+
+fn events(sessions: &[Session]) -> Result<DataFrame> {
+    let mut session_ids = Vec::new();
+    let mut task_names = Vec::new();
+    let mut timestamps = Vec::new();
+    let mut offsets = Vec::new();
+    let mut kinds = Vec::new();
+    let mut payloads = Vec::new();
+    for session in sessions {
+        for action in session.ordered_actions() {
+            let (kind, payload) = action.kind_and_payload();
+            session_ids.push(session.session_id.clone());
+            task_names.push(session.task_name.clone());
+            offsets.push(analysis::offset_ms(session, action.timestamp()));
+            timestamps.push(action.timestamp().to_string());
+            kinds.push(kind);
+            payloads.push(payload);
+        }
+    }
+    let [session_id, task_name, timestamp, offset_ms, kind, payload] = COLUMNS;
+    Ok(df!(
+        session_id => session_ids,
+        task_name => task_names,
+        timestamp => timestamps,
+        offset_ms => offsets,
+        kind => kinds,
+        payload => payloads,
+    )?)
+}
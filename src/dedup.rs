@@ -0,0 +1,132 @@
+//! Finding duplicate sessions and events when datasets are imported or
+//! merged, e.g. a session present in both the sessions file and a daily
+//! export, or events repeated by a replayed capture.
+
+use crate::analysis;
+use crate::types::{Action, Session};
+use chrono::DateTime;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+/// What de-duplication removed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DedupReport {
+    /// Extra copies of a session that were dropped.
+    pub duplicate_sessions: usize,
+    /// Of those, copies with events the kept copy lacked, which were added
+    /// to it.
+    pub reconciled_sessions: usize,
+    /// Events with the same type, timestamp and payload as an earlier one
+    /// in the same session.
+    pub duplicate_events: usize,
+}
+
+impl DedupReport {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl std::fmt::Display for DedupReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} duplicate sessions ({} reconciled), {} duplicate events",
+            self.duplicate_sessions, self.reconciled_sessions, self.duplicate_events
+        )
+    }
+}
+
+/// Drops events identical to an earlier one in `session`, returning how
+/// many were dropped.
+pub fn dedup_events(session: &mut Session) -> usize {
+    let actions = session.ordered_actions();
+    let before = actions.len();
+    let mut seen = HashSet::new();
+    let kept: Vec<Action> = actions
+        .into_iter()
+        .filter(|action| seen.insert(action.to_csv_string()))
+        .collect();
+    let dropped = before - kept.len();
+    if dropped > 0 {
+        session.replace_actions(kept);
+    }
+    dropped
+}
+
+/// Adds the events of `other`, a copy of the same session, that `kept`
+/// lacks, in timestamp order. The end time is extended and missing notes
+/// and machine id are filled in from `other`. Returns whether any events
+/// were added.
+pub fn reconcile(kept: &mut Session, other: &Session) -> bool {
+    let mut actions = kept.ordered_actions();
+    let seen: HashSet<String> = actions.iter().map(Action::to_csv_string).collect();
+    let before = actions.len();
+    actions.extend(
+        other
+            .ordered_actions()
+            .into_iter()
+            .filter(|action| !seen.contains(&action.to_csv_string())),
+    );
+    let added = actions.len() > before;
+    if added {
+        // Stable, so events at the same instant keep their recorded order.
+        actions.sort_by_key(|action| {
+            DateTime::parse_from_rfc3339(action.timestamp())
+                .ok()
+                .map(|t| t.timestamp_millis())
+        });
+        kept.replace_actions(actions);
+    }
+
+    if let Some(end) = &other.end_time {
+        let later = |a: &str, b: &str| match (
+            DateTime::parse_from_rfc3339(a),
+            DateTime::parse_from_rfc3339(b),
+        ) {
+            (Ok(a), Ok(b)) => a > b,
+            _ => false,
+        };
+        if kept.end_time.as_deref().is_none_or(|kept| later(end, kept)) {
+            kept.end_time = Some(end.clone());
+        }
+    }
+    if kept.notes.is_none() {
+        kept.notes = other.notes.clone();
+    }
+    if kept.machine_id.is_none() {
+        kept.machine_id = other.machine_id.clone();
+    }
+    if kept.density.is_some() {
+        kept.density = Some(analysis::event_density(kept));
+    }
+    added
+}
+
+/// Keeps the first of each group of sessions with the same `key`,
+/// reconciling the later copies into it, and drops duplicate events within
+/// every session. Order is otherwise preserved.
+pub fn dedup_sessions<K: Eq + Hash>(
+    sessions: Vec<Session>,
+    key: impl Fn(&Session) -> K,
+) -> (Vec<Session>, DedupReport) {
+    let mut report = DedupReport::default();
+    let mut index: HashMap<K, usize> = HashMap::new();
+    let mut kept: Vec<Session> = Vec::new();
+    for mut session in sessions {
+        report.duplicate_events += dedup_events(&mut session);
+        match index.get(&key(&session)) {
+            Some(&i) => {
+                report.duplicate_sessions += 1;
+                if reconcile(&mut kept[i], &session) {
+                    report.reconciled_sessions += 1;
+                }
+            }
+            None => {
+                index.insert(key(&session), kept.len());
+                kept.push(session);
+            }
+        }
+    }
+    (kept, report)
+}
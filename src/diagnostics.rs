@@ -0,0 +1,117 @@
+use crate::config::DiagnosticsConfig;
+use std::time::{Duration, Instant};
+
+const SAMPLE_WINDOW: Duration = Duration::from_secs(1);
+
+/// The monitor's own overhead over the last sample window.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DiagnosticsSnapshot {
+    /// Process CPU usage as a percentage of one core, where supported.
+    pub cpu_percent: Option<f32>,
+    /// Resident memory in bytes, where supported.
+    pub memory_bytes: Option<u64>,
+    pub events_per_sec: f32,
+    pub avg_write_latency: Duration,
+    /// Whether low-overhead mode is currently slowing down capture.
+    pub throttled: bool,
+}
+
+#[derive(Debug)]
+pub struct Diagnostics {
+    window_start: Instant,
+    window_events: u32,
+    window_write_time: Duration,
+    last_cpu: Option<(Instant, Duration)>,
+    snapshot: DiagnosticsSnapshot,
+}
+
+impl Default for Diagnostics {
+    fn default() -> Self {
+        Self {
+            window_start: Instant::now(),
+            window_events: 0,
+            window_write_time: Duration::ZERO,
+            last_cpu: None,
+            snapshot: DiagnosticsSnapshot::default(),
+        }
+    }
+}
+
+impl Diagnostics {
+    pub fn snapshot(&self) -> DiagnosticsSnapshot {
+        self.snapshot
+    }
+
+    /// Counts one written event and how long the write took.
+    pub fn record_write(&mut self, elapsed: Duration) {
+        self.window_events += 1;
+        self.window_write_time += elapsed;
+    }
+
+    /// Refreshes the snapshot once per sample window and updates the
+    /// low-overhead throttle from it.
+    pub fn tick(&mut self, config: &DiagnosticsConfig) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.window_start);
+        if elapsed < SAMPLE_WINDOW {
+            return;
+        }
+
+        let cpu_time = process_cpu_time();
+        let cpu_percent = match (self.last_cpu, cpu_time) {
+            (Some((last_at, last_cpu)), Some(cpu)) => {
+                let wall = now.duration_since(last_at).as_secs_f32();
+                Some((cpu.saturating_sub(last_cpu).as_secs_f32() / wall) * 100.0)
+            }
+            _ => None,
+        };
+        self.last_cpu = cpu_time.map(|cpu| (now, cpu));
+
+        // Hysteresis: throttle above the threshold, release below half of it.
+        let throttled = config.low_overhead_mode
+            && cpu_percent.is_some_and(|cpu| {
+                cpu > config.cpu_threshold_percent
+                    || (self.snapshot.throttled && cpu > config.cpu_threshold_percent / 2.0)
+            });
+
+        self.snapshot = DiagnosticsSnapshot {
+            cpu_percent,
+            memory_bytes: process_memory_bytes(),
+            events_per_sec: self.window_events as f32 / elapsed.as_secs_f32(),
+            avg_write_latency: self
+                .window_write_time
+                .checked_div(self.window_events)
+                .unwrap_or_default(),
+            throttled,
+        };
+        self.window_start = now;
+        self.window_events = 0;
+        self.window_write_time = Duration::ZERO;
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn process_cpu_time() -> Option<Duration> {
+    // First field is nanoseconds spent on the CPU.
+    let schedstat = std::fs::read_to_string("/proc/self/schedstat").ok()?;
+    let nanos = schedstat.split_whitespace().next()?.parse().ok()?;
+    Some(Duration::from_nanos(nanos))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn process_cpu_time() -> Option<Duration> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn process_memory_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmRSS:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn process_memory_bytes() -> Option<u64> {
+    None
+}
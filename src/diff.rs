@@ -0,0 +1,291 @@
+//! Compares two recordings of the same workflow, for QA: the steps of each
+//! (clicks, key presses, shortcuts, focus changes and custom events) are
+//! aligned like lines in a text diff, and whatever doesn't line up is
+//! reported as missing, extra or changed.
+//!
+//! Mouse moves aren't compared; only where clicks landed is.
+
+use crate::analysis;
+use crate::types::{Action, Session, WindowBounds};
+
+/// Clicks this far apart or closer count as the same click.
+pub const CLICK_TOLERANCE_PX: i32 = 20;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    /// Milliseconds since the session started.
+    pub offset_ms: i64,
+    pub kind: StepKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StepKind {
+    Click {
+        button: String,
+        coords: (i32, i32),
+    },
+    Key {
+        key: String,
+    },
+    Shortcut {
+        name: String,
+    },
+    /// Window IDs differ between runs, so windows are told apart by their
+    /// position and size when those were captured.
+    Focus {
+        bounds: Option<WindowBounds>,
+    },
+    Custom {
+        kind: String,
+    },
+}
+
+impl StepKind {
+    /// Whether `self` and `other` are the same step, though maybe with
+    /// different details (where a click landed, which window).
+    fn same_step(&self, other: &StepKind) -> bool {
+        match (self, other) {
+            (StepKind::Click { button: a, .. }, StepKind::Click { button: b, .. }) => a == b,
+            (StepKind::Key { key: a }, StepKind::Key { key: b }) => a == b,
+            (StepKind::Shortcut { name: a }, StepKind::Shortcut { name: b }) => a == b,
+            (StepKind::Focus { .. }, StepKind::Focus { .. }) => true,
+            (StepKind::Custom { kind: a }, StepKind::Custom { kind: b }) => a == b,
+            _ => false,
+        }
+    }
+
+    /// `click`, `key`, ... for counting steps by kind.
+    fn label(&self) -> &'static str {
+        match self {
+            StepKind::Click { .. } => "click",
+            StepKind::Key { .. } => "key",
+            StepKind::Shortcut { .. } => "shortcut",
+            StepKind::Focus { .. } => "focus change",
+            StepKind::Custom { .. } => "custom event",
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            StepKind::Click { button, coords } => {
+                format!("click {} at ({}, {})", button, coords.0, coords.1)
+            }
+            StepKind::Key { key } => format!("key {}", key),
+            StepKind::Shortcut { name } => format!("shortcut {}", name),
+            StepKind::Focus { bounds: Some(b) } => format!(
+                "focus window at ({}, {}) {}x{}",
+                b.x, b.y, b.width, b.height
+            ),
+            StepKind::Focus { bounds: None } => "focus change".to_string(),
+            StepKind::Custom { kind } => format!("`{}` event", kind),
+        }
+    }
+}
+
+/// How a step lined up with the other session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Entry {
+    /// The same step in both, at these indices into `a` and `b`.
+    Same(usize, usize),
+    /// The same step, but it went differently.
+    Changed(usize, usize, Change),
+    /// Only in `a`.
+    Missing(usize),
+    /// Only in `b`.
+    Extra(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    /// A click landed more than [`CLICK_TOLERANCE_PX`] away.
+    ClickedElsewhere,
+    /// Focus went to a window at a different position or of a different
+    /// size.
+    DifferentWindow,
+}
+
+impl Change {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Change::ClickedElsewhere => "clicked elsewhere",
+            Change::DifferentWindow => "different window",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    pub a: Vec<Step>,
+    pub b: Vec<Step>,
+    pub entries: Vec<Entry>,
+}
+
+impl Comparison {
+    /// Entries other than [`Entry::Same`].
+    pub fn divergences(&self) -> impl Iterator<Item = &Entry> {
+        self.entries
+            .iter()
+            .filter(|entry| !matches!(entry, Entry::Same(..)))
+    }
+
+    /// A Markdown report comparing `a` with `b`, the sessions compared.
+    pub fn to_markdown(&self, a: &Session, b: &Session) -> String {
+        let mut same = 0;
+        let (mut changed, mut missing, mut extra) = (Vec::new(), Vec::new(), Vec::new());
+        for entry in &self.entries {
+            match *entry {
+                Entry::Same(..) => same += 1,
+                Entry::Changed(_, _, change) => changed.push(change.as_str()),
+                Entry::Missing(i) => missing.push(self.a[i].kind.label()),
+                Entry::Extra(j) => extra.push(self.b[j].kind.label()),
+            }
+        }
+
+        let mut md = String::from("# Session comparison\n\n");
+        for (name, session, steps) in [("A", a, &self.a), ("B", b, &self.b)] {
+            md.push_str(&format!(
+                "- {}: {} ({}), {} steps\n",
+                name,
+                session.session_id,
+                session.task_name,
+                steps.len()
+            ));
+        }
+        md.push_str(&format!("- Same: {}\n", same));
+        md.push_str(&format!("- Changed: {}\n", counted(&changed)));
+        md.push_str(&format!("- Missing from B: {}\n", counted(&missing)));
+        md.push_str(&format!("- Extra in B: {}\n", counted(&extra)));
+
+        md.push_str("\n## Divergences\n\n");
+        if self.divergences().next().is_none() {
+            md.push_str("None: B follows A step for step.\n");
+            return md;
+        }
+        let cell = |step: &Step| {
+            format!(
+                "{} {}",
+                analysis::format_offset(step.offset_ms),
+                step.kind.describe()
+            )
+        };
+        md.push_str("| A | B | Difference |\n|---|---|---|\n");
+        for entry in self.divergences() {
+            let (in_a, in_b, difference) = match *entry {
+                Entry::Changed(i, j, change) => {
+                    (cell(&self.a[i]), cell(&self.b[j]), change.as_str())
+                }
+                Entry::Missing(i) => (cell(&self.a[i]), "-".to_string(), "missing from B"),
+                Entry::Extra(j) => ("-".to_string(), cell(&self.b[j]), "extra in B"),
+                Entry::Same(..) => continue,
+            };
+            md.push_str(&format!("| {} | {} | {} |\n", in_a, in_b, difference));
+        }
+        md
+    }
+}
+
+/// `n (x click, y key)` for a list of labels.
+fn counted(labels: &[&str]) -> String {
+    if labels.is_empty() {
+        return "0".to_string();
+    }
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for label in labels {
+        match counts.iter_mut().find(|(l, _)| l == label) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((label, 1)),
+        }
+    }
+    let parts: Vec<String> = counts
+        .iter()
+        .map(|(label, count)| format!("{} {}", count, label))
+        .collect();
+    format!("{} ({})", labels.len(), parts.join(", "))
+}
+
+/// The steps of `session` that are compared, in recording order.
+pub fn steps(session: &Session) -> Vec<Step> {
+    let mut steps = Vec::new();
+    let mut held: Vec<String> = Vec::new();
+    for action in &session.actions {
+        let offset_ms = analysis::offset_ms(session, action.timestamp());
+        let mut push = |kind| steps.push(Step { offset_ms, kind });
+        match action {
+            Action::MouseDown { button, coords, .. } => push(StepKind::Click {
+                button: button.clone(),
+                coords: *coords,
+            }),
+            Action::KeyPress { keys, .. } => {
+                // Key presses record every key held; a step is a key going
+                // down.
+                for key in keys.iter().filter(|key| !held.contains(key)) {
+                    push(StepKind::Key { key: key.clone() });
+                }
+                held = keys.clone();
+            }
+            Action::Shortcut { name, .. } => push(StepKind::Shortcut { name: name.clone() }),
+            Action::FocusChange { bounds, .. } => push(StepKind::Focus { bounds: *bounds }),
+            Action::Custom { kind, .. } => push(StepKind::Custom { kind: kind.clone() }),
+            _ => {}
+        }
+    }
+    steps
+}
+
+/// Lines up the steps of `a` and `b` (a longest common subsequence of
+/// same steps) and notes how matched steps went differently.
+pub fn compare(a: &Session, b: &Session) -> Comparison {
+    let (steps_a, steps_b) = (steps(a), steps(b));
+    let (n, m) = (steps_a.len(), steps_b.len());
+    let same = |i: usize, j: usize| steps_a[i].kind.same_step(&steps_b[j].kind);
+
+    // lcs[i * (m + 1) + j]: longest common subsequence of a[i..] and b[j..].
+    let mut lcs = vec![0u32; (n + 1) * (m + 1)];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i * (m + 1) + j] = if same(i, j) {
+                lcs[(i + 1) * (m + 1) + j + 1] + 1
+            } else {
+                lcs[(i + 1) * (m + 1) + j].max(lcs[i * (m + 1) + j + 1])
+            };
+        }
+    }
+
+    let mut entries = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && same(i, j) {
+            entries.push(match change(&steps_a[i].kind, &steps_b[j].kind) {
+                Some(change) => Entry::Changed(i, j, change),
+                None => Entry::Same(i, j),
+            });
+            i += 1;
+            j += 1;
+        } else if j == m || (i < n && lcs[(i + 1) * (m + 1) + j] >= lcs[i * (m + 1) + j + 1]) {
+            entries.push(Entry::Missing(i));
+            i += 1;
+        } else {
+            entries.push(Entry::Extra(j));
+            j += 1;
+        }
+    }
+    Comparison {
+        a: steps_a,
+        b: steps_b,
+        entries,
+    }
+}
+
+/// How the same step went differently in `b`, if it did.
+fn change(a: &StepKind, b: &StepKind) -> Option<Change> {
+    match (a, b) {
+        (StepKind::Click { coords: a, .. }, StepKind::Click { coords: b, .. }) => {
+            let (dx, dy) = ((a.0 - b.0) as f64, (a.1 - b.1) as f64);
+            (dx.hypot(dy) > CLICK_TOLERANCE_PX as f64).then_some(Change::ClickedElsewhere)
+        }
+        (StepKind::Focus { bounds: Some(a) }, StepKind::Focus { bounds: Some(b) }) => {
+            (a != b).then_some(Change::DifferentWindow)
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,91 @@
+//! Turns on the desktop's Do Not Disturb mode for the length of a session.
+//!
+//! Supported on GNOME (and desktops sharing its notification settings) by
+//! turning off notification banners. macOS Focus and Windows Focus Assist
+//! have no public API for this, so enabling fails there.
+
+use anyhow::Result;
+
+/// Do Not Disturb turned on by [`enable`]. The previous setting is restored
+/// by [`DoNotDisturb::restore`], or on drop if the session ends some other
+/// way.
+#[derive(Debug)]
+pub struct DoNotDisturb {
+    previous: Option<String>,
+}
+
+/// Turns Do Not Disturb on, remembering the current setting.
+pub fn enable() -> Result<DoNotDisturb> {
+    let previous = platform::get()?;
+    platform::set("false")?;
+    Ok(DoNotDisturb {
+        previous: Some(previous),
+    })
+}
+
+impl DoNotDisturb {
+    pub fn restore(mut self) -> Result<()> {
+        match self.previous.take() {
+            Some(previous) => platform::set(&previous),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for DoNotDisturb {
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous.take() {
+            if let Err(e) = platform::set(&previous) {
+                eprintln!("Couldn't restore Do Not Disturb: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use anyhow::{bail, Context, Result};
+    use std::process::Command;
+
+    const SCHEMA: &str = "org.gnome.desktop.notifications";
+    const KEY: &str = "show-banners";
+
+    /// The current `show-banners` value (`true` or `false`).
+    pub fn get() -> Result<String> {
+        let output = Command::new("gsettings")
+            .args(["get", SCHEMA, KEY])
+            .output()
+            .context("running gsettings")?;
+        if !output.status.success() {
+            bail!(
+                "GNOME notification settings unavailable: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    pub fn set(value: &str) -> Result<()> {
+        let status = Command::new("gsettings")
+            .args(["set", SCHEMA, KEY, value])
+            .status()
+            .context("running gsettings")?;
+        if !status.success() {
+            bail!("gsettings set {} {} failed ({})", SCHEMA, KEY, status);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod platform {
+    use anyhow::{bail, Result};
+
+    pub fn get() -> Result<String> {
+        bail!("Do Not Disturb can't be controlled on this platform")
+    }
+
+    pub fn set(_value: &str) -> Result<()> {
+        bail!("Do Not Disturb can't be controlled on this platform")
+    }
+}
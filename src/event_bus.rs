@@ -0,0 +1,151 @@
+//! Fan-out of monitor events to subscribers. Every sink gets its own
+//! bounded channel and is only ever offered events without waiting, so a
+//! slow consumer such as an uploader can't hold up capture; what happens to
+//! events it has no room for is up to its [`SinkPolicy`].
+
+use std::collections::VecDeque;
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+
+/// Channel capacity for [`crate::ActivityMonitor::subscribe`].
+pub const DEFAULT_CAPACITY: usize = 1024;
+
+/// What to do with an event when a sink's channel is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SinkPolicy {
+    /// Drop it.
+    Drop,
+    /// Hold up to this many events on the capture side and hand them over
+    /// as the sink catches up, dropping the oldest beyond that.
+    Buffer(usize),
+}
+
+/// How a sink is keeping up, for diagnostics.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SinkHealth {
+    pub name: String,
+    pub capacity: usize,
+    pub policy: SinkPolicy,
+    pub delivered: u64,
+    pub dropped: u64,
+    /// Events held back until the sink has room.
+    pub buffered: usize,
+    /// Whether the channel was full the last time an event was offered.
+    pub backlogged: bool,
+}
+
+#[derive(Debug)]
+struct Sink<T> {
+    tx: SyncSender<T>,
+    overflow: VecDeque<T>,
+    health: SinkHealth,
+}
+
+impl<T> Sink<T> {
+    /// Hands over as many buffered events as there's room for. Returns
+    /// false once the receiver is gone.
+    fn flush(&mut self) -> bool {
+        while let Some(held) = self.overflow.pop_front() {
+            match self.tx.try_send(held) {
+                Ok(()) => self.health.delivered += 1,
+                Err(TrySendError::Full(held)) => {
+                    self.overflow.push_front(held);
+                    break;
+                }
+                Err(TrySendError::Disconnected(_)) => return false,
+            }
+        }
+        self.health.buffered = self.overflow.len();
+        true
+    }
+
+    /// Hands over buffered events, then `event`. Returns false once the
+    /// receiver is gone.
+    fn offer(&mut self, event: T) -> bool {
+        if !self.flush() {
+            return false;
+        }
+
+        let full = if self.overflow.is_empty() {
+            match self.tx.try_send(event) {
+                Ok(()) => {
+                    self.health.delivered += 1;
+                    None
+                }
+                Err(TrySendError::Full(event)) => Some(event),
+                Err(TrySendError::Disconnected(_)) => return false,
+            }
+        } else {
+            // Keep events in order behind the ones already held.
+            Some(event)
+        };
+
+        self.health.backlogged = full.is_some();
+        if let Some(event) = full {
+            match self.health.policy {
+                SinkPolicy::Drop => self.health.dropped += 1,
+                SinkPolicy::Buffer(max) => {
+                    self.overflow.push_back(event);
+                    while self.overflow.len() > max {
+                        self.overflow.pop_front();
+                        self.health.dropped += 1;
+                    }
+                }
+            }
+        }
+        self.health.buffered = self.overflow.len();
+        true
+    }
+}
+
+/// The set of sinks events are published to.
+#[derive(Debug)]
+pub struct EventBus<T> {
+    sinks: Vec<Sink<T>>,
+}
+
+impl<T> Default for EventBus<T> {
+    fn default() -> Self {
+        Self { sinks: Vec::new() }
+    }
+}
+
+impl<T: Clone> EventBus<T> {
+    /// Adds a sink with a channel of `capacity` events. Dropping the
+    /// receiver removes it.
+    pub fn subscribe(&mut self, name: &str, capacity: usize, policy: SinkPolicy) -> Receiver<T> {
+        let (tx, rx) = mpsc::sync_channel(capacity);
+        self.sinks.push(Sink {
+            tx,
+            overflow: VecDeque::new(),
+            health: SinkHealth {
+                name: name.to_string(),
+                capacity,
+                policy,
+                delivered: 0,
+                dropped: 0,
+                buffered: 0,
+                backlogged: false,
+            },
+        });
+        rx
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+
+    /// Offers `event` to every sink without blocking.
+    pub fn publish(&mut self, event: T) {
+        self.sinks.retain_mut(|sink| sink.offer(event.clone()));
+    }
+
+    /// Hands buffered events to sinks that have caught up, so they arrive
+    /// even while nothing new is published.
+    pub fn flush(&mut self) {
+        self.sinks.retain_mut(|sink| sink.flush());
+    }
+
+    pub fn health(&self) -> Vec<SinkHealth> {
+        self.sinks.iter().map(|sink| sink.health.clone()).collect()
+    }
+}
@@ -0,0 +1,842 @@
+use crate::analysis::{self, TrialSummary};
+use crate::config::{Config, ExportFormat};
+use crate::consent::ConsentRecord;
+use crate::integrity;
+use crate::jiggle;
+use crate::keystroke;
+use crate::metrics;
+use crate::quality::QualityFlag;
+use crate::storage::SESSION_HEADERS;
+use crate::types::{format_timestamp, Action, Session};
+use crate::xlsx::{self, Cell, Sheet};
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::Path};
+
+/// Shape of a session in JSON exports.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct JsonSession {
+    session_id: String,
+    task_name: String,
+    start_time: String,
+    end_time: Option<String>,
+    notes: Option<String>,
+    #[serde(default = "default_sample_rate")]
+    mouse_sample_rate: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    machine_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    quality_flags: Option<Vec<QualityFlag>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    density: Option<Vec<u32>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    profile: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    consent: Option<ConsentRecord>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    metadata: BTreeMap<String, String>,
+    /// Derived metrics at export time; ignored when loading.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    metrics: BTreeMap<String, f64>,
+    /// Trials marked in the session; ignored when loading.
+    #[serde(default, skip_deserializing, skip_serializing_if = "Vec::is_empty")]
+    trials: Vec<TrialSummary>,
+    actions: Vec<Action>,
+}
+
+fn default_sample_rate() -> u32 {
+    1
+}
+
+impl From<&Session> for JsonSession {
+    fn from(session: &Session) -> Self {
+        Self {
+            session_id: session.session_id.clone(),
+            task_name: session.task_name.clone(),
+            start_time: session.start_time.clone(),
+            end_time: session.end_time.clone(),
+            notes: session.notes.clone(),
+            mouse_sample_rate: session.mouse_sample_rate,
+            machine_id: session.machine_id.clone(),
+            quality_flags: session.quality_flags.clone(),
+            density: session.density.clone(),
+            profile: session.profile.clone(),
+            consent: session.consent.clone(),
+            metadata: session.metadata.clone(),
+            metrics: BTreeMap::new(),
+            trials: Vec::new(),
+            actions: session.ordered_actions(),
+        }
+    }
+}
+
+impl JsonSession {
+    pub(crate) fn into_session(self) -> Session {
+        let mut session = Session {
+            session_id: self.session_id,
+            task_name: self.task_name,
+            start_time: self.start_time,
+            end_time: self.end_time,
+            notes: self.notes,
+            mouse_sample_rate: self.mouse_sample_rate,
+            machine_id: self.machine_id,
+            quality_flags: self.quality_flags,
+            density: self.density,
+            profile: self.profile,
+            consent: self.consent,
+            metadata: self.metadata,
+            ..Default::default()
+        };
+        for action in self.actions {
+            match action {
+                Action::MouseMove { timestamp, coords } => {
+                    if let Ok(ts) = DateTime::parse_from_rfc3339(&timestamp) {
+                        session.record_mouse_move(ts.with_timezone(&Local), coords);
+                    }
+                }
+                action => session.actions.push(action),
+            }
+        }
+        session
+    }
+}
+
+/// Renders a short Markdown report of a finished session.
+pub fn session_markdown(session: &Session, config: &Config) -> String {
+    let key_presses = session
+        .actions
+        .iter()
+        .filter(|action| matches!(action, Action::KeyPress { .. }))
+        .count();
+
+    let mut md = format!("# {}\n\n", session.task_name);
+    md.push_str(&format!("- Session: {}\n", session.session_id));
+    md.push_str(&format!("- Started: {}\n", session.start_time));
+    md.push_str(&format!(
+        "- Ended: {}\n",
+        session.end_time.as_deref().unwrap_or("-")
+    ));
+    for (field, value) in &session.metadata {
+        md.push_str(&format!("- {}: {}\n", field, value));
+    }
+    if let Some(duration) = session.duration() {
+        md.push_str(&format!(
+            "- Duration: {}m {}s\n",
+            duration.num_minutes(),
+            duration.num_seconds() % 60
+        ));
+    }
+    md.push_str(&format!("- Keyboard events: {}\n", key_presses));
+    md.push_str(&format!("- Mouse moves: {}\n", session.mouse_path.len()));
+    md.push_str(&format!(
+        "- Mouse clicks: {}\n",
+        analysis::click_stats(session).describe()
+    ));
+    let shapes = analysis::cursor_shapes(session);
+    if !shapes.is_empty() {
+        let total: usize = shapes.values().sum();
+        let shares: Vec<String> = shapes
+            .iter()
+            .map(|(shape, count)| format!("{} {}%", shape, count * 100 / total))
+            .collect();
+        md.push_str(&format!("- Pointer over: {}\n", shares.join(", ")));
+    }
+    md.push_str(&format!(
+        "- Mouse distance: {}\n",
+        analysis::format_mouse_distance(session, &config.screen)
+    ));
+    let pseudo_idle = jiggle::pseudo_idle_secs(session, &config.quality.mouse_jiggle);
+    if pseudo_idle > 0 {
+        md.push_str(&format!(
+            "- Pseudo-idle (mouse jiggle): {}m {}s\n",
+            pseudo_idle / 60,
+            pseudo_idle % 60
+        ));
+    }
+    if session.mouse_sample_rate > 1 {
+        md.push_str(&format!(
+            "- Mouse sampling: 1 in {}\n",
+            session.mouse_sample_rate
+        ));
+    }
+    if let Some(flags) = session.quality_flags.as_ref().filter(|f| !f.is_empty()) {
+        let names: Vec<&str> = flags.iter().map(|flag| flag.as_str()).collect();
+        md.push_str(&format!("- Quality flags: {}\n", names.join(", ")));
+    }
+
+    let values = metrics::session_metrics(config, session);
+    if !values.is_empty() {
+        md.push_str("\n## Metrics\n\n");
+        for (name, value) in values {
+            md.push_str(&format!("- {}: {:.2}\n", name, value));
+        }
+    }
+
+    let layouts = analysis::layout_segments(session);
+    if layouts.len() > 1 {
+        md.push_str("\n## Keyboard layouts\n\n");
+        for segment in layouts {
+            md.push_str(&format!(
+                "- {}: {}s, {} keyboard events\n",
+                segment.layout,
+                (segment.end - segment.start).num_seconds(),
+                segment.key_events
+            ));
+        }
+    }
+
+    let breaks = analysis::break_compliance(session);
+    if breaks.reminders > 0 || breaks.breaks > 0 || breaks.micro_breaks > 0 {
+        md.push_str("\n## Breaks\n\n");
+        md.push_str(&format!(
+            "- Break reminders: {} ({} followed by a break)\n",
+            breaks.reminders, breaks.breaks_after_reminder
+        ));
+        md.push_str(&format!("- Breaks taken: {}\n", breaks.breaks));
+        md.push_str(&format!("- Micro-breaks taken: {}\n", breaks.micro_breaks));
+    }
+
+    let trials = analysis::trial_summaries(session);
+    if !trials.is_empty() {
+        md.push_str("\n## Trials\n\n");
+        for trial in trials {
+            md.push_str(&format!(
+                "- {}: {}, {} events ({} keyboard, {} mouse moves, {} clicks)\n",
+                trial.trial_id,
+                trial
+                    .duration_ms
+                    .map_or("-".to_string(), |ms| format!("{:.1}s", ms as f64 / 1000.0)),
+                trial.events,
+                trial.key_presses,
+                trial.mouse_moves,
+                trial.clicks
+            ));
+        }
+    }
+
+    let files = analysis::ide_files(session);
+    if !files.is_empty() {
+        md.push_str("\n## Files\n\n");
+        for file in files {
+            md.push_str(&format!(
+                "- {}: {} saves, {} opens\n",
+                file.path, file.saves, file.opens
+            ));
+        }
+    }
+
+    if let Some(notes) = session.notes.as_deref().filter(|n| !n.trim().is_empty()) {
+        md.push_str("\n## Notes\n\n");
+        md.push_str(notes.trim());
+        md.push('\n');
+    }
+    md
+}
+
+pub fn write_markdown(session: &Session, path: impl AsRef<Path>, config: &Config) -> Result<()> {
+    fs::write(path, session_markdown(session, config))?;
+    Ok(())
+}
+
+/// Writes `sessions` to `path` along with a `.sha256` checksum manifest.
+/// JSON exports include the configured derived metrics of each session.
+pub fn write_sessions(
+    sessions: &[Session],
+    path: impl AsRef<Path>,
+    format: ExportFormat,
+    config: &Config,
+) -> Result<()> {
+    let path = path.as_ref();
+    match format {
+        ExportFormat::Csv => {
+            let mut writer = csv::Writer::from_path(path)?;
+            writer.write_record(SESSION_HEADERS)?;
+            for session in sessions {
+                writer.write_record(session.to_csv_record())?;
+            }
+            writer.flush()?;
+        }
+        ExportFormat::Json => {
+            let records: Vec<JsonSession> = sessions
+                .iter()
+                .map(|session| JsonSession {
+                    metrics: metrics::session_metrics(config, session),
+                    trials: analysis::trial_summaries(session),
+                    ..JsonSession::from(session)
+                })
+                .collect();
+            fs::write(path, serde_json::to_string_pretty(&records)?)?;
+        }
+        ExportFormat::Xlsx => write_xlsx(sessions, path, config)?,
+    }
+    integrity::write_manifest(path)?;
+    Ok(())
+}
+
+/// Keystroke-dynamics dataset: one row per typing burst with its hold times
+/// and down-down / up-down latencies in milliseconds, each a space-separated
+/// vector. `keys` is left empty when `hide_keys` is set. Sessions' metadata
+/// fields follow as a column each.
+pub fn write_keystroke_dynamics(
+    sessions: &[Session],
+    path: impl AsRef<Path>,
+    hide_keys: bool,
+) -> Result<usize> {
+    let path = path.as_ref();
+    let metadata_fields = metadata_fields(sessions);
+    let mut writer = csv::Writer::from_path(path)?;
+    let mut header = vec![
+        "session_id",
+        "task_name",
+        "burst",
+        "start_time",
+        "keystrokes",
+        "keys",
+        "hold_ms",
+        "down_down_ms",
+        "up_down_ms",
+    ];
+    header.extend(&metadata_fields);
+    writer.write_record(header)?;
+    let mut rows = 0;
+    for session in sessions {
+        for (i, burst) in keystroke::bursts(session).iter().enumerate() {
+            let start = DateTime::from_timestamp_millis(burst.keystrokes[0].press_ms)
+                .map(|t| format_timestamp(t.with_timezone(&Local)))
+                .unwrap_or_default();
+            let keys = if hide_keys {
+                String::new()
+            } else {
+                let keys: Vec<&str> = burst.keystrokes.iter().map(|k| k.key.as_str()).collect();
+                keys.join(" ")
+            };
+            let mut row = vec![
+                session.session_id.clone(),
+                session.task_name.clone(),
+                (i + 1).to_string(),
+                start,
+                burst.keystrokes.len().to_string(),
+                keys,
+                join_ms(&burst.hold_times()),
+                join_ms(&burst.down_down()),
+                join_ms(&burst.up_down()),
+            ];
+            row.extend(
+                metadata_fields
+                    .iter()
+                    .map(|field| session.metadata.get(*field).cloned().unwrap_or_default()),
+            );
+            writer.write_record(row)?;
+            rows += 1;
+        }
+    }
+    writer.flush()?;
+    integrity::write_manifest(path)?;
+    Ok(rows)
+}
+
+/// Key bigram and trigram frequency table: one row per key sequence typed
+/// in a session, with how often it was typed and its share of the
+/// sequences of that length typed under the same layout. With
+/// `categories`, key names are replaced by their category (letter, digit,
+/// modifier...). Returns the number of rows.
+pub fn write_key_ngrams(
+    sessions: &[Session],
+    path: impl AsRef<Path>,
+    categories: bool,
+) -> Result<usize> {
+    let path = path.as_ref();
+    let metadata_fields = metadata_fields(sessions);
+    let mut writer = csv::Writer::from_path(path)?;
+    let mut header = vec![
+        "session_id",
+        "task_name",
+        "layout",
+        "n",
+        "sequence",
+        "count",
+        "frequency",
+    ];
+    header.extend(&metadata_fields);
+    writer.write_record(header)?;
+    let mut rows = 0;
+    for session in sessions {
+        for n in [2, 3] {
+            let ngrams = keystroke::ngrams(session, n, categories);
+            let mut totals: BTreeMap<&str, usize> = BTreeMap::new();
+            for ngram in &ngrams {
+                *totals.entry(&ngram.layout).or_default() += ngram.count;
+            }
+            for ngram in &ngrams {
+                let mut row = vec![
+                    session.session_id.clone(),
+                    session.task_name.clone(),
+                    ngram.layout.clone(),
+                    n.to_string(),
+                    ngram.keys.join(" "),
+                    ngram.count.to_string(),
+                    format!(
+                        "{:.4}",
+                        ngram.count as f64 / totals[ngram.layout.as_str()] as f64
+                    ),
+                ];
+                row.extend(
+                    metadata_fields
+                        .iter()
+                        .map(|field| session.metadata.get(*field).cloned().unwrap_or_default()),
+                );
+                writer.write_record(row)?;
+                rows += 1;
+            }
+        }
+    }
+    writer.flush()?;
+    integrity::write_manifest(path)?;
+    Ok(rows)
+}
+
+/// Trial datasets: `events_path` gets one row per event with the trial it
+/// fell in (empty outside trials), and `summaries_path` one row per trial
+/// marked in a session (see [`analysis::trial_summaries`]). Returns the
+/// number of trials.
+pub fn write_trials(
+    sessions: &[Session],
+    events_path: impl AsRef<Path>,
+    summaries_path: impl AsRef<Path>,
+) -> Result<usize> {
+    let (events_path, summaries_path) = (events_path.as_ref(), summaries_path.as_ref());
+    let mut events = csv::Writer::from_path(events_path)?;
+    events.write_record([
+        "session_id",
+        "task_name",
+        "trial_id",
+        "timestamp",
+        "type",
+        "details",
+    ])?;
+    let mut summaries = csv::Writer::from_path(summaries_path)?;
+    summaries.write_record([
+        "session_id",
+        "task_name",
+        "trial_id",
+        "start_time",
+        "end_time",
+        "duration_ms",
+        "events",
+        "key_presses",
+        "mouse_moves",
+        "clicks",
+    ])?;
+
+    let mut trials = 0;
+    for session in sessions {
+        let actions = session.ordered_actions();
+        for (action, trial_id) in actions.iter().zip(analysis::trial_ids(&actions)) {
+            let (kind, details) = action.kind_and_payload();
+            events.write_record([
+                session.session_id.as_str(),
+                session.task_name.as_str(),
+                trial_id.as_deref().unwrap_or_default(),
+                action.timestamp(),
+                kind.as_str(),
+                details.as_str(),
+            ])?;
+        }
+        for trial in analysis::trial_summaries(session) {
+            summaries.write_record([
+                session.session_id.clone(),
+                session.task_name.clone(),
+                trial.trial_id,
+                trial.start,
+                trial.end.unwrap_or_default(),
+                trial
+                    .duration_ms
+                    .map(|ms| ms.to_string())
+                    .unwrap_or_default(),
+                trial.events.to_string(),
+                trial.key_presses.to_string(),
+                trial.mouse_moves.to_string(),
+                trial.clicks.to_string(),
+            ])?;
+            trials += 1;
+        }
+    }
+    events.flush()?;
+    summaries.flush()?;
+    integrity::write_manifest(events_path)?;
+    integrity::write_manifest(summaries_path)?;
+    Ok(trials)
+}
+
+/// Every metadata field set on any of `sessions`, sorted.
+fn metadata_fields(sessions: &[Session]) -> Vec<&str> {
+    let fields: std::collections::BTreeSet<&str> = sessions
+        .iter()
+        .flat_map(|session| session.metadata.keys().map(String::as_str))
+        .collect();
+    fields.into_iter().collect()
+}
+
+fn join_ms(values: &[i64]) -> String {
+    let values: Vec<String> = values.iter().map(i64::to_string).collect();
+    values.join(" ")
+}
+
+/// Workbook for spreadsheet users: a per-task summary, one row per session,
+/// one row per trial when any were marked, then one sheet of events per
+/// session. Event sheets stop at Excel's row limit.
+fn write_xlsx(sessions: &[Session], path: &Path, config: &Config) -> Result<()> {
+    struct TaskSummary {
+        sessions: usize,
+        tracked_secs: i64,
+        key_presses: usize,
+        mouse_moves: usize,
+    }
+
+    let mut tasks: BTreeMap<&str, TaskSummary> = BTreeMap::new();
+    let metadata_fields = metadata_fields(sessions);
+    let metric_names: Vec<&str> = config.metrics.iter().map(|m| m.name.as_str()).collect();
+    let mut header = vec![
+        "Session",
+        "Task",
+        "Start",
+        "End",
+        "Duration (s)",
+        "Key presses",
+        "Mouse moves",
+        "Mouse distance (px)",
+        "Notes",
+        "Quality flags",
+    ];
+    header.extend(&metadata_fields);
+    header.extend(&metric_names);
+    let mut sessions_sheet = Sheet::new("Sessions", &header);
+    let mut trials_sheet = Sheet::new(
+        "Trials",
+        &[
+            "Session",
+            "Trial",
+            "Start",
+            "End",
+            "Duration (s)",
+            "Events",
+            "Key presses",
+            "Mouse moves",
+            "Clicks",
+        ],
+    );
+    let trials: Vec<Vec<TrialSummary>> = sessions.iter().map(analysis::trial_summaries).collect();
+    let with_trials = trials.iter().any(|t| !t.is_empty());
+    let mut event_sheets = Vec::new();
+
+    for (i, session) in sessions.iter().enumerate() {
+        let key_presses = session
+            .actions
+            .iter()
+            .filter(|action| matches!(action, Action::KeyPress { .. }))
+            .count();
+        let duration_secs = session.duration().map(|d| d.num_seconds());
+        let task = tasks.entry(&session.task_name).or_insert(TaskSummary {
+            sessions: 0,
+            tracked_secs: 0,
+            key_presses: 0,
+            mouse_moves: 0,
+        });
+        task.sessions += 1;
+        task.tracked_secs += duration_secs.unwrap_or(0);
+        task.key_presses += key_presses;
+        task.mouse_moves += session.mouse_path.len();
+
+        let flags = session
+            .quality_flags
+            .iter()
+            .flatten()
+            .map(|flag| flag.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut row: Vec<Cell> = vec![
+            session.session_id.as_str().into(),
+            session.task_name.as_str().into(),
+            session.start_time.as_str().into(),
+            session.end_time.as_deref().into(),
+            duration_secs.into(),
+            key_presses.into(),
+            session.mouse_path.len().into(),
+            analysis::mouse_distance_px(session).into(),
+            session.notes.as_deref().into(),
+            flags.into(),
+        ];
+        row.extend(
+            metadata_fields
+                .iter()
+                .map(|field| session.metadata.get(*field).map(String::as_str).into()),
+        );
+        let values = metrics::session_metrics(config, session);
+        row.extend(
+            metric_names
+                .iter()
+                .map(|name| values.get(*name).copied().into()),
+        );
+        sessions_sheet.push_row(&row);
+
+        for trial in &trials[i] {
+            trials_sheet.push_row(&[
+                session.session_id.as_str().into(),
+                trial.trial_id.as_str().into(),
+                trial.start.as_str().into(),
+                trial.end.as_deref().into(),
+                trial.duration_ms.map(|ms| ms as f64 / 1000.0).into(),
+                trial.events.into(),
+                trial.key_presses.into(),
+                trial.mouse_moves.into(),
+                trial.clicks.into(),
+            ]);
+        }
+
+        let event_header: &[&str] = if with_trials {
+            &["Time", "Type", "Details", "Trial"]
+        } else {
+            &["Time", "Type", "Details"]
+        };
+        let mut events = Sheet::new(&format!("{} {}", i + 1, session.session_id), event_header);
+        let actions = session.ordered_actions();
+        for (action, trial_id) in actions.iter().zip(analysis::trial_ids(&actions)) {
+            if events.is_full() {
+                break;
+            }
+            let (kind, payload) = action.kind_and_payload();
+            let mut row: Vec<Cell> = vec![action.timestamp().into(), kind.into(), payload.into()];
+            if with_trials {
+                row.push(trial_id.into());
+            }
+            events.push_row(&row);
+        }
+        event_sheets.push(events);
+    }
+
+    let mut summary = Sheet::new(
+        "Summary",
+        &[
+            "Task",
+            "Sessions",
+            "Tracked (h)",
+            "Key presses",
+            "Mouse moves",
+        ],
+    );
+    for (name, task) in &tasks {
+        summary.push_row(&[
+            (*name).into(),
+            task.sessions.into(),
+            (task.tracked_secs as f64 / 3600.0).into(),
+            task.key_presses.into(),
+            task.mouse_moves.into(),
+        ]);
+    }
+    summary.push_row(&[
+        "Total".into(),
+        sessions.len().into(),
+        (tasks.values().map(|t| t.tracked_secs).sum::<i64>() as f64 / 3600.0).into(),
+        tasks.values().map(|t| t.key_presses).sum::<usize>().into(),
+        tasks.values().map(|t| t.mouse_moves).sum::<usize>().into(),
+    ]);
+
+    let mut sheets = vec![summary, sessions_sheet];
+    if with_trials {
+        sheets.push(trials_sheet);
+    }
+    sheets.extend(event_sheets);
+    xlsx::write(path, &sheets)
+}
+
+/// Schema of `.sqlite` exports. `events` holds one row per recorded action
+/// in recording order; `data` is the action as JSON, in the same shape as
+/// JSON exports, and `trial` the trial it fell in, if any. `session_id` isn't unique (back-to-back sessions can share
+/// one), so events reference the session's `id`. `user_version` is bumped
+/// whenever this changes.
+#[cfg(feature = "sqlite")]
+pub const SQLITE_SCHEMA: &str = "
+PRAGMA user_version = 4;
+CREATE TABLE sessions (
+    id INTEGER PRIMARY KEY,
+    session_id TEXT NOT NULL,
+    task_name TEXT NOT NULL,
+    start_time TEXT NOT NULL,
+    end_time TEXT,
+    duration_secs INTEGER,
+    notes TEXT,
+    mouse_sample_rate INTEGER NOT NULL,
+    machine_id TEXT,
+    metadata TEXT
+);
+CREATE TABLE events (
+    session INTEGER NOT NULL REFERENCES sessions(id),
+    seq INTEGER NOT NULL,
+    type TEXT NOT NULL,
+    timestamp TEXT NOT NULL,
+    x INTEGER,
+    y INTEGER,
+    data TEXT NOT NULL,
+    trial TEXT,
+    PRIMARY KEY (session, seq)
+);
+CREATE INDEX events_by_type ON events(type, timestamp);
+";
+
+/// Writes `sessions` and their events to a new SQLite database at `path`
+/// (see [`SQLITE_SCHEMA`]) along with a `.sha256` checksum manifest.
+#[cfg(feature = "sqlite")]
+pub fn write_sqlite<'a>(
+    sessions: impl IntoIterator<Item = &'a Session>,
+    path: impl AsRef<Path>,
+) -> Result<()> {
+    let path = path.as_ref();
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+    let mut conn = rusqlite::Connection::open(path)?;
+    conn.execute_batch(SQLITE_SCHEMA)?;
+    let tx = conn.transaction()?;
+    {
+        let mut insert_session = tx.prepare(INSERT_SESSION_SQL)?;
+        let mut insert_event = tx.prepare(INSERT_EVENT_SQL)?;
+
+        for (id, session) in sessions.into_iter().enumerate() {
+            insert_sqlite_session(
+                &mut insert_session,
+                &mut insert_event,
+                id as i64 + 1,
+                session,
+            )?;
+        }
+    }
+    tx.commit()?;
+    drop(conn);
+
+    integrity::write_manifest(path)?;
+    Ok(())
+}
+
+#[cfg(feature = "sqlite")]
+pub(crate) const INSERT_SESSION_SQL: &str =
+    "INSERT INTO sessions VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)";
+#[cfg(feature = "sqlite")]
+pub(crate) const INSERT_EVENT_SQL: &str =
+    "INSERT INTO events VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)";
+
+/// Inserts `session` with row ID `id` and its events, using statements
+/// prepared from [`INSERT_SESSION_SQL`] and [`INSERT_EVENT_SQL`].
+#[cfg(feature = "sqlite")]
+pub(crate) fn insert_sqlite_session(
+    insert_session: &mut rusqlite::Statement<'_>,
+    insert_event: &mut rusqlite::Statement<'_>,
+    id: i64,
+    session: &Session,
+) -> Result<()> {
+    use rusqlite::params;
+
+    let metadata = (!session.metadata.is_empty())
+        .then(|| serde_json::to_string(&session.metadata))
+        .transpose()?;
+    insert_session.execute(params![
+        id,
+        session.session_id,
+        session.task_name,
+        session.start_time,
+        session.end_time,
+        session.duration().map(|d| d.num_seconds()),
+        session.notes,
+        i64::from(session.mouse_sample_rate),
+        session.machine_id,
+        metadata,
+    ])?;
+
+    let actions = session.ordered_actions();
+    let trial_ids = analysis::trial_ids(&actions);
+    for (seq, (action, trial_id)) in actions.iter().zip(&trial_ids).enumerate() {
+        let data = serde_json::to_value(action)?;
+        let kind = data["type"].as_str().unwrap_or_default().to_string();
+        let timestamp = data["timestamp"].as_str().unwrap_or_default().to_string();
+        let coords = match action {
+            Action::MouseMove { coords, .. } => Some(*coords),
+            _ => None,
+        };
+        insert_event.execute(params![
+            id,
+            seq as i64,
+            kind,
+            timestamp,
+            coords.map(|c| i64::from(c.0)),
+            coords.map(|c| i64::from(c.1)),
+            data.to_string(),
+            trial_id,
+        ])?;
+    }
+    Ok(())
+}
+
+/// The database's `user_version` (see [`SQLITE_SCHEMA`]).
+#[cfg(feature = "sqlite")]
+pub(crate) fn sqlite_version(conn: &rusqlite::Connection) -> Result<i64> {
+    Ok(conn.query_row("PRAGMA user_version", [], |row| row.get(0))?)
+}
+
+/// Reads sessions back from a `.sqlite` export written by [`write_sqlite`].
+#[cfg(feature = "sqlite")]
+pub fn read_sqlite(path: impl AsRef<Path>) -> Result<Vec<Session>> {
+    use anyhow::Context;
+    use rusqlite::{Connection, OpenFlags};
+
+    let path = path.as_ref();
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("opening {}", path.display()))?;
+    // Version 2 exports predate the metadata column.
+    let metadata = if sqlite_version(&conn)? >= 3 {
+        "metadata"
+    } else {
+        "NULL"
+    };
+    let mut select = conn.prepare(&format!(
+        "SELECT id, session_id, task_name, start_time, end_time, notes, mouse_sample_rate, \
+         machine_id, {} FROM sessions ORDER BY id",
+        metadata
+    ))?;
+    let records = select
+        .query_map([], |row| {
+            let text = |i| row.get::<_, Option<String>>(i);
+            Ok((
+                row.get::<_, i64>(0)?,
+                JsonSession {
+                    session_id: text(1)?.unwrap_or_default(),
+                    task_name: text(2)?.unwrap_or_default(),
+                    start_time: text(3)?.unwrap_or_default(),
+                    end_time: text(4)?,
+                    notes: text(5)?,
+                    mouse_sample_rate: row.get::<_, Option<i64>>(6)?.unwrap_or(1).max(1) as u32,
+                    machine_id: text(7)?,
+                    quality_flags: None,
+                    density: None,
+                    profile: None,
+                    consent: None,
+                    metadata: text(8)?
+                        .and_then(|s| serde_json::from_str(&s).ok())
+                        .unwrap_or_default(),
+                    metrics: BTreeMap::new(),
+                    trials: Vec::new(),
+                    actions: Vec::new(),
+                },
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut events = conn.prepare("SELECT data FROM events WHERE session = ?1 ORDER BY seq")?;
+    let mut sessions = Vec::with_capacity(records.len());
+    for (id, mut record) in records {
+        for data in events.query_map([id], |row| row.get::<_, String>(0))? {
+            record.actions.push(serde_json::from_str(&data?)?);
+        }
+        sessions.push(record.into_session());
+    }
+    Ok(sessions)
+}
@@ -1,68 +1,2762 @@
-use crate::monitor::ActivityMonitor;
+use crate::analysis::{self, BurnDown};
+use crate::autostart;
+use crate::billing;
+use crate::calibration::{self, PauseDistribution};
+use crate::config::{
+    AppAlert, Config, DistanceUnit, ExportFormat, RoundingMode, ScheduleAction, ScheduleRule,
+    TaskBudget,
+};
+use crate::consent::{self, CONSENT_FILE};
+use crate::contribute::{self, Contribution};
+use crate::crash;
+use crate::event_bus::{SinkPolicy, DEFAULT_CAPACITY};
+use crate::export;
+use crate::import::{ConflictResolution, ImportPreview};
+use crate::jiggle;
+use crate::metrics;
+use crate::monitor::{ActivityMonitor, MonitorEvent};
+use crate::portable;
+use crate::privacy::RedactionRule;
+use crate::scheduler::{self, ScheduleEvent};
+use crate::share;
+use crate::stats::{self, Stats};
+use crate::types::{Action, Session};
+use crate::updates::{self, Release};
+use crate::watchdog;
+use chrono::{Datelike, Days, Local, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
 use eframe::egui;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant};
+
+/// How many recent task names get a quick-start button.
+const QUICK_START_TASKS: usize = 5;
+
+/// Weeks shown in the heat map, ending with the current one.
+const HEAT_MAP_WEEKS: u64 = 53;
+const HEAT_MAP_CELL: f32 = 11.0;
+const HEAT_MAP_GAP: f32 = 2.0;
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+/// Weeks listed under the heat map, including the current one.
+const WEEKLY_ROWS: u64 = 8;
+
+/// The activity sparkline covers this many buckets of `SPARKLINE_BUCKET`.
+const SPARKLINE_BUCKETS: usize = 10;
+const SPARKLINE_BUCKET: Duration = Duration::from_secs(30);
+const WINDOW_TITLE: &str = "Desktop Activity Monitor";
+/// Hover text on settings locked by the policy file.
+const POLICY_LOCKED: &str = "Set by your organization's policy";
+
+/// A section added to the main window by a crate embedding this one, shown
+/// collapsible under "Reports".
+pub trait Panel {
+    fn title(&self) -> &str;
+    fn ui(&mut self, ui: &mut egui::Ui, state: &MonitorState<'_>);
+}
+
+/// What custom panels can see of the app.
+pub struct MonitorState<'a> {
+    pub monitor: &'a ActivityMonitor,
+    /// Saved sessions, oldest first.
+    pub history: &'a [Session],
+    pub stats: &'a Stats,
+}
+
+/// What the main window is showing, each with its own controls. The
+/// recording modes follow the monitor, which the schedule, hotkeys and
+/// other threads can also start and stop; Settings is opened and closed
+/// from the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Idle,
+    Recording,
+    Paused,
+    /// A stopped session waiting for its notes.
+    Reviewing,
+    Settings,
+}
 
 pub struct MonitorApp {
     monitor: ActivityMonitor,
+    mode: Mode,
+    notes_input: String,
+    switch_task_input: String,
+    /// ID for the next trial marked in the session; numbered when empty.
+    trial_id_input: String,
+    /// Name typed under Settings to save the current settings as a profile.
+    profile_name_input: String,
+    /// Participant ID on the consent screen, filled in from the last
+    /// acceptance when asked to consent again.
+    participant_id_input: String,
+    /// Saved sessions backing the dashboard, reloaded whenever the monitor
+    /// saves another one.
+    history: Vec<Session>,
+    events: Receiver<MonitorEvent>,
+    stats: Stats,
+    /// When a pending Start's countdown ends.
+    start_at: Option<Instant>,
+    /// A dropped export waiting for the user to confirm the import.
+    import: Option<ImportPreview>,
+    import_resolution: ConflictResolution,
+    /// Day picked in the heat map, whose sessions are listed under it.
+    selected_day: Option<NaiveDate>,
+    budget_task_input: String,
+    budget_hours_input: f64,
+    /// Pauses on the latest recorded day, for idle calibration.
+    pauses: PauseDistribution,
+    schedule: Receiver<ScheduleEvent>,
+    /// The upcoming automatic action being announced.
+    schedule_prompt: Option<(ScheduleRule, NaiveDateTime)>,
+    /// Automatic actions the user chose to skip.
+    skipped: Vec<(ScheduleRule, NaiveDateTime)>,
+    panels: Vec<Box<dyn Panel>>,
+    /// "Discard session" was clicked and awaits confirmation.
+    confirm_discard: bool,
+    /// Whether the app is registered to launch at login, read at startup.
+    launch_at_login: bool,
+    activity: Activity,
+    /// Last title sent to the window, so it's only changed when it differs.
+    title: String,
+    update_check: Option<Receiver<anyhow::Result<Option<Release>>>>,
+    update_install: Option<Receiver<anyhow::Result<PathBuf>>>,
+    /// A newer release found by the last check, until dismissed.
+    update: Option<Release>,
+    update_status: Option<String>,
+    /// Statistics due to be contributed, shown for review until sent or
+    /// put off.
+    contribution: Option<Contribution>,
+    contribution_send: Option<Receiver<anyhow::Result<PathBuf>>>,
+    contribution_status: Option<String>,
+    /// "Not now" was clicked; offered again at the next launch.
+    contribution_dismissed: bool,
+    /// A crash report from an earlier run, offered once.
+    crash_report: Option<PathBuf>,
+    /// Folder typed in "Choose new location", while that's open.
+    relocate_input: Option<String>,
+    /// A history row's rename, tag or delete, while its window is open.
+    history_prompt: Option<HistoryPrompt>,
 }
 
-impl MonitorApp {
-    pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
+/// Picked from a session's right-click menu in the history.
+enum HistoryAction {
+    Export(ExportFormat),
+    OpenFolder,
+    Prompt(Box<HistoryPrompt>),
+}
+
+/// A history action that needs input or confirmation first, with the
+/// session it's for as found in storage.
+enum HistoryPrompt {
+    /// With the new task name typed so far.
+    Rename(Session, String),
+    /// With the tag typed so far.
+    Tag(Session, String),
+    Delete(Session),
+}
+
+/// Recorded events per bucket over the last few minutes, oldest first; the
+/// last bucket is the one filling up.
+struct Activity {
+    buckets: VecDeque<u32>,
+    current_start: Instant,
+}
+
+impl Activity {
+    fn new() -> Self {
         Self {
-            monitor: ActivityMonitor::new().unwrap(),
+            buckets: VecDeque::from(vec![0; SPARKLINE_BUCKETS]),
+            current_start: Instant::now(),
         }
     }
+
+    /// Starts new buckets for the time that has passed.
+    fn advance(&mut self, now: Instant) {
+        while now.duration_since(self.current_start) >= SPARKLINE_BUCKET {
+            self.buckets.pop_front();
+            self.buckets.push_back(0);
+            self.current_start += SPARKLINE_BUCKET;
+        }
+    }
+
+    fn record(&mut self, now: Instant) {
+        self.advance(now);
+        if let Some(current) = self.buckets.back_mut() {
+            *current += 1;
+        }
+    }
+
+    /// The buckets as block characters scaled to the busiest one.
+    fn sparkline(&self) -> String {
+        const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+        let max = self.buckets.iter().copied().max().unwrap_or(0).max(1);
+        self.buckets
+            .iter()
+            .map(|&count| BLOCKS[(count as usize * (BLOCKS.len() - 1)).div_ceil(max as usize)])
+            .collect()
+    }
 }
 
-impl eframe::App for MonitorApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // Update monitor state
-        self.monitor.update();
+impl MonitorApp {
+    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        let mut monitor = ActivityMonitor::new().unwrap();
+        let events = monitor.subscribe_sink(
+            "gui",
+            DEFAULT_CAPACITY,
+            SinkPolicy::Buffer(DEFAULT_CAPACITY),
+        );
+        // Capture runs on frames, so restarting it, or running calls from
+        // other threads, means asking for one.
+        let ctx = cc.egui_ctx.clone();
+        watchdog::spawn(monitor.heartbeat(), move || ctx.request_repaint());
+        let ctx = cc.egui_ctx.clone();
+        monitor.set_waker(move || ctx.request_repaint());
+        let mut app = Self {
+            monitor,
+            mode: Mode::Idle,
+            notes_input: String::new(),
+            switch_task_input: String::new(),
+            trial_id_input: String::new(),
+            profile_name_input: String::new(),
+            participant_id_input: consent::load(CONSENT_FILE)
+                .ok()
+                .and_then(|log| log.last().map(|c| c.participant_id.clone()))
+                .unwrap_or_default(),
+            history: Vec::new(),
+            events,
+            stats: Stats::default(),
+            start_at: None,
+            import: None,
+            import_resolution: ConflictResolution::Skip,
+            selected_day: None,
+            budget_task_input: String::new(),
+            budget_hours_input: 10.0,
+            pauses: PauseDistribution::default(),
+            schedule: scheduler::spawn_schedule(),
+            schedule_prompt: None,
+            skipped: Vec::new(),
+            panels: Vec::new(),
+            confirm_discard: false,
+            launch_at_login: autostart::is_enabled(),
+            activity: Activity::new(),
+            title: WINDOW_TITLE.to_string(),
+            update_check: None,
+            update_install: None,
+            update: None,
+            update_status: None,
+            contribution: None,
+            contribution_send: None,
+            contribution_status: None,
+            contribution_dismissed: false,
+            crash_report: crash::unseen(Path::new(crash::DIRECTORY)),
+            relocate_input: None,
+            history_prompt: None,
+        };
+        if app.monitor.config().updates.check_at_startup {
+            app.update_check = Some(updates::spawn_check(app.monitor.config().updates.clone()));
+        }
+        app.reload_history();
+        app
+    }
+
+    /// Adds a custom section to the main window, after any added before.
+    pub fn add_panel(&mut self, panel: Box<dyn Panel>) {
+        self.panels.push(panel);
+    }
+
+    fn reload_history(&mut self) {
+        self.history = self.monitor.stored_sessions().unwrap_or_else(|e| {
+            eprintln!("Error loading stored sessions: {}", e);
+            Vec::new()
+        });
+        self.pauses = calibration::latest_day_pauses(&self.history);
+        self.stats =
+            stats::update(&self.history, &self.monitor.config().stats).unwrap_or_else(|e| {
+                eprintln!("Error updating stats: {}", e);
+                Stats::default()
+            });
+        if !self.contribution_dismissed && self.contribution_send.is_none() {
+            self.contribution = contribute::pending(
+                &self.monitor.config().contribute,
+                &self.history,
+                Local::now().date_naive(),
+            );
+        }
+    }
+
+    /// The mode the monitor is in, leaving Settings aside.
+    fn recording_mode(&self) -> Mode {
+        if self.monitor.awaiting_notes() {
+            Mode::Reviewing
+        } else if self.monitor.is_paused() {
+            Mode::Paused
+        } else if self.monitor.is_running() {
+            Mode::Recording
+        } else {
+            Mode::Idle
+        }
+    }
+
+    /// Follows the monitor into its current mode. Settings stays open while
+    /// recording starts or stops, but not over a session waiting for its
+    /// notes.
+    fn sync_mode(&mut self) {
+        let mode = self.recording_mode();
+        if self.mode != Mode::Settings || mode == Mode::Reviewing {
+            self.mode = mode;
+        }
+    }
+
+    /// Starts recording, after the configured countdown if there is one.
+    fn request_start(&mut self, ctx: &egui::Context) {
+        match self.monitor.config().start_countdown_secs {
+            0 => self.start(ctx),
+            secs => self.start_at = Some(Instant::now() + Duration::from_secs(secs)),
+        }
+    }
+
+    fn start(&mut self, ctx: &egui::Context) {
+        self.start_at = None;
+        self.monitor.start_monitoring();
+        if self.monitor.is_running() && self.monitor.config().minimize_on_start {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(true));
+        }
+    }
+
+    /// Announces upcoming schedule rules and carries out due ones, unless
+    /// they were skipped. A start while already recording, or a stop while
+    /// not, does nothing.
+    fn handle_schedule(&mut self, ctx: &egui::Context, event: ScheduleEvent) {
+        match event {
+            ScheduleEvent::Upcoming { rule, at } => {
+                ctx.send_viewport_cmd(egui::ViewportCommand::RequestUserAttention(
+                    egui::UserAttentionType::Informational,
+                ));
+                self.schedule_prompt = Some((rule, at));
+            }
+            ScheduleEvent::Due { rule, at } => {
+                if self
+                    .schedule_prompt
+                    .as_ref()
+                    .is_some_and(|prompt| *prompt == (rule.clone(), at))
+                {
+                    self.schedule_prompt = None;
+                }
+                let skipped = self.skipped.len();
+                self.skipped.retain(|s| *s != (rule.clone(), at));
+                if self.skipped.len() < skipped {
+                    return;
+                }
+                match rule.action {
+                    ScheduleAction::Start if !self.monitor.is_running() => {
+                        if !rule.task.trim().is_empty() {
+                            self.monitor.set_task_name(rule.task);
+                        }
+                        self.start(ctx);
+                    }
+                    ScheduleAction::Stop if self.monitor.is_running() => {
+                        self.monitor.stop_monitoring();
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn schedule_window(&mut self, ctx: &egui::Context) {
+        let Some((rule, at)) = &self.schedule_prompt else {
+            return;
+        };
+        let message = match rule.action {
+            ScheduleAction::Start => {
+                let task = if rule.task.trim().is_empty() {
+                    self.monitor.task_name()
+                } else {
+                    &rule.task
+                };
+                format!("Recording \"{}\" starts at {}", task, at.format("%H:%M"))
+            }
+            ScheduleAction::Stop => format!("Recording stops at {}", at.format("%H:%M")),
+        };
 
-        if self
-            .monitor
-            .is_monitoring
-            .load(std::sync::atomic::Ordering::SeqCst)
+        let (mut dismissed, mut skip) = (false, false);
+        egui::Window::new("Scheduled")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(message);
+                ui.horizontal(|ui| {
+                    dismissed = ui.button("OK").clicked();
+                    skip = ui.button("Skip this time").clicked();
+                });
+            });
+        if skip {
+            self.skipped.extend(self.schedule_prompt.take());
+        } else if dismissed {
+            self.schedule_prompt = None;
+        }
+    }
+
+    /// Shown instead of recording until the participant accepts the
+    /// consent text, when it's required.
+    fn consent_window(&mut self, ctx: &egui::Context) {
+        if !self.monitor.needs_consent() {
+            return;
+        }
+        let mut accepted = false;
+        egui::Window::new("Consent")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical()
+                    .max_height(240.0)
+                    .show(ui, |ui| ui.label(&self.monitor.config().consent.text));
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Participant ID:");
+                    ui.text_edit_singleline(&mut self.participant_id_input);
+                });
+                ui.horizontal(|ui| {
+                    accepted = ui
+                        .add_enabled(
+                            !self.participant_id_input.trim().is_empty(),
+                            egui::Button::new("I agree"),
+                        )
+                        .clicked();
+                    ui.weak("Nothing is recorded until you agree.");
+                });
+            });
+        if accepted {
+            let participant_id = self.participant_id_input.trim().to_string();
+            if let Err(e) = self.monitor.accept_consent(&participant_id) {
+                self.monitor
+                    .set_status(format!("Error recording consent: {}", e));
+            }
+        }
+    }
+
+    /// The session's metadata fields, editable before recording.
+    fn metadata_fields(&mut self, ui: &mut egui::Ui) {
+        let metadata = &mut self.monitor.config_mut().metadata;
+        let mut changed = false;
+        for field in metadata.fields.clone() {
+            ui.horizontal(|ui| {
+                ui.label(format!("{}:", field));
+                let value = metadata.values.entry(field).or_default();
+                changed |= ui
+                    .add(egui::TextEdit::singleline(value).desired_width(140.0))
+                    .changed();
+            });
+        }
+        if changed {
+            if let Err(e) = self.monitor.config().save() {
+                self.monitor
+                    .set_status(format!("Error saving settings: {}", e));
+            }
+        }
+    }
+
+    /// The running session's metadata, on one line.
+    fn metadata_summary(&self, ui: &mut egui::Ui) {
+        let metadata = &self.monitor.current_session().metadata;
+        if !metadata.is_empty() {
+            let fields: Vec<String> = metadata
+                .iter()
+                .map(|(field, value)| format!("{}: {}", field, value))
+                .collect();
+            ui.weak(fields.join(" · "));
+        }
+    }
+
+    fn discard_window(&mut self, ctx: &egui::Context) {
+        if !self.confirm_discard {
+            return;
+        }
+        if !self.monitor.is_running() && !self.monitor.awaiting_notes() {
+            self.confirm_discard = false;
+            return;
+        }
+        let (mut discard, mut cancel) = (false, false);
+        egui::Window::new("Discard session?")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "Everything recorded for \"{}\" in this session will be deleted \
+                     instead of saved. This can't be undone.",
+                    self.monitor.task_name()
+                ));
+                ui.horizontal(|ui| {
+                    discard = ui.button("Discard").clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+        if discard {
+            self.monitor.discard_session();
+            self.notes_input.clear();
+        }
+        if discard || cancel {
+            self.confirm_discard = false;
+        }
+    }
+
+    /// Offers the report left by a crash in an earlier run.
+    fn crash_window(&mut self, ctx: &egui::Context) {
+        let Some(report) = &self.crash_report else {
+            return;
+        };
+        let (mut open, mut dismiss) = (false, false);
+        egui::Window::new("The monitor crashed last time")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "A crash report was saved to {}. It has the error, where it \
+                     happened and your settings, but no recorded input or task names.",
+                    report.display()
+                ));
+                ui.horizontal(|ui| {
+                    open = ui.button("Open report").clicked();
+                    dismiss = ui.button("Dismiss").clicked();
+                });
+            });
+        if open {
+            if let Err(e) = share::open_url(&report.to_string_lossy()) {
+                self.monitor
+                    .set_status(format!("Error opening crash report: {}", e));
+            }
+        }
+        if open || dismiss {
+            if let Err(e) = crash::mark_seen(Path::new(crash::DIRECTORY), report) {
+                eprintln!("Error saving crash report state: {}", e);
+            }
+            self.crash_report = None;
+        }
+    }
+
+    /// The controls for the current mode, then the live status lines.
+    fn controls(&mut self, ui: &mut egui::Ui) {
+        match self.mode {
+            Mode::Idle => self.idle_controls(ui),
+            Mode::Recording => self.recording_controls(ui, false),
+            Mode::Paused => self.recording_controls(ui, true),
+            Mode::Reviewing => self.review_controls(ui),
+            Mode::Settings => self.settings_controls(ui),
+        }
+
+        if let Some(problem) = self.monitor.write_problem() {
+            ui.horizontal_wrapped(|ui| {
+                ui.colored_label(egui::Color32::RED, format!("💾 {}", problem));
+                if ui.button("Retry now").clicked() {
+                    self.monitor.retry_writes();
+                }
+                if ui.button("Choose new location…").clicked() {
+                    self.relocate_input = Some(String::new());
+                }
+            });
+        }
+        if let Some(problem) = self.monitor.capture_problem() {
+            ui.colored_label(egui::Color32::RED, format!("⚠ {}", problem));
+        }
+        if let Some(alert) = self.monitor.app_alert() {
+            ui.colored_label(egui::Color32::from_rgb(200, 120, 0), format!("⏱ {}", alert));
+        }
+        if let Some(reminder) = self.monitor.break_reminder() {
+            ui.colored_label(
+                egui::Color32::from_rgb(200, 120, 0),
+                format!("☕ {}", reminder),
+            );
+        }
+        if matches!(self.mode, Mode::Recording | Mode::Paused) {
+            self.task_time(ui);
+            ui.label(format!("Activity: {}", self.activity.sparkline()))
+                .on_hover_text("Events recorded over the last 5 minutes, 30 s per bar");
+        }
+        ui.label(self.monitor.status());
+        if let Some(layout) = self.monitor.keyboard_layout() {
+            ui.label(format!("Keyboard layout: {}", layout));
+        }
+    }
+
+    /// Asks for a folder to write data to instead, once writing has failed.
+    fn relocate_window(&mut self, ctx: &egui::Context) {
+        let Some(input) = &mut self.relocate_input else {
+            return;
+        };
+        let (mut confirmed, mut cancel) = (false, false);
+        egui::Window::new("Choose new location")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(
+                    "Sessions, the detailed log and summaries will be written to this \
+                     folder from now on, starting with everything held in memory.",
+                );
+                ui.add(
+                    egui::TextEdit::singleline(input)
+                        .hint_text("Folder path")
+                        .desired_width(280.0),
+                );
+                ui.horizontal(|ui| {
+                    confirmed = ui
+                        .add_enabled(!input.trim().is_empty(), egui::Button::new("Write here"))
+                        .clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+        if confirmed {
+            let dir = PathBuf::from(input.trim());
+            match self.monitor.relocate(&dir) {
+                Ok(()) => {
+                    self.relocate_input = None;
+                    self.reload_history();
+                }
+                Err(e) => {
+                    self.monitor
+                        .set_status(format!("Couldn't write to {}: {}", dir.display(), e))
+                }
+            }
+        } else if cancel {
+            self.relocate_input = None;
+        }
+    }
+
+    /// Does what was picked from a history row's menu, or opens its prompt.
+    fn history_action(&mut self, session: &Session, action: HistoryAction) {
+        match action {
+            HistoryAction::Export(format) => {
+                let path = format!("{}.{}", session.session_id, format.extension());
+                let status = match export::write_sessions(
+                    std::slice::from_ref(session),
+                    &path,
+                    format,
+                    self.monitor.config(),
+                ) {
+                    Ok(()) => format!("Exported to {}", path),
+                    Err(e) => format!("Error exporting to {}: {}", path, e),
+                };
+                self.monitor.set_status(status);
+            }
+            HistoryAction::OpenFolder => {
+                let opened = std::env::current_dir()
+                    .map(|cwd| cwd.join(self.monitor.data_dir()))
+                    .map_err(anyhow::Error::from)
+                    .and_then(|dir| share::open_url(&dir.to_string_lossy()));
+                if let Err(e) = opened {
+                    self.monitor
+                        .set_status(format!("Error opening the data folder: {}", e));
+                }
+            }
+            HistoryAction::Prompt(prompt) => self.history_prompt = Some(*prompt),
+        }
+    }
+
+    /// Asks for a history row's new task name or tag, or to confirm
+    /// deleting it.
+    fn history_window(&mut self, ctx: &egui::Context) {
+        let Some(prompt) = &mut self.history_prompt else {
+            return;
+        };
+        let title = match prompt {
+            HistoryPrompt::Rename(..) => "Rename task",
+            HistoryPrompt::Tag(..) => "Add tag",
+            HistoryPrompt::Delete(_) => "Delete session?",
+        };
+        let (mut confirmed, mut cancel) = (false, false);
+        egui::Window::new(title)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let (input, button) = match prompt {
+                    HistoryPrompt::Rename(session, input) => {
+                        ui.label(format!(
+                            "New task name for the session started {}. Other \
+                             sessions of \"{}\" keep theirs.",
+                            session.start_time, session.task_name
+                        ));
+                        (Some(input), "Rename")
+                    }
+                    HistoryPrompt::Tag(session, input) => {
+                        let tags = session.tags();
+                        ui.label(if tags.is_empty() {
+                            "No tags yet".to_string()
+                        } else {
+                            format!("Tags: {}", tags.join(", "))
+                        });
+                        (Some(input), "Add")
+                    }
+                    HistoryPrompt::Delete(session) => {
+                        ui.label(format!(
+                            "The session of \"{}\" started {} will be deleted from the \
+                             history. This can't be undone.",
+                            session.task_name, session.start_time
+                        ));
+                        (None, "Delete")
+                    }
+                };
+                let ready = match input {
+                    Some(input) => {
+                        ui.add(egui::TextEdit::singleline(input).desired_width(240.0));
+                        !input.trim().is_empty()
+                    }
+                    None => true,
+                };
+                ui.horizontal(|ui| {
+                    confirmed = ui.add_enabled(ready, egui::Button::new(button)).clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+        if cancel {
+            self.history_prompt = None;
+        }
+        if !confirmed {
+            return;
+        }
+        let result = match self.history_prompt.take() {
+            Some(HistoryPrompt::Rename(session, name)) => {
+                let name = name.trim().to_string();
+                self.monitor
+                    .update_stored(&session, |s| s.task_name = name.clone())
+                    .map(|()| format!("Renamed the session's task to {}", name))
+            }
+            Some(HistoryPrompt::Tag(session, tag)) => self
+                .monitor
+                .update_stored(&session, |s| {
+                    s.add_tag(&tag);
+                })
+                .map(|()| format!("Tagged the session {}", tag.trim())),
+            Some(HistoryPrompt::Delete(session)) => self
+                .monitor
+                .remove_stored(&session)
+                .map(|()| "Deleted the session".to_string()),
+            None => return,
+        };
+        match result {
+            Ok(status) => {
+                self.monitor.set_status(status);
+                self.reload_history();
+            }
+            Err(e) => self.monitor.set_status(format!("Error: {}", e)),
+        }
+    }
+
+    /// Task, template, metadata and profile for the next session, with the
+    /// ways to start it.
+    fn idle_controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Task:");
+            let mut task_name = self.monitor.task_name().to_string();
+            if ui
+                .add(egui::TextEdit::singleline(&mut task_name).desired_width(140.0))
+                .changed()
+            {
+                self.monitor.set_task_name(task_name);
+            }
+
+            if let Some(start_at) = self.start_at {
+                let left = start_at.saturating_duration_since(Instant::now());
+                ui.label(format!("Starting in {}…", left.as_secs() + 1));
+                if ui.button("Cancel").clicked() {
+                    self.start_at = None;
+                }
+                return;
+            }
+            let can_start = !self.monitor.task_name().trim().is_empty();
+            if ui
+                .add_enabled(can_start, egui::Button::new("Start"))
+                .clicked()
+            {
+                self.request_start(ui.ctx());
+            }
+            let trial_secs = self.monitor.config().trials.duration_secs;
+            if ui
+                .add_enabled(can_start, egui::Button::new("Start trial"))
+                .on_hover_text(format!("Record for {}s, then stop and export", trial_secs))
+                .clicked()
+            {
+                self.monitor.start_trial();
+            }
+            if ui
+                .button("Bug report")
+                .on_hover_text(format!(
+                    "Record{} for {}s, then bundle it into a ZIP",
+                    if self.monitor.config().bug_report.screenshots {
+                        " with screenshots"
+                    } else {
+                        ""
+                    },
+                    self.monitor.config().bug_report.duration_secs
+                ))
+                .clicked()
+            {
+                self.monitor.start_bug_report();
+            }
+            let buffer_secs = self.monitor.config().ring_buffer.seconds;
+            if buffer_secs > 0
+                && ui
+                    .button(format!("Save last {}s", buffer_secs))
+                    .on_hover_text("Save the input from before recording as a session")
+                    .clicked()
+            {
+                if let Err(e) = self.monitor.save_ring_buffer() {
+                    self.monitor.set_status(format!("Error: {}", e));
+                }
+            }
+            if ui.button("Settings").clicked() {
+                self.mode = Mode::Settings;
+            }
+        });
+
+        if !self.monitor.config().templates.is_empty() {
+            let current = self.monitor.selected_template().map(str::to_string);
+            let mut selected = current.clone();
+            ui.horizontal(|ui| {
+                ui.label("Template:");
+                egui::ComboBox::from_id_source("session_template")
+                    .selected_text(current.as_deref().unwrap_or("None"))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut selected, None, "None");
+                        for template in &self.monitor.config().templates {
+                            ui.selectable_value(
+                                &mut selected,
+                                Some(template.name.clone()),
+                                &template.name,
+                            );
+                        }
+                    });
+            });
+            if selected != current {
+                self.monitor.select_template(selected.as_deref());
+            }
+        }
+
+        self.metadata_fields(ui);
+        self.profile_picker(ui);
+    }
+
+    /// The running session's task and time left, with Pause/Resume, Stop
+    /// and Discard. Task switches, trial marks and sync markers need input
+    /// to be captured, so they're only offered while not `paused`.
+    fn recording_controls(&mut self, ui: &mut egui::Ui, paused: bool) {
+        ui.horizontal(|ui| {
+            ui.label("Task:");
+            ui.strong(self.monitor.task_name());
+            if let Some(left) = self.monitor.bug_report_remaining() {
+                ui.label(format!(
+                    "Bug report ends in {}:{:02}",
+                    left.as_secs() / 60,
+                    left.as_secs() % 60
+                ));
+            }
+            if let Some(left) = self.monitor.trial_remaining() {
+                ui.label(format!(
+                    "Trial ends in {}:{:02}",
+                    left.as_secs() / 60,
+                    left.as_secs() % 60
+                ));
+            }
+            if paused {
+                if ui.button("Resume").clicked() {
+                    self.monitor.resume();
+                }
+            } else if self.monitor.trial_remaining().is_none()
+                && self.monitor.bug_report_remaining().is_none()
+                && ui
+                    .button("Pause")
+                    .on_hover_text("Stop capturing input without ending the session")
+                    .clicked()
+            {
+                self.monitor.pause();
+            }
+            if ui.button("Stop").clicked() {
+                self.monitor.stop_monitoring();
+            }
+            if ui.button("Discard…").clicked() {
+                self.confirm_discard = true;
+            }
+            if ui.button("Settings").clicked() {
+                self.mode = Mode::Settings;
+            }
+        });
+
+        self.metadata_summary(ui);
+        self.profile_picker(ui);
+
+        if paused {
+            ui.colored_label(
+                egui::Color32::from_rgb(200, 120, 0),
+                "⏸ Paused: nothing is being recorded",
+            );
+            return;
+        }
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.switch_task_input).desired_width(140.0));
+            let can_switch = !self.switch_task_input.trim().is_empty();
+            if ui
+                .add_enabled(can_switch, egui::Button::new("Switch task"))
+                .clicked()
+            {
+                self.monitor
+                    .switch_task(std::mem::take(&mut self.switch_task_input));
+            }
+        });
+        ui.horizontal(|ui| {
+            let result = if let Some(trial) = self.monitor.open_trial() {
+                ui.label(format!("In trial {}", trial));
+                if !ui.button("End trial").clicked() {
+                    return;
+                }
+                self.monitor.mark_trial_end().map(drop)
+            } else {
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.trial_id_input)
+                        .hint_text("Trial ID (optional)")
+                        .desired_width(140.0),
+                );
+                if !ui.button("Mark trial start").clicked() {
+                    return;
+                }
+                let trial_id = std::mem::take(&mut self.trial_id_input);
+                self.monitor.mark_trial_start(Some(trial_id)).map(drop)
+            };
+            if let Err(e) = result {
+                self.monitor.set_status(format!("Error: {}", e));
+            }
+        });
+        if ui
+            .button("Sync marker")
+            .on_hover_text("Record a marker for lining up with another device's data")
+            .clicked()
         {
-            ctx.request_repaint();
+            if let Err(e) = self.monitor.sync_marker(None) {
+                self.monitor.set_status(format!("Error: {}", e));
+            }
         }
+    }
 
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.heading("Desktop Activity Monitor");
-            ui.add_space(20.0);
+    /// The notes prompt for a stopped session, which is saved once they're
+    /// given or skipped.
+    fn review_controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Task:");
+            ui.strong(self.monitor.task_name());
+        });
+        ui.label("What did you accomplish?");
+        ui.text_edit_multiline(&mut self.notes_input);
+        let mut submitted = None;
+        ui.horizontal(|ui| {
+            if ui.button("Save").clicked() {
+                submitted = Some(Some(self.notes_input.clone()));
+            }
+            if ui.button("Skip").clicked() {
+                submitted = Some(None);
+            }
+            if ui.button("Discard…").clicked() {
+                self.confirm_discard = true;
+            }
+        });
+        if let Some(notes) = submitted {
+            self.monitor.finish_session(notes);
+            self.notes_input.clear();
+        }
+    }
 
+    fn settings_controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.heading("Settings");
+            if self.monitor.is_running() {
+                ui.label(format!("(recording {})", self.monitor.task_name()));
+            }
+            if ui.button("Done").clicked() {
+                self.mode = self.recording_mode();
+            }
+        });
+    }
+
+    fn profile_picker(&mut self, ui: &mut egui::Ui) {
+        if self.monitor.config().profiles.is_empty() {
+            return;
+        }
+        let current = self.monitor.config().active_profile.clone();
+        let mut selected = current.clone();
+        ui.horizontal(|ui| {
+            ui.label("Profile:");
+            egui::ComboBox::from_id_source("config_profile")
+                .selected_text(if current.is_empty() { "None" } else { &current })
+                .show_ui(ui, |ui| {
+                    for profile in &self.monitor.config().profiles {
+                        ui.selectable_value(&mut selected, profile.name.clone(), &profile.name);
+                    }
+                });
+        });
+        if selected != current {
+            let result = self
+                .monitor
+                .switch_profile(&selected)
+                .and_then(|()| self.monitor.config().save());
+            if let Err(e) = result {
+                self.monitor
+                    .set_status(format!("Error switching profile: {}", e));
+            }
+        }
+    }
+
+    /// Every setting, on its own screen.
+    fn settings(&mut self, ui: &mut egui::Ui) {
+        let policy = self.monitor.config().policy.clone();
+        if let Some(source) = &policy.source {
+            ui.colored_label(
+                egui::Color32::from_rgb(80, 140, 220),
+                format!("🔒 Managed by {}", source.display()),
+            );
+            if !policy.notice.is_empty() {
+                ui.label(&policy.notice);
+            }
+        }
+        // Registering would point this machine at a stick that won't always
+        // be plugged in.
+        if ui
+            .add_enabled(
+                !portable::is_active(),
+                egui::Checkbox::new(&mut self.launch_at_login, "Launch at login"),
+            )
+            .on_disabled_hover_text("Not available in portable mode")
+            .changed()
+        {
+            let result = if self.launch_at_login {
+                autostart::enable()
+            } else {
+                autostart::disable()
+            };
+            if let Err(e) = result {
+                self.monitor
+                    .set_status(format!("Error changing launch at login: {}", e));
+                self.launch_at_login = autostart::is_enabled();
+            }
+        }
+
+        ui.horizontal(|ui| {
+            let config = self.monitor.config_mut();
+            if policy_checkbox(
+                ui,
+                &mut config.updates.check_at_startup,
+                policy.update_checks,
+                "Check for updates at startup",
+            )
+            .changed()
+            {
+                if let Err(e) = config.save() {
+                    self.monitor
+                        .set_status(format!("Error saving settings: {}", e));
+                }
+            }
+            if ui
+                .add_enabled(self.update_check.is_none(), egui::Button::new("Check now"))
+                .clicked()
+            {
+                self.update_status = None;
+                self.update_check =
+                    Some(updates::spawn_check(self.monitor.config().updates.clone()));
+            }
+        });
+        if self.update.is_none() {
+            if let Some(status) = &self.update_status {
+                ui.label(status);
+            }
+        }
+
+        self.profiles(ui);
+
+        let config = self.monitor.config_mut();
+        let mut changed = ui
+            .checkbox(
+                &mut config.prompt_session_notes,
+                "Ask for session notes when stopping",
+            )
+            .changed();
+        ui.horizontal(|ui| {
+            ui.label("Countdown before recording (s): ");
+            changed |= ui
+                .add(egui::DragValue::new(&mut config.start_countdown_secs).clamp_range(0..=60))
+                .changed();
+        });
+        changed |= ui
+            .checkbox(
+                &mut config.minimize_on_start,
+                "Minimize when recording starts",
+            )
+            .changed();
+        changed |= ui
+            .checkbox(
+                &mut config.do_not_disturb,
+                "Do Not Disturb while recording (GNOME)",
+            )
+            .changed();
+
+        ui.label("Capture:");
+        changed |= policy_checkbox(
+            ui,
+            &mut config.capture.keyboard,
+            policy.capture.keyboard,
+            "Keyboard events",
+        )
+        .changed();
+        changed |= policy_checkbox(
+            ui,
+            &mut config.capture.mouse_moves,
+            policy.capture.mouse_moves,
+            "Mouse moves",
+        )
+        .changed();
+        changed |= policy_checkbox(
+            ui,
+            &mut config.capture.mouse_buttons,
+            policy.capture.mouse_buttons,
+            "Mouse clicks",
+        )
+        .changed();
+        changed |= policy_checkbox(
+            ui,
+            &mut config.capture.scrolls,
+            policy.capture.scrolls,
+            "Scrolling",
+        )
+        .changed();
+        changed |= policy_checkbox(
+            ui,
+            &mut config.capture.cursor_shapes,
+            policy.capture.cursor_shapes,
+            "Cursor shape",
+        )
+        .changed();
+        changed |= policy_checkbox(
+            ui,
+            &mut config.capture.click_targets,
+            policy.capture.click_targets,
+            "Clicked element",
+        )
+        .changed();
+        changed |= policy_checkbox(
+            ui,
+            &mut config.capture.focus_changes,
+            policy.capture.focus_changes,
+            "Focused window changes",
+        )
+        .changed();
+        ui.add_enabled_ui(config.capture.focus_changes, |ui| {
+            changed |= policy_checkbox(
+                ui,
+                &mut config.capture.window_geometry,
+                policy.capture.window_geometry,
+                "Window position and size",
+            )
+            .changed();
+            changed |= policy_checkbox(
+                ui,
+                &mut config.capture.window_titles,
+                policy.capture.window_titles,
+                "Window titles",
+            )
+            .changed();
+        });
+        changed |= policy_checkbox(
+            ui,
+            &mut config.capture.clipboard,
+            policy.capture.clipboard,
+            "Clipboard copies",
+        )
+        .changed();
+        changed |= ui
+            .checkbox(
+                &mut config.capture.ignore_self,
+                "Ignore input to this window",
+            )
+            .changed();
+        ui.horizontal(|ui| {
+            ui.label("Keep 1 in N mouse moves: ");
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut config.capture.mouse_sample_rate)
+                        .clamp_range(1..=1000),
+                )
+                .changed();
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Minutes per day to keep a streak: ");
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut config.stats.streak_min_minutes).clamp_range(1..=720),
+                )
+                .changed();
+        });
+
+        changed |= ui
+            .checkbox(
+                &mut config.quality.mouse_jiggle.enabled,
+                "Count mouse jiggling as pseudo-idle",
+            )
+            .on_hover_text(
+                "Tiny, regular pointer moves with no other input, \
+                 e.g. from a mouse jiggler",
+            )
+            .changed();
+
+        let calendar = &mut config.calendar;
+        ui.horizontal(|ui| {
+            ui.label("Weeks start on: ");
+            egui::ComboBox::from_id_source("first_day_of_week")
+                .selected_text(calendar.first_day_of_week.to_string())
+                .show_ui(ui, |ui| {
+                    for day in WEEKDAYS {
+                        changed |= ui
+                            .selectable_value(&mut calendar.first_day_of_week, day, day.to_string())
+                            .changed();
+                    }
+                });
+        });
+        ui.horizontal(|ui| {
+            ui.label("Workweek: ");
+            for day in calendar.weekdays().collect::<Vec<_>>() {
+                let mut on = calendar.is_workday(day);
+                if ui.toggle_value(&mut on, day.to_string()).changed() {
+                    calendar.workweek.retain(|d| *d != day);
+                    if on {
+                        calendar.workweek.push(day);
+                    }
+                    changed = true;
+                }
+            }
+        });
+
+        let billing = &mut config.billing;
+        ui.horizontal(|ui| {
+            ui.label("Bill in increments of (minutes): ");
+            changed |= ui
+                .add(egui::DragValue::new(&mut billing.round_to_minutes).clamp_range(0..=240))
+                .changed();
+            for (mode, label) in [
+                (RoundingMode::Nearest, "nearest"),
+                (RoundingMode::Up, "up"),
+                (RoundingMode::Down, "down"),
+            ] {
+                changed |= ui.radio_value(&mut billing.rounding, mode, label).changed();
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Minimum billed per session (minutes): ");
+            changed |= ui
+                .add(egui::DragValue::new(&mut billing.minimum_minutes).clamp_range(0..=480))
+                .changed();
+        });
+
+        let wellness = &mut config.wellness;
+        changed |= ui
+            .checkbox(&mut wellness.enabled, "Break reminders")
+            .changed();
+        ui.add_enabled_ui(wellness.enabled, |ui| {
             ui.horizontal(|ui| {
-                ui.label("Task Name: ");
-                if !self
-                    .monitor
-                    .is_monitoring
-                    .load(std::sync::atomic::Ordering::SeqCst)
+                ui.label("Remind after (minutes): ");
+                changed |= ui
+                    .add(
+                        egui::DragValue::new(&mut wellness.remind_after_minutes)
+                            .clamp_range(5..=240),
+                    )
+                    .changed();
+            });
+            ui.horizontal(|ui| {
+                ui.label("Break length (minutes): ");
+                changed |= ui
+                    .add(egui::DragValue::new(&mut wellness.break_minutes).clamp_range(1..=60))
+                    .changed();
+            });
+            changed |= ui
+                .checkbox(&mut wellness.micro_breaks, "Suggest micro-breaks")
+                .changed();
+        });
+
+        ui.label("App usage alerts (apps separated by commas):");
+        let mut remove = None;
+        for (i, alert) in config.app_alerts.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                changed |= ui
+                    .add(egui::TextEdit::singleline(&mut alert.name).desired_width(80.0))
+                    .changed();
+                ui.label(" apps: ");
+                let mut apps = alert.apps.join(",");
+                if ui
+                    .add(egui::TextEdit::singleline(&mut apps).desired_width(140.0))
+                    .changed()
                 {
-                    ui.text_edit_singleline(&mut self.monitor.task_name);
-                } else {
-                    ui.label(&self.monitor.task_name);
+                    alert.apps = apps.split(',').map(str::to_string).collect();
+                    changed = true;
+                }
+                ui.label(" after (minutes): ");
+                changed |= ui
+                    .add(egui::DragValue::new(&mut alert.minutes).clamp_range(1..=480))
+                    .changed();
+                if ui.small_button("Remove").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+        if let Some(i) = remove {
+            config.app_alerts.remove(i);
+            changed = true;
+        }
+        if ui.button("Add app alert").clicked() {
+            config.app_alerts.push(AppAlert {
+                name: format!("Alert {}", config.app_alerts.len() + 1),
+                apps: Vec::new(),
+                minutes: 20,
+            });
+            changed = true;
+        }
+
+        let screen = &mut config.screen;
+        ui.horizontal(|ui| {
+            let mut known = screen.dpi.is_some();
+            changed |= ui.checkbox(&mut known, "Screen DPI: ").changed();
+            if known != screen.dpi.is_some() {
+                screen.dpi = known.then_some(96.0);
+            }
+            if let Some(dpi) = &mut screen.dpi {
+                changed |= ui
+                    .add(egui::DragValue::new(dpi).clamp_range(50.0..=1000.0))
+                    .changed();
+            }
+            changed |= ui
+                .radio_value(&mut screen.unit, DistanceUnit::Cm, "cm")
+                .changed();
+            changed |= ui
+                .radio_value(&mut screen.unit, DistanceUnit::In, "in")
+                .changed();
+        });
+
+        let daily = &mut config.daily_export;
+        changed |= policy_checkbox(
+            ui,
+            &mut daily.enabled,
+            policy.daily_export_enabled,
+            "Export previous day's sessions daily",
+        )
+        .changed();
+        ui.add_enabled_ui(daily.enabled, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("At (HH:MM): ");
+                changed |= ui.text_edit_singleline(&mut daily.time).changed();
+            });
+            ui.horizontal(|ui| {
+                ui.label("To directory: ");
+                changed |= ui
+                    .add_enabled(
+                        policy.daily_export_directory.is_none(),
+                        egui::TextEdit::singleline(&mut daily.directory),
+                    )
+                    .on_disabled_hover_text(POLICY_LOCKED)
+                    .changed();
+            });
+            ui.horizontal(|ui| {
+                ui.label("Format: ");
+                changed |= ui
+                    .radio_value(&mut daily.format, ExportFormat::Csv, "CSV")
+                    .changed();
+                changed |= ui
+                    .radio_value(&mut daily.format, ExportFormat::Json, "JSON")
+                    .changed();
+                changed |= ui
+                    .radio_value(&mut daily.format, ExportFormat::Xlsx, "XLSX")
+                    .changed();
+            });
+        });
+
+        let archive = &mut config.archive;
+        changed |= ui
+            .checkbox(
+                &mut archive.enabled,
+                "Keep each session in a folder per week",
+            )
+            .on_hover_text(
+                "<directory>/<year>/W<week>/<start>_<elapsed>.csv instead of \
+                 monitoring_sessions.csv, from the next start",
+            )
+            .changed();
+        ui.add_enabled_ui(archive.enabled, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("In directory: ");
+                changed |= ui.text_edit_singleline(&mut archive.directory).changed();
+            });
+        });
+
+        let trials = &mut config.trials;
+        ui.horizontal(|ui| {
+            ui.label("Trial length (s): ");
+            changed |= ui
+                .add(egui::DragValue::new(&mut trials.duration_secs).clamp_range(1..=86_400))
+                .changed();
+            ui.label("Hotkey: ");
+            changed |= ui
+                .add(
+                    egui::TextEdit::singleline(&mut trials.hotkey)
+                        .hint_text("Ctrl+Alt+T")
+                        .desired_width(90.0),
+                )
+                .changed();
+        });
+        ui.horizontal(|ui| {
+            ui.label("Trial start/end mark hotkey: ");
+            changed |= ui
+                .add(
+                    egui::TextEdit::singleline(&mut trials.mark_hotkey)
+                        .hint_text("Ctrl+Alt+M")
+                        .desired_width(90.0),
+                )
+                .changed();
+        });
+        ui.horizontal(|ui| {
+            ui.label("Export trials to: ");
+            changed |= ui.text_edit_singleline(&mut trials.directory).changed();
+            changed |= ui
+                .radio_value(&mut trials.format, ExportFormat::Csv, "CSV")
+                .changed();
+            changed |= ui
+                .radio_value(&mut trials.format, ExportFormat::Json, "JSON")
+                .changed();
+            changed |= ui
+                .radio_value(&mut trials.format, ExportFormat::Xlsx, "XLSX")
+                .changed();
+        });
+        ui.horizontal(|ui| {
+            ui.label("Sync marker hotkey: ");
+            changed |= ui
+                .add(
+                    egui::TextEdit::singleline(&mut config.sync.hotkey)
+                        .hint_text("Ctrl+Alt+S")
+                        .desired_width(90.0),
+                )
+                .changed();
+        });
+        let bug_report = &mut config.bug_report;
+        ui.horizontal(|ui| {
+            ui.label("Bug report length (s): ");
+            changed |= ui
+                .add(egui::DragValue::new(&mut bug_report.duration_secs).clamp_range(1..=3600))
+                .changed();
+            changed |= policy_checkbox(
+                ui,
+                &mut bug_report.screenshots,
+                policy.bug_report_screenshots,
+                "Screenshot every (s): ",
+            )
+            .changed();
+            changed |= ui
+                .add_enabled(
+                    bug_report.screenshots,
+                    egui::DragValue::new(&mut bug_report.screenshot_interval_secs)
+                        .clamp_range(1..=600),
+                )
+                .changed();
+        });
+        ui.horizontal(|ui| {
+            ui.label("Save bug reports to: ");
+            changed |= ui.text_edit_singleline(&mut bug_report.directory).changed();
+        });
+        let buffer = &mut config.ring_buffer;
+        ui.horizontal(|ui| {
+            ui.label("Buffer input while idle (s, 0 = off): ");
+            changed |= ui
+                .add(egui::DragValue::new(&mut buffer.seconds).clamp_range(0..=3600))
+                .changed();
+            ui.label("Save hotkey: ");
+            changed |= ui
+                .add(
+                    egui::TextEdit::singleline(&mut buffer.hotkey)
+                        .hint_text("Ctrl+Alt+B")
+                        .desired_width(90.0),
+                )
+                .changed();
+        });
+
+        let schedule = &mut config.schedule;
+        changed |= ui
+            .checkbox(&mut schedule.enabled, "Start and stop on a schedule")
+            .changed();
+        ui.add_enabled_ui(schedule.enabled, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Prompt before (minutes, 0 = never): ");
+                changed |= ui
+                    .add(egui::DragValue::new(&mut schedule.notify_minutes).clamp_range(0..=60))
+                    .changed();
+            });
+            let mut remove = None;
+            for (i, rule) in schedule.rules.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    changed |= ui
+                        .radio_value(&mut rule.action, ScheduleAction::Start, "Start")
+                        .changed();
+                    changed |= ui
+                        .radio_value(&mut rule.action, ScheduleAction::Stop, "Stop")
+                        .changed();
+                    ui.label("at");
+                    changed |= ui
+                        .add(egui::TextEdit::singleline(&mut rule.time).desired_width(45.0))
+                        .changed();
+                    if NaiveTime::parse_from_str(&rule.time, "%H:%M").is_err() {
+                        ui.colored_label(egui::Color32::RED, "invalid")
+                            .on_hover_text("Use HH:MM");
+                    }
+                    for day in calendar.weekdays() {
+                        let mut on = rule.days.contains(&day);
+                        if ui.toggle_value(&mut on, day.to_string()).changed() {
+                            rule.days.retain(|d| *d != day);
+                            if on {
+                                rule.days.push(day);
+                            }
+                            changed = true;
+                        }
+                    }
+                    if rule.action == ScheduleAction::Start {
+                        changed |= ui
+                            .add(
+                                egui::TextEdit::singleline(&mut rule.task)
+                                    .hint_text("task")
+                                    .desired_width(100.0),
+                            )
+                            .changed();
+                    }
+                    if ui.small_button("✕").clicked() {
+                        remove = Some(i);
+                    }
+                });
+            }
+            if let Some(i) = remove {
+                schedule.rules.remove(i);
+                changed = true;
+            }
+            if ui.button("Add schedule rule").clicked() {
+                schedule.rules.push(ScheduleRule {
+                    days: calendar.workweek.clone(),
+                    ..Default::default()
+                });
+                changed = true;
+            }
+        });
+
+        ui.label("Privacy: redact task names and notes matching (regex):");
+        let rules = &mut config.privacy.redact;
+        let mut remove = None;
+        for (i, rule) in rules.iter_mut().enumerate() {
+            if policy.is_enforced_rule(rule) {
+                ui.add_enabled(false, egui::Label::new(format!("🔒 {}", rule.pattern)))
+                    .on_disabled_hover_text(POLICY_LOCKED);
+                continue;
+            }
+            ui.horizontal(|ui| {
+                changed |= ui.text_edit_singleline(&mut rule.pattern).changed();
+                if ui.small_button("✕").clicked() {
+                    remove = Some(i);
+                }
+                if let Err(e) = regex::Regex::new(&rule.pattern) {
+                    ui.colored_label(egui::Color32::RED, "invalid")
+                        .on_hover_text(e.to_string());
+                }
+            });
+        }
+        if let Some(i) = remove {
+            rules.remove(i);
+            changed = true;
+        }
+        if ui.button("Add redaction rule").clicked() {
+            rules.push(RedactionRule::default());
+            changed = true;
+        }
+        changed |= policy_checkbox(
+            ui,
+            &mut config.privacy.hide_keys,
+            policy.hide_keys,
+            "Leave key names out of keystroke exports",
+        )
+        .changed();
+        ui.horizontal(|ui| {
+            ui.label("Mouse position grid (px, 0 = exact):");
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut config.privacy.mouse_grid_px)
+                        .clamp_range(policy.min_mouse_grid_px.unwrap_or(0)..=1000),
+                )
+                .changed();
+        });
+        let contribute = &mut config.contribute;
+        changed |= policy_checkbox(
+            ui,
+            &mut contribute.enabled,
+            policy.contribute_enabled,
+            "Contribute anonymized statistics to a research dataset",
+        )
+        .on_hover_text("Totals only, shown in full before each upload")
+        .changed();
+        ui.add_enabled_ui(contribute.enabled, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Endpoint: ");
+                changed |= ui
+                    .add_enabled(
+                        policy.contribute_endpoint.is_none(),
+                        egui::TextEdit::singleline(&mut contribute.endpoint).hint_text("https://"),
+                    )
+                    .on_disabled_hover_text(POLICY_LOCKED)
+                    .changed();
+                ui.label("Every (days): ");
+                changed |= ui
+                    .add(egui::DragValue::new(&mut contribute.interval_days).clamp_range(1..=90))
+                    .changed();
+            });
+        });
+
+        if changed {
+            if let Err(e) = self.monitor.config().save() {
+                self.monitor
+                    .set_status(format!("Error saving settings: {}", e));
+            }
+        }
+    }
+
+    /// Collects the results of a running update check or install. They
+    /// arrive on other threads, so frames keep coming until they do.
+    fn poll_updates(&mut self, ctx: &egui::Context) {
+        if let Some(result) = self.update_check.as_ref().and_then(|r| r.try_recv().ok()) {
+            self.update_check = None;
+            match result {
+                Ok(Some(release)) => self.update = Some(release),
+                Ok(None) => {
+                    self.update_status = Some(format!("Up to date ({})", updates::CURRENT_VERSION));
+                }
+                Err(e) => self.update_status = Some(format!("Update check failed: {}", e)),
+            }
+        }
+        if let Some(result) = self.update_install.as_ref().and_then(|r| r.try_recv().ok()) {
+            self.update_install = None;
+            self.update_status = Some(match result {
+                Ok(_) => "Update installed; restart to use it".to_string(),
+                Err(e) => format!("Update failed: {}", e),
+            });
+        }
+        if self.update_check.is_some() || self.update_install.is_some() {
+            ctx.request_repaint_after(Duration::from_millis(500));
+        }
+    }
+
+    /// A newer release's notes, with a link to it and, where the binary
+    /// can be replaced in place, a button to install it.
+    fn update_banner(&mut self, ui: &mut egui::Ui) {
+        let Some(release) = &self.update else {
+            return;
+        };
+        let mut dismiss = false;
+        let mut install = false;
+        ui.horizontal(|ui| {
+            ui.colored_label(
+                egui::Color32::from_rgb(60, 140, 220),
+                format!(
+                    "⬆ {} is available (running {})",
+                    release.name,
+                    updates::CURRENT_VERSION
+                ),
+            );
+            ui.hyperlink_to("Download", &release.page_url);
+            if release.can_install() {
+                install = ui
+                    .add_enabled(self.update_install.is_none(), egui::Button::new("Install"))
+                    .clicked();
+            }
+            dismiss = ui.small_button("✕").clicked();
+        });
+        if !release.notes.trim().is_empty() {
+            ui.collapsing("Release notes", |ui| {
+                egui::ScrollArea::vertical()
+                    .max_height(160.0)
+                    .show(ui, |ui| ui.label(release.notes.trim()));
+            });
+        }
+        if install {
+            self.update_install = Some(updates::spawn_install(release.clone()));
+            self.update_status = Some(format!("Downloading {}...", release.version));
+        }
+        if let Some(status) = &self.update_status {
+            ui.label(status);
+        }
+        if dismiss {
+            self.update = None;
+        }
+        ui.add_space(6.0);
+    }
+
+    /// Statistics due to be contributed, in full as they'd be sent, with
+    /// buttons to send them or put them off until the next launch.
+    fn contribution_banner(&mut self, ui: &mut egui::Ui) {
+        if let Some(result) = self
+            .contribution_send
+            .as_ref()
+            .and_then(|r| r.try_recv().ok())
+        {
+            self.contribution_send = None;
+            self.contribution_status = Some(match result {
+                Ok(path) => {
+                    self.contribution = None;
+                    format!("Statistics contributed; a copy is in {}", path.display())
+                }
+                Err(e) => format!("Contributing failed: {}", e),
+            });
+        }
+        if self.contribution_send.is_some() {
+            ui.ctx().request_repaint_after(Duration::from_millis(500));
+        }
+
+        if let Some(contribution) = &self.contribution {
+            let json = contribution.to_json().unwrap_or_default();
+            let mut send = false;
+            ui.horizontal(|ui| {
+                ui.colored_label(
+                    egui::Color32::from_rgb(60, 140, 220),
+                    format!(
+                        "📊 Statistics for {} – {} are ready to contribute to {}",
+                        contribution.from,
+                        contribution.until,
+                        self.monitor.config().contribute.endpoint
+                    ),
+                );
+                send = ui
+                    .add_enabled(self.contribution_send.is_none(), egui::Button::new("Send"))
+                    .clicked();
+                if ui
+                    .add_enabled(
+                        self.contribution_send.is_none(),
+                        egui::Button::new("Not now"),
+                    )
+                    .clicked()
+                {
+                    self.contribution_dismissed = true;
                 }
             });
+            ui.collapsing("Exactly what will be sent", |ui| {
+                ui.code(&json);
+            });
+            if send {
+                self.contribution_send = Some(contribute::spawn_send(
+                    self.monitor.config().contribute.clone(),
+                    contribution.clone(),
+                ));
+                self.contribution_status = Some("Sending...".to_string());
+            }
+        }
+        if self.contribution_dismissed {
+            self.contribution = None;
+        }
+        if let Some(status) = &self.contribution_status {
+            ui.label(status);
+        }
+    }
+
+    /// Reads a dropped file and opens the import preview for it.
+    fn preview_import(&mut self, path: &Path) {
+        match ImportPreview::load(path, &self.history) {
+            Ok(preview) if preview.sessions.is_empty() => {
+                self.monitor
+                    .set_status(format!("No sessions found in {}", path.display()));
+            }
+            Ok(preview) => self.import = Some(preview),
+            Err(e) => {
+                self.monitor
+                    .set_status(format!("Can't import {}: {}", path.display(), e));
+            }
+        }
+    }
+
+    /// Preview of a dropped export with the conflict choice and Import /
+    /// Cancel buttons.
+    fn import_window(&mut self, ctx: &egui::Context) {
+        let Some(preview) = &self.import else {
+            return;
+        };
+        let monitoring = self.monitor.is_running();
+        let mut close = false;
+        let mut confirmed = false;
+        egui::Window::new("Import sessions")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(preview.path.display().to_string());
+                ui.strong(format!("{} sessions", preview.sessions.len()));
+                if let Some((first, last)) = preview.date_range() {
+                    ui.label(format!("From {} to {}", first, last));
+                }
+                for (task, count) in preview.tasks().iter().take(5) {
+                    ui.label(format!("  {} ({})", task, count));
+                }
+                if !preview.duplicates.is_empty() {
+                    ui.label(format!("Folded together {}", preview.duplicates));
+                }
+
+                if !preview.conflicts.is_empty() {
+                    ui.add_space(6.0);
+                    ui.label(format!(
+                        "{} of these are already stored:",
+                        preview.conflicts.len()
+                    ));
+                    ui.radio_value(
+                        &mut self.import_resolution,
+                        ConflictResolution::Skip,
+                        "Keep the stored copies",
+                    );
+                    ui.radio_value(
+                        &mut self.import_resolution,
+                        ConflictResolution::Replace,
+                        "Replace them with the imported ones",
+                    );
+                    ui.radio_value(
+                        &mut self.import_resolution,
+                        ConflictResolution::Combine,
+                        "Add the imported events to them",
+                    );
+                }
+
+                ui.add_space(6.0);
+                if monitoring {
+                    ui.label("Stop recording to import.");
+                }
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(!monitoring, egui::Button::new("Import"))
+                        .clicked()
+                    {
+                        confirmed = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        close = true;
+                    }
+                });
+            });
+
+        if confirmed {
+            let preview = self.import.take().unwrap();
+            let source = preview.path.display().to_string();
+            match self.monitor.import(preview, self.import_resolution) {
+                Ok(report) => {
+                    self.monitor.set_status(format!(
+                        "Imported {} sessions from {} ({} replaced, {} combined, {} skipped; {})",
+                        report.imported + report.replaced + report.combined,
+                        source,
+                        report.replaced,
+                        report.combined,
+                        report.skipped,
+                        report.duplicates
+                    ));
+                    self.reload_history();
+                }
+                Err(e) => self.monitor.set_status(format!("Import failed: {}", e)),
+            }
+        } else if close {
+            self.import = None;
+        }
+    }
+
+    /// Time ever tracked against the current task, including the running
+    /// session.
+    fn task_time(&self, ui: &mut egui::Ui) {
+        let now = Local::now();
+        let mut time =
+            analysis::task_time(&self.history, self.monitor.task_name(), now.date_naive());
+        if let Some(start) = self.monitor.current_session().started_at() {
+            let running = (now - start).num_seconds().max(0);
+            time.total_secs += running;
+            if start.date_naive() == now.date_naive() {
+                time.today_secs += running;
+            }
+        }
+        ui.label(format!(
+            "This task: {} today · {} all time ({} earlier sessions)",
+            format_duration(time.today_secs),
+            format_duration(time.total_secs),
+            time.sessions
+        ));
+    }
+
+    /// Today's total, streak, last session and quick-start buttons.
+    fn dashboard(&mut self, ui: &mut egui::Ui) {
+        let metrics = self.monitor.metrics();
+        let streak = self.stats.current_streak;
+
+        ui.horizontal(|ui| {
+            ui.vertical(|ui| {
+                ui.label("Tracked today");
+                ui.heading(format_duration(metrics.tracked_today_secs));
+            });
+            if matches!(self.mode, Mode::Recording | Mode::Paused) {
+                ui.add_space(30.0);
+                ui.vertical(|ui| {
+                    ui.label("Actions/min");
+                    ui.heading(metrics.apm.to_string());
+                });
+            }
+            ui.add_space(30.0);
+            ui.vertical(|ui| {
+                ui.label("Current streak");
+                ui.heading(format_days(streak));
+            });
+        });
+
+        if metrics.task_secs.len() > 1 {
+            let mut tasks: Vec<_> = metrics.task_secs.iter().collect();
+            tasks.sort_by_key(|(_, secs)| std::cmp::Reverse(**secs));
+            ui.label(
+                tasks
+                    .iter()
+                    .map(|(task, secs)| format!("{} {}", task, format_duration(**secs)))
+                    .collect::<Vec<_>>()
+                    .join(" · "),
+            );
+        }
+
+        ui.add_space(10.0);
+        ui.label("Personal bests");
+        ui.label(format!(
+            "Longest streak: {}",
+            format_days(self.stats.longest_streak)
+        ));
+        if let Some(task) = &self.stats.longest_focus_task {
+            ui.label(format!(
+                "Longest focus block: {} ({})",
+                format_duration(self.stats.longest_focus_secs),
+                task
+            ));
+        }
+        ui.label(format!(
+            "Best day: {}",
+            format_duration(self.stats.best_day_secs)
+        ));
+        if !self.stats.badges.is_empty() {
+            ui.horizontal_wrapped(|ui| {
+                for badge in &self.stats.badges {
+                    ui.label(egui::RichText::new(format!("🏅 {}", badge.name)).strong())
+                        .on_hover_text(format!(
+                            "{} (earned {})",
+                            badge.description, badge.earned_on
+                        ));
+                }
+            });
+        }
 
+        ui.add_space(10.0);
+        ui.label("Last session");
+        match self.history.last() {
+            Some(last) => {
+                ui.strong(&last.task_name);
+                ui.label(format!(
+                    "{} · {} events · mouse {}",
+                    last.duration()
+                        .map_or("-".to_string(), |d| format_duration(d.num_seconds())),
+                    analysis::event_count(last),
+                    analysis::format_mouse_distance(last, &self.monitor.config().screen)
+                ));
+                for (name, value) in metrics::session_metrics(self.monitor.config(), last) {
+                    ui.label(format!("{}: {:.2}", name, value));
+                }
+                if let Some(notes) = &last.notes {
+                    ui.label(notes);
+                }
+            }
+            None => {
+                ui.label("No sessions recorded yet");
+            }
+        }
+
+        let mut recent: Vec<&str> = Vec::new();
+        for session in self.history.iter().rev() {
+            if recent.len() == QUICK_START_TASKS {
+                break;
+            }
+            if !recent.contains(&session.task_name.as_str()) {
+                recent.push(&session.task_name);
+            }
+        }
+        if !recent.is_empty() {
             ui.add_space(10.0);
+            ui.label("Quick start");
+            let mut start = None;
+            ui.horizontal_wrapped(|ui| {
+                for task in recent {
+                    if ui
+                        .add_enabled(
+                            self.mode == Mode::Idle && self.start_at.is_none(),
+                            egui::Button::new(task),
+                        )
+                        .clicked()
+                    {
+                        start = Some(task.to_string());
+                    }
+                }
+            });
+            if let Some(task) = start {
+                self.monitor.set_task_name(task);
+                self.request_start(ui.ctx());
+            }
+        }
+    }
+
+    /// Year-view heat map of tracked time per day. Clicking a day lists
+    /// its sessions.
+    fn reports(&mut self, ui: &mut egui::Ui) {
+        let today = Local::now().date_naive();
+        let totals: HashMap<NaiveDate, i64> = analysis::daily_totals(&self.history)
+            .into_iter()
+            .map(|total| (total.date, total.tracked_secs))
+            .collect();
+        let max_secs = totals.values().copied().max().unwrap_or(0);
+        let calendar = &self.monitor.config().calendar;
+        // Columns are weeks from the configured first day; the last one
+        // holds today.
+        let first = calendar.week_start(today) - Days::new(7 * (HEAT_MAP_WEEKS - 1));
 
-            if !self.monitor.task_name.trim().is_empty() {
-                if ui.button("Start Monitoring").clicked() {
-                    self.monitor.start_monitoring();
+        let step = HEAT_MAP_CELL + HEAT_MAP_GAP;
+        let (rect, response) = ui.allocate_exact_size(
+            egui::vec2(HEAT_MAP_WEEKS as f32 * step, 7.0 * step),
+            egui::Sense::click(),
+        );
+        let painter = ui.painter_at(rect);
+        for week in 0..HEAT_MAP_WEEKS {
+            for weekday in 0..7 {
+                let date = first + Days::new(week * 7 + weekday);
+                if date > today {
+                    break;
+                }
+                let cell = egui::Rect::from_min_size(
+                    rect.min + egui::vec2(week as f32 * step, weekday as f32 * step),
+                    egui::vec2(HEAT_MAP_CELL, HEAT_MAP_CELL),
+                );
+                let secs = totals.get(&date).copied().unwrap_or(0);
+                if secs <= 0 && !calendar.is_workday(date.weekday()) {
+                    // Empty days off are outlined rather than filled, so
+                    // they don't read as missed workdays.
+                    painter.rect_stroke(
+                        cell.shrink(0.5),
+                        2.0,
+                        egui::Stroke::new(1.0, ui.visuals().faint_bg_color),
+                    );
+                } else {
+                    painter.rect_filled(cell, 2.0, heat_color(ui, secs, max_secs));
                 }
+                if self.selected_day == Some(date) {
+                    painter.rect_stroke(cell, 2.0, ui.visuals().selection.stroke);
+                }
+            }
+        }
+
+        let hovered = response
+            .hover_pos()
+            .filter(|pos| rect.contains(*pos))
+            .map(|pos| {
+                let offset = pos - rect.min;
+                let (week, weekday) = ((offset.x / step) as u64, (offset.y / step) as u64);
+                first + Days::new(week * 7 + weekday.min(6))
+            })
+            .filter(|date| *date <= today);
+        if response.clicked() {
+            if let Some(date) = hovered {
+                self.selected_day = (self.selected_day != Some(date)).then_some(date);
+            }
+        }
+        if let Some(date) = hovered {
+            response.on_hover_text(format!(
+                "{}: {}",
+                date,
+                format_duration(totals.get(&date).copied().unwrap_or(0))
+            ));
+        }
+
+        self.weekly_totals(ui, today);
+
+        let Some(day) = self.selected_day else {
+            ui.label("Click a day to see its sessions");
+            return;
+        };
+        ui.add_space(6.0);
+        ui.strong(format!(
+            "{} · {}",
+            day.format("%A %Y-%m-%d"),
+            format_duration(totals.get(&day).copied().unwrap_or(0))
+        ));
+        let mut any = false;
+        let mut picked = None;
+        for (i, session) in self.history.iter().enumerate() {
+            let Some(start) = session.started_at().filter(|t| t.date_naive() == day) else {
+                continue;
+            };
+            any = true;
+            let billing = &self.monitor.config().billing;
+            let duration = match session.duration().map(|d| d.num_seconds()) {
+                Some(secs) if billing::rounds(billing) => format!(
+                    "{}, billed {}",
+                    format_duration(secs),
+                    format_duration(billing::billed_secs(secs, billing))
+                ),
+                Some(secs) => format_duration(secs),
+                None => "-".to_string(),
+            };
+            let pseudo_idle =
+                jiggle::pseudo_idle_secs(session, &self.monitor.config().quality.mouse_jiggle);
+            let pseudo_idle = if pseudo_idle > 0 {
+                format!(", {} pseudo-idle", format_duration(pseudo_idle))
             } else {
-                ui.add_enabled(false, egui::Button::new("Start Monitoring"));
+                String::new()
+            };
+            let tags = session.tags();
+            let tags = if tags.is_empty() {
+                String::new()
+            } else {
+                format!("  [{}]", tags.join(", "))
+            };
+            let row = egui::Label::new(format!(
+                "{}  {} ({}{}){}",
+                start.format("%H:%M"),
+                session.task_name,
+                duration,
+                pseudo_idle,
+                tags
+            ))
+            .sense(egui::Sense::click());
+            ui.add(row)
+                .on_hover_text("Right-click for more")
+                .context_menu(|ui| {
+                    if let Some(action) = history_menu(ui, session, self.monitor.config()) {
+                        picked = Some((i, action));
+                    }
+                });
+        }
+        if !any {
+            ui.label("No sessions");
+        }
+        if let Some((i, action)) = picked {
+            let session = self.history[i].clone();
+            self.history_action(&session, action);
+        }
+    }
+
+    /// Tracked time over the last few weeks, laid out by the configured
+    /// calendar.
+    fn weekly_totals(&self, ui: &mut egui::Ui, today: NaiveDate) {
+        let calendar = &self.monitor.config().calendar;
+        let since = calendar.week_start(today) - Days::new(7 * (WEEKLY_ROWS - 1));
+        let weeks = analysis::weekly_totals(
+            self.history
+                .iter()
+                .filter(|s| s.started_at().is_some_and(|t| t.date_naive() >= since)),
+            calendar,
+        );
+        ui.add_space(6.0);
+        ui.strong("Weekly");
+        if weeks.is_empty() {
+            ui.label("Nothing tracked in the last few weeks");
+            return;
+        }
+        let workdays = calendar.workweek.len().max(1) as i64;
+        egui::Grid::new("weekly_totals").show(ui, |ui| {
+            for week in weeks.iter().rev() {
+                ui.label(format!("Week of {}", week.week_start.format("%b %-d")));
+                ui.label(format_duration(week.tracked_secs));
+                ui.label(format!(
+                    "{} per workday",
+                    format_duration((week.tracked_secs - week.off_day_secs) / workdays)
+                ));
+                if week.off_day_secs > 0 {
+                    ui.label(format!(
+                        "{} outside the workweek",
+                        format_duration(week.off_day_secs)
+                    ));
+                }
+                ui.end_row();
             }
+        });
+    }
 
-            if ui.button("Stop Monitoring").clicked() {
-                self.monitor.stop_monitoring();
+    /// Histogram of the pauses between input on the latest recorded day and
+    /// the idle threshold they suggest for micro-breaks.
+    fn idle_calibration(&mut self, ui: &mut egui::Ui) {
+        ui.strong("Idle calibration");
+        let pauses = &self.pauses;
+        let Some(day) = pauses.day else {
+            ui.label("Record a day of work to see your pauses");
+            return;
+        };
+        ui.label(format!("{} pauses of 1s or more on {}", pauses.pauses, day));
+
+        let max = pauses.counts.iter().copied().max().unwrap_or(0).max(1);
+        egui::Grid::new("pause_histogram").show(ui, |ui| {
+            for (i, &count) in pauses.counts.iter().enumerate() {
+                ui.label(PauseDistribution::bucket_label(i));
+                let (rect, _) =
+                    ui.allocate_exact_size(egui::vec2(200.0, 10.0), egui::Sense::hover());
+                let width = rect.width() * count as f32 / max as f32;
+                ui.painter().rect_filled(
+                    egui::Rect::from_min_size(rect.min, egui::vec2(width, rect.height())),
+                    2.0,
+                    ui.visuals().selection.bg_fill,
+                );
+                ui.label(count.to_string());
+                ui.end_row();
+            }
+        });
+
+        let Some(secs) = pauses.suggested_secs else {
+            ui.label(format!(
+                "Keep recording; a suggestion needs {} pauses",
+                calibration::MIN_PAUSES
+            ));
+            return;
+        };
+        let current = self.monitor.config().wellness.micro_break_secs;
+        ui.label(format!(
+            "Suggested idle threshold: {}s (95% of your pauses are shorter); \
+             micro-breaks currently need {}s",
+            secs, current
+        ));
+        if ui
+            .add_enabled(secs != current, egui::Button::new("Use for micro-breaks"))
+            .clicked()
+        {
+            self.monitor.config_mut().wellness.micro_break_secs = secs;
+            if let Err(e) = self.monitor.config().save() {
+                self.monitor
+                    .set_status(format!("Error saving settings: {}", e));
             }
+        }
+    }
+
+    /// Burn-down of each task budget, and a form to add one.
+    fn budgets(&mut self, ui: &mut egui::Ui) {
+        ui.strong("Budgets");
+        let today = Local::now().date_naive();
+        let mut remove = None;
+        for (i, budget) in self.monitor.config().budgets.iter().enumerate() {
+            let burn = analysis::burn_down(
+                &self.history,
+                &budget.task_name,
+                budget.planned_hours,
+                today,
+            );
+            ui.add_space(4.0);
+            ui.horizontal(|ui| {
+                ui.label(format!(
+                    "{}: {} of {}",
+                    budget.task_name,
+                    format_duration(burn.tracked_secs),
+                    format_duration(burn.planned_secs)
+                ));
+                if ui.small_button("Remove").clicked() {
+                    remove = Some(i);
+                }
+            });
+            burn_down_chart(ui, &burn, today);
+            ui.label(match burn.projected_completion {
+                _ if burn.remaining_secs() <= 0 => {
+                    format!("Over budget by {}", format_duration(-burn.remaining_secs()))
+                }
+                Some(date) => format!(
+                    "{} left, used up around {} at this pace",
+                    format_duration(burn.remaining_secs()),
+                    date
+                ),
+                None => "Nothing tracked yet".to_string(),
+            });
+        }
 
-            ui.add_space(20.0);
-            ui.label(&self.monitor.status_text);
+        let mut add = false;
+        ui.horizontal(|ui| {
+            ui.label("Task: ");
+            ui.text_edit_singleline(&mut self.budget_task_input);
+            ui.label(" hours: ");
+            ui.add(
+                egui::DragValue::new(&mut self.budget_hours_input)
+                    .clamp_range(0.5..=10_000.0)
+                    .speed(0.5),
+            );
+            add = ui
+                .add_enabled(
+                    !self.budget_task_input.trim().is_empty(),
+                    egui::Button::new("Add budget"),
+                )
+                .clicked();
+        });
+
+        if remove.is_none() && !add {
+            return;
+        }
+        let budgets = &mut self.monitor.config_mut().budgets;
+        if let Some(i) = remove {
+            budgets.remove(i);
+        }
+        if add {
+            let task_name = self.budget_task_input.trim().to_string();
+            budgets.retain(|b| b.task_name != task_name);
+            budgets.push(TaskBudget {
+                task_name,
+                planned_hours: self.budget_hours_input,
+            });
+            self.budget_task_input.clear();
+        }
+        if let Err(e) = self.monitor.config().save() {
+            self.monitor
+                .set_status(format!("Error saving settings: {}", e));
+        }
+    }
 
-            ui.add_space(20.0);
-            ui.label("Sessions are saved in: monitoring_sessions.csv");
-            ui.label("Latest detailed events are in: latest_session_details.csv");
+    /// Saving the current capture, privacy and export settings as a named
+    /// profile, and deleting profiles. Switching is in the controls above.
+    fn profiles(&mut self, ui: &mut egui::Ui) {
+        let mut remove = None;
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Profiles: ");
+            if self.monitor.config().profiles.is_empty() {
+                ui.weak("none");
+            }
+            for profile in &self.monitor.config().profiles {
+                ui.label(&profile.name);
+                if ui.small_button("✖").on_hover_text("Delete").clicked() {
+                    remove = Some(profile.name.clone());
+                }
+            }
+        });
+        let mut save = false;
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.profile_name_input).desired_width(140.0));
+            save = ui
+                .add_enabled(
+                    !self.profile_name_input.trim().is_empty(),
+                    egui::Button::new("Save settings as profile"),
+                )
+                .on_hover_text("Capture, privacy and daily export settings")
+                .clicked();
         });
+
+        if remove.is_none() && !save {
+            return;
+        }
+        let config = self.monitor.config_mut();
+        if let Some(name) = remove {
+            config.remove_profile(&name);
+        }
+        if save {
+            config.save_profile(self.profile_name_input.trim());
+            self.profile_name_input.clear();
+        }
+        if let Err(e) = self.monitor.config().save() {
+            self.monitor
+                .set_status(format!("Error saving settings: {}", e));
+        }
+    }
+}
+
+impl eframe::App for MonitorApp {
+    /// Portable mode keeps nothing outside its folder, and eframe would
+    /// write its state to the user's data directory.
+    fn persist_egui_memory(&self) -> bool {
+        !portable::is_active()
     }
+
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // Update monitor state
+        let (focused, hovered) = ctx.input(|i| (i.focused, i.pointer.has_pointer()));
+        self.monitor.set_self_focus(focused, hovered);
+        self.monitor.update();
+        self.sync_mode();
+        crash::record_status(self.monitor.status(), self.monitor.task_name());
+
+        // Capture runs on frames, so keep them coming while recording or
+        // filling the ring buffer, at the monitor's adaptive rate rather than
+        // continuously.
+        if self.monitor.is_running() || self.monitor.config().ring_buffer.seconds > 0 {
+            ctx.request_repaint_after(self.monitor.poll_interval());
+        } else if !self.monitor.config().trials.hotkey.trim().is_empty() {
+            // The trial hotkey is only seen on frames.
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+
+        if let Some(start_at) = self.start_at {
+            if Instant::now() >= start_at {
+                self.start(ctx);
+            } else {
+                ctx.request_repaint_after(Duration::from_millis(100));
+            }
+        }
+
+        let scheduled: Vec<ScheduleEvent> = self.schedule.try_iter().collect();
+        for event in scheduled {
+            self.handle_schedule(ctx, event);
+        }
+        if self.monitor.config().schedule.enabled {
+            ctx.request_repaint_after(Duration::from_secs(1));
+        }
+
+        self.poll_updates(ctx);
+        // Held writes are retried on frames.
+        if self.monitor.write_problem().is_some() {
+            ctx.request_repaint_after(Duration::from_secs(1));
+        }
+
+        let now = Instant::now();
+        let mut saved = false;
+        for event in self.events.try_iter() {
+            match event {
+                MonitorEvent::Started { .. } => self.activity = Activity::new(),
+                MonitorEvent::Recorded(action) => {
+                    if matches!(&action, Action::Custom { kind, .. } if kind == "app_alert") {
+                        ctx.send_viewport_cmd(egui::ViewportCommand::RequestUserAttention(
+                            egui::UserAttentionType::Informational,
+                        ));
+                    }
+                    self.activity.record(now);
+                }
+                MonitorEvent::SessionSaved { .. } => saved = true,
+                _ => {}
+            }
+        }
+        if saved {
+            self.reload_history();
+        }
+        self.activity.advance(now);
+
+        // The title shows in the taskbar/dock, so momentum can be checked
+        // while the window is minimized.
+        let title = if self.monitor.is_paused() {
+            format!("⏸ {} — {}", self.monitor.task_name(), WINDOW_TITLE)
+        } else if self.monitor.is_running() {
+            format!(
+                "{} {} — {}",
+                self.activity.sparkline(),
+                self.monitor.task_name(),
+                WINDOW_TITLE
+            )
+        } else {
+            WINDOW_TITLE.to_string()
+        };
+        if title != self.title {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(title.clone()));
+            self.title = title;
+        }
+
+        egui::TopBottomPanel::top("controls").show(ctx, |ui| {
+            ui.add_space(4.0);
+            self.controls(ui);
+            ui.add_space(4.0);
+        });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                if self.mode == Mode::Settings {
+                    self.settings(ui);
+                    return;
+                }
+                self.update_banner(ui);
+                self.contribution_banner(ui);
+                self.dashboard(ui);
+
+                ui.add_space(10.0);
+                ui.collapsing("Reports", |ui| {
+                    self.reports(ui);
+                    ui.add_space(10.0);
+                    self.budgets(ui);
+                    ui.add_space(10.0);
+                    self.idle_calibration(ui);
+                });
+                let state = MonitorState {
+                    monitor: &self.monitor,
+                    history: &self.history,
+                    stats: &self.stats,
+                };
+                for panel in &mut self.panels {
+                    ui.collapsing(panel.title().to_string(), |ui| panel.ui(ui, &state));
+                }
+
+                ui.add_space(20.0);
+                if let Some(dir) = portable::data_dir() {
+                    ui.label(format!("Portable mode: data is kept in {}", dir.display()));
+                }
+                ui.label("Sessions are saved in: monitoring_sessions.csv");
+                ui.label("Latest detailed events are in: latest_session_details.csv");
+
+                ui.add_space(10.0);
+                ui.collapsing("Diagnostics", |ui| {
+                    let diag = self.monitor.diagnostics();
+                    ui.label(format!(
+                        "CPU: {}",
+                        diag.cpu_percent
+                            .map_or("n/a".to_string(), |cpu| format!("{:.1}%", cpu))
+                    ));
+                    ui.label(format!(
+                        "Memory: {}",
+                        diag.memory_bytes.map_or("n/a".to_string(), |bytes| format!(
+                            "{:.1} MB",
+                            bytes as f64 / (1024.0 * 1024.0)
+                        ))
+                    ));
+                    ui.label(format!("Events/sec: {:.1}", diag.events_per_sec));
+                    ui.label(format!(
+                        "Avg write latency: {:.2} ms",
+                        diag.avg_write_latency.as_secs_f64() * 1000.0
+                    ));
+                    for sink in self.monitor.sink_health() {
+                        let text = format!(
+                            "Sink {}: {} delivered, {} dropped, {} buffered{}",
+                            sink.name,
+                            sink.delivered,
+                            sink.dropped,
+                            sink.buffered,
+                            if sink.backlogged {
+                                " (falling behind)"
+                            } else {
+                                ""
+                            }
+                        );
+                        if sink.backlogged || sink.dropped > 0 {
+                            ui.colored_label(egui::Color32::YELLOW, text);
+                        } else {
+                            ui.label(text);
+                        }
+                    }
+
+                    let config = &mut self.monitor.config_mut().diagnostics;
+                    let mut changed = false;
+                    ui.horizontal(|ui| {
+                        ui.label("Polls per second while active: ");
+                        changed |= ui
+                            .add(
+                                egui::DragValue::new(&mut config.active_poll_hz)
+                                    .clamp_range(1..=240),
+                            )
+                            .changed();
+                        ui.label(" idle: ");
+                        changed |= ui
+                            .add(egui::DragValue::new(&mut config.idle_poll_hz).clamp_range(1..=60))
+                            .changed();
+                    });
+                    changed |= ui
+                        .checkbox(&mut config.low_overhead_mode, "Low-overhead mode")
+                        .changed();
+                    ui.horizontal(|ui| {
+                        ui.label("Throttle above CPU %: ");
+                        changed |= ui
+                            .add(
+                                egui::DragValue::new(&mut config.cpu_threshold_percent)
+                                    .clamp_range(0.5..=100.0)
+                                    .speed(0.5),
+                            )
+                            .changed();
+                    });
+                    if diag.throttled {
+                        ui.label("Capture is throttled to reduce overhead");
+                    }
+                    if changed {
+                        if let Err(e) = self.monitor.config().save() {
+                            self.monitor
+                                .set_status(format!("Error saving settings: {}", e));
+                        }
+                    }
+                });
+            });
+        });
+
+        let dropped = ctx.input(|i| {
+            i.raw
+                .dropped_files
+                .iter()
+                .find_map(|file| file.path.clone())
+        });
+        if let Some(path) = dropped {
+            self.preview_import(&path);
+        }
+        if ctx.input(|i| !i.raw.hovered_files.is_empty()) {
+            let painter = ctx.layer_painter(egui::LayerId::new(
+                egui::Order::Foreground,
+                egui::Id::new("file_drop"),
+            ));
+            let rect = ctx.screen_rect();
+            painter.rect_filled(rect, 0.0, egui::Color32::from_black_alpha(160));
+            painter.text(
+                rect.center(),
+                egui::Align2::CENTER_CENTER,
+                "Drop an export to import it",
+                egui::FontId::proportional(18.0),
+                egui::Color32::WHITE,
+            );
+        }
+        self.import_window(ctx);
+        self.schedule_window(ctx);
+        self.discard_window(ctx);
+        self.consent_window(ctx);
+        self.crash_window(ctx);
+        self.relocate_window(ctx);
+        self.history_window(ctx);
+    }
+}
+
+/// A checkbox that's locked when the policy file `enforced` a value.
+fn policy_checkbox(
+    ui: &mut egui::Ui,
+    value: &mut bool,
+    enforced: Option<bool>,
+    text: &str,
+) -> egui::Response {
+    ui.add_enabled(enforced.is_none(), egui::Checkbox::new(value, text))
+        .on_disabled_hover_text(POLICY_LOCKED)
+}
+
+/// GitHub-style shades of green in four steps relative to the busiest day;
+/// days with nothing tracked get the background colour.
+fn heat_color(ui: &egui::Ui, secs: i64, max_secs: i64) -> egui::Color32 {
+    const LEVELS: [egui::Color32; 4] = [
+        egui::Color32::from_rgb(14, 68, 41),
+        egui::Color32::from_rgb(0, 109, 50),
+        egui::Color32::from_rgb(38, 166, 65),
+        egui::Color32::from_rgb(57, 211, 83),
+    ];
+    if secs <= 0 || max_secs <= 0 {
+        return ui.visuals().faint_bg_color;
+    }
+    let level = (secs * LEVELS.len() as i64 - 1) / max_secs;
+    LEVELS[level.clamp(0, LEVELS.len() as i64 - 1) as usize]
+}
+
+fn format_days(days: u32) -> String {
+    format!("{} day{}", days, if days == 1 { "" } else { "s" })
+}
+
+/// Cumulative tracked time (solid) against the budget line, with the
+/// projection to completion dashed, from the first tracked day on.
+fn burn_down_chart(ui: &mut egui::Ui, burn: &BurnDown, today: NaiveDate) {
+    let Some(&(first, _)) = burn.days.first() else {
+        return;
+    };
+    let last = burn.projected_completion.unwrap_or(today).max(today);
+    let span_days = (last - first).num_days().max(1) as f32;
+    let max_secs = burn.planned_secs.max(burn.tracked_secs).max(1) as f32;
+
+    let (rect, _) = ui.allocate_exact_size(
+        egui::vec2(ui.available_width().min(360.0), 80.0),
+        egui::Sense::hover(),
+    );
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+    let point = |date: NaiveDate, secs: i64| {
+        egui::pos2(
+            rect.left() + rect.width() * (date - first).num_days() as f32 / span_days,
+            rect.bottom() - rect.height() * secs as f32 / max_secs,
+        )
+    };
+
+    let budget_y = point(first, burn.planned_secs).y;
+    painter.hline(
+        rect.x_range(),
+        budget_y,
+        egui::Stroke::new(1.0, ui.visuals().warn_fg_color),
+    );
+
+    let stroke = egui::Stroke::new(2.0, ui.visuals().selection.bg_fill);
+    let mut line = vec![point(first, 0)];
+    let mut previous = 0;
+    for &(date, secs) in &burn.days {
+        line.push(point(date, previous));
+        line.push(point(date, secs));
+        previous = secs;
+    }
+    line.push(point(today, burn.tracked_secs));
+    painter.add(egui::Shape::line(line, stroke));
+
+    if let Some(done) = burn.projected_completion {
+        painter.extend(egui::Shape::dashed_line(
+            &[
+                point(today, burn.tracked_secs),
+                point(done, burn.planned_secs),
+            ],
+            stroke,
+            4.0,
+            4.0,
+        ));
+    }
+}
+
+/// The right-click menu of a session in the history. "Copy summary" is
+/// done right away; anything else picked is returned.
+fn history_menu(ui: &mut egui::Ui, session: &Session, config: &Config) -> Option<HistoryAction> {
+    let mut action = None;
+    ui.menu_button("Export", |ui| {
+        for format in [ExportFormat::Csv, ExportFormat::Json, ExportFormat::Xlsx] {
+            if ui.button(format.extension().to_uppercase()).clicked() {
+                action = Some(HistoryAction::Export(format));
+            }
+        }
+    });
+    if ui.button("Rename task…").clicked() {
+        let name = session.task_name.clone();
+        let prompt = HistoryPrompt::Rename(session.clone(), name);
+        action = Some(HistoryAction::Prompt(Box::new(prompt)));
+    }
+    if ui.button("Add tag…").clicked() {
+        let prompt = HistoryPrompt::Tag(session.clone(), String::new());
+        action = Some(HistoryAction::Prompt(Box::new(prompt)));
+    }
+    if ui.button("Open containing folder").clicked() {
+        action = Some(HistoryAction::OpenFolder);
+    }
+    if ui.button("Copy summary").clicked() {
+        let summary = session_summary(session, config);
+        ui.output_mut(|o| o.copied_text = summary);
+        ui.close_menu();
+    }
+    ui.separator();
+    if ui.button("Delete…").clicked() {
+        let prompt = HistoryPrompt::Delete(session.clone());
+        action = Some(HistoryAction::Prompt(Box::new(prompt)));
+    }
+    if action.is_some() {
+        ui.close_menu();
+    }
+    action
+}
+
+/// One line about `session`, for pasting elsewhere.
+fn session_summary(session: &Session, config: &Config) -> String {
+    let mut parts = vec![
+        session.started_at().map_or_else(
+            || session.start_time.clone(),
+            |t| t.format("%Y-%m-%d %H:%M").to_string(),
+        ),
+        session.task_name.clone(),
+        session
+            .duration()
+            .map_or("-".to_string(), |d| format_duration(d.num_seconds())),
+        format!("{} events", analysis::event_count(session)),
+        format!(
+            "mouse {}",
+            analysis::format_mouse_distance(session, &config.screen)
+        ),
+    ];
+    let tags = session.tags();
+    if !tags.is_empty() {
+        parts.push(format!("tags: {}", tags.join(", ")));
+    }
+    if let Some(notes) = &session.notes {
+        parts.push(notes.clone());
+    }
+    parts.join(" · ")
+}
+
+fn format_duration(secs: i64) -> String {
+    format!("{}h {:02}m", secs / 3600, (secs % 3600) / 60)
 }
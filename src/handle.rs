@@ -0,0 +1,170 @@
+//! A handle for driving an [`ActivityMonitor`] from other threads: the HTTP
+//! API, integrations, a future tray icon. Input devices can't leave the
+//! thread that opened them, so instead of sharing the monitor behind a lock
+//! its owner keeps it, and calls made through a handle are queued and run
+//! on the owner's next `update()`. Owners that only update on demand, like
+//! the GUI, register a waker so queued calls don't wait for input.
+
+use crate::aggregates::MetricsSnapshot;
+use crate::monitor::ActivityMonitor;
+use crate::sync::SyncMarker;
+use anyhow::{anyhow, Result};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How long [`MonitorHandle::call`] waits for the owning thread.
+pub const CALL_TIMEOUT: Duration = Duration::from_secs(2);
+
+type Command = Box<dyn FnOnce(&mut ActivityMonitor) + Send>;
+type Waker = Arc<dyn Fn() + Send + Sync>;
+
+/// The monitor's state at one point, as seen from another thread.
+#[derive(Debug, Clone)]
+pub struct LiveState {
+    pub running: bool,
+    pub task_name: String,
+    pub status: String,
+    pub metrics: MetricsSnapshot,
+}
+
+/// Cheap to clone and `Send`; every clone drives the same monitor.
+#[derive(Clone)]
+pub struct MonitorHandle {
+    commands: Sender<Command>,
+    waker: Arc<Mutex<Option<Waker>>>,
+}
+
+/// The monitor's end of its handles.
+pub(crate) struct CommandQueue {
+    commands: Receiver<Command>,
+    handle: MonitorHandle,
+}
+
+pub(crate) fn queue() -> CommandQueue {
+    let (tx, rx) = mpsc::channel();
+    CommandQueue {
+        commands: rx,
+        handle: MonitorHandle {
+            commands: tx,
+            waker: Arc::new(Mutex::new(None)),
+        },
+    }
+}
+
+impl CommandQueue {
+    pub(crate) fn handle(&self) -> MonitorHandle {
+        self.handle.clone()
+    }
+
+    pub(crate) fn set_waker(&self, waker: Waker) {
+        *self.handle.waker.lock().unwrap() = Some(waker);
+    }
+
+    /// Calls queued since the last update.
+    pub(crate) fn pending(&self) -> Vec<Command> {
+        self.commands.try_iter().collect()
+    }
+}
+
+impl MonitorHandle {
+    /// Queues `f` to run on the monitor's thread without waiting for it.
+    pub fn send(&self, f: impl FnOnce(&mut ActivityMonitor) + Send + 'static) -> Result<()> {
+        self.commands
+            .send(Box::new(f))
+            .map_err(|_| anyhow!("the monitor has shut down"))?;
+        if let Some(wake) = self.waker.lock().unwrap().as_ref() {
+            wake();
+        }
+        Ok(())
+    }
+
+    /// Runs `f` on the monitor's thread and returns its result, failing
+    /// if the monitor doesn't get to it within [`CALL_TIMEOUT`].
+    pub fn call<R: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut ActivityMonitor) -> R + Send + 'static,
+    ) -> Result<R> {
+        let (tx, rx) = mpsc::channel();
+        self.send(move |monitor| {
+            let _ = tx.send(f(monitor));
+        })?;
+        rx.recv_timeout(CALL_TIMEOUT)
+            .map_err(|_| anyhow!("the monitor didn't respond"))
+    }
+
+    pub fn live_state(&self) -> Result<LiveState> {
+        self.call(|monitor| LiveState {
+            running: monitor.is_running(),
+            task_name: monitor.task_name().to_string(),
+            status: monitor.status().to_string(),
+            metrics: monitor.metrics(),
+        })
+    }
+
+    pub fn start(&self) -> Result<()> {
+        self.call(|monitor| monitor.start_monitoring())
+    }
+
+    /// Starts a fixed-length trial; see `ActivityMonitor::start_trial`.
+    /// Returns whether it started and the status line, which says why not.
+    pub fn start_trial(&self) -> Result<(bool, String)> {
+        self.call(|monitor| {
+            monitor.start_trial();
+            (monitor.is_running(), monitor.status().to_string())
+        })
+    }
+
+    /// Starts a bug-report capture; see `ActivityMonitor::start_bug_report`.
+    /// Returns whether it started and the status line.
+    pub fn start_bug_report(&self) -> Result<(bool, String)> {
+        self.call(|monitor| {
+            monitor.start_bug_report();
+            (monitor.is_running(), monitor.status().to_string())
+        })
+    }
+
+    /// Marks the start of a trial within the current session; see
+    /// `ActivityMonitor::mark_trial_start`.
+    pub fn mark_trial_start(&self, trial_id: Option<String>) -> Result<String> {
+        self.call(move |monitor| monitor.mark_trial_start(trial_id))?
+    }
+
+    pub fn mark_trial_end(&self) -> Result<Option<String>> {
+        self.call(|monitor| monitor.mark_trial_end())?
+    }
+
+    /// Records a sync marker; see `ActivityMonitor::sync_marker`.
+    pub fn sync_marker(&self, label: Option<String>) -> Result<SyncMarker> {
+        self.call(move |monitor| monitor.sync_marker(label))?
+    }
+
+    /// Saves the input buffered while not recording; see
+    /// `ActivityMonitor::save_ring_buffer`.
+    pub fn save_ring_buffer(&self) -> Result<String> {
+        self.call(|monitor| monitor.save_ring_buffer())?
+    }
+
+    pub fn stop(&self) -> Result<()> {
+        self.call(|monitor| monitor.stop_monitoring())
+    }
+
+    pub fn switch_task(&self, task_name: impl Into<String>) -> Result<()> {
+        let task_name = task_name.into();
+        self.call(move |monitor| monitor.switch_task(task_name))
+    }
+
+    /// Switches config profile and saves the config.
+    pub fn switch_profile(&self, name: impl Into<String>) -> Result<()> {
+        let name = name.into();
+        self.call(move |monitor| {
+            monitor.switch_profile(&name)?;
+            monitor.config().save()
+        })?
+    }
+
+    pub fn inject_event(&self, kind: impl Into<String>, payload: serde_json::Value) -> Result<()> {
+        let kind = kind.into();
+        self.call(move |monitor| monitor.inject_event(kind, payload))?
+    }
+}
@@ -0,0 +1,63 @@
+use crate::monitor::ActivityMonitor;
+use anyhow::{bail, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Sessions are saved and restarted this often, so a hard kill (e.g. on
+/// Windows logoff) loses at most one period of data.
+const SESSION_LENGTH: Duration = Duration::from_secs(3600);
+
+static STOP: AtomicBool = AtomicBool::new(false);
+
+/// Records `task_name` without a window until the process is asked to stop
+/// (SIGTERM, SIGINT or SIGHUP on Unix), saving the open session on the way
+/// out. Used by the login service.
+pub fn run(task_name: String) -> Result<()> {
+    install_stop_handler();
+
+    let mut monitor = ActivityMonitor::new()?;
+    monitor.set_task_name(task_name);
+    // Nobody is there to answer the notes prompt.
+    monitor.config_mut().prompt_session_notes = false;
+    monitor.select_template(None);
+    monitor.start_monitoring();
+    if !monitor.is_running() {
+        // E.g. consent hasn't been given; that takes the window.
+        bail!("{}", monitor.status());
+    }
+    println!("✓ Recording \"{}\" in the background", monitor.task_name());
+
+    let mut session_started = Instant::now();
+    while !STOP.load(Ordering::SeqCst) {
+        monitor.update();
+        if session_started.elapsed() >= SESSION_LENGTH {
+            monitor.stop_monitoring();
+            monitor.start_monitoring();
+            session_started = Instant::now();
+        }
+        thread::sleep(monitor.poll_interval());
+    }
+
+    monitor.stop_monitoring();
+    monitor.finish_session(None);
+    println!("{}", monitor.status());
+    Ok(())
+}
+
+#[cfg(unix)]
+fn install_stop_handler() {
+    extern "C" fn request_stop(_signal: libc::c_int) {
+        STOP.store(true, Ordering::SeqCst);
+    }
+
+    let handler = request_stop as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    unsafe {
+        libc::signal(libc::SIGTERM, handler);
+        libc::signal(libc::SIGINT, handler);
+        libc::signal(libc::SIGHUP, handler);
+    }
+}
+
+#[cfg(not(unix))]
+fn install_stop_handler() {}
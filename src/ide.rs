@@ -0,0 +1,158 @@
+//! Localhost endpoint for IDE plugins to report file, build, test and commit
+//! events, which are added to the running session as `ide` custom events.
+//!
+//! Protocol (version 1):
+//! - `GET /` returns `{"protocol": 1, "events": [...]}` so plugins can check
+//!   they're talking to a compatible monitor.
+//! - `POST /events` takes one JSON event object or an array of them, and
+//!   returns `202 {"accepted": n}`. Each object needs an `event` field (one of
+//!   [`EVENTS`]); every other field is kept as-is, e.g.
+//!   `{"event": "file_save", "path": "src/main.rs", "project": "app"}`.
+//!
+//! Events are recorded only while a session is running. Requests from web
+//! pages are refused, as for the query API.
+
+use crate::api;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+pub const PROTOCOL_VERSION: u32 = 1;
+/// Custom event kind the reported events are recorded under.
+pub const EVENT_KIND: &str = "ide";
+/// Event types plugins can report.
+pub const EVENTS: [&str; 5] = ["file_open", "file_save", "build", "test", "commit"];
+/// Bodies larger than this are rejected.
+const MAX_BODY_BYTES: usize = 1 << 20;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IdeConfig {
+    pub enabled: bool,
+    pub port: u16,
+}
+
+impl Default for IdeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 7879,
+        }
+    }
+}
+
+/// Starts the endpoint on `127.0.0.1:port` in a background thread. Accepted
+/// events arrive on the returned channel as JSON objects.
+pub fn spawn(port: u16) -> Result<Receiver<Value>> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    println!(
+        "✓ IDE plugin endpoint listening on http://127.0.0.1:{}",
+        port
+    );
+
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if let Err(e) = handle_connection(stream, port, &tx) {
+                eprintln!("IDE request failed: {}", e);
+            }
+        }
+    });
+    Ok(rx)
+}
+
+fn handle_connection(mut stream: TcpStream, port: u16, events: &Sender<Value>) -> Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    let mut content_length = 0;
+    let (host, origin) = api::read_headers(&mut reader, |name, value| {
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = value.parse().unwrap_or(0);
+        }
+    })?;
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let path = parts.next().unwrap_or_default();
+
+    let (status, body) = match (method, path) {
+        _ if !api::is_local_request(port, method, host.as_deref(), origin.as_deref()) => (
+            403,
+            json!({ "error": "only local programs may report events" }),
+        ),
+        ("GET", "/") => (
+            200,
+            json!({ "protocol": PROTOCOL_VERSION, "events": EVENTS }),
+        ),
+        ("POST", "/events") if content_length > MAX_BODY_BYTES => {
+            (413, json!({ "error": "body too large" }))
+        }
+        ("POST", "/events") => {
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body)?;
+            match parse_events(&body) {
+                Ok(parsed) => {
+                    let accepted = parsed.len();
+                    for event in parsed {
+                        // The monitor is gone only when the app is exiting.
+                        let _ = events.send(event);
+                    }
+                    (202, json!({ "accepted": accepted }))
+                }
+                Err(e) => (400, json!({ "error": e.to_string() })),
+            }
+        }
+        (_, "/" | "/events") => (405, json!({ "error": "method not allowed" })),
+        _ => (404, json!({ "error": "not found" })),
+    };
+
+    let body = body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason(status),
+        body.len(),
+        body
+    )?;
+    Ok(())
+}
+
+/// Parses a request body into validated event objects. A batch is rejected
+/// as a whole if any event is invalid.
+pub fn parse_events(body: &[u8]) -> Result<Vec<Value>> {
+    let events = match serde_json::from_slice(body)? {
+        Value::Array(events) => events,
+        event => vec![event],
+    };
+    for event in &events {
+        let Some(kind) = event.get("event").and_then(Value::as_str) else {
+            anyhow::bail!("every event needs an \"event\" field");
+        };
+        if !EVENTS.contains(&kind) {
+            anyhow::bail!("unknown event {:?} (expected one of {:?})", kind, EVENTS);
+        }
+    }
+    Ok(events)
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        _ => "Error",
+    }
+}
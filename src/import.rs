@@ -0,0 +1,144 @@
+//! Importing an exported dataset into the sessions already stored, e.g. one
+//! dropped onto the window.
+
+use crate::dedup::{self, DedupReport};
+use crate::storage::{self, Storage};
+use crate::types::Session;
+use anyhow::Result;
+use chrono::NaiveDate;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// What to do with imported sessions that are already stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictResolution {
+    /// Keep the stored copy.
+    Skip,
+    /// Overwrite the stored copy with the imported one.
+    Replace,
+    /// Add the imported copy's events to the stored one.
+    Combine,
+}
+
+/// An export that has been read but not yet imported.
+pub struct ImportPreview {
+    pub path: PathBuf,
+    pub sessions: Vec<Session>,
+    /// Indices into `sessions` of sessions that are already stored.
+    pub conflicts: Vec<usize>,
+    /// Copies of sessions and events within the export that were folded
+    /// together on load.
+    pub duplicates: DedupReport,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ImportReport {
+    pub imported: usize,
+    pub replaced: usize,
+    pub skipped: usize,
+    /// Stored sessions the imported events were combined into.
+    pub combined: usize,
+    pub duplicates: DedupReport,
+}
+
+/// Sessions are the same if they have the same id and start time.
+fn key(session: &Session) -> (&str, &str) {
+    (&session.session_id, &session.start_time)
+}
+
+impl ImportPreview {
+    /// Reads `path` (anything `storage::load_dataset` accepts) and checks
+    /// it against the `existing` sessions.
+    pub fn load(path: &Path, existing: &[Session]) -> Result<Self> {
+        let (sessions, duplicates) = dedup::dedup_sessions(storage::load_dataset(path)?, |s| {
+            (s.session_id.clone(), s.start_time.clone())
+        });
+        let stored: HashSet<_> = existing.iter().map(key).collect();
+        let conflicts = sessions
+            .iter()
+            .enumerate()
+            .filter(|(_, session)| stored.contains(&key(session)))
+            .map(|(i, _)| i)
+            .collect();
+        Ok(Self {
+            path: path.to_path_buf(),
+            sessions,
+            conflicts,
+            duplicates,
+        })
+    }
+
+    /// First and last session start dates.
+    pub fn date_range(&self) -> Option<(NaiveDate, NaiveDate)> {
+        let mut dates = self
+            .sessions
+            .iter()
+            .filter_map(|s| s.started_at().map(|t| t.date_naive()));
+        let first = dates.next()?;
+        Some(dates.fold((first, first), |(lo, hi), d| (lo.min(d), hi.max(d))))
+    }
+
+    /// Session count per task name, most sessions first.
+    pub fn tasks(&self) -> Vec<(String, usize)> {
+        let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+        for session in &self.sessions {
+            *counts.entry(&session.task_name).or_default() += 1;
+        }
+        let mut tasks: Vec<_> = counts
+            .into_iter()
+            .map(|(task, count)| (task.to_string(), count))
+            .collect();
+        tasks.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        tasks
+    }
+
+    /// Adds the previewed sessions to `storage`, resolving the ones already
+    /// stored as `resolution` says.
+    pub fn apply(
+        self,
+        storage: &mut dyn Storage,
+        resolution: ConflictResolution,
+    ) -> Result<ImportReport> {
+        let mut report = ImportReport {
+            duplicates: self.duplicates.clone(),
+            ..Default::default()
+        };
+        let conflicts: HashSet<usize> = self.conflicts.iter().copied().collect();
+        for (i, session) in self.sessions.iter().enumerate() {
+            if !conflicts.contains(&i) {
+                storage.append(session)?;
+            }
+        }
+        report.imported = self.sessions.len() - conflicts.len();
+        if resolution == ConflictResolution::Skip {
+            report.skipped = conflicts.len();
+            return Ok(report);
+        }
+
+        let stored: HashMap<_, Session> = storage
+            .load()?
+            .into_iter()
+            .map(|s| ((s.session_id.clone(), s.start_time.clone()), s))
+            .collect();
+        for &i in &self.conflicts {
+            let copy = &self.sessions[i];
+            if resolution == ConflictResolution::Combine {
+                let Some(mut combined) = stored
+                    .get(&(copy.session_id.clone(), copy.start_time.clone()))
+                    .cloned()
+                else {
+                    continue;
+                };
+                dedup::reconcile(&mut combined, copy);
+                storage.replace(&combined)?;
+            } else {
+                storage.replace(copy)?;
+            }
+        }
+        match resolution {
+            ConflictResolution::Combine => report.combined = self.conflicts.len(),
+            _ => report.replaced = self.conflicts.len(),
+        }
+        Ok(report)
+    }
+}
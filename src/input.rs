@@ -0,0 +1,267 @@
+use crate::clipboard;
+use crate::crash;
+use crate::cursor;
+use crate::layout;
+use crate::screenshot::{self, Screenshot};
+use crate::window::{self, ClickTarget, FocusedWindow};
+use anyhow::{anyhow, Result};
+use device_query::{DeviceQuery, DeviceState, Keycode, MouseState};
+use std::sync::{Arc, Mutex};
+
+/// Where the monitor reads keyboard and mouse state from. Implemented by
+/// `device_query::DeviceState` for real capture and by [`MockInput`] for
+/// tests and embedding.
+///
+/// Reads don't return errors; a backend that loses its devices panics, and
+/// the monitor treats that as an interrupted capture.
+pub trait InputSource {
+    fn get_keys(&self) -> Vec<Keycode>;
+    fn get_mouse(&self) -> MouseState;
+
+    /// Name of the active keyboard layout, if the source can tell.
+    fn keyboard_layout(&self) -> Option<String> {
+        None
+    }
+
+    /// The window with keyboard focus, if the source can tell.
+    fn focused_window(&self) -> Option<FocusedWindow> {
+        None
+    }
+
+    /// Name of the application owning the focused window, if the source
+    /// can tell.
+    fn focused_app(&self) -> Option<String> {
+        None
+    }
+
+    /// Title of the focused window, if the source can tell.
+    fn focused_title(&self) -> Option<String> {
+        None
+    }
+
+    /// Name of the current cursor shape, if the source can tell.
+    fn cursor_shape(&self) -> Option<String> {
+        None
+    }
+
+    /// The element at `coords`, if the source can tell.
+    fn element_at(&self, _coords: (i32, i32)) -> Option<ClickTarget> {
+        None
+    }
+
+    /// How many times something was copied or cut to the clipboard since
+    /// the last call, if the source can tell.
+    fn clipboard_changes(&self) -> u32 {
+        0
+    }
+
+    /// A screenshot of the whole screen, if the source can take one.
+    fn screenshot(&self) -> Option<Screenshot> {
+        None
+    }
+
+    /// Re-opens the underlying devices after a failed read.
+    fn reconnect(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl InputSource for DeviceState {
+    fn get_keys(&self) -> Vec<Keycode> {
+        DeviceQuery::get_keys(self)
+    }
+
+    fn get_mouse(&self) -> MouseState {
+        DeviceQuery::get_mouse(self)
+    }
+
+    fn keyboard_layout(&self) -> Option<String> {
+        layout::current_layout()
+    }
+
+    fn focused_window(&self) -> Option<FocusedWindow> {
+        window::focused_window()
+    }
+
+    fn focused_app(&self) -> Option<String> {
+        window::focused_app()
+    }
+
+    fn focused_title(&self) -> Option<String> {
+        window::focused_title()
+    }
+
+    fn cursor_shape(&self) -> Option<String> {
+        cursor::current_shape().map(str::to_string)
+    }
+
+    fn element_at(&self, coords: (i32, i32)) -> Option<ClickTarget> {
+        window::element_at(coords)
+    }
+
+    fn clipboard_changes(&self) -> u32 {
+        clipboard::changes().unwrap_or(0)
+    }
+
+    fn screenshot(&self) -> Option<Screenshot> {
+        screenshot::capture()
+    }
+
+    fn reconnect(&mut self) -> Result<()> {
+        *self = open_device_state()?;
+        Ok(())
+    }
+}
+
+/// Opens the system devices, turning `device_query`'s panic on failure
+/// (no X display, missing accessibility permission) into an error.
+pub fn open_device_state() -> Result<DeviceState> {
+    crash::catch_expected(DeviceState::new)
+        .map_err(|payload| anyhow!("could not open input devices: {}", panic_message(&*payload)))
+}
+
+/// Text of a caught panic payload.
+pub(crate) fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown error".to_string())
+}
+
+/// Scriptable input source. Clones share state, so a test can keep one
+/// handle and drive the copy owned by the monitor.
+#[derive(Debug, Clone, Default)]
+pub struct MockInput {
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    keys: Vec<Keycode>,
+    mouse: MouseState,
+    layout: Option<String>,
+    focused_window: Option<FocusedWindow>,
+    focused_app: Option<String>,
+    focused_title: Option<String>,
+    cursor_shape: Option<String>,
+    element: Option<ClickTarget>,
+    clipboard_changes: u32,
+    screenshot: Option<Screenshot>,
+    failing: bool,
+}
+
+impl MockInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_keys(&self, keys: Vec<Keycode>) {
+        self.state.lock().unwrap().keys = keys;
+    }
+
+    pub fn set_mouse_position(&self, coords: (i32, i32)) {
+        self.state.lock().unwrap().mouse.coords = coords;
+    }
+
+    pub fn set_mouse_buttons(&self, button_pressed: Vec<bool>) {
+        self.state.lock().unwrap().mouse.button_pressed = button_pressed;
+    }
+
+    /// Makes reads and reconnects fail like a lost device until cleared.
+    pub fn set_failing(&self, failing: bool) {
+        self.state.lock().unwrap().failing = failing;
+    }
+
+    fn read(&self) -> std::sync::MutexGuard<'_, MockState> {
+        let state = self.state.lock().unwrap();
+        if state.failing {
+            drop(state);
+            panic!("mock input device unavailable");
+        }
+        state
+    }
+
+    pub fn set_keyboard_layout(&self, layout: Option<String>) {
+        self.state.lock().unwrap().layout = layout;
+    }
+
+    pub fn set_focused_window(&self, window: Option<FocusedWindow>) {
+        self.state.lock().unwrap().focused_window = window;
+    }
+
+    pub fn set_focused_app(&self, app: Option<&str>) {
+        self.state.lock().unwrap().focused_app = app.map(str::to_string);
+    }
+
+    pub fn set_focused_title(&self, title: Option<&str>) {
+        self.state.lock().unwrap().focused_title = title.map(str::to_string);
+    }
+
+    pub fn set_cursor_shape(&self, shape: Option<&str>) {
+        self.state.lock().unwrap().cursor_shape = shape.map(str::to_string);
+    }
+
+    /// Sets the element reported under the pointer, wherever it is.
+    pub fn set_element(&self, element: Option<ClickTarget>) {
+        self.state.lock().unwrap().element = element;
+    }
+
+    /// Reports a copy to the clipboard at the next check.
+    pub fn copy_to_clipboard(&self) {
+        self.state.lock().unwrap().clipboard_changes += 1;
+    }
+
+    pub fn set_screenshot(&self, screenshot: Option<Screenshot>) {
+        self.state.lock().unwrap().screenshot = screenshot;
+    }
+}
+
+impl InputSource for MockInput {
+    fn get_keys(&self) -> Vec<Keycode> {
+        self.read().keys.clone()
+    }
+
+    fn get_mouse(&self) -> MouseState {
+        self.read().mouse.clone()
+    }
+
+    fn keyboard_layout(&self) -> Option<String> {
+        self.state.lock().unwrap().layout.clone()
+    }
+
+    fn focused_window(&self) -> Option<FocusedWindow> {
+        self.state.lock().unwrap().focused_window
+    }
+
+    fn focused_app(&self) -> Option<String> {
+        self.state.lock().unwrap().focused_app.clone()
+    }
+
+    fn focused_title(&self) -> Option<String> {
+        self.state.lock().unwrap().focused_title.clone()
+    }
+
+    fn cursor_shape(&self) -> Option<String> {
+        self.state.lock().unwrap().cursor_shape.clone()
+    }
+
+    fn element_at(&self, _coords: (i32, i32)) -> Option<ClickTarget> {
+        self.state.lock().unwrap().element.clone()
+    }
+
+    fn clipboard_changes(&self) -> u32 {
+        std::mem::take(&mut self.state.lock().unwrap().clipboard_changes)
+    }
+
+    fn screenshot(&self) -> Option<Screenshot> {
+        self.state.lock().unwrap().screenshot.clone()
+    }
+
+    fn reconnect(&mut self) -> Result<()> {
+        if self.state.lock().unwrap().failing {
+            return Err(anyhow!("mock input device unavailable"));
+        }
+        Ok(())
+    }
+}
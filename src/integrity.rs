@@ -0,0 +1,101 @@
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::{
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+};
+
+const MANIFEST_EXTENSION: &str = "sha256";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyStatus {
+    Ok,
+    Mismatch,
+    Missing,
+}
+
+#[derive(Debug, Clone)]
+pub struct VerifyResult {
+    pub file: PathBuf,
+    pub status: VerifyStatus,
+}
+
+pub fn sha256_file(path: impl AsRef<Path>) -> Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Path of the checksum manifest kept next to `artifact`.
+pub fn manifest_path(artifact: &Path) -> PathBuf {
+    let mut name = artifact.as_os_str().to_owned();
+    name.push(".");
+    name.push(MANIFEST_EXTENSION);
+    PathBuf::from(name)
+}
+
+/// Writes `<artifact>.sha256` in `sha256sum` format, so it can also be
+/// checked with standard tools.
+pub fn write_manifest(artifact: impl AsRef<Path>) -> Result<PathBuf> {
+    let artifact = artifact.as_ref();
+    let file_name = artifact
+        .file_name()
+        .context("artifact has no file name")?
+        .to_string_lossy();
+    let manifest = manifest_path(artifact);
+    fs::write(
+        &manifest,
+        format!("{}  {}\n", sha256_file(artifact)?, file_name),
+    )?;
+    Ok(manifest)
+}
+
+/// Verifies a manifest, an artifact with a manifest next to it, or every
+/// manifest in a directory.
+pub fn verify(path: impl AsRef<Path>) -> Result<Vec<VerifyResult>> {
+    let path = path.as_ref();
+    if path.is_dir() {
+        let mut manifests: Vec<_> = fs::read_dir(path)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext == MANIFEST_EXTENSION))
+            .collect();
+        manifests.sort();
+
+        let mut results = Vec::new();
+        for manifest in manifests {
+            results.extend(verify_manifest(&manifest)?);
+        }
+        return Ok(results);
+    }
+
+    if path
+        .extension()
+        .is_some_and(|ext| ext == MANIFEST_EXTENSION)
+    {
+        verify_manifest(path)
+    } else {
+        verify_manifest(&manifest_path(path))
+    }
+}
+
+fn verify_manifest(manifest: &Path) -> Result<Vec<VerifyResult>> {
+    let contents =
+        fs::read_to_string(manifest).with_context(|| format!("reading {}", manifest.display()))?;
+    let dir = manifest.parent().unwrap_or(Path::new("."));
+
+    let mut results = Vec::new();
+    for line in contents.lines().filter(|l| !l.trim().is_empty()) {
+        let (expected, name) = line
+            .split_once("  ")
+            .with_context(|| format!("malformed line in {}: {}", manifest.display(), line))?;
+        let file = dir.join(name.trim());
+        let status = match sha256_file(&file) {
+            Ok(actual) if actual.eq_ignore_ascii_case(expected.trim()) => VerifyStatus::Ok,
+            Ok(_) => VerifyStatus::Mismatch,
+            Err(_) => VerifyStatus::Missing,
+        };
+        results.push(VerifyResult { file, status });
+    }
+    Ok(results)
+}
@@ -0,0 +1,146 @@
+//! Detecting "mouse jiggler" patterns: a device or script nudging the
+//! pointer back and forth at a steady pace so the machine never looks idle.
+//! Such stretches move the mouse within a few pixels, at regular intervals,
+//! with no other input, and are reported as pseudo-idle rather than active
+//! time.
+
+use crate::types::{Action, Session};
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+
+/// Moves closer together than this belong to the same nudge.
+const NUDGE_MS: i64 = 1000;
+
+/// Off by default; organizations reporting time honestly turn it on under
+/// `"quality"`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JiggleConfig {
+    pub enabled: bool,
+    /// Moves within a box this many pixels wide and high count as a jiggle.
+    pub max_distance_px: u32,
+    /// Shorter stretches are left alone; people do rest a hand on the mouse.
+    pub min_minutes: u32,
+    /// How much the time between moves may vary, as a fraction of the
+    /// average. Jigglers are regular; a resting hand isn't.
+    pub max_interval_variation: f64,
+}
+
+impl Default for JiggleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_distance_px: 10,
+            min_minutes: 5,
+            max_interval_variation: 0.25,
+        }
+    }
+}
+
+/// A stretch of jiggling, from its first move to its last.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JigglePeriod {
+    pub start: DateTime<FixedOffset>,
+    pub end: DateTime<FixedOffset>,
+    pub moves: usize,
+}
+
+impl JigglePeriod {
+    pub fn secs(&self) -> i64 {
+        (self.end - self.start).num_seconds()
+    }
+}
+
+/// Moves recorded since the last input that wasn't a tiny move.
+#[derive(Default)]
+struct Run {
+    moves: Vec<(DateTime<FixedOffset>, (i32, i32))>,
+    /// Smallest and largest x and y so far.
+    bounds: Option<((i32, i32), (i32, i32))>,
+}
+
+impl Run {
+    /// Adds the move if it keeps the run within `max` pixels each way.
+    fn push(&mut self, at: DateTime<FixedOffset>, coords: (i32, i32), max: i32) -> bool {
+        let (low, high) = self.bounds.unwrap_or((coords, coords));
+        let low = (low.0.min(coords.0), low.1.min(coords.1));
+        let high = (high.0.max(coords.0), high.1.max(coords.1));
+        if high.0 - low.0 > max || high.1 - low.1 > max {
+            return false;
+        }
+        self.bounds = Some((low, high));
+        self.moves.push((at, coords));
+        true
+    }
+
+    fn period(&self, config: &JiggleConfig) -> Option<JigglePeriod> {
+        let (&(start, _), &(end, _)) = (self.moves.first()?, self.moves.last()?);
+        if (end - start).num_seconds() < i64::from(config.min_minutes) * 60 {
+            return None;
+        }
+        // Jigglers often move away and straight back, so moves less than
+        // NUDGE_MS apart are one nudge and the pace is measured between
+        // nudges.
+        let mut nudges = vec![start];
+        for pair in self.moves.windows(2) {
+            if (pair[1].0 - pair[0].0).num_milliseconds() >= NUDGE_MS {
+                nudges.push(pair[1].0);
+            }
+        }
+        if nudges.len() < 3 {
+            return None;
+        }
+        let intervals: Vec<f64> = nudges
+            .windows(2)
+            .map(|pair| (pair[1] - pair[0]).num_milliseconds() as f64)
+            .collect();
+        let mean = intervals.iter().sum::<f64>() / intervals.len() as f64;
+        let variance =
+            intervals.iter().map(|i| (i - mean).powi(2)).sum::<f64>() / intervals.len() as f64;
+        (variance.sqrt() / mean <= config.max_interval_variation).then_some(JigglePeriod {
+            start,
+            end,
+            moves: self.moves.len(),
+        })
+    }
+}
+
+/// Jiggle periods in `session`, oldest first, or none if detection is off.
+/// Key presses, shortcuts and clicks end a period; events that aren't input,
+/// like focus or cursor changes, don't.
+pub fn detect(session: &Session, config: &JiggleConfig) -> Vec<JigglePeriod> {
+    if !config.enabled {
+        return Vec::new();
+    }
+    let max = config.max_distance_px as i32;
+    let mut periods = Vec::new();
+    let mut run = Run::default();
+    for action in session.ordered_actions() {
+        let Ok(at) = DateTime::parse_from_rfc3339(action.timestamp()) else {
+            continue;
+        };
+        match action {
+            // Moves that fit are added by the guard; the rest start a new run.
+            Action::MouseMove { coords, .. } if !run.push(at, coords, max) => {
+                periods.extend(run.period(config));
+                run = Run::default();
+                run.push(at, coords, max);
+            }
+            Action::KeyPress { .. }
+            | Action::Shortcut { .. }
+            | Action::MouseDown { .. }
+            | Action::MouseUp { .. } => {
+                periods.extend(run.period(config));
+                run = Run::default();
+            }
+            _ => {}
+        }
+    }
+    periods.extend(run.period(config));
+    periods
+}
+
+/// Time in `session` spent jiggling rather than working.
+pub fn pseudo_idle_secs(session: &Session, config: &JiggleConfig) -> i64 {
+    detect(session, config).iter().map(JigglePeriod::secs).sum()
+}
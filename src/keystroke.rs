@@ -0,0 +1,205 @@
+//! Keystroke dynamics: per-key press and release times recovered from the
+//! recorded key sets, grouped into typing bursts.
+
+use crate::types::{Action, Session};
+use chrono::DateTime;
+use std::collections::HashMap;
+
+/// A pause between presses longer than this starts a new burst.
+pub const BURST_GAP_MS: i64 = 2000;
+
+/// One key press, in milliseconds since the Unix epoch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keystroke {
+    pub key: String,
+    pub press_ms: i64,
+    pub release_ms: i64,
+}
+
+impl Keystroke {
+    pub fn hold_ms(&self) -> i64 {
+        self.release_ms - self.press_ms
+    }
+}
+
+/// A run of keystrokes without a pause longer than [`BURST_GAP_MS`].
+#[derive(Debug, Clone, Default)]
+pub struct Burst {
+    pub keystrokes: Vec<Keystroke>,
+}
+
+impl Burst {
+    /// Hold time of each key.
+    pub fn hold_times(&self) -> Vec<i64> {
+        self.keystrokes.iter().map(Keystroke::hold_ms).collect()
+    }
+
+    /// Press-to-press latency between consecutive keys.
+    pub fn down_down(&self) -> Vec<i64> {
+        self.keystrokes
+            .windows(2)
+            .map(|pair| pair[1].press_ms - pair[0].press_ms)
+            .collect()
+    }
+
+    /// Release-to-press latency between consecutive keys; negative when the
+    /// next key went down before the previous one came up.
+    pub fn up_down(&self) -> Vec<i64> {
+        self.keystrokes
+            .windows(2)
+            .map(|pair| pair[1].press_ms - pair[0].release_ms)
+            .collect()
+    }
+}
+
+/// Keystrokes of `session` in press order. `KeyPress` events hold the set of
+/// keys down after each change, so a key is pressed when it joins the set and
+/// released when it leaves. Keys still down when the session ended are left
+/// out, since their hold time is unknown.
+pub fn keystrokes(session: &Session) -> Vec<Keystroke> {
+    let mut held: HashMap<String, i64> = HashMap::new();
+    let mut keystrokes = Vec::new();
+    for action in session.ordered_actions() {
+        let Action::KeyPress { timestamp, keys } = action else {
+            continue;
+        };
+        let Ok(at) = DateTime::parse_from_rfc3339(&timestamp) else {
+            continue;
+        };
+        let at = at.timestamp_millis();
+        held.retain(|key, press_ms| {
+            if keys.contains(key) {
+                return true;
+            }
+            keystrokes.push(Keystroke {
+                key: key.clone(),
+                press_ms: *press_ms,
+                release_ms: at,
+            });
+            false
+        });
+        for key in keys {
+            held.entry(key).or_insert(at);
+        }
+    }
+    keystrokes.sort_by(|a, b| a.press_ms.cmp(&b.press_ms).then(a.key.cmp(&b.key)));
+    keystrokes
+}
+
+/// Splits `session`'s keystrokes into typing bursts.
+pub fn bursts(session: &Session) -> Vec<Burst> {
+    let mut bursts: Vec<Burst> = Vec::new();
+    for keystroke in keystrokes(session) {
+        match bursts.last_mut() {
+            Some(burst)
+                if burst.keystrokes.last().is_some_and(|previous| {
+                    keystroke.press_ms - previous.press_ms <= BURST_GAP_MS
+                }) =>
+            {
+                burst.keystrokes.push(keystroke)
+            }
+            _ => bursts.push(Burst {
+                keystrokes: vec![keystroke],
+            }),
+        }
+    }
+    bursts
+}
+
+/// Broad class of a key, for sharing key sequences without the keys
+/// themselves. Names are `device_query` key names, which follow physical
+/// keys rather than the characters the active layout gives them.
+pub fn key_category(key: &str) -> &'static str {
+    let digit = key
+        .strip_prefix("Key")
+        .or_else(|| key.strip_prefix("Numpad"))
+        .unwrap_or(key);
+    match key {
+        "LShift" | "RShift" | "LControl" | "RControl" | "LAlt" | "RAlt" | "LMeta" | "RMeta"
+        | "Meta" | "Command" | "RCommand" | "LOption" | "ROption" | "CapsLock" => "modifier",
+        "Space" | "Tab" | "Enter" | "NumpadEnter" => "whitespace",
+        "Backspace" | "Delete" | "Insert" => "editing",
+        "Up" | "Down" | "Left" | "Right" | "Home" | "End" | "PageUp" | "PageDown" => "navigation",
+        "Grave" | "Minus" | "Equal" | "LeftBracket" | "RightBracket" | "BackSlash"
+        | "Semicolon" | "Apostrophe" | "Comma" | "Dot" | "Slash" | "NumpadAdd"
+        | "NumpadSubtract" | "NumpadMultiply" | "NumpadDivide" | "NumpadDecimal"
+        | "NumpadEquals" => "punctuation",
+        _ if digit.len() == 1 && digit.as_bytes()[0].is_ascii_digit() => "digit",
+        _ if key.len() == 1 && key.as_bytes()[0].is_ascii_alphabetic() => "letter",
+        _ if key == "Escape" || (key.starts_with('F') && key[1..].parse::<u8>().is_ok()) => {
+            "function"
+        }
+        _ => "other",
+    }
+}
+
+/// How often a run of consecutive keys was typed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ngram {
+    /// Keyboard layout active when the run started, empty if no layout
+    /// change was recorded before it.
+    pub layout: String,
+    pub keys: Vec<String>,
+    pub count: usize,
+}
+
+/// Runs of `n` consecutive keystrokes in `session` (bigrams for 2,
+/// trigrams for 3) and how often each was typed, by keyboard layout. Runs
+/// don't span typing bursts. With `categories`, keys are replaced by their
+/// [`key_category`].
+///
+/// Sorted by layout, then most frequent first.
+pub fn ngrams(session: &Session, n: usize, categories: bool) -> Vec<Ngram> {
+    let layouts: Vec<(i64, String)> = session
+        .ordered_actions()
+        .into_iter()
+        .filter_map(|action| match action {
+            Action::LayoutChange { timestamp, layout } => {
+                let at = DateTime::parse_from_rfc3339(&timestamp).ok()?;
+                Some((at.timestamp_millis(), layout))
+            }
+            _ => None,
+        })
+        .collect();
+    let layout_at = |ms: i64| {
+        layouts
+            .iter()
+            .take_while(|(at, _)| *at <= ms)
+            .last()
+            .map_or("", |(_, layout)| layout.as_str())
+    };
+
+    let mut counts: HashMap<(&str, Vec<String>), usize> = HashMap::new();
+    for burst in bursts(session) {
+        for run in burst.keystrokes.windows(n.max(1)) {
+            let keys = run
+                .iter()
+                .map(|keystroke| {
+                    if categories {
+                        key_category(&keystroke.key).to_string()
+                    } else {
+                        keystroke.key.clone()
+                    }
+                })
+                .collect();
+            *counts
+                .entry((layout_at(run[0].press_ms), keys))
+                .or_default() += 1;
+        }
+    }
+    let mut ngrams: Vec<Ngram> = counts
+        .into_iter()
+        .map(|((layout, keys), count)| Ngram {
+            layout: layout.to_string(),
+            keys,
+            count,
+        })
+        .collect();
+    ngrams.sort_by(|a, b| {
+        a.layout
+            .cmp(&b.layout)
+            .then(b.count.cmp(&a.count))
+            .then(a.keys.cmp(&b.keys))
+    });
+    ngrams
+}
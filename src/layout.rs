@@ -0,0 +1,68 @@
+/// Name of the active keyboard layout (e.g. "English (US)"), where
+/// supported.
+///
+/// On Linux this is the active XKB group name. Input-method engines such as
+/// IBus or Fcitx switch languages without changing the XKB group, so those
+/// switches aren't visible here.
+#[cfg(target_os = "linux")]
+pub fn current_layout() -> Option<String> {
+    use std::cell::Cell;
+    use std::ffi::CStr;
+    use x11::xlib;
+
+    const XKB_USE_CORE_KBD: u32 = 0x0100;
+    const XKB_GROUP_NAMES_MASK: u32 = 1 << 12;
+
+    thread_local! {
+        static DISPLAY: Cell<*mut xlib::Display> = const { Cell::new(std::ptr::null_mut()) };
+    }
+
+    DISPLAY.with(|display| unsafe {
+        if display.get().is_null() {
+            display.set(xlib::XOpenDisplay(std::ptr::null()));
+        }
+        let dpy = display.get();
+        if dpy.is_null() {
+            return None;
+        }
+
+        let mut state: xlib::XkbStateRec = std::mem::zeroed();
+        if xlib::XkbGetState(dpy, XKB_USE_CORE_KBD, &mut state) != 0 {
+            return None;
+        }
+
+        let desc = xlib::XkbGetKeyboard(dpy, XKB_GROUP_NAMES_MASK, XKB_USE_CORE_KBD);
+        if desc.is_null() {
+            return None;
+        }
+        let names = (*desc).names;
+        let atom = if names.is_null() {
+            0
+        } else {
+            (*names)
+                .groups
+                .get(state.group as usize)
+                .copied()
+                .unwrap_or(0)
+        };
+        let name = if atom == 0 {
+            None
+        } else {
+            let raw = xlib::XGetAtomName(dpy, atom);
+            if raw.is_null() {
+                None
+            } else {
+                let name = CStr::from_ptr(raw).to_string_lossy().into_owned();
+                xlib::XFree(raw.cast());
+                Some(name)
+            }
+        };
+        xlib::XkbFreeKeyboard(desc, 0, xlib::True);
+        name
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn current_layout() -> Option<String> {
+    None
+}
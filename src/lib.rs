@@ -1,8 +1,73 @@
+pub mod aggregates;
+pub mod analysis;
+pub mod anonymize;
+pub mod api;
+pub mod app_alerts;
+pub mod autostart;
+pub mod billing;
+pub mod bug_report;
+pub mod calibration;
+pub mod clipboard;
+pub mod compaction;
+pub mod config;
+pub mod consent;
+pub mod contribute;
+pub mod crash;
+pub mod cursor;
+#[cfg(feature = "polars")]
+pub mod dataframe;
+pub mod dedup;
+pub mod diagnostics;
+pub mod diff;
+pub mod dnd;
+pub mod event_bus;
+pub mod export;
 pub mod gui;
+pub mod handle;
+pub mod headless;
+pub mod ide;
+pub mod import;
+pub mod input;
+pub mod integrity;
+pub mod jiggle;
+pub mod keystroke;
+pub mod layout;
+pub mod lsl;
+pub mod merge;
+pub mod metrics;
 pub mod monitor;
+pub mod playback;
+pub mod policy;
+pub mod portable;
+pub mod privacy;
+pub mod quality;
+pub mod rename;
+pub mod ring_buffer;
+pub mod scheduler;
+pub mod screenshot;
+pub mod script;
+pub mod service;
+pub mod share;
+pub mod shortcuts;
+pub mod spool;
+pub mod stats;
+pub mod storage;
+pub mod sync;
+pub mod timeline;
 pub mod types;
+pub mod updates;
+pub mod viewer;
+pub mod watchdog;
+pub mod wellness;
+pub mod window;
+pub mod xlsx;
 
 // Re-export commonly used items
-pub use gui::MonitorApp;
-pub use monitor::ActivityMonitor;
-pub use types::{Action, DetailedEvent, Session};
+pub use aggregates::MetricsSnapshot;
+pub use config::{Config, ExportFormat};
+pub use gui::{MonitorApp, MonitorState, Panel};
+pub use handle::MonitorHandle;
+pub use input::{InputSource, MockInput};
+pub use monitor::{ActivityMonitor, MonitorEvent};
+pub use types::{Action, DetailedEvent, MousePath, MousePoint, Session, WindowBounds};
+pub use viewer::ViewerApp;
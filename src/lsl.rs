@@ -0,0 +1,165 @@
+//! Publishes monitor events as a Lab Streaming Layer (LSL) marker stream,
+//! so they can be recorded next to physiological sensors with LabRecorder
+//! and similar tools. Each sample is one string channel holding the event
+//! as JSON, stamped on LSL's clock at the time the event was recorded.
+//!
+//! Publishing needs the `lsl` feature, which links against liblsl; the
+//! config and sample conversion are always built.
+
+use crate::monitor::MonitorEvent;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+/// LSL content type of the stream, as expected by marker-aware tools.
+pub const STREAM_TYPE: &str = "Markers";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LslConfig {
+    pub enabled: bool,
+    pub stream_name: String,
+}
+
+impl Default for LslConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            stream_name: "DeskMonitor".to_string(),
+        }
+    }
+}
+
+/// An event ready to push: its JSON and when it happened.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub json: String,
+    pub recorded_at: DateTime<Local>,
+}
+
+/// The sample for `event`, or `None` for events that don't go out over
+/// LSL (saves, discards and capture health).
+pub fn sample(event: &MonitorEvent) -> Option<Sample> {
+    let now = Local::now();
+    let (value, recorded_at) = match event {
+        MonitorEvent::Recorded(action) => {
+            let recorded_at = DateTime::parse_from_rfc3339(action.timestamp())
+                .map_or(now, |t| t.with_timezone(&Local));
+            (serde_json::to_value(action).ok()?, recorded_at)
+        }
+        MonitorEvent::Started { task_name } => (
+            json!({ "type": "session_started", "task_name": task_name }),
+            now,
+        ),
+        MonitorEvent::Stopped { task_name } => (
+            json!({ "type": "session_stopped", "task_name": task_name }),
+            now,
+        ),
+        MonitorEvent::TaskSwitched { from, to } => (
+            json!({ "type": "task_switch", "from": from, "to": to }),
+            now,
+        ),
+        _ => return None,
+    };
+    Some(Sample {
+        json: value.to_string(),
+        recorded_at,
+    })
+}
+
+#[cfg(feature = "lsl")]
+mod ffi {
+    use std::ffi::{c_char, c_double, c_int, c_void};
+
+    /// `cft_string` in `lsl_c.h`.
+    pub const CFT_STRING: c_int = 3;
+    /// Nominal rate of streams with irregular samples.
+    pub const IRREGULAR_RATE: c_double = 0.0;
+
+    #[link(name = "lsl")]
+    extern "C" {
+        pub fn lsl_create_streaminfo(
+            name: *const c_char,
+            kind: *const c_char,
+            channel_count: c_int,
+            nominal_srate: c_double,
+            channel_format: c_int,
+            source_id: *const c_char,
+        ) -> *mut c_void;
+        pub fn lsl_destroy_streaminfo(info: *mut c_void);
+        pub fn lsl_create_outlet(
+            info: *mut c_void,
+            chunk_size: c_int,
+            max_buffered: c_int,
+        ) -> *mut c_void;
+        pub fn lsl_destroy_outlet(outlet: *mut c_void);
+        pub fn lsl_push_sample_strt(
+            outlet: *mut c_void,
+            data: *const *const c_char,
+            timestamp: c_double,
+        ) -> c_int;
+        pub fn lsl_local_clock() -> c_double;
+    }
+}
+
+/// Opens the outlet and publishes events from `events` on a background
+/// thread until the monitor drops its end.
+#[cfg(feature = "lsl")]
+pub fn spawn(
+    config: &LslConfig,
+    events: std::sync::mpsc::Receiver<MonitorEvent>,
+) -> anyhow::Result<std::thread::JoinHandle<()>> {
+    use std::ffi::CString;
+
+    let name = CString::new(config.stream_name.as_str())?;
+    let kind = CString::new(STREAM_TYPE)?;
+    let source_id = CString::new(format!("desk-monitor-{}", config.stream_name))?;
+    let info = unsafe {
+        ffi::lsl_create_streaminfo(
+            name.as_ptr(),
+            kind.as_ptr(),
+            1,
+            ffi::IRREGULAR_RATE,
+            ffi::CFT_STRING,
+            source_id.as_ptr(),
+        )
+    };
+    if info.is_null() {
+        anyhow::bail!("couldn't describe the LSL stream");
+    }
+    // The outlet keeps its own copy of the stream info.
+    let outlet = unsafe { ffi::lsl_create_outlet(info, 0, 360) };
+    unsafe { ffi::lsl_destroy_streaminfo(info) };
+    if outlet.is_null() {
+        anyhow::bail!("couldn't open the LSL outlet");
+    }
+    println!(
+        "✓ Publishing events on LSL stream \"{}\"",
+        config.stream_name
+    );
+
+    // Raw pointers aren't `Send`; the outlet is only used from the thread.
+    let outlet = outlet as usize;
+    Ok(std::thread::spawn(move || {
+        let outlet = outlet as *mut std::ffi::c_void;
+        for event in events {
+            let Some(sample) = sample(&event) else {
+                continue;
+            };
+            let Ok(json) = CString::new(sample.json) else {
+                continue;
+            };
+            let age = (Local::now() - sample.recorded_at)
+                .num_microseconds()
+                .unwrap_or(0)
+                .max(0) as f64
+                / 1e6;
+            let data = [json.as_ptr()];
+            unsafe {
+                let timestamp = ffi::lsl_local_clock() - age;
+                ffi::lsl_push_sample_strt(outlet, data.as_ptr(), timestamp);
+            }
+        }
+        unsafe { ffi::lsl_destroy_outlet(outlet) };
+    }))
+}
@@ -1,12 +1,206 @@
 use anyhow::Result;
-use desk_monitor::MonitorApp;
+use chrono::{DateTime, Local, NaiveDate, NaiveTime};
+use desk_monitor::analysis;
+use desk_monitor::anonymize::{self, Pseudonymizer};
+use desk_monitor::billing;
+use desk_monitor::compaction;
+use desk_monitor::diff;
+use desk_monitor::integrity::{self, VerifyStatus};
+use desk_monitor::jiggle;
+use desk_monitor::merge::{self, MergeSource};
+use desk_monitor::policy::{self, Policy};
+use desk_monitor::portable;
+use desk_monitor::privacy;
+use desk_monitor::rename;
+use desk_monitor::script::{self, ScriptFormat, ScriptOptions};
+use desk_monitor::service;
+use desk_monitor::storage::{FolderStorage, Storage, DETAILS_FILE, SESSIONS_FILE};
+use desk_monitor::updates;
+use desk_monitor::{MonitorApp, ViewerApp};
 use eframe::egui;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
 fn main() -> Result<()> {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    if portable::requested(&args) {
+        portable::enter()?;
+        args.retain(|arg| arg != portable::FLAG);
+    }
+    match args.first().map(String::as_str) {
+        Some("--viewer") => {
+            let Some(path) = args.get(1) else {
+                anyhow::bail!("usage: desk-monitor --viewer <path>");
+            };
+            return run_viewer(PathBuf::from(path));
+        }
+        Some("verify") => {
+            let Some(path) = args.get(1) else {
+                anyhow::bail!("usage: desk-monitor verify <file|manifest|directory>");
+            };
+            return run_verify(PathBuf::from(path));
+        }
+        Some("compact") => {
+            let retention_days = match args.get(1) {
+                Some(days) => days.parse()?,
+                None => desk_monitor::Config::load()?.compaction.retention_days,
+            };
+            return run_compact(retention_days, &desk_monitor::Config::load()?);
+        }
+        Some("merge") => {
+            let format = if args.iter().any(|a| a == "--json") {
+                desk_monitor::ExportFormat::Json
+            } else if args.iter().any(|a| a == "--xlsx") {
+                desk_monitor::ExportFormat::Xlsx
+            } else {
+                desk_monitor::ExportFormat::Csv
+            };
+            let rest: Vec<&String> = args[1..]
+                .iter()
+                .filter(|a| *a != "--json" && *a != "--xlsx")
+                .collect();
+            let (Some(out_dir), true) = (rest.first(), rest.len() > 1) else {
+                anyhow::bail!(
+                    "usage: desk-monitor merge <output-dir> [machine=]<path>... [--json | --xlsx]"
+                );
+            };
+            return run_merge(PathBuf::from(out_dir), &rest[1..], format);
+        }
+        Some("rename-task") => {
+            let dry_run = args.iter().any(|a| a == "--dry-run");
+            let rest: Vec<&String> = args[1..].iter().filter(|a| *a != "--dry-run").collect();
+            let [from, to] = rest[..] else {
+                anyhow::bail!("usage: desk-monitor rename-task <old> <new> [--dry-run]");
+            };
+            return run_rename_task(from, to, dry_run);
+        }
+        Some("delete-range") => {
+            let (Some(session_id), Some(from), Some(until)) =
+                (args.get(1), args.get(2), args.get(3))
+            else {
+                anyhow::bail!("usage: desk-monitor delete-range <session_id> <from> <until>");
+            };
+            return run_delete_range(session_id, from, until);
+        }
+        Some("stats") => return run_stats(&args[1..]),
+        Some("invoice") => return run_invoice(&args[1..]),
+        Some("keystrokes") => {
+            let Some(out) = args.get(1) else {
+                anyhow::bail!("usage: desk-monitor keystrokes <output.csv> [path]");
+            };
+            let input = input_or_stored(args.get(2))?;
+            return run_keystrokes(&input, Path::new(out));
+        }
+        Some("ngrams") => {
+            let Some(out) = args.get(1) else {
+                anyhow::bail!("usage: desk-monitor ngrams <output.csv> [path]");
+            };
+            let input = input_or_stored(args.get(2))?;
+            return run_ngrams(&input, Path::new(out));
+        }
+        Some("archive") => {
+            let input = match args.get(1) {
+                Some(input) => PathBuf::from(input),
+                None => desk_monitor::Config::load()?.sessions_path(),
+            };
+            return run_archive(&input);
+        }
+        Some("trials") => {
+            let Some(out_dir) = args.get(1) else {
+                anyhow::bail!("usage: desk-monitor trials <output-dir> [path]");
+            };
+            let input = input_or_stored(args.get(2))?;
+            return run_trials(&input, Path::new(out_dir));
+        }
+        Some("script") => return run_script(&args[1..]),
+        Some("diff") => {
+            let (Some(a), Some(b), Some(out)) = (args.get(1), args.get(2), args.get(3)) else {
+                anyhow::bail!(
+                    "usage: desk-monitor diff <session_a> <session_b> <report.md> [path]"
+                );
+            };
+            let input = input_or_stored(args.get(4))?;
+            return run_diff(a, b, Path::new(out), &input);
+        }
+        Some("anonymize") => {
+            let format = if args.iter().any(|a| a == "--json") {
+                desk_monitor::ExportFormat::Json
+            } else if args.iter().any(|a| a == "--xlsx") {
+                desk_monitor::ExportFormat::Xlsx
+            } else {
+                desk_monitor::ExportFormat::Csv
+            };
+            let rest: Vec<&String> = args[1..]
+                .iter()
+                .filter(|a| *a != "--json" && *a != "--xlsx")
+                .collect();
+            let (Some(out_dir), 1..=2) = (rest.first(), rest.len()) else {
+                anyhow::bail!(
+                    "usage: desk-monitor anonymize <output-dir> [path] [--json | --xlsx]"
+                );
+            };
+            let input = input_or_stored(rest.get(1).copied())?;
+            return run_anonymize(&input, Path::new(out_dir), format);
+        }
+        Some("headless") => {}
+        Some("install-service") => {
+            let task = args.get(1).map_or(service::DEFAULT_TASK, String::as_str);
+            service::install(task)?;
+            println!(
+                "✓ Installed {} to record \"{}\" at login",
+                service::SERVICE_NAME,
+                task
+            );
+            return Ok(());
+        }
+        Some("uninstall") => {
+            service::uninstall()?;
+            println!("✓ Uninstalled {}", service::SERVICE_NAME);
+            return Ok(());
+        }
+        Some("status") => return service::status(),
+        Some("profile") => {
+            let save = args.iter().any(|a| a == "--save");
+            let name = args[1..].iter().find(|a| *a != "--save");
+            if save && name.is_none() {
+                anyhow::bail!("usage: desk-monitor profile [<name> | --save <name>]");
+            }
+            return run_profile(name.map(String::as_str), save);
+        }
+        Some("meta") => return run_meta(&args[1..]),
+        Some("policy") => {
+            let path = args.get(1).map_or_else(policy::system_path, PathBuf::from);
+            return run_policy(&path);
+        }
+        Some("check-update") => {
+            return run_check_update(args.iter().any(|a| a == "--install"));
+        }
+        _ => {}
+    }
+
+    let config = desk_monitor::Config::load()?;
+    desk_monitor::crash::install(&config);
+    // Compaction rewrites the sessions file, so it runs before the monitor
+    // opens it.
+    if config.compaction.enabled && Path::new(SESSIONS_FILE).exists() {
+        run_compact(config.compaction.retention_days, &config)?;
+    }
+
+    desk_monitor::scheduler::spawn_daily_export();
+
+    if args.first().map(String::as_str) == Some("headless") {
+        let task = args.get(1).map_or(service::DEFAULT_TASK, String::as_str);
+        return desk_monitor::headless::run(task.to_string());
+    }
+
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
-            .with_inner_size([400.0, 200.0])
+            .with_inner_size([480.0, 520.0])
+            .with_min_inner_size([360.0, 200.0])
             .with_title("Desktop Activity Monitor"),
+        // Window geometry and panel state are restored from the previous run,
+        // except in portable mode, where nothing is kept outside its folder.
+        persist_window: !portable::is_active(),
         ..Default::default()
     };
 
@@ -19,3 +213,670 @@ fn main() -> Result<()> {
 
     Ok(())
 }
+
+fn run_viewer(path: PathBuf) -> Result<()> {
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_inner_size([900.0, 600.0])
+            .with_title("Desktop Activity Monitor — Viewer"),
+        persist_window: !portable::is_active(),
+        ..Default::default()
+    };
+
+    eframe::run_native(
+        "Desktop Activity Monitor Viewer",
+        options,
+        Box::new(move |cc| Box::new(ViewerApp::new(cc, path))),
+    )
+    .unwrap();
+
+    Ok(())
+}
+
+fn run_verify(path: PathBuf) -> Result<()> {
+    let results = integrity::verify(&path)?;
+    if results.is_empty() {
+        anyhow::bail!("no checksum manifests found at {}", path.display());
+    }
+
+    let mut failures = 0;
+    for result in &results {
+        let label = match result.status {
+            VerifyStatus::Ok => "OK",
+            VerifyStatus::Mismatch => "FAILED",
+            VerifyStatus::Missing => "MISSING",
+        };
+        if result.status != VerifyStatus::Ok {
+            failures += 1;
+        }
+        println!("{:<8}{}", label, result.file.display());
+    }
+
+    if failures > 0 {
+        anyhow::bail!(
+            "{} of {} files failed verification",
+            failures,
+            results.len()
+        );
+    }
+    println!("All {} files verified", results.len());
+    Ok(())
+}
+
+fn run_compact(retention_days: i64, config: &desk_monitor::Config) -> Result<()> {
+    let report = compaction::compact(retention_days, config)?;
+    println!(
+        "✓ Compacted {} sessions older than {} days ({} mouse moves -> {} minute rollups)",
+        report.sessions_compacted,
+        retention_days,
+        report.mouse_moves_dropped,
+        report.rollups_written
+    );
+    Ok(())
+}
+
+fn run_rename_task(from: &str, to: &str, dry_run: bool) -> Result<()> {
+    let mut config = desk_monitor::Config::load()?;
+    let report = rename::rename_task(from, to, dry_run, &mut config)?;
+    if dry_run {
+        for session in &report.sessions {
+            println!("  {}", session);
+        }
+    }
+    println!(
+        "{} {:?} -> {:?}: {} sessions, {} task switches, {} rollups, {} detailed events, {} templates, {} budgets",
+        if dry_run { "Would rename" } else { "✓ Renamed" },
+        from,
+        to,
+        report.sessions.len(),
+        report.task_switches,
+        report.rollups,
+        report.detailed_events,
+        report.templates.len(),
+        report.budgets
+    );
+    Ok(())
+}
+
+/// The dataset a command was given, or the sessions stored by the monitor
+/// (the sessions file, or its week folders when the archive is on).
+fn input_or_stored(input: Option<impl AsRef<Path>>) -> Result<PathBuf> {
+    match input {
+        Some(input) => Ok(input.as_ref().to_path_buf()),
+        None => Ok(desk_monitor::Config::load()?.sessions_source()),
+    }
+}
+
+fn run_delete_range(session_id: &str, from: &str, until: &str) -> Result<()> {
+    let config = desk_monitor::Config::load()?;
+    let sessions = config.stored_sessions()?;
+    let mut matching = sessions.into_iter().filter(|s| s.session_id == session_id);
+    let (Some(session), None) = (matching.next(), matching.next()) else {
+        anyhow::bail!("expected exactly one session with ID {}", session_id);
+    };
+    let Some(start) = session.started_at() else {
+        anyhow::bail!("session {} has no valid start time", session_id);
+    };
+    let from = parse_time(from, start)?;
+    let until = parse_time(until, start)?;
+    if until < from {
+        anyhow::bail!("the range ends before it starts");
+    }
+
+    let deletion = privacy::delete_stored_range(&config, &session, from, until)?;
+    println!(
+        "✓ Deleted {} events from {} between {} and {}",
+        deletion.events,
+        session_id,
+        from.format("%Y-%m-%d %H:%M:%S"),
+        until.format("%Y-%m-%d %H:%M:%S")
+    );
+    for copy in &deletion.copies {
+        println!("  {}", copy.display());
+    }
+    if deletion.detailed_events > 0 {
+        println!("  {} ({} rows)", DETAILS_FILE, deletion.detailed_events);
+    }
+    println!("Exports written before still hold the range; delete or redo them by hand.");
+    Ok(())
+}
+
+/// Parses an RFC 3339 timestamp, or a local `HH:MM[:SS]` on the day
+/// `session_start` falls on.
+fn parse_time(s: &str, session_start: DateTime<Local>) -> Result<DateTime<Local>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+        return Ok(time.with_timezone(&Local));
+    }
+    let time = NaiveTime::parse_from_str(s, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(s, "%H:%M"))
+        .map_err(|_| anyhow::anyhow!("invalid time {:?} (expected HH:MM[:SS] or RFC 3339)", s))?;
+    session_start
+        .date_naive()
+        .and_time(time)
+        .and_local_timezone(Local)
+        .earliest()
+        .ok_or_else(|| anyhow::anyhow!("{} doesn't exist in the local timezone", s))
+}
+
+/// Prints per-task totals for the sessions matching `args`:
+/// `[--today] [--task <name>] [--since YYYY-MM-DD] [--until YYYY-MM-DD] [path]`.
+fn run_invoice(args: &[String]) -> Result<()> {
+    const USAGE: &str = "usage: desk-monitor invoice <client> <from> <until> \
+                         [--rate <per-hour>] [--out <dir>] [path]";
+    let config = desk_monitor::Config::load()?;
+    let (mut rate, mut out_dir, mut positional) = (config.billing.hourly_rate, ".", Vec::new());
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--rate" => {
+                let value = args.next().ok_or_else(|| anyhow::anyhow!(USAGE))?;
+                rate = value
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("invalid rate: {}", value))?;
+            }
+            "--out" => out_dir = args.next().ok_or_else(|| anyhow::anyhow!(USAGE))?,
+            flag if flag.starts_with("--") => anyhow::bail!(USAGE),
+            value => positional.push(value),
+        }
+    }
+    let (client, from, until, input) = match positional[..] {
+        [client, from, until] => (client, from, until, None),
+        [client, from, until, input] => (client, from, until, Some(input)),
+        _ => anyhow::bail!(USAGE),
+    };
+    let parse_date = |value: &str| {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .map_err(|_| anyhow::anyhow!("invalid date (expected YYYY-MM-DD): {}", value))
+    };
+    let (from, until) = (parse_date(from)?, parse_date(until)?);
+    if rate <= 0.0 {
+        anyhow::bail!("no hourly rate; pass --rate or set billing.hourly_rate in the config");
+    }
+
+    let sessions = desk_monitor::storage::load_dataset(input_or_stored(input)?)?;
+    let invoice = billing::invoice(&sessions, client, from, until, rate, &config.billing);
+    billing::write_invoice(&invoice, Path::new(out_dir))?;
+    let billed = invoice.billed_secs();
+    println!(
+        "✓ Invoiced {} sessions ({}h {:02}m) for {:.2} {} to {}",
+        invoice.lines.len(),
+        billed / 3600,
+        (billed % 3600) / 60,
+        invoice.total(),
+        invoice.currency,
+        Path::new(out_dir)
+            .join(format!("{}.csv", invoice.file_stem()))
+            .display()
+    );
+    Ok(())
+}
+
+fn run_stats(args: &[String]) -> Result<()> {
+    const TOP_APPS: usize = 5;
+    const USAGE: &str = "usage: desk-monitor stats [--today | --week] [--task <name>] \
+                         [--since YYYY-MM-DD] [--until YYYY-MM-DD] [path]";
+    let parse_date = |value: Option<&String>| -> Result<NaiveDate> {
+        let value = value.ok_or_else(|| anyhow::anyhow!(USAGE))?;
+        NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .map_err(|_| anyhow::anyhow!("invalid date (expected YYYY-MM-DD): {}", value))
+    };
+
+    let (mut task, mut since, mut until, mut input) = (None, None, None, None);
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--today" => {
+                let today = Local::now().date_naive();
+                since = Some(today);
+                until = Some(today);
+            }
+            "--week" => {
+                let today = Local::now().date_naive();
+                since = Some(desk_monitor::Config::load()?.calendar.week_start(today));
+                until = Some(today);
+            }
+            "--task" => task = Some(args.next().ok_or_else(|| anyhow::anyhow!(USAGE))?),
+            "--since" => since = Some(parse_date(args.next())?),
+            "--until" => until = Some(parse_date(args.next())?),
+            flag if flag.starts_with("--") => anyhow::bail!(USAGE),
+            path => input = Some(path),
+        }
+    }
+
+    let sessions: Vec<_> = desk_monitor::storage::load_dataset(input_or_stored(input)?)?
+        .into_iter()
+        .filter(|s| task.is_none_or(|t| &s.task_name == t))
+        .filter(|s| {
+            let date = s.started_at().map(|t| t.date_naive());
+            since.is_none_or(|d| date >= Some(d)) && until.is_none_or(|d| date <= Some(d))
+        })
+        .collect();
+    let summaries = analysis::task_summaries(&sessions);
+
+    let period = match (since, until) {
+        (Some(since), Some(until)) if since == until => format!("on {}", since),
+        (Some(since), Some(until)) => format!("from {} to {}", since, until),
+        (Some(since), None) => format!("since {}", since),
+        (None, Some(until)) => format!("until {}", until),
+        (None, None) => "in total".to_string(),
+    };
+    println!("{} sessions {}", sessions.len(), period);
+    if summaries.is_empty() {
+        return Ok(());
+    }
+
+    // Billed time is only shown when it can differ from tracked time, and
+    // pseudo-idle time when jiggle detection is on.
+    let config = desk_monitor::Config::load()?;
+    let billing = &config.billing;
+    let mut billed: BTreeMap<&str, i64> = BTreeMap::new();
+    if billing::rounds(billing) {
+        for session in &sessions {
+            let secs = session.duration().map_or(0, |d| d.num_seconds());
+            *billed.entry(&session.task_name).or_default() += billing::billed_secs(secs, billing);
+        }
+    }
+    let jiggle = &config.quality.mouse_jiggle;
+    let mut pseudo_idle: BTreeMap<&str, i64> = BTreeMap::new();
+    if jiggle.enabled {
+        for session in &sessions {
+            *pseudo_idle.entry(&session.task_name).or_default() +=
+                jiggle::pseudo_idle_secs(session, jiggle);
+        }
+    }
+    let hm = |secs: i64| format!("{}h {:02}m", secs / 3600, (secs % 3600) / 60);
+    let width = summaries
+        .iter()
+        .map(|s| s.task_name.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max("Total".len());
+    let billed_header = if billed.is_empty() { "" } else { "    Billed" };
+    let idle_header = if jiggle.enabled { "  Pseudo-idle" } else { "" };
+    println!(
+        "{:<width$}  {:>8}  {:>8}{}{}  {:>10}  {:>11}",
+        "Task", "Sessions", "Tracked", billed_header, idle_header, "Keystrokes", "Mouse moves"
+    );
+    let row = |name: &str,
+               sessions: usize,
+               secs: i64,
+               billed_secs: i64,
+               idle_secs: i64,
+               keys: usize,
+               moves: usize| {
+        let billed = if billed.is_empty() {
+            String::new()
+        } else {
+            format!("  {:>8}", hm(billed_secs))
+        };
+        let idle = if jiggle.enabled {
+            format!("  {:>11}", hm(idle_secs))
+        } else {
+            String::new()
+        };
+        println!(
+            "{:<width$}  {:>8}  {:>8}{}{}  {:>10}  {:>11}",
+            name,
+            sessions,
+            hm(secs),
+            billed,
+            idle,
+            keys,
+            moves
+        );
+    };
+    for s in &summaries {
+        row(
+            &s.task_name,
+            s.sessions,
+            s.tracked_secs,
+            billed.get(s.task_name.as_str()).copied().unwrap_or(0),
+            pseudo_idle.get(s.task_name.as_str()).copied().unwrap_or(0),
+            s.keystrokes,
+            s.mouse_moves,
+        );
+    }
+    if summaries.len() > 1 {
+        row(
+            "Total",
+            sessions.len(),
+            summaries.iter().map(|s| s.tracked_secs).sum(),
+            billed.values().sum(),
+            pseudo_idle.values().sum(),
+            summaries.iter().map(|s| s.keystrokes).sum(),
+            summaries.iter().map(|s| s.mouse_moves).sum(),
+        );
+    }
+
+    // Apps come from focus changes, so there are none unless those were
+    // captured.
+    let apps = analysis::app_times(&sessions);
+    if !apps.is_empty() {
+        println!();
+        println!("Top apps");
+        let width = apps
+            .iter()
+            .take(TOP_APPS)
+            .map(|a| a.app.chars().count())
+            .max()
+            .unwrap_or(0);
+        for app in apps.iter().take(TOP_APPS) {
+            println!("  {:<width$}  {:>8}", app.app, hm(app.focused_secs));
+        }
+    }
+    Ok(())
+}
+
+fn run_profile(name: Option<&str>, save: bool) -> Result<()> {
+    let mut config = desk_monitor::Config::load()?;
+    let Some(name) = name else {
+        if config.profiles.is_empty() {
+            println!("No profiles; save one with `desk-monitor profile --save <name>`");
+        }
+        for profile in &config.profiles {
+            let active = if profile.name == config.active_profile {
+                "*"
+            } else {
+                " "
+            };
+            println!("{} {}", active, profile.name);
+        }
+        return Ok(());
+    };
+    if save {
+        config.save_profile(name);
+        config.save()?;
+        println!("✓ Saved the current settings as profile \"{}\"", name);
+    } else {
+        config.switch_profile(name)?;
+        config.save()?;
+        println!("✓ Switched to profile \"{}\"", name);
+    }
+    Ok(())
+}
+
+/// Lists the session metadata fields, or sets `field=value` presets for the
+/// next sessions. An empty value clears the field.
+fn run_meta(assignments: &[String]) -> Result<()> {
+    let mut config = desk_monitor::Config::load()?;
+    let metadata = &mut config.metadata;
+    if assignments.is_empty() {
+        if metadata.fields.is_empty() {
+            println!("No metadata fields; add one with `desk-monitor meta <field>=<value>`");
+        }
+        for field in &metadata.fields {
+            let value = metadata.values.get(field).map_or("", String::as_str);
+            println!("{}={}", field, value);
+        }
+        return Ok(());
+    }
+    for assignment in assignments {
+        let Some((field, value)) = assignment.split_once('=') else {
+            anyhow::bail!("usage: desk-monitor meta [<field>=<value>...]");
+        };
+        let field = field.trim();
+        if field.is_empty() {
+            anyhow::bail!("usage: desk-monitor meta [<field>=<value>...]");
+        }
+        if !metadata.fields.iter().any(|f| f == field) {
+            metadata.fields.push(field.to_string());
+        }
+        metadata
+            .values
+            .insert(field.to_string(), value.trim().to_string());
+    }
+    config.save()?;
+    println!("✓ Saved metadata for the next sessions");
+    Ok(())
+}
+
+fn run_policy(path: &Path) -> Result<()> {
+    let Some(policy) = Policy::load(path)? else {
+        println!("No policy at {}; settings aren't managed", path.display());
+        return Ok(());
+    };
+    println!("✓ {} is valid", path.display());
+    if !policy.notice.is_empty() {
+        println!("{}", policy.notice);
+    }
+    let mut config = desk_monitor::Config::default();
+    policy.apply(&mut config);
+    println!("{}", serde_json::to_string_pretty(&config)?);
+    Ok(())
+}
+
+fn run_check_update(install: bool) -> Result<()> {
+    let config = desk_monitor::Config::load()?.updates;
+    let Some(release) = updates::check(&config)? else {
+        println!("✓ Up to date ({})", updates::CURRENT_VERSION);
+        return Ok(());
+    };
+    println!(
+        "{} is available (running {}): {}",
+        release.name,
+        updates::CURRENT_VERSION,
+        release.page_url
+    );
+    if !release.notes.trim().is_empty() {
+        println!("\n{}\n", release.notes.trim());
+    }
+    if install {
+        let path = updates::install(&release)?;
+        println!(
+            "✓ Installed {} to {}; restart to use it",
+            release.version,
+            path.display()
+        );
+    } else if release.can_install() {
+        println!("Run `desk-monitor check-update --install` to install it");
+    }
+    Ok(())
+}
+
+fn run_keystrokes(input: &Path, out: &Path) -> Result<()> {
+    let config = desk_monitor::Config::load()?;
+    let sessions = desk_monitor::storage::load_dataset(input)?;
+    let bursts =
+        desk_monitor::export::write_keystroke_dynamics(&sessions, out, config.privacy.hide_keys)?;
+    println!(
+        "✓ Exported {} typing bursts from {} sessions to {}{}",
+        bursts,
+        sessions.len(),
+        out.display(),
+        if config.privacy.hide_keys {
+            " (keys hidden)"
+        } else {
+            ""
+        }
+    );
+    Ok(())
+}
+
+fn run_ngrams(input: &Path, out: &Path) -> Result<()> {
+    let config = desk_monitor::Config::load()?;
+    let sessions = desk_monitor::storage::load_dataset(input)?;
+    let rows = desk_monitor::export::write_key_ngrams(&sessions, out, config.privacy.hide_keys)?;
+    println!(
+        "✓ Exported {} key sequences from {} sessions to {}{}",
+        rows,
+        sessions.len(),
+        out.display(),
+        if config.privacy.hide_keys {
+            " (key categories only)"
+        } else {
+            ""
+        }
+    );
+    Ok(())
+}
+
+fn run_archive(input: &Path) -> Result<()> {
+    let config = desk_monitor::Config::load()?;
+    let sessions = desk_monitor::storage::load_dataset(input)?;
+    let directory = Path::new(&config.data_dir).join(&config.archive.directory);
+    let mut archive = FolderStorage::new(&directory);
+    let archived = archive.load()?;
+    let mut added = 0;
+    for session in &sessions {
+        let already = archived.iter().any(|stored| {
+            stored.session_id == session.session_id && stored.start_time == session.start_time
+        });
+        if !already {
+            archive.append(session)?;
+            added += 1;
+        }
+    }
+    println!(
+        "✓ Filed {} sessions into week folders under {} ({} were already there)",
+        added,
+        directory.display(),
+        sessions.len() - added
+    );
+    Ok(())
+}
+
+fn run_trials(input: &Path, out_dir: &Path) -> Result<()> {
+    let sessions = desk_monitor::storage::load_dataset(input)?;
+    std::fs::create_dir_all(out_dir)?;
+    let trials = desk_monitor::export::write_trials(
+        &sessions,
+        out_dir.join("trial_events.csv"),
+        out_dir.join("trial_summaries.csv"),
+    )?;
+    println!(
+        "✓ Exported {} trials from {} sessions to {}",
+        trials,
+        sessions.len(),
+        out_dir.display()
+    );
+    Ok(())
+}
+
+fn run_script(args: &[String]) -> Result<()> {
+    const USAGE: &str = "usage: desk-monitor script <session_id> <output.rs|output.json> [path] \
+                         [--no-timing] [--normalize <width>x<height>]";
+    let mut options = ScriptOptions::default();
+    let mut positional = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--no-timing" => options.keep_timing = false,
+            "--normalize" => {
+                let size = args.next().ok_or_else(|| anyhow::anyhow!(USAGE))?;
+                let Some((Ok(width), Ok(height))) = size
+                    .split_once('x')
+                    .map(|(width, height)| (width.parse(), height.parse()))
+                else {
+                    anyhow::bail!("screen size should look like 1920x1080, not {}", size);
+                };
+                options.normalize = Some((width, height));
+            }
+            flag if flag.starts_with("--") => anyhow::bail!(USAGE),
+            value => positional.push(value),
+        }
+    }
+    let (session_id, out, input) = match positional[..] {
+        [session_id, out] => (session_id, out, input_or_stored(None::<&str>)?),
+        [session_id, out, input] => (session_id, out, PathBuf::from(input)),
+        _ => anyhow::bail!(USAGE),
+    };
+    let out = Path::new(out);
+    let Some(format) = ScriptFormat::from_path(out) else {
+        anyhow::bail!("{} should end in .rs or .json", out.display());
+    };
+
+    let sessions = desk_monitor::storage::load_dataset(&input)?;
+    let Some(session) = sessions.iter().find(|s| s.session_id == session_id) else {
+        anyhow::bail!("no session {} in {}", session_id, input.display());
+    };
+    script::write_script(session, out, format, options)?;
+    println!(
+        "✓ Wrote {} steps from session {} to {}",
+        script::steps(session, options).len(),
+        session_id,
+        out.display()
+    );
+    Ok(())
+}
+
+fn run_diff(a: &str, b: &str, out: &Path, input: &Path) -> Result<()> {
+    let sessions = desk_monitor::storage::load_dataset(input)?;
+    let find = |id: &str| {
+        sessions
+            .iter()
+            .find(|s| s.session_id == id)
+            .ok_or_else(|| anyhow::anyhow!("no session {} in {}", id, input.display()))
+    };
+    let (a, b) = (find(a)?, find(b)?);
+    let comparison = diff::compare(a, b);
+    std::fs::write(out, comparison.to_markdown(a, b))?;
+    integrity::write_manifest(out)?;
+    let divergences = comparison.divergences().count();
+    if divergences == 0 {
+        println!("✓ {} follows {} step for step", b.session_id, a.session_id);
+    } else {
+        println!(
+            "{} divergences between {} and {}",
+            divergences, a.session_id, b.session_id
+        );
+    }
+    println!("  Report written to {}", out.display());
+    Ok(())
+}
+
+fn run_anonymize(input: &Path, out_dir: &Path, format: desk_monitor::ExportFormat) -> Result<()> {
+    let sessions = desk_monitor::storage::load_dataset(input)?;
+    let mut pseudonymizer = Pseudonymizer::load(Path::new("."))?;
+    let anonymized: Vec<_> = sessions
+        .into_iter()
+        .map(|session| pseudonymizer.anonymize(session))
+        .collect();
+
+    std::fs::create_dir_all(out_dir)?;
+    let out = out_dir.join(format!("sessions.{}", format.extension()));
+    desk_monitor::export::write_sessions(
+        &anonymized,
+        &out,
+        format,
+        &desk_monitor::Config::load()?,
+    )?;
+    pseudonymizer.write_mapping(anonymize::MAPPING_FILE)?;
+    println!(
+        "✓ Exported {} anonymized sessions to {}",
+        anonymized.len(),
+        out.display()
+    );
+    println!(
+        "  Pseudonyms are listed in {}; keep it and {} private",
+        anonymize::MAPPING_FILE,
+        anonymize::SALT_FILE
+    );
+    Ok(())
+}
+
+fn run_merge(
+    out_dir: PathBuf,
+    sources: &[&String],
+    format: desk_monitor::ExportFormat,
+) -> Result<()> {
+    let sources: Vec<MergeSource> = sources.iter().map(|s| MergeSource::parse(s)).collect();
+    let (sessions, duplicates) = merge::merge(&sources)?;
+    merge::write_merged(&sessions, &out_dir, format, &desk_monitor::Config::load()?)?;
+
+    for machine in analysis::machine_totals(&sessions) {
+        println!(
+            "  {:<16}{} sessions, {} events",
+            machine.machine_id, machine.sessions, machine.events
+        );
+    }
+    println!(
+        "✓ Merged {} sessions from {} sources into {}",
+        sessions.len(),
+        sources.len(),
+        out_dir.display()
+    );
+    if !duplicates.is_empty() {
+        println!("  Skipped {}", duplicates);
+    }
+    Ok(())
+}
@@ -0,0 +1,168 @@
+use crate::analysis::{self, DailyTotal};
+use crate::config::{Config, ExportFormat};
+use crate::dedup::{self, DedupReport};
+use crate::export;
+use crate::integrity;
+use crate::storage;
+use crate::types::Session;
+use anyhow::{bail, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// One machine's data directory or export, as given to `merge`.
+#[derive(Debug, Clone)]
+pub struct MergeSource {
+    pub machine_id: String,
+    pub path: PathBuf,
+}
+
+impl MergeSource {
+    /// Parses `machine=path`, or a bare path labelled with its file or
+    /// directory name.
+    pub fn parse(arg: &str) -> Self {
+        match arg.split_once('=') {
+            Some((machine_id, path)) if !machine_id.is_empty() => Self {
+                machine_id: machine_id.to_string(),
+                path: PathBuf::from(path),
+            },
+            _ => {
+                let path = PathBuf::from(arg);
+                let machine_id = path
+                    .file_stem()
+                    .map_or_else(|| arg.to_string(), |s| s.to_string_lossy().into_owned());
+                Self { machine_id, path }
+            }
+        }
+    }
+}
+
+/// Loads every source, tags its sessions with the source's machine id and
+/// returns them oldest first. Sessions already tagged (from an earlier
+/// merge) keep their id. A session seen twice on the same machine, e.g. in
+/// both the sessions file and a daily export, is kept once with the events
+/// of both copies, and repeated events are dropped; the report says what
+/// was folded together.
+pub fn merge(sources: &[MergeSource]) -> Result<(Vec<Session>, DedupReport)> {
+    let mut loaded = Vec::new();
+    for source in sources {
+        if !source.path.exists() {
+            bail!("{} does not exist", source.path.display());
+        }
+        for mut session in storage::load_dataset(&source.path)? {
+            session
+                .machine_id
+                .get_or_insert_with(|| source.machine_id.clone());
+            loaded.push(session);
+        }
+    }
+    let (mut merged, report) = dedup::dedup_sessions(loaded, |session| {
+        (
+            session.machine_id.clone(),
+            session.session_id.clone(),
+            session.start_time.clone(),
+        )
+    });
+    merged.sort_by_key(|s| s.started_at());
+    Ok((merged, report))
+}
+
+/// Writes a merged dataset to `out_dir`: the sessions in `format`, daily
+/// totals per machine as CSV and a Markdown report with per-machine
+/// breakdowns. Data files get `.sha256` manifests like other exports.
+pub fn write_merged(
+    sessions: &[Session],
+    out_dir: &Path,
+    format: ExportFormat,
+    config: &Config,
+) -> Result<()> {
+    fs::create_dir_all(out_dir)?;
+    export::write_sessions(
+        sessions,
+        out_dir.join(format!("sessions.{}", format.extension())),
+        format,
+        config,
+    )?;
+
+    let daily_path = out_dir.join("daily_by_machine.csv");
+    let mut writer = csv::Writer::from_path(&daily_path)?;
+    writer.write_record(["date", "machine_id", "sessions", "tracked_secs", "events"])?;
+    for (machine_id, days) in daily_by_machine(sessions) {
+        for day in days {
+            writer.write_record([
+                day.date.to_string(),
+                machine_id.clone(),
+                day.sessions.to_string(),
+                day.tracked_secs.to_string(),
+                day.events.to_string(),
+            ])?;
+        }
+    }
+    writer.flush()?;
+    drop(writer);
+    integrity::write_manifest(&daily_path)?;
+
+    fs::write(out_dir.join("report.md"), report_markdown(sessions))?;
+    Ok(())
+}
+
+fn daily_by_machine(sessions: &[Session]) -> Vec<(String, Vec<DailyTotal>)> {
+    analysis::machine_totals(sessions)
+        .into_iter()
+        .map(|machine| {
+            let days = analysis::daily_totals(
+                sessions
+                    .iter()
+                    .filter(|s| s.machine_id.as_deref().unwrap_or("local") == machine.machine_id),
+            );
+            (machine.machine_id, days)
+        })
+        .collect()
+}
+
+/// Renders totals for the whole merged dataset, per machine and per day.
+pub fn report_markdown(sessions: &[Session]) -> String {
+    let machines = analysis::machine_totals(sessions);
+    let total_secs: i64 = machines.iter().map(|m| m.tracked_secs).sum();
+
+    let mut md = String::from("# Merged activity report\n\n");
+    md.push_str(&format!(
+        "- Machines: {}\n- Sessions: {}\n- Tracked: {}\n",
+        machines.len(),
+        sessions.len(),
+        format_secs(total_secs)
+    ));
+
+    md.push_str("\n## By machine\n\n");
+    md.push_str("| Machine | Sessions | Tracked | Events |\n|---|---|---|---|\n");
+    for machine in &machines {
+        md.push_str(&format!(
+            "| {} | {} | {} | {} |\n",
+            machine.machine_id,
+            machine.sessions,
+            format_secs(machine.tracked_secs),
+            machine.events
+        ));
+    }
+
+    md.push_str("\n## By day\n\n");
+    md.push_str("| Date | Machine | Sessions | Tracked |\n|---|---|---|---|\n");
+    let mut rows: Vec<(String, DailyTotal)> = daily_by_machine(sessions)
+        .into_iter()
+        .flat_map(|(machine_id, days)| days.into_iter().map(move |d| (machine_id.clone(), d)))
+        .collect();
+    rows.sort_by(|a, b| a.1.date.cmp(&b.1.date).then_with(|| a.0.cmp(&b.0)));
+    for (machine_id, day) in rows {
+        md.push_str(&format!(
+            "| {} | {} | {} | {} |\n",
+            day.date,
+            machine_id,
+            day.sessions,
+            format_secs(day.tracked_secs)
+        ));
+    }
+    md
+}
+
+fn format_secs(secs: i64) -> String {
+    format!("{}h {:02}m", secs / 3600, (secs % 3600) / 60)
+}
@@ -0,0 +1,255 @@
+//! Config-defined derived metrics such as `keystrokes / active_minutes`.
+//!
+//! Expressions support numbers, the variables below, `+ - * /`, unary minus
+//! and parentheses. A metric that divides by zero or names an unknown
+//! variable has no value for that session or minute.
+//!
+//! Variables, per session and per minute:
+//! `keystrokes`, `mouse_moves`, `mouse_distance` (pixels), `clicks`,
+//! `other_events`, `events`, `active_minutes` (minutes with any event) and
+//! `duration_minutes`. Sessions also have `shortcuts`. When the screen size
+//! is configured, `mouse_distance_cm` and `mouse_distance_in` too.
+
+use crate::compaction::{self, MinuteRollup};
+use crate::config::{Config, ScreenConfig};
+use crate::types::{Action, Session};
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DerivedMetric {
+    pub name: String,
+    pub expression: String,
+}
+
+pub type Variables = BTreeMap<&'static str, f64>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Variable(String),
+    Neg(Box<Expr>),
+    Binary(Box<Expr>, char, Box<Expr>),
+}
+
+impl Expr {
+    pub fn parse(source: &str) -> Result<Self> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.expr()?;
+        if parser.pos != parser.tokens.len() {
+            bail!("unexpected {:?} in {:?}", parser.tokens[parser.pos], source);
+        }
+        Ok(expr)
+    }
+
+    pub fn eval(&self, vars: &Variables) -> Option<f64> {
+        let value = match self {
+            Expr::Number(n) => *n,
+            Expr::Variable(name) => *vars.get(name.as_str())?,
+            Expr::Neg(inner) => -inner.eval(vars)?,
+            Expr::Binary(lhs, op, rhs) => {
+                let (lhs, rhs) = (lhs.eval(vars)?, rhs.eval(vars)?);
+                match op {
+                    '+' => lhs + rhs,
+                    '-' => lhs - rhs,
+                    '*' => lhs * rhs,
+                    _ if rhs == 0.0 => return None,
+                    _ => lhs / rhs,
+                }
+            }
+        };
+        value.is_finite().then_some(value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(char),
+    Open,
+    Close,
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '+' | '-' | '*' | '/' => tokens.push(Token::Op(c)),
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            c if c.is_ascii_digit() || c == '.' => {
+                let mut end = start + 1;
+                while let Some(&(i, c)) = chars.peek() {
+                    if !(c.is_ascii_digit() || c == '.') {
+                        break;
+                    }
+                    end = i + 1;
+                    chars.next();
+                }
+                let number = &source[start..end];
+                tokens.push(Token::Number(
+                    number
+                        .parse()
+                        .map_err(|_| anyhow!("invalid number {:?}", number))?,
+                ));
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut end = start + 1;
+                while let Some(&(i, c)) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || c == '_') {
+                        break;
+                    }
+                    end = i + 1;
+                    chars.next();
+                }
+                tokens.push(Token::Ident(source[start..end].to_string()));
+            }
+            _ => bail!("unexpected {:?} in {:?}", c, source),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn peek_op(&self, ops: &[char]) -> Option<char> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(op)) if ops.contains(op) => Some(*op),
+            _ => None,
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr> {
+        let mut lhs = self.term()?;
+        while let Some(op) = self.peek_op(&['+', '-']) {
+            self.pos += 1;
+            lhs = Expr::Binary(Box::new(lhs), op, Box::new(self.term()?));
+        }
+        Ok(lhs)
+    }
+
+    fn term(&mut self) -> Result<Expr> {
+        let mut lhs = self.factor()?;
+        while let Some(op) = self.peek_op(&['*', '/']) {
+            self.pos += 1;
+            lhs = Expr::Binary(Box::new(lhs), op, Box::new(self.factor()?));
+        }
+        Ok(lhs)
+    }
+
+    fn factor(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Expr::Number(n)),
+            Some(Token::Ident(name)) => Ok(Expr::Variable(name)),
+            Some(Token::Op('-')) => Ok(Expr::Neg(Box::new(self.factor()?))),
+            Some(Token::Open) => {
+                let inner = self.expr()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(inner),
+                    _ => bail!("missing )"),
+                }
+            }
+            Some(token) => bail!("unexpected {:?}", token),
+            None => bail!("unexpected end of expression"),
+        }
+    }
+}
+
+/// Evaluates every metric against `vars`, leaving out metrics without a
+/// value. Metrics that don't parse are reported once per call.
+pub fn evaluate(metrics: &[DerivedMetric], vars: &Variables) -> BTreeMap<String, f64> {
+    let mut values = BTreeMap::new();
+    for metric in metrics {
+        match Expr::parse(&metric.expression) {
+            Ok(expr) => {
+                if let Some(value) = expr.eval(vars) {
+                    values.insert(metric.name.clone(), value);
+                }
+            }
+            Err(e) => eprintln!("Skipping metric {}: {}", metric.name, e),
+        }
+    }
+    values
+}
+
+fn add_physical_distance(vars: &mut Variables, screen: &ScreenConfig) {
+    let px = vars.get("mouse_distance").copied().unwrap_or_default();
+    if let Some(dpi) = screen.dpi() {
+        vars.insert("mouse_distance_in", px / dpi);
+        vars.insert("mouse_distance_cm", px / dpi * 2.54);
+    }
+}
+
+pub fn minute_variables(rollup: &MinuteRollup, screen: &ScreenConfig) -> Variables {
+    let events = rollup.mouse_moves + rollup.key_events + rollup.other_events;
+    let mut vars = BTreeMap::from([
+        ("keystrokes", rollup.key_events as f64),
+        ("mouse_moves", rollup.mouse_moves as f64),
+        ("mouse_distance", rollup.mouse_distance_px),
+        ("clicks", rollup.clicks as f64),
+        ("other_events", rollup.other_events as f64),
+        ("events", events as f64),
+        ("active_minutes", if events > 0 { 1.0 } else { 0.0 }),
+        ("duration_minutes", 1.0),
+    ]);
+    add_physical_distance(&mut vars, screen);
+    vars
+}
+
+pub fn session_variables(session: &Session, screen: &ScreenConfig) -> Variables {
+    let mut vars: Variables = BTreeMap::new();
+    let rollups = compaction::minute_rollups(session);
+    for rollup in &rollups {
+        for (name, value) in minute_variables(rollup, &ScreenConfig::default()) {
+            if name != "duration_minutes" {
+                *vars.entry(name).or_default() += value;
+            }
+        }
+    }
+    for name in [
+        "keystrokes",
+        "mouse_moves",
+        "mouse_distance",
+        "clicks",
+        "other_events",
+        "events",
+        "active_minutes",
+    ] {
+        vars.entry(name).or_default();
+    }
+
+    let shortcuts = session
+        .actions
+        .iter()
+        .filter(|action| matches!(action, Action::Shortcut { .. }))
+        .count();
+    vars.insert("shortcuts", shortcuts as f64);
+    add_physical_distance(&mut vars, screen);
+    if let Some(duration) = session.duration() {
+        vars.insert("duration_minutes", duration.num_seconds() as f64 / 60.0);
+    }
+    vars
+}
+
+/// Values of the configured metrics for a whole session.
+pub fn session_metrics(config: &Config, session: &Session) -> BTreeMap<String, f64> {
+    if config.metrics.is_empty() {
+        return BTreeMap::new();
+    }
+    evaluate(&config.metrics, &session_variables(session, &config.screen))
+}
@@ -1,24 +1,178 @@
-use crate::types::{Action, DetailedEvent, Session};
+use crate::aggregates::{LiveAggregates, MetricsSnapshot};
+use crate::analysis::{self, PAUSE_KIND, RESUME_KIND, TRIAL_END_KIND, TRIAL_START_KIND};
+use crate::api;
+use crate::app_alerts::AppAlertTracker;
+use crate::bug_report::{self, BugReport};
+use crate::clipboard;
+use crate::config::{CaptureConfig, Config, SessionTemplate};
+use crate::consent::{self, ConsentRecord, CONSENT_FILE};
+use crate::crash;
+use crate::diagnostics::{Diagnostics, DiagnosticsSnapshot};
+use crate::dnd::{self, DoNotDisturb};
+use crate::event_bus::{EventBus, SinkHealth, SinkPolicy, DEFAULT_CAPACITY};
+use crate::export;
+use crate::handle::{self, CommandQueue, MonitorHandle};
+use crate::ide;
+use crate::import::{ConflictResolution, ImportPreview, ImportReport};
+use crate::input::{self, InputSource};
+use crate::lsl::LslConfig;
+use crate::privacy::{self, Redactor};
+use crate::quality;
+use crate::ring_buffer::RingBuffer;
+use crate::shortcuts;
+use crate::spool::Spool;
+use crate::storage::{Storage, DETAILS_FILE};
+use crate::sync::{self, SyncMarker};
+use crate::types::{format_timestamp, Action, DetailedEvent, Session, WindowBounds};
+use crate::watchdog::{self, Heartbeat};
+use crate::wellness::{BreakKind, BreakTracker, WellnessEvent};
+use crate::window::{self, FocusedWindow};
 use anyhow::Result;
-use chrono::Local;
-use csv::Writer;
-use device_query::{DeviceQuery, DeviceState, MouseState};
+use chrono::{DateTime, Local};
+use csv::{Writer, WriterBuilder};
+use device_query::{Keycode, MouseState};
 use std::{
-    fs::{File, OpenOptions},
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    panic::AssertUnwindSafe,
+    path::{Path, PathBuf},
     sync::atomic::{AtomicBool, Ordering},
+    sync::mpsc::Receiver,
+    sync::Arc,
+    time::{Duration, Instant},
 };
 
+/// Wall-clock drift from monotonic time that counts as a clock change.
+const CLOCK_JUMP_THRESHOLD_MS: i64 = 2000;
+/// A press released within this long, and this close to where it went
+/// down, is also recorded as a click.
+const CLICK_MAX_HOLD_MS: i64 = 500;
+const CLICK_MAX_TRAVEL_PX: i32 = 4;
+/// How often the active keyboard layout is re-read.
+const LAYOUT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// How often the focused window is re-read when focus changes are captured.
+const FOCUS_POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// Input unchanged for this long switches polling to the idle rate.
+const IDLE_AFTER: Duration = Duration::from_secs(2);
+/// First and longest wait between attempts to reopen failed input devices.
+const RECONNECT_BACKOFF_MIN: Duration = Duration::from_secs(1);
+const RECONNECT_BACKOFF_MAX: Duration = Duration::from_secs(60);
+
+/// A focused window as recorded: its id, bounds, title and app.
+type Focus = (u64, Option<WindowBounds>, Option<String>, Option<String>);
+
+/// Something that happened in the monitor, sent to every subscriber.
+#[derive(Debug, Clone)]
+pub enum MonitorEvent {
+    Started {
+        task_name: String,
+    },
+    /// Recording stopped; the session is saved now or once its notes are in.
+    Stopped {
+        task_name: String,
+    },
+    SessionSaved {
+        session_id: String,
+        task_name: String,
+    },
+    /// The current session was thrown away without being saved.
+    Discarded {
+        task_name: String,
+    },
+    TaskSwitched {
+        from: String,
+        to: String,
+    },
+    CaptureFailed {
+        reason: String,
+    },
+    CaptureResumed,
+    /// Input or environment activity recorded into the current session:
+    /// key presses, mouse moves, shortcuts, layout and clock changes, break
+    /// reminders and breaks, and injected custom events.
+    Recorded(Action),
+}
+
+/// Input capture that has failed and is waiting to be re-initialized.
+struct CaptureFailure {
+    reason: String,
+    next_attempt: Instant,
+    backoff: Duration,
+}
+
 pub struct ActivityMonitor {
-    pub is_monitoring: AtomicBool,
-    session_writer: Writer<File>,
-    pub detailed_writer: Writer<File>,
-    pub events_recorded: AtomicBool,
-    pub status_text: String,
-    device_state: DeviceState,
-    last_keys: Vec<device_query::Keycode>,
+    is_monitoring: AtomicBool,
+    storage: Box<dyn Storage>,
+    /// `None` after writing to it failed, until it's reopened.
+    detailed_writer: Option<Writer<File>>,
+    /// Writes held back while the disk refuses them.
+    spool: Spool,
+    /// Where the detailed log and session summary go: the working
+    /// directory until `relocate` moves them.
+    data_dir: PathBuf,
+    events_recorded: AtomicBool,
+    status_text: String,
+    input: Box<dyn InputSource>,
+    last_keys: Vec<Keycode>,
     last_mouse_pos: (i32, i32),
-    pub current_session: Session,
-    pub task_name: String,
+    last_buttons: Vec<bool>,
+    /// When and where each held button's press was recorded, by
+    /// `button_pressed` index.
+    buttons_down: HashMap<usize, (Instant, (i32, i32))>,
+    current_session: Session,
+    mouse_moves_seen: u64,
+    clock_anchor: Option<(Instant, DateTime<Local>)>,
+    keyboard_layout: Option<String>,
+    last_layout_check: Option<Instant>,
+    /// The focused window last recorded, with its bounds and title only if
+    /// they were, and its app.
+    focused_window: Option<Focus>,
+    last_focus_check: Option<Instant>,
+    /// The cursor shape last recorded.
+    cursor_shape: Option<String>,
+    app_alerts: AppAlertTracker,
+    last_app_check: Option<Instant>,
+    task_name: String,
+    config: Config,
+    template: Option<SessionTemplate>,
+    awaiting_notes: bool,
+    /// When the running session was paused, while it is.
+    paused: Option<Instant>,
+    /// When the running trial stops; `None` outside trials.
+    trial_ends: Option<Instant>,
+    trial_hotkey_held: bool,
+    /// The trial marked as started within the current session and not yet
+    /// ended.
+    open_trial: Option<String>,
+    /// Trials started in the current session, for numbering them.
+    trials_marked: u32,
+    /// Sync markers recorded so far, across sessions.
+    sync_markers: u64,
+    /// Input seen while not recording (see [`crate::ring_buffer`]).
+    ring_buffer: RingBuffer,
+    bug_report: Option<BugReport>,
+    diagnostics: Diagnostics,
+    last_poll: Option<Instant>,
+    /// When keyboard or mouse state last changed.
+    last_input: Option<Instant>,
+    capture_failure: Option<CaptureFailure>,
+    breaks: BreakTracker,
+    /// Compiled from `config.privacy` when a session starts.
+    redactor: Redactor,
+    /// The latest logged acceptance of the consent text's current version.
+    consent: Option<ConsentRecord>,
+    bus: EventBus<MonitorEvent>,
+    /// Events from the IDE plugin endpoint, when it's enabled.
+    ide_events: Option<Receiver<serde_json::Value>>,
+    /// Set while Do Not Disturb is on for the current session.
+    dnd: Option<DoNotDisturb>,
+    aggregates: LiveAggregates,
+    /// Whether the monitor's own window has keyboard focus and the pointer,
+    /// as last reported by the GUI.
+    self_focused: bool,
+    self_hovered: bool,
+    heartbeat: Heartbeat,
+    commands: CommandQueue,
 }
 
 impl ActivityMonitor {
@@ -26,7 +180,7 @@ impl ActivityMonitor {
         println!("=== Desktop Activity Monitor ===");
         println!("Initializing...");
 
-        let test_device = DeviceState::new();
+        let test_device = input::open_device_state()?;
         let test_mouse = test_device.get_mouse();
         println!(
             "✓ Mouse detection working (current position: {:?})",
@@ -39,57 +193,120 @@ impl ActivityMonitor {
             test_keys
         );
 
-        let session_file = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .truncate(true)
-            .open("monitoring_sessions.csv")?;
+        Self::with_input(Box::new(test_device))
+    }
+
+    /// Creates a monitor that reads from `input` instead of the system
+    /// devices.
+    pub fn with_input(input: Box<dyn InputSource>) -> Result<Self> {
+        let config = Config::load()?;
+        let storage = config.open_storage()?;
+        println!(
+            "✓ Using {} for storing sessions",
+            config.sessions_source().display()
+        );
+        Self::with_storage(input, storage)
+    }
 
+    /// Creates a monitor that reads from `input` and keeps finished sessions
+    /// in `storage` instead of the configured one. Other data goes to
+    /// `Config::data_dir`.
+    pub fn with_storage(input: Box<dyn InputSource>, storage: Box<dyn Storage>) -> Result<Self> {
+        let config = Config::load()?;
+        let data_dir = PathBuf::from(&config.data_dir);
         let detailed_file = OpenOptions::new()
             .create(true)
             .write(true)
             .truncate(true)
-            .open("latest_session_details.csv")?;
-
-        let mut session_writer = Writer::from_writer(session_file);
+            .open(data_dir.join(DETAILS_FILE))?;
         let detailed_writer = Writer::from_writer(detailed_file);
 
-        session_writer.write_record(&[
-            "session_id",
-            "task_name",
-            "start_time",
-            "end_time",
-            "actions",
-        ])?;
-        session_writer.flush()?;
-
-        println!("✓ Created monitoring_sessions.csv for storing sessions");
-        println!("✓ Created latest_session_details.csv for detailed events");
+        println!("✓ Created {} for detailed events", DETAILS_FILE);
 
-        let session_file = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open("monitoring_sessions.csv")?;
+        let aggregates = match storage.load() {
+            Ok(sessions) => LiveAggregates::seed(&sessions),
+            Err(e) => {
+                eprintln!("Error loading sessions for today's totals: {}", e);
+                LiveAggregates::default()
+            }
+        };
+        let consent = consent::accepted(CONSENT_FILE, &config.consent).unwrap_or_else(|e| {
+            eprintln!("Error reading {}: {}", CONSENT_FILE, e);
+            None
+        });
+        let commands = handle::queue();
+        if config.api.enabled {
+            if let Err(e) = api::spawn(config.api.port, Some(commands.handle())) {
+                eprintln!("Query API unavailable: {}", e);
+            }
+        }
+        let ide_events = if config.ide.enabled {
+            match ide::spawn(config.ide.port) {
+                Ok(events) => Some(events),
+                Err(e) => {
+                    eprintln!("IDE plugin endpoint unavailable: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
 
-        let session_writer = Writer::from_writer(session_file);
+        let mut bus = EventBus::default();
+        if config.lsl.enabled {
+            spawn_lsl(&config.lsl, &mut bus);
+        }
 
         Ok(Self {
             is_monitoring: AtomicBool::new(false),
-            session_writer,
-            detailed_writer,
+            storage,
+            detailed_writer: Some(detailed_writer),
+            spool: Spool::default(),
+            data_dir,
             events_recorded: AtomicBool::new(false),
             status_text: String::from("Enter task name to start monitoring"),
-            device_state: DeviceState::new(),
+            input,
             last_keys: Vec::new(),
             last_mouse_pos: (0, 0),
-            current_session: Session {
-                session_id: Local::now().format("%Y%m%d_%H%M%S").to_string(),
-                task_name: String::new(),
-                start_time: Local::now().to_rfc3339(),
-                end_time: None,
-                actions: Vec::new(),
-            },
+            last_buttons: Vec::new(),
+            buttons_down: HashMap::new(),
+            current_session: Session::new(String::new()),
+            mouse_moves_seen: 0,
+            clock_anchor: None,
+            keyboard_layout: None,
+            last_layout_check: None,
+            focused_window: None,
+            last_focus_check: None,
+            cursor_shape: None,
+            app_alerts: AppAlertTracker::default(),
+            last_app_check: None,
             task_name: String::new(),
+            config,
+            template: None,
+            awaiting_notes: false,
+            paused: None,
+            trial_ends: None,
+            trial_hotkey_held: false,
+            open_trial: None,
+            trials_marked: 0,
+            sync_markers: 0,
+            ring_buffer: RingBuffer::default(),
+            bug_report: None,
+            diagnostics: Diagnostics::default(),
+            last_poll: None,
+            last_input: None,
+            capture_failure: None,
+            breaks: BreakTracker::default(),
+            redactor: Redactor::default(),
+            consent,
+            bus,
+            ide_events,
+            dnd: None,
+            aggregates,
+            self_focused: false,
+            self_hovered: false,
+            heartbeat: Heartbeat::default(),
+            commands,
         })
     }
 
@@ -99,148 +316,1970 @@ impl ActivityMonitor {
             return;
         }
 
+        if self.awaiting_notes {
+            self.finish_session(None);
+        }
+
         if self.task_name.trim().is_empty() {
             self.status_text = "Please enter a task name first".to_string();
             return;
         }
+        if self.needs_consent() {
+            self.status_text = "Consent is needed before recording".to_string();
+            return;
+        }
+        if let Some(field) = self
+            .config
+            .metadata
+            .missing()
+            .filter(|_| self.config.metadata.required)
+        {
+            self.status_text = format!("Fill in {} before recording", field);
+            return;
+        }
 
-        self.current_session = Session {
-            session_id: Local::now().format("%Y%m%d_%H%M%S").to_string(),
-            task_name: self.task_name.clone(),
-            start_time: Local::now().to_rfc3339(),
-            end_time: None,
-            actions: Vec::new(),
-        };
+        self.current_session = Session::new(self.task_name.clone());
+        self.current_session.mouse_sample_rate = self.capture_config().mouse_sample_rate.max(1);
+        self.current_session.profile =
+            Some(self.config.active_profile.clone()).filter(|p| !p.is_empty());
+        self.current_session.consent = self
+            .consent
+            .clone()
+            .filter(|c| c.version == self.config.consent.version);
+        self.current_session.metadata = self.config.metadata.session_values();
+        self.mouse_moves_seen = 0;
+        self.buttons_down.clear();
+        self.paused = None;
+        self.open_trial = None;
+        self.trials_marked = 0;
+        // The session records from here on; the buffer restarts after it.
+        self.ring_buffer.clear();
+        self.clock_anchor = None;
+        self.keyboard_layout = None;
+        self.last_layout_check = None;
+        self.focused_window = None;
+        self.last_focus_check = None;
+        self.cursor_shape = None;
+        self.app_alerts.reset();
+        self.last_app_check = None;
+        self.capture_failure = None;
+        self.breaks.reset();
+        self.redactor = Redactor::new(&self.config.privacy);
 
-        let detailed_file = OpenOptions::new()
+        // The detailed log starts over, so events held for the last one go.
+        self.spool.clear_events();
+        match OpenOptions::new()
             .create(true)
             .write(true)
             .truncate(true)
-            .open("latest_session_details.csv")
-            .unwrap();
-        self.detailed_writer = Writer::from_writer(detailed_file);
+            .open(self.data_dir.join(DETAILS_FILE))
+        {
+            Ok(file) => self.detailed_writer = Some(Writer::from_writer(file)),
+            Err(e) => {
+                self.detailed_writer = None;
+                self.spool.failed(e, &self.config.spool);
+            }
+        }
 
         self.status_text = format!("Started monitoring task: {}", self.task_name);
         self.is_monitoring.store(true, Ordering::SeqCst);
+        self.heartbeat.beat();
+        if let Some(start) = self.current_session.started_at() {
+            self.aggregates.session_started(&self.task_name, start);
+        }
+        self.emit(MonitorEvent::Started {
+            task_name: self.task_name.clone(),
+        });
+
+        if self.config.do_not_disturb {
+            match dnd::enable() {
+                Ok(dnd) => {
+                    self.dnd = Some(dnd);
+                    self.annotate_dnd(true);
+                }
+                Err(e) => {
+                    self.status_text =
+                        format!("{} (Do Not Disturb unavailable: {})", self.status_text, e)
+                }
+            }
+        }
     }
 
-    pub fn stop_monitoring(&mut self) {
-        if !self.is_monitoring.load(Ordering::SeqCst) {
-            self.status_text = "Monitoring is not running".to_string();
+    /// Starts a trial: a recording that stops by itself after
+    /// `TrialConfig::duration_secs`, skips the notes prompt and is exported
+    /// to the trial directory once saved.
+    pub fn start_trial(&mut self) {
+        if self.is_running() {
+            self.status_text = "Already monitoring!".to_string();
+            return;
+        }
+        self.start_monitoring();
+        if !self.is_running() {
             return;
         }
 
-        self.status_text = "Stopping monitoring...".to_string();
-        self.is_monitoring.store(false, Ordering::SeqCst);
+        let duration_secs = self.config.trials.duration_secs;
+        self.trial_ends = Some(Instant::now() + Duration::from_secs(duration_secs));
+        if let Err(e) = self.inject_event(
+            "trial",
+            serde_json::json!({ "duration_secs": duration_secs }),
+        ) {
+            self.status_text = format!("Error: {}", e);
+            return;
+        }
+        self.status_text = format!(
+            "Started {}s trial for task: {}",
+            duration_secs, self.task_name
+        );
+    }
 
-        self.current_session.end_time = Some(Local::now().to_rfc3339());
+    /// Starts a bug-report capture (see [`bug_report`]): a recording of the
+    /// current task, or "Bug report" if none is entered, that takes
+    /// screenshots and follows window focus, stops by itself after
+    /// `BugReportConfig::duration_secs` and is bundled into a ZIP.
+    pub fn start_bug_report(&mut self) {
+        if self.is_running() {
+            self.status_text = "Already monitoring!".to_string();
+            return;
+        }
+        if self.task_name.trim().is_empty() {
+            self.task_name = "Bug report".to_string();
+        }
+        self.start_monitoring();
+        if !self.is_running() {
+            return;
+        }
 
-        let record = vec![
-            self.current_session.session_id.clone(),
-            self.current_session.task_name.clone(),
-            self.current_session.start_time.clone(),
-            self.current_session.end_time.clone().unwrap_or_default(),
-            self.current_session
-                .actions
-                .iter()
-                .map(|action| action.to_csv_string())
-                .collect::<Vec<_>>()
-                .join(";"),
-        ];
+        self.bug_report = Some(BugReport::new(&self.config.bug_report));
+        self.status_text = format!(
+            "Capturing a bug report for {}s{}",
+            self.config.bug_report.duration_secs,
+            if self.config.bug_report.screenshots {
+                ""
+            } else {
+                " without screenshots"
+            }
+        );
+    }
+
+    /// Time left in the running bug-report capture, if one is running.
+    pub fn bug_report_remaining(&self) -> Option<Duration> {
+        self.bug_report.as_ref().map(BugReport::remaining)
+    }
 
-        if let Err(e) = self.session_writer.write_record(&record) {
-            self.status_text = format!("Error saving session: {}", e);
+    /// Takes a screenshot for the running bug report when one is due.
+    fn check_bug_report_screenshot(&mut self) {
+        let config = &self.config.bug_report;
+        let interval = Duration::from_secs(config.screenshot_interval_secs.max(1));
+        let Some(report) = &mut self.bug_report else {
+            return;
+        };
+        if !config.screenshots {
+            return;
+        }
+        if !report.screenshot_due(interval) {
+            return;
         }
-        if let Err(e) = self.session_writer.flush() {
-            self.status_text = format!("Error flushing session data: {}", e);
+        let shot = crash::catch_expected(AssertUnwindSafe(|| self.input.screenshot()));
+        let Ok(Some(shot)) = shot else {
+            return;
+        };
+        let result = report
+            .add_screenshot(&shot.downscaled(config.screenshot_max_width))
+            .and_then(|file| {
+                self.inject_event(
+                    bug_report::SCREENSHOT_KIND,
+                    serde_json::json!({ "file": file }),
+                )
+            });
+        if let Err(e) = result {
+            self.status_text = format!("Error: {}", e);
         }
+    }
 
-        if self.events_recorded.load(Ordering::SeqCst) {
-            self.status_text = format!(
-                "Monitoring stopped for task: {}. Activities were recorded.",
-                self.task_name
-            );
+    /// Bundles the just-saved bug-report session into the bug report
+    /// directory.
+    fn export_bug_report(&mut self, report: &BugReport) {
+        let directory = &self.config.bug_report.directory;
+        let path = Path::new(directory).join(format!(
+            "bug_report_{}.zip",
+            self.current_session.session_id
+        ));
+        let result = fs::create_dir_all(directory)
+            .map_err(anyhow::Error::from)
+            .and_then(|()| report.write_bundle(&self.current_session, &self.config, &path));
+        match result {
+            Ok(()) => self.status_text = format!("Bug report saved to {}", path.display()),
+            Err(e) => self.status_text = format!("Error writing bug report: {}", e),
+        }
+    }
+
+    /// Time left in the running trial, if one is running.
+    pub fn trial_remaining(&self) -> Option<Duration> {
+        self.trial_ends
+            .map(|ends| ends.saturating_duration_since(Instant::now()))
+    }
+
+    /// Starts a trial when the trial hotkey is pressed while nothing is
+    /// being recorded.
+    fn check_trial_hotkey(&mut self) {
+        if self.config.trials.hotkey.trim().is_empty() || self.awaiting_notes {
+            return;
+        }
+        let Ok(keys) = crash::catch_expected(AssertUnwindSafe(|| self.input.get_keys())) else {
+            return;
+        };
+        let held = chord_held(&keys, &self.config.trials.hotkey);
+        if held && !self.trial_hotkey_held {
+            self.start_trial();
+            // The chord started the trial; it isn't part of it.
+            self.last_keys = keys;
+        }
+        self.trial_hotkey_held = held;
+    }
+
+    /// Marks the start of a trial within the current session, ending the
+    /// open one first. Trials without an ID are numbered from 1 in each
+    /// session. Returns the trial's ID.
+    pub fn mark_trial_start(&mut self, trial_id: Option<String>) -> Result<String> {
+        if !self.is_running() {
+            anyhow::bail!("monitoring is not running");
+        }
+        self.mark_trial_end()?;
+        self.trials_marked += 1;
+        let trial_id = trial_id
+            .map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty())
+            .unwrap_or_else(|| self.trials_marked.to_string());
+        self.inject_event(
+            TRIAL_START_KIND,
+            serde_json::json!({ "trial_id": trial_id }),
+        )?;
+        self.open_trial = Some(trial_id.clone());
+        self.status_text = format!("Trial {} started", trial_id);
+        Ok(trial_id)
+    }
+
+    /// Marks the end of the open trial. Returns its ID, or `None` when no
+    /// trial was open.
+    pub fn mark_trial_end(&mut self) -> Result<Option<String>> {
+        let Some(trial_id) = self.open_trial.take() else {
+            return Ok(None);
+        };
+        self.inject_event(TRIAL_END_KIND, serde_json::json!({ "trial_id": trial_id }))?;
+        self.status_text = format!("Trial {} ended", trial_id);
+        Ok(Some(trial_id))
+    }
+
+    /// The trial marked as started in the current session and not ended.
+    pub fn open_trial(&self) -> Option<&str> {
+        self.open_trial.as_deref()
+    }
+
+    /// Records a sync marker (see [`sync`]) into the current session and
+    /// returns it, for passing on to the other device.
+    pub fn sync_marker(&mut self, label: Option<String>) -> Result<SyncMarker> {
+        if !self.is_running() {
+            anyhow::bail!("monitoring is not running");
+        }
+        let marker = SyncMarker::now(self.sync_markers + 1, label);
+        self.inject_event(sync::EVENT_KIND, serde_json::to_value(&marker)?)?;
+        self.sync_markers = marker.counter;
+        self.status_text = format!("Sync marker {}", marker.counter);
+        Ok(marker)
+    }
+
+    /// Starts a trial within the session, or ends the open one, when `keys`
+    /// are the trial mark hotkey. Returns whether they were.
+    fn check_trial_mark_hotkey(&mut self, keys: &[Keycode]) -> bool {
+        if !chord_held(keys, &self.config.trials.mark_hotkey) {
+            return false;
+        }
+        let result = if self.open_trial.is_some() {
+            self.mark_trial_end().map(drop)
         } else {
-            self.status_text = format!(
-                "Monitoring stopped for task: {}. No activities were recorded.",
-                self.task_name
-            );
+            self.mark_trial_start(None).map(drop)
+        };
+        if let Err(e) = result {
+            self.status_text = format!("Error: {}", e);
         }
+        true
     }
 
-    pub fn update(&mut self) {
-        if !self.is_monitoring.load(Ordering::SeqCst) {
+    /// Records a sync marker when `keys` are the sync hotkey. Returns
+    /// whether they were.
+    fn check_sync_hotkey(&mut self, keys: &[Keycode]) -> bool {
+        if !chord_held(keys, &self.config.sync.hotkey) {
+            return false;
+        }
+        if let Err(e) = self.sync_marker(None) {
+            self.status_text = format!("Error: {}", e);
+        }
+        true
+    }
+
+    /// Keeps the last `RingBufferConfig::seconds` of input while nothing is
+    /// being recorded, and saves it when the buffer hotkey is pressed.
+    fn buffer_input(&mut self) {
+        let window = Duration::from_secs(self.config.ring_buffer.seconds);
+        if self.is_running() || window.is_zero() || self.needs_consent() {
             return;
         }
+        let reads = crash::catch_expected(AssertUnwindSafe(|| {
+            (self.input.get_keys(), self.input.get_mouse())
+        }));
+        let Ok((keys, mut mouse)) = reads else {
+            return;
+        };
+        mouse.coords = privacy::bin_coords(mouse.coords, self.config.privacy.mouse_grid_px);
 
-        // Monitor keyboard
-        let keys = self.device_state.get_keys();
+        let now = Instant::now();
+        let timestamp = format_timestamp(Local::now());
+        let capture = self.capture_config().clone();
+        let mut actions = Vec::new();
         if keys != self.last_keys {
-            let timestamp = Local::now().to_rfc3339();
-            let keys_str: Vec<String> = keys.iter().map(|k| format!("{:?}", k)).collect();
-            let mouse: MouseState = self.device_state.get_mouse();
+            self.last_input = Some(now);
+            if chord_held(&keys, &self.config.ring_buffer.hotkey) {
+                if let Err(e) = self.save_ring_buffer() {
+                    self.status_text = format!("Error: {}", e);
+                }
+            } else if capture.keyboard && !(capture.ignore_self && self.self_focused) {
+                actions.push(Action::KeyPress {
+                    timestamp: timestamp.clone(),
+                    keys: keys.iter().map(|k| format!("{:?}", k)).collect(),
+                });
+            }
+            self.last_keys = keys;
+        }
 
-            let action = Action::KeyPress {
-                timestamp: timestamp.clone(),
-                keys: keys_str.clone(),
-            };
-            self.current_session.actions.push(action);
+        let pointer_ignored = capture.ignore_self && self.self_hovered;
+        if mouse.coords != self.last_mouse_pos {
+            self.last_input = Some(now);
+            if capture.mouse_moves && !pointer_ignored {
+                actions.push(Action::MouseMove {
+                    timestamp: timestamp.clone(),
+                    coords: mouse.coords,
+                });
+            }
+            self.last_mouse_pos = mouse.coords;
+        }
+        if mouse.button_pressed != self.last_buttons {
+            self.last_input = Some(now);
+            for button in 0..mouse.button_pressed.len().max(self.last_buttons.len()) {
+                let down = mouse.button_pressed.get(button) == Some(&true);
+                let was_down = self.last_buttons.get(button) == Some(&true);
+                if down == was_down {
+                    continue;
+                }
+                if down && capture.records_button(button) && !pointer_ignored {
+                    self.buttons_down.insert(button, (now, mouse.coords));
+                    actions.push(Action::MouseDown {
+                        timestamp: timestamp.clone(),
+                        button: button_name(button),
+                        coords: mouse.coords,
+                    });
+                } else if let Some((down_at, down_coords)) = self.buttons_down.remove(&button) {
+                    let hold_ms = down_at.elapsed().as_millis() as i64;
+                    actions.push(Action::MouseUp {
+                        timestamp: timestamp.clone(),
+                        button: button_name(button),
+                        coords: mouse.coords,
+                        hold_ms,
+                    });
+                    actions.extend(click(
+                        &timestamp,
+                        button,
+                        down_coords,
+                        mouse.coords,
+                        hold_ms,
+                    ));
+                }
+            }
+            self.last_buttons = mouse.button_pressed;
+        }
 
-            let detailed_event = DetailedEvent {
-                timestamp,
-                task_name: self.task_name.clone(),
-                event_type: "keyboard".to_string(),
-                details: format!("{:?}", keys_str),
-                mouse_x: mouse.coords.0,
-                mouse_y: mouse.coords.1,
-            };
+        for action in actions {
+            self.ring_buffer.push(now, action, window);
+        }
+        self.ring_buffer.expire(now, window);
+    }
 
-            if let Err(e) = self.detailed_writer.serialize(&detailed_event) {
-                self.status_text = format!("Error: {}", e);
-            } else {
-                self.events_recorded.store(true, Ordering::SeqCst);
-                self.status_text = format!("Task: {} - Keyboard: {:?}", self.task_name, keys_str);
+    /// Saves the input buffered while not recording as a session of its
+    /// own, under `RingBufferConfig::task_name`. Returns the session's ID.
+    pub fn save_ring_buffer(&mut self) -> Result<String> {
+        if self.is_running() {
+            anyhow::bail!("already recording; the session has this input");
+        }
+        if self.awaiting_notes {
+            self.finish_session(None);
+        }
+        let buffer = &self.config.ring_buffer;
+        let Some(mut session) = self.ring_buffer.take_session(&buffer.task_name) else {
+            anyhow::bail!("nothing has been buffered");
+        };
+        let seconds = buffer.seconds;
+        session.profile = Some(self.config.active_profile.clone()).filter(|p| !p.is_empty());
+        session.consent = self
+            .consent
+            .clone()
+            .filter(|c| c.version == self.config.consent.version);
+        session.metadata = self.config.metadata.session_values();
+
+        self.redactor = Redactor::new(&self.config.privacy);
+        self.current_session = session;
+        if !self.save_session() {
+            anyhow::bail!("{}", self.status_text);
+        }
+        let session_id = self.current_session.session_id.clone();
+        self.status_text = format!(
+            "Saved the last {}s of input as session {}",
+            seconds, session_id
+        );
+        Ok(session_id)
+    }
+
+    /// Writes the just-saved trial session to the trial directory.
+    fn export_trial(&mut self) {
+        let trials = &self.config.trials;
+        let path = Path::new(&trials.directory).join(format!(
+            "trial_{}.{}",
+            self.current_session.session_id,
+            trials.format.extension()
+        ));
+        let result = fs::create_dir_all(&trials.directory)
+            .map_err(anyhow::Error::from)
+            .and_then(|()| {
+                export::write_sessions(
+                    std::slice::from_ref(&self.current_session),
+                    &path,
+                    trials.format,
+                    &self.config,
+                )
+            });
+        match result {
+            Ok(()) => {
+                self.status_text = format!(
+                    "Trial finished for task: {}. Exported to {}",
+                    self.task_name,
+                    path.display()
+                )
             }
-            self.detailed_writer
-                .flush()
-                .unwrap_or_else(|e| eprintln!("Error flushing: {}", e));
-            self.last_keys = keys;
+            Err(e) => self.status_text = format!("Error exporting trial: {}", e),
         }
+    }
 
-        // Monitor mouse
-        let mouse: MouseState = self.device_state.get_mouse();
-        let current_pos = mouse.coords;
-        if current_pos != self.last_mouse_pos {
-            let timestamp = Local::now().to_rfc3339();
+    /// Records a Do Not Disturb period starting or ending as a `dnd` custom
+    /// event.
+    fn annotate_dnd(&mut self, enabled: bool) {
+        if let Err(e) = self.inject_event("dnd", serde_json::json!({ "enabled": enabled })) {
+            self.status_text = format!("Error: {}", e);
+        }
+    }
 
-            let action = Action::MouseMove {
-                timestamp: timestamp.clone(),
-                coords: current_pos,
-            };
-            self.current_session.actions.push(action);
+    /// Returns a receiver for every event from now on, buffering up to
+    /// [`DEFAULT_CAPACITY`] more while it falls behind. Dropping it
+    /// unsubscribes.
+    pub fn subscribe(&mut self) -> Receiver<MonitorEvent> {
+        self.subscribe_sink(
+            "subscriber",
+            DEFAULT_CAPACITY,
+            SinkPolicy::Buffer(DEFAULT_CAPACITY),
+        )
+    }
 
-            let detailed_event = DetailedEvent {
-                timestamp,
-                task_name: self.task_name.clone(),
-                event_type: "mouse_move".to_string(),
-                details: format!("Moved to {:?}", current_pos),
-                mouse_x: current_pos.0,
-                mouse_y: current_pos.1,
-            };
+    /// Like [`Self::subscribe`], with the sink's name in diagnostics, its
+    /// channel capacity and what happens to events once that's full.
+    pub fn subscribe_sink(
+        &mut self,
+        name: &str,
+        capacity: usize,
+        policy: SinkPolicy,
+    ) -> Receiver<MonitorEvent> {
+        self.bus.subscribe(name, capacity, policy)
+    }
+
+    /// How each subscriber is keeping up, including events dropped for it.
+    pub fn sink_health(&self) -> Vec<SinkHealth> {
+        self.bus.health()
+    }
+
+    fn emit(&mut self, event: MonitorEvent) {
+        self.bus.publish(event);
+    }
+
+    /// Adds an activity action to the current session and announces it.
+    fn record_action(&mut self, action: Action) {
+        self.aggregates.recorded(&action, Instant::now());
+        if !self.bus.is_empty() {
+            self.emit(MonitorEvent::Recorded(action.clone()));
+        }
+        self.current_session.actions.push(action);
+    }
+
+    /// Records an integration-defined event into the current session, where
+    /// it flows to subscribers, the detailed log and every export like
+    /// captured input. Fails when monitoring isn't running.
+    pub fn inject_event(
+        &mut self,
+        kind: impl Into<String>,
+        payload: serde_json::Value,
+    ) -> Result<()> {
+        if !self.is_monitoring.load(Ordering::SeqCst) {
+            anyhow::bail!("monitoring is not running");
+        }
+        let kind = kind.into();
+        if kind.is_empty() {
+            anyhow::bail!("custom events need a kind");
+        }
+        let timestamp = format_timestamp(Local::now());
+        let details = format!("{} {}", kind, payload);
+        self.record_action(Action::Custom {
+            timestamp: timestamp.clone(),
+            kind,
+            payload,
+        });
+
+        let detailed_event = DetailedEvent {
+            timestamp,
+            task_name: self.task_name.clone(),
+            event_type: "custom".to_string(),
+            details,
+            mouse_x: self.last_mouse_pos.0,
+            mouse_y: self.last_mouse_pos.1,
+        };
+        self.write_detailed(&detailed_event)?;
+        Ok(())
+    }
 
-            if let Err(e) = self.detailed_writer.serialize(&detailed_event) {
+    /// Adds pending IDE plugin events to the session; ones that arrive while
+    /// nothing is being recorded are dropped.
+    fn receive_ide_events(&mut self) {
+        let Some(events) = &self.ide_events else {
+            return;
+        };
+        let pending: Vec<serde_json::Value> = events.try_iter().collect();
+        if !self.is_monitoring.load(Ordering::SeqCst) || self.paused.is_some() {
+            return;
+        }
+        for event in pending {
+            if let Err(e) = self.inject_event(ide::EVENT_KIND, event) {
                 self.status_text = format!("Error: {}", e);
-            } else {
-                self.events_recorded.store(true, Ordering::SeqCst);
-                self.status_text = format!(
-                    "Task: {} - Mouse: ({}, {})",
-                    self.task_name, current_pos.0, current_pos.1
-                );
             }
-            self.detailed_writer
-                .flush()
-                .unwrap_or_else(|e| eprintln!("Error flushing: {}", e));
-            self.last_mouse_pos = current_pos;
         }
     }
+
+    pub fn stop_monitoring(&mut self) {
+        if !self.is_monitoring.load(Ordering::SeqCst) {
+            self.status_text = "Monitoring is not running".to_string();
+            return;
+        }
+
+        if let Err(e) = self.mark_trial_end() {
+            eprintln!("Couldn't mark the end of the open trial: {}", e);
+        }
+        self.status_text = "Stopping monitoring...".to_string();
+        if let Some(dnd) = self.dnd.take() {
+            self.annotate_dnd(false);
+            if let Err(e) = dnd.restore() {
+                eprintln!("Couldn't restore Do Not Disturb: {}", e);
+            }
+        }
+        self.is_monitoring.store(false, Ordering::SeqCst);
+        self.paused = None;
+        self.aggregates.session_ended();
+        self.heartbeat.clear();
+        let trial = self.trial_ends.take().is_some();
+        let bug_report = self.bug_report.take();
+
+        self.current_session.end_time = Some(format_timestamp(Local::now()));
+        self.emit(MonitorEvent::Stopped {
+            task_name: self.task_name.clone(),
+        });
+
+        let prompt_notes = self
+            .template
+            .as_ref()
+            .and_then(|t| t.prompt_session_notes)
+            .unwrap_or(self.config.prompt_session_notes);
+        if prompt_notes && !trial && bug_report.is_none() {
+            self.awaiting_notes = true;
+            self.status_text = format!(
+                "Monitoring stopped for task: {}. Add notes?",
+                self.task_name
+            );
+            return;
+        }
+
+        if !self.save_session() {
+            return;
+        }
+        if trial {
+            self.export_trial();
+        }
+        if let Some(report) = bug_report {
+            self.export_bug_report(&report);
+        }
+    }
+
+    /// Stops capturing input without ending the session, until `resume`.
+    /// The pause is recorded as `pause` and `resume` custom events. Trials
+    /// and bug reports run for a fixed time, so they can't be paused.
+    pub fn pause(&mut self) {
+        if !self.is_running() {
+            self.status_text = "Monitoring is not running".to_string();
+            return;
+        }
+        if self.paused.is_some() {
+            self.status_text = "Already paused".to_string();
+            return;
+        }
+        if self.trial_ends.is_some() || self.bug_report.is_some() {
+            self.status_text = "Trials and bug reports can't be paused".to_string();
+            return;
+        }
+        if let Err(e) = self.inject_event(PAUSE_KIND, serde_json::json!({})) {
+            self.status_text = format!("Error: {}", e);
+            return;
+        }
+        self.paused = Some(Instant::now());
+        // Nothing is captured while paused, which isn't a stall.
+        self.heartbeat.clear();
+        self.status_text = format!("Paused task: {}", self.task_name);
+    }
+
+    /// Carries on capturing into the paused session.
+    pub fn resume(&mut self) {
+        let Some(paused) = self.paused.take() else {
+            self.status_text = "Monitoring is not paused".to_string();
+            return;
+        };
+        self.heartbeat.beat();
+        let paused_secs = paused.elapsed().as_secs();
+        if let Err(e) = self.inject_event(
+            RESUME_KIND,
+            serde_json::json!({ "paused_secs": paused_secs }),
+        ) {
+            self.status_text = format!("Error: {}", e);
+            return;
+        }
+        self.status_text = format!("Resumed task: {}", self.task_name);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.is_some()
+    }
+
+    /// Stops recording, or drops a stopped session still waiting for its
+    /// notes, without saving anything. The session's detailed events are
+    /// cleared too.
+    pub fn discard_session(&mut self) {
+        if !self.is_running() && !self.awaiting_notes {
+            self.status_text = "No session to discard".to_string();
+            return;
+        }
+        if let Some(dnd) = self.dnd.take() {
+            if let Err(e) = dnd.restore() {
+                eprintln!("Couldn't restore Do Not Disturb: {}", e);
+            }
+        }
+        self.is_monitoring.store(false, Ordering::SeqCst);
+        self.aggregates.session_ended();
+        self.heartbeat.clear();
+        self.awaiting_notes = false;
+        self.paused = None;
+        self.trial_ends = None;
+        self.bug_report = None;
+        self.open_trial = None;
+        self.current_session = Session::new(String::new());
+        self.spool.clear_events();
+
+        self.status_text = format!("Discarded session for task: {}", self.task_name);
+        match OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(self.data_dir.join(DETAILS_FILE))
+        {
+            Ok(file) => self.detailed_writer = Some(Writer::from_writer(file)),
+            Err(e) => {
+                self.status_text = format!("Error clearing {}: {}", DETAILS_FILE, e);
+            }
+        }
+        self.emit(MonitorEvent::Discarded {
+            task_name: self.task_name.clone(),
+        });
+    }
+
+    /// A handle for calling into the monitor from other threads. Calls run
+    /// on the next `update()`.
+    pub fn handle(&self) -> MonitorHandle {
+        self.commands.handle()
+    }
+
+    /// Called by handles after queuing a call, for owners that only update
+    /// on demand (e.g. to request a GUI repaint).
+    pub fn set_waker(&self, wake: impl Fn() + Send + Sync + 'static) {
+        self.commands.set_waker(Arc::new(wake));
+    }
+
+    /// The capture heartbeat, for a [`watchdog`] to watch.
+    pub fn heartbeat(&self) -> Heartbeat {
+        self.heartbeat.clone()
+    }
+
+    /// Tells the monitor whether its own window has keyboard focus and
+    /// whether the pointer is over it, so input aimed at the monitor itself
+    /// can be left out (see `CaptureConfig::ignore_self`).
+    pub fn set_self_focus(&mut self, focused: bool, hovered: bool) {
+        self.self_focused = focused;
+        self.self_hovered = hovered;
+    }
+
+    /// Today's tracked time, per-task time and APM, kept up to date as
+    /// sessions run and are saved.
+    pub fn metrics(&self) -> MetricsSnapshot {
+        self.aggregates.snapshot()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.is_monitoring.load(Ordering::SeqCst)
+    }
+
+    /// The latest status message for the GUI's status line.
+    pub fn status(&self) -> &str {
+        &self.status_text
+    }
+
+    pub fn set_status(&mut self, status: impl Into<String>) {
+        self.status_text = status.into();
+    }
+
+    pub fn task_name(&self) -> &str {
+        &self.task_name
+    }
+
+    /// Sets the task for the next session. Ignored while recording; use
+    /// `switch_task` instead.
+    pub fn set_task_name(&mut self, task_name: impl Into<String>) {
+        if !self.is_running() {
+            self.task_name = task_name.into();
+        }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Settings changes apply from the next tick; capture settings affect a
+    /// running session, privacy rules the next one.
+    pub fn config_mut(&mut self) -> &mut Config {
+        &mut self.config
+    }
+
+    /// Whether recording is blocked until the participant accepts the
+    /// current version of the consent text.
+    pub fn needs_consent(&self) -> bool {
+        let config = &self.config.consent;
+        config.required
+            && self
+                .consent
+                .as_ref()
+                .is_none_or(|c| c.version != config.version)
+    }
+
+    /// The consent recorded sessions are attached to, if given.
+    pub fn consent(&self) -> Option<&ConsentRecord> {
+        self.consent.as_ref()
+    }
+
+    /// Logs `participant_id`'s acceptance of the current consent text to
+    /// `consent_log.csv`, allowing recording to start.
+    pub fn accept_consent(&mut self, participant_id: &str) -> Result<()> {
+        let record = consent::accept(CONSENT_FILE, &self.config.consent, participant_id)?;
+        self.status_text = format!("Consent recorded for {}", record.participant_id);
+        self.consent = Some(record);
+        Ok(())
+    }
+
+    /// Switches to config profile `name` (see `Config::switch_profile`)
+    /// without saving the config. Like other settings changes, capture
+    /// settings apply from the next tick and privacy rules and the profile
+    /// recorded on sessions from the next session.
+    pub fn switch_profile(&mut self, name: &str) -> Result<()> {
+        self.config.switch_profile(name)?;
+        self.status_text = format!("Switched to profile: {}", name);
+        Ok(())
+    }
+
+    /// The session being recorded, or the last one once stopped.
+    pub fn current_session(&self) -> &Session {
+        &self.current_session
+    }
+
+    /// Selects the template applied to the next session, or clears it.
+    /// Ignored while recording.
+    pub fn select_template(&mut self, name: Option<&str>) {
+        if self.is_monitoring.load(Ordering::SeqCst) {
+            return;
+        }
+        self.template = name.and_then(|name| {
+            self.config
+                .templates
+                .iter()
+                .find(|t| t.name == name)
+                .cloned()
+        });
+        if let Some(task_name) = self.template.as_ref().and_then(|t| t.task_name.clone()) {
+            self.task_name = task_name;
+        }
+    }
+
+    /// The keyboard layout last seen while recording, if known.
+    pub fn keyboard_layout(&self) -> Option<&str> {
+        self.keyboard_layout.as_deref()
+    }
+
+    pub fn selected_template(&self) -> Option<&str> {
+        self.template.as_ref().map(|t| t.name.as_str())
+    }
+
+    /// Capture settings for the current session: the template's if it sets
+    /// them, the global ones otherwise.
+    fn capture_config(&self) -> &CaptureConfig {
+        self.template
+            .as_ref()
+            .and_then(|t| t.capture.as_ref())
+            .unwrap_or(&self.config.capture)
+    }
+
+    /// Ends the current session and immediately starts one for `new_task`
+    /// in the same recording run. The new session starts at the exact
+    /// instant the old one ends, so no events fall between them.
+    pub fn switch_task(&mut self, new_task: String) {
+        if !self.is_monitoring.load(Ordering::SeqCst) {
+            self.status_text = "Monitoring is not running".to_string();
+            return;
+        }
+        let new_task = new_task.trim().to_string();
+        if new_task.is_empty() || new_task == self.task_name {
+            self.status_text = "Enter a different task name to switch to".to_string();
+            return;
+        }
+        if self.trial_ends.is_some() {
+            self.status_text = "Tasks can't be switched during a trial".to_string();
+            return;
+        }
+        if let Err(e) = self.mark_trial_end() {
+            eprintln!("Couldn't mark the end of the open trial: {}", e);
+        }
+        self.trials_marked = 0;
+
+        let now = format_timestamp(Local::now());
+        self.current_session.end_time = Some(now.clone());
+        self.save_session();
+
+        let previous_task = std::mem::replace(&mut self.task_name, new_task);
+        let mouse_sample_rate = self.current_session.mouse_sample_rate;
+        // Privacy rules were compiled at the start, so the profile they came
+        // from carries over too.
+        let profile = self.current_session.profile.take();
+        let consent = self.current_session.consent.take();
+        let metadata = std::mem::take(&mut self.current_session.metadata);
+        self.current_session = Session::new(self.task_name.clone());
+        self.current_session.start_time = now.clone();
+        self.current_session.mouse_sample_rate = mouse_sample_rate;
+        self.current_session.profile = profile;
+        self.current_session.consent = consent;
+        self.current_session.metadata = metadata;
+        if let Some(start) = self.current_session.started_at() {
+            self.aggregates.session_started(&self.task_name, start);
+        }
+        self.current_session.actions.push(Action::TaskSwitch {
+            timestamp: now.clone(),
+            from: previous_task.clone(),
+            to: self.task_name.clone(),
+        });
+        if let Some(layout) = self.keyboard_layout.clone() {
+            self.current_session.actions.push(Action::LayoutChange {
+                timestamp: now.clone(),
+                layout,
+            });
+        }
+        if let Some((window, bounds, title, app)) = self.focused_window.clone() {
+            self.current_session.actions.push(Action::FocusChange {
+                timestamp: now.clone(),
+                window,
+                bounds,
+                title,
+                app,
+            });
+        }
+        if let Some(shape) = self.cursor_shape.clone() {
+            self.current_session.actions.push(Action::CursorChange {
+                timestamp: now.clone(),
+                shape,
+            });
+        }
+        if self.dnd.is_some() {
+            self.current_session.actions.push(Action::Custom {
+                timestamp: now.clone(),
+                kind: "dnd".to_string(),
+                payload: serde_json::json!({ "enabled": true }),
+            });
+        }
+
+        let detailed_event = DetailedEvent {
+            timestamp: now,
+            task_name: self.task_name.clone(),
+            event_type: "task_switch".to_string(),
+            details: format!("{} -> {}", previous_task, self.task_name),
+            mouse_x: self.last_mouse_pos.0,
+            mouse_y: self.last_mouse_pos.1,
+        };
+        if let Err(e) = self.write_detailed(&detailed_event) {
+            self.status_text = format!("Error: {}", e);
+            return;
+        }
+
+        self.status_text = format!(
+            "Switched from task: {} to task: {}",
+            previous_task, self.task_name
+        );
+        self.emit(MonitorEvent::TaskSwitched {
+            from: previous_task,
+            to: self.task_name.clone(),
+        });
+    }
+
+    /// Whether a stopped session is waiting for its notes before being saved.
+    pub fn awaiting_notes(&self) -> bool {
+        self.awaiting_notes
+    }
+
+    /// Attaches the notes from the stop prompt and saves the session.
+    pub fn finish_session(&mut self, notes: Option<String>) {
+        if !self.awaiting_notes {
+            return;
+        }
+        self.awaiting_notes = false;
+        self.current_session.notes = notes.filter(|n| !n.trim().is_empty());
+        self.save_session();
+    }
+
+    /// Saves the current session, returning whether it was stored.
+    fn save_session(&mut self) -> bool {
+        self.redactor.redact_session(&mut self.current_session);
+        self.current_session.quality_flags =
+            Some(quality::check(&self.current_session, &self.config.quality));
+        self.current_session.density = Some(analysis::event_density(&self.current_session));
+
+        let stored = if self.spool.is_holding() {
+            Err(anyhow::anyhow!("writes are held back"))
+        } else {
+            self.storage.append(&self.current_session)
+        };
+        if let Err(e) = stored {
+            if !self.spool.is_holding() {
+                self.spool.failed(&e, &self.config.spool);
+            }
+            self.spool.hold_session(self.current_session.clone());
+            self.status_text = format!(
+                "Couldn't save the session for task: {} ({}); it's kept until it can be",
+                self.task_name, e
+            );
+            return false;
+        }
+        self.session_stored(&self.current_session.clone());
+        if let Err(e) = export::write_markdown(
+            &self.current_session,
+            self.data_dir.join("latest_session_summary.md"),
+            &self.config,
+        ) {
+            self.status_text = format!("Error writing session summary: {}", e);
+            return true;
+        }
+        if self.events_recorded.load(Ordering::SeqCst) {
+            self.status_text = format!(
+                "Monitoring stopped for task: {}. Activities were recorded.",
+                self.task_name
+            );
+        } else {
+            self.status_text = format!(
+                "Monitoring stopped for task: {}. No activities were recorded.",
+                self.task_name
+            );
+        }
+        true
+    }
+
+    /// Announces a session that was just stored.
+    fn session_stored(&mut self, session: &Session) {
+        self.aggregates.session_saved(session);
+        self.emit(MonitorEvent::SessionSaved {
+            session_id: session.session_id.clone(),
+            task_name: session.task_name.clone(),
+        });
+    }
+
+    /// Stores `session`, as found in storage, again with `change` made to
+    /// it, e.g. a task renamed or a tag added from the history. The ID and
+    /// start time must stay the same, since they're how it's found.
+    pub fn update_stored(
+        &mut self,
+        session: &Session,
+        change: impl FnOnce(&mut Session),
+    ) -> Result<()> {
+        let mut updated = session.clone();
+        change(&mut updated);
+        self.storage.replace(&updated)?;
+        self.aggregates.session_removed(session);
+        self.aggregates.session_saved(&updated);
+        Ok(())
+    }
+
+    /// Every stored session, oldest first.
+    pub fn stored_sessions(&self) -> Result<Vec<Session>> {
+        self.storage.load()
+    }
+
+    /// Adds the sessions in `preview` to storage; see [`ImportPreview::apply`].
+    pub fn import(
+        &mut self,
+        preview: ImportPreview,
+        resolution: ConflictResolution,
+    ) -> Result<ImportReport> {
+        let report = preview.apply(self.storage.as_mut(), resolution)?;
+        self.aggregates = LiveAggregates::seed(&self.storage.load()?);
+        Ok(report)
+    }
+
+    /// Deletes `session`, as found in storage, e.g. from the history.
+    pub fn remove_stored(&mut self, session: &Session) -> Result<()> {
+        self.storage.remove(session)?;
+        self.aggregates.session_removed(session);
+        Ok(())
+    }
+
+    /// What couldn't be written and is held in memory, while writes are
+    /// held back (see [`crate::spool`]).
+    pub fn write_problem(&self) -> Option<String> {
+        self.spool.problem()
+    }
+
+    /// Tries writing what was held back now, rather than at the next retry.
+    pub fn retry_writes(&mut self) {
+        if !self.spool.is_holding() {
+            return;
+        }
+        match self.write_held() {
+            Ok(()) => {
+                self.spool.recovered();
+                self.status_text = "Writing data again".to_string();
+            }
+            Err(e) => self.spool.failed(e, &self.config.spool),
+        }
+    }
+
+    /// Reopens the detailed log and writes held detailed events after
+    /// whatever made it into it, then held sessions to storage.
+    fn write_held(&mut self) -> Result<()> {
+        // A failed writer may still hold part of an event; drop it first.
+        self.detailed_writer = None;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.data_dir.join(DETAILS_FILE))?;
+        let is_new = file.metadata()?.len() == 0;
+        let mut writer = WriterBuilder::new().has_headers(is_new).from_writer(file);
+        for event in self.spool.events() {
+            writer.serialize(event)?;
+        }
+        writer.flush()?;
+        self.spool.clear_events();
+        self.detailed_writer = Some(writer);
+
+        let mut sessions = self.spool.take_sessions().into_iter();
+        while let Some(session) = sessions.next() {
+            if let Err(e) = self.storage.append(&session) {
+                self.spool.hold_session(session);
+                sessions.for_each(|session| self.spool.hold_session(session));
+                return Err(e);
+            }
+            self.session_stored(&session);
+        }
+        Ok(())
+    }
+
+    /// Where data is written: relative to the working directory unless
+    /// `relocate` moved it.
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    /// Writes data under `dir` from now on, creating it if needed, and
+    /// writes what was held back there. Sessions go to the storage's file
+    /// of the same name under `dir` (see [`Storage::relocate`]), and the
+    /// detailed log and session summary follow; the working directory, and
+    /// so settings, stay where they are. `dir` is kept as
+    /// `Config::data_dir`, so later launches write there too.
+    pub fn relocate(&mut self, dir: &Path) -> Result<()> {
+        fs::create_dir_all(dir)?;
+        self.storage.relocate(dir)?;
+        self.data_dir = dir.to_path_buf();
+        self.config.data_dir = dir.display().to_string();
+        self.config.save()?;
+        self.write_held()?;
+        self.spool.recovered();
+        self.status_text = format!("Writing data to {} now", dir.display());
+        Ok(())
+    }
+
+    /// How long to wait before the next `update`: the active rate while input
+    /// is changing, the idle rate once it has been still for a while, and
+    /// no faster than the throttle allows in low-overhead mode.
+    pub fn poll_interval(&self) -> Duration {
+        let config = &self.config.diagnostics;
+        let hz = if self.last_input.is_some_and(|at| at.elapsed() < IDLE_AFTER) {
+            config.active_poll_hz
+        } else {
+            config.idle_poll_hz
+        };
+        let interval = Duration::from_secs(1) / hz.max(1);
+        if self.diagnostics.snapshot().throttled {
+            interval.max(Duration::from_millis(config.throttled_poll_ms))
+        } else {
+            interval
+        }
+    }
+
+    pub fn update(&mut self) {
+        for command in self.commands.pending() {
+            command(self);
+        }
+        self.bus.flush();
+        if self.spool.retry_due() {
+            self.retry_writes();
+        }
+        self.receive_ide_events();
+        if !self.is_running() {
+            self.check_trial_hotkey();
+            self.buffer_input();
+        }
+        if !self.is_monitoring.load(Ordering::SeqCst) {
+            return;
+        }
+        if self.trial_ends.is_some_and(|ends| Instant::now() >= ends)
+            || self.bug_report.as_ref().is_some_and(BugReport::is_over)
+        {
+            self.stop_monitoring();
+            return;
+        }
+        if self.paused.is_some() {
+            return;
+        }
+
+        if let Some(gap) = self.heartbeat.silent_for() {
+            if gap >= watchdog::STALL_AFTER {
+                self.record_capture_gap(gap);
+            }
+        }
+        self.heartbeat.beat();
+
+        self.diagnostics.tick(&self.config.diagnostics);
+        if self.diagnostics.snapshot().throttled {
+            let interval = Duration::from_millis(self.config.diagnostics.throttled_poll_ms);
+            if self.last_poll.is_some_and(|last| last.elapsed() < interval) {
+                return;
+            }
+        }
+        self.last_poll = Some(Instant::now());
+
+        self.check_clock();
+        if !self.recover_capture() {
+            return;
+        }
+
+        let reads = crash::catch_expected(AssertUnwindSafe(|| {
+            (self.input.get_keys(), self.input.get_mouse())
+        }));
+        let (keys, mut mouse): (Vec<Keycode>, MouseState) = match reads {
+            Ok(reads) => reads,
+            Err(payload) => {
+                self.capture_failed(input::panic_message(&*payload));
+                return;
+            }
+        };
+
+        mouse.coords = privacy::bin_coords(mouse.coords, self.config.privacy.mouse_grid_px);
+
+        self.check_layout();
+        self.check_focus();
+        self.check_clipboard();
+        self.check_app_alerts();
+        self.check_bug_report_screenshot();
+
+        let active = keys != self.last_keys
+            || mouse.coords != self.last_mouse_pos
+            || mouse.button_pressed != self.last_buttons;
+        if active {
+            self.last_input = Some(Instant::now());
+        }
+        for event in self
+            .breaks
+            .tick(Instant::now(), active, &self.config.wellness)
+        {
+            self.record_wellness(event);
+        }
+
+        let capture = self.capture_config().clone();
+        let pointer_changed =
+            mouse.coords != self.last_mouse_pos || mouse.button_pressed != self.last_buttons;
+        if capture.cursor_shapes && pointer_changed && !(capture.ignore_self && self.self_hovered) {
+            self.check_cursor();
+        }
+
+        // Monitor keyboard
+        if keys != self.last_keys {
+            let marked = self.check_trial_mark_hotkey(&keys) || self.check_sync_hotkey(&keys);
+            if capture.keyboard && !marked && !(capture.ignore_self && self.self_focused) {
+                self.record_keyboard(&keys, mouse.coords);
+            }
+            self.last_keys = keys;
+        }
+
+        // Monitor mouse
+        let current_pos = mouse.coords;
+        if current_pos != self.last_mouse_pos {
+            if capture.mouse_moves
+                && !(capture.ignore_self && self.self_hovered)
+                && self.sample_mouse_move()
+            {
+                self.record_mouse_move(current_pos);
+            }
+            self.last_mouse_pos = current_pos;
+        }
+
+        // Monitor mouse buttons
+        if mouse.button_pressed != self.last_buttons {
+            let pointer_ignored = capture.ignore_self && self.self_hovered;
+            for button in 0..mouse.button_pressed.len().max(self.last_buttons.len()) {
+                let down = mouse.button_pressed.get(button) == Some(&true);
+                let was_down = self.last_buttons.get(button) == Some(&true);
+                let record = capture.records_button(button) && !pointer_ignored;
+                if down != was_down && (record || !down) {
+                    self.record_mouse_button(button, down, current_pos);
+                }
+            }
+            self.last_buttons = mouse.button_pressed;
+        }
+    }
+
+    /// Text for the app usage banner while focus is still within an alert's
+    /// apps after it fired.
+    pub fn app_alert(&self) -> Option<String> {
+        let (name, focused_for) = self.app_alerts.due(Instant::now())?;
+        Some(format!(
+            "You've been in {} for {} minutes.",
+            name,
+            focused_for.as_secs() / 60
+        ))
+    }
+
+    /// Text for the break reminder banner while a reminder is pending.
+    pub fn break_reminder(&self) -> Option<String> {
+        let minutes = self.breaks.active_for(Instant::now()).as_secs() / 60;
+        match self.breaks.due()? {
+            BreakKind::Break => Some(format!(
+                "You've been active for {} minutes. Time for a {}-minute break.",
+                minutes, self.config.wellness.break_minutes
+            )),
+            BreakKind::MicroBreak => Some(format!(
+                "Micro-break: look away from the screen for {} seconds.",
+                self.config.wellness.micro_break_secs
+            )),
+        }
+    }
+
+    fn record_wellness(&mut self, event: WellnessEvent) {
+        let timestamp = format_timestamp(Local::now());
+        let (action, details) = match event {
+            WellnessEvent::Reminder { kind, active_for } => (
+                Action::BreakReminder {
+                    timestamp: timestamp.clone(),
+                    kind: kind.as_str().to_string(),
+                    active_secs: active_for.as_secs() as i64,
+                },
+                format!("{} reminder after {}s", kind.as_str(), active_for.as_secs()),
+            ),
+            WellnessEvent::BreakTaken {
+                kind,
+                idle_for,
+                reminded,
+            } => (
+                Action::BreakTaken {
+                    timestamp: timestamp.clone(),
+                    kind: kind.as_str().to_string(),
+                    idle_secs: idle_for.as_secs() as i64,
+                    reminded,
+                },
+                format!(
+                    "{} of {}s{}",
+                    kind.as_str(),
+                    idle_for.as_secs(),
+                    if reminded { " after reminder" } else { "" }
+                ),
+            ),
+        };
+        self.record_action(action);
+
+        let detailed_event = DetailedEvent {
+            timestamp,
+            task_name: self.task_name.clone(),
+            event_type: "wellness".to_string(),
+            details,
+            mouse_x: self.last_mouse_pos.0,
+            mouse_y: self.last_mouse_pos.1,
+        };
+        if let Err(e) = self.write_detailed(&detailed_event) {
+            self.status_text = format!("Error: {}", e);
+        }
+    }
+
+    /// Describes an ongoing capture interruption for the GUI banner.
+    pub fn capture_problem(&self) -> Option<String> {
+        self.capture_failure.as_ref().map(|failure| {
+            let retry_in = failure
+                .next_attempt
+                .saturating_duration_since(Instant::now())
+                .as_secs();
+            format!(
+                "Input capture interrupted ({}). Retrying in {}s...",
+                failure.reason, retry_in
+            )
+        })
+    }
+
+    /// Records a `CaptureGap` for capture that didn't tick for `gap`.
+    fn record_capture_gap(&mut self, gap: Duration) {
+        let start = Local::now() - chrono::Duration::from_std(gap).unwrap_or_default();
+        let timestamp = format_timestamp(start);
+        let gap_ms = gap.as_millis() as i64;
+        self.current_session.actions.push(Action::CaptureGap {
+            timestamp: timestamp.clone(),
+            gap_ms,
+        });
+
+        let detailed_event = DetailedEvent {
+            timestamp,
+            task_name: self.task_name.clone(),
+            event_type: "capture_gap".to_string(),
+            details: format!("No capture for {}ms", gap_ms),
+            mouse_x: self.last_mouse_pos.0,
+            mouse_y: self.last_mouse_pos.1,
+        };
+        if let Err(e) = self.write_detailed(&detailed_event) {
+            self.status_text = format!("Error: {}", e);
+        }
+    }
+
+    /// Records a `CaptureInterrupted` action and schedules the first
+    /// reconnect attempt.
+    fn capture_failed(&mut self, reason: String) {
+        let timestamp = format_timestamp(Local::now());
+        self.current_session
+            .actions
+            .push(Action::CaptureInterrupted {
+                timestamp: timestamp.clone(),
+                reason: reason.clone(),
+                resumed_at: None,
+            });
+
+        let detailed_event = DetailedEvent {
+            timestamp,
+            task_name: self.task_name.clone(),
+            event_type: "capture_interrupted".to_string(),
+            details: reason.clone(),
+            mouse_x: self.last_mouse_pos.0,
+            mouse_y: self.last_mouse_pos.1,
+        };
+        if let Err(e) = self.write_detailed(&detailed_event) {
+            self.status_text = format!("Error: {}", e);
+        }
+
+        self.emit(MonitorEvent::CaptureFailed {
+            reason: reason.clone(),
+        });
+        self.capture_failure = Some(CaptureFailure {
+            reason,
+            next_attempt: Instant::now() + RECONNECT_BACKOFF_MIN,
+            backoff: RECONNECT_BACKOFF_MIN,
+        });
+    }
+
+    /// Retries a failed capture backend with exponential backoff. Returns
+    /// whether input can be read this tick.
+    fn recover_capture(&mut self) -> bool {
+        let Some(failure) = &mut self.capture_failure else {
+            return true;
+        };
+        if Instant::now() < failure.next_attempt {
+            return false;
+        }
+
+        if let Err(e) = self.input.reconnect() {
+            failure.reason = e.to_string();
+            failure.backoff = (failure.backoff * 2).min(RECONNECT_BACKOFF_MAX);
+            failure.next_attempt = Instant::now() + failure.backoff;
+            return false;
+        }
+        self.capture_failure = None;
+
+        let now = format_timestamp(Local::now());
+        let interruption = self
+            .current_session
+            .actions
+            .iter_mut()
+            .rev()
+            .find_map(|action| match action {
+                Action::CaptureInterrupted { resumed_at, .. } if resumed_at.is_none() => {
+                    Some(resumed_at)
+                }
+                _ => None,
+            });
+        if let Some(resumed_at) = interruption {
+            *resumed_at = Some(now.clone());
+        }
+
+        let detailed_event = DetailedEvent {
+            timestamp: now,
+            task_name: self.task_name.clone(),
+            event_type: "capture_resumed".to_string(),
+            details: String::new(),
+            mouse_x: self.last_mouse_pos.0,
+            mouse_y: self.last_mouse_pos.1,
+        };
+        if let Err(e) = self.write_detailed(&detailed_event) {
+            self.status_text = format!("Error: {}", e);
+        }
+        self.emit(MonitorEvent::CaptureResumed);
+        true
+    }
+
+    /// Compares wall-clock progress against monotonic time since the last
+    /// tick and records a `ClockChange` if the system clock jumped or the UTC
+    /// offset changed.
+    fn check_clock(&mut self) {
+        let instant = Instant::now();
+        let now = Local::now();
+
+        if let Some((last_instant, last_now)) = self.clock_anchor {
+            let monotonic_ms = instant.duration_since(last_instant).as_millis() as i64;
+            let jump_ms = (now - last_now).num_milliseconds() - monotonic_ms;
+            let jumped = jump_ms.abs() >= CLOCK_JUMP_THRESHOLD_MS;
+            let offset_changed =
+                now.offset().local_minus_utc() != last_now.offset().local_minus_utc();
+
+            if jumped || offset_changed {
+                self.record_clock_change(now, if jumped { jump_ms } else { 0 });
+            }
+        }
+        self.clock_anchor = Some((instant, now));
+    }
+
+    fn record_clock_change(&mut self, now: DateTime<Local>, jump_ms: i64) {
+        let timestamp = format_timestamp(now);
+        let utc_offset_secs = now.offset().local_minus_utc();
+        self.record_action(Action::ClockChange {
+            timestamp: timestamp.clone(),
+            jump_ms,
+            utc_offset_secs,
+        });
+
+        let detailed_event = DetailedEvent {
+            timestamp,
+            task_name: self.task_name.clone(),
+            event_type: "clock_change".to_string(),
+            details: format!(
+                "Clock jumped by {}ms (UTC offset {}s)",
+                jump_ms, utc_offset_secs
+            ),
+            mouse_x: self.last_mouse_pos.0,
+            mouse_y: self.last_mouse_pos.1,
+        };
+        if let Err(e) = self.write_detailed(&detailed_event) {
+            self.status_text = format!("Error: {}", e);
+        }
+    }
+
+    /// Records a `LayoutChange` when the active keyboard layout differs from
+    /// the last one seen, including the first reading of a session.
+    fn check_layout(&mut self) {
+        if self
+            .last_layout_check
+            .is_some_and(|last| last.elapsed() < LAYOUT_POLL_INTERVAL)
+        {
+            return;
+        }
+        self.last_layout_check = Some(Instant::now());
+
+        let Some(layout) = self.input.keyboard_layout() else {
+            return;
+        };
+        if self.keyboard_layout.as_ref() == Some(&layout) {
+            return;
+        }
+
+        let timestamp = format_timestamp(Local::now());
+        self.record_action(Action::LayoutChange {
+            timestamp: timestamp.clone(),
+            layout: layout.clone(),
+        });
+
+        let detailed_event = DetailedEvent {
+            timestamp,
+            task_name: self.task_name.clone(),
+            event_type: "layout_change".to_string(),
+            details: layout.clone(),
+            mouse_x: self.last_mouse_pos.0,
+            mouse_y: self.last_mouse_pos.1,
+        };
+        if let Err(e) = self.write_detailed(&detailed_event) {
+            self.status_text = format!("Error: {}", e);
+        }
+        self.keyboard_layout = Some(layout);
+    }
+
+    /// Fires app usage alerts whose apps have had focus long enough, logging
+    /// each as an `app_alert` event.
+    fn check_app_alerts(&mut self) {
+        if self.config.app_alerts.is_empty()
+            || self
+                .last_app_check
+                .is_some_and(|last| last.elapsed() < FOCUS_POLL_INTERVAL)
+        {
+            return;
+        }
+        let now = Instant::now();
+        self.last_app_check = Some(now);
+
+        let app = self.input.focused_app();
+        for event in self
+            .app_alerts
+            .tick(now, app.as_deref(), &self.config.app_alerts)
+        {
+            let timestamp = format_timestamp(Local::now());
+            self.record_action(Action::Custom {
+                timestamp: timestamp.clone(),
+                kind: "app_alert".to_string(),
+                payload: serde_json::json!({
+                    "alert": event.alert,
+                    "app": event.app,
+                    "focused_secs": event.focused_for.as_secs(),
+                }),
+            });
+
+            let detailed_event = DetailedEvent {
+                timestamp,
+                task_name: self.task_name.clone(),
+                event_type: "app_alert".to_string(),
+                details: format!(
+                    "{} ({}) for {}s",
+                    event.alert,
+                    event.app,
+                    event.focused_for.as_secs()
+                ),
+                mouse_x: self.last_mouse_pos.0,
+                mouse_y: self.last_mouse_pos.1,
+            };
+            if let Err(e) = self.write_detailed(&detailed_event) {
+                self.status_text = format!("Error: {}", e);
+            }
+        }
+    }
+
+    /// Records a `CursorChange` when the cursor shape differs from the last
+    /// one recorded. Only read as the pointer moves or clicks, so it comes
+    /// just before the event it applies to.
+    fn check_cursor(&mut self) {
+        let Some(shape) = self.input.cursor_shape() else {
+            return;
+        };
+        if self.cursor_shape.as_ref() == Some(&shape) {
+            return;
+        }
+
+        let timestamp = format_timestamp(Local::now());
+        self.record_action(Action::CursorChange {
+            timestamp: timestamp.clone(),
+            shape: shape.clone(),
+        });
+
+        let detailed_event = DetailedEvent {
+            timestamp,
+            task_name: self.task_name.clone(),
+            event_type: "cursor_change".to_string(),
+            details: shape.clone(),
+            mouse_x: self.last_mouse_pos.0,
+            mouse_y: self.last_mouse_pos.1,
+        };
+        if let Err(e) = self.write_detailed(&detailed_event) {
+            self.status_text = format!("Error: {}", e);
+        }
+        self.cursor_shape = Some(shape);
+    }
+
+    /// Records a `FocusChange` when another window gets focus, or when the
+    /// focused window moves or is resized while geometry is captured.
+    fn check_focus(&mut self) {
+        // Bug reports always follow focus, with window positions.
+        let capture = self.capture_config();
+        let bug_report = self.bug_report.is_some();
+        if !capture.focus_changes && !bug_report {
+            return;
+        }
+        let with_bounds = capture.window_geometry || bug_report;
+        let with_title = capture.window_titles;
+        if self
+            .last_focus_check
+            .is_some_and(|last| last.elapsed() < FOCUS_POLL_INTERVAL)
+        {
+            return;
+        }
+        self.last_focus_check = Some(Instant::now());
+
+        let Some(FocusedWindow { id, bounds }) = self.input.focused_window() else {
+            return;
+        };
+        // Redacted before anything is kept, so a title is never held whole.
+        let title = with_title
+            .then(|| self.input.focused_title())
+            .flatten()
+            .map(|title| self.redactor.redact(&title));
+        let app = self.input.focused_app();
+        let focused = (id, with_bounds.then_some(bounds), title, app);
+        if self.focused_window.as_ref() == Some(&focused) {
+            return;
+        }
+
+        let timestamp = format_timestamp(Local::now());
+        self.record_action(Action::FocusChange {
+            timestamp: timestamp.clone(),
+            window: id,
+            bounds: focused.1,
+            title: focused.2.clone(),
+            app: focused.3.clone(),
+        });
+
+        let mut details = match focused.1 {
+            Some(b) => format!("{} at ({},{}) {}x{}", id, b.x, b.y, b.width, b.height),
+            None => id.to_string(),
+        };
+        if let Some(title) = &focused.2 {
+            details = format!("{} {:?}", details, title);
+        }
+        if let Some(app) = &focused.3 {
+            details = format!("{} [{}]", details, app);
+        }
+        let detailed_event = DetailedEvent {
+            timestamp,
+            task_name: self.task_name.clone(),
+            event_type: "focus_change".to_string(),
+            details,
+            mouse_x: self.last_mouse_pos.0,
+            mouse_y: self.last_mouse_pos.1,
+        };
+        if let Err(e) = self.write_detailed(&detailed_event) {
+            self.status_text = format!("Error: {}", e);
+        }
+        self.focused_window = Some(focused);
+    }
+
+    /// Records a `clipboard` event for each copy or cut since the last
+    /// check. Changes are read even when they aren't recorded, so turning
+    /// capture on doesn't record ones from before.
+    fn check_clipboard(&mut self) {
+        let changes = self.input.clipboard_changes();
+        let capture = self.capture_config();
+        if !capture.clipboard || (capture.ignore_self && self.self_focused) {
+            return;
+        }
+        for _ in 0..changes {
+            if let Err(e) = self.inject_event(clipboard::EVENT_KIND, serde_json::json!({})) {
+                self.status_text = format!("Error: {}", e);
+            }
+        }
+    }
+
+    /// Keeps every Nth mouse move according to the session's sample rate.
+    fn sample_mouse_move(&mut self) -> bool {
+        let keep = self
+            .mouse_moves_seen
+            .is_multiple_of(self.current_session.mouse_sample_rate as u64);
+        self.mouse_moves_seen += 1;
+        keep
+    }
+
+    fn record_keyboard(&mut self, keys: &[Keycode], mouse_pos: (i32, i32)) {
+        let timestamp = format_timestamp(Local::now());
+        let keys_str: Vec<String> = keys.iter().map(|k| format!("{:?}", k)).collect();
+
+        let action = Action::KeyPress {
+            timestamp: timestamp.clone(),
+            keys: keys_str.clone(),
+        };
+        self.record_action(action);
+
+        let detailed_event = DetailedEvent {
+            timestamp,
+            task_name: self.task_name.clone(),
+            event_type: "keyboard".to_string(),
+            details: format!("{:?}", keys_str),
+            mouse_x: mouse_pos.0,
+            mouse_y: mouse_pos.1,
+        };
+
+        if let Err(e) = self.write_detailed(&detailed_event) {
+            self.status_text = format!("Error: {}", e);
+        } else {
+            self.events_recorded.store(true, Ordering::SeqCst);
+            self.status_text = format!("Task: {} - Keyboard: {:?}", self.task_name, keys_str);
+        }
+        self.record_shortcut(&keys_str);
+    }
+
+    /// Logs a `Shortcut` event when the held keys form a mapped chord.
+    fn record_shortcut(&mut self, keys: &[String]) {
+        if !self.config.shortcuts.enabled {
+            return;
+        }
+        let Some(chord) = shortcuts::chord_name(keys) else {
+            return;
+        };
+        let app = self.input.focused_app();
+        let Some(name) = self.config.shortcuts.resolve(app.as_deref(), &chord) else {
+            return;
+        };
+        let name = name.to_string();
+
+        let timestamp = format_timestamp(Local::now());
+        self.record_action(Action::Shortcut {
+            timestamp: timestamp.clone(),
+            chord: chord.clone(),
+            name: name.clone(),
+        });
+
+        let detailed_event = DetailedEvent {
+            timestamp,
+            task_name: self.task_name.clone(),
+            event_type: "shortcut".to_string(),
+            details: format!("{} ({})", name, chord),
+            mouse_x: self.last_mouse_pos.0,
+            mouse_y: self.last_mouse_pos.1,
+        };
+        if let Err(e) = self.write_detailed(&detailed_event) {
+            self.status_text = format!("Error: {}", e);
+        }
+    }
+
+    fn record_mouse_move(&mut self, current_pos: (i32, i32)) {
+        let now = Local::now();
+        self.current_session.record_mouse_move(now, current_pos);
+        if !self.bus.is_empty() {
+            self.emit(MonitorEvent::Recorded(Action::MouseMove {
+                timestamp: format_timestamp(now),
+                coords: current_pos,
+            }));
+        }
+
+        let detailed_event = DetailedEvent {
+            timestamp: format_timestamp(now),
+            task_name: self.task_name.clone(),
+            event_type: "mouse_move".to_string(),
+            details: format!("Moved to {:?}", current_pos),
+            mouse_x: current_pos.0,
+            mouse_y: current_pos.1,
+        };
+
+        if let Err(e) = self.write_detailed(&detailed_event) {
+            self.status_text = format!("Error: {}", e);
+        } else {
+            self.events_recorded.store(true, Ordering::SeqCst);
+            self.status_text = format!(
+                "Task: {} - Mouse: ({}, {})",
+                self.task_name, current_pos.0, current_pos.1
+            );
+        }
+    }
+
+    /// Records a press of `button`, with the element under it when click
+    /// targets are captured, or its release if the press was recorded, with
+    /// how long it was held.
+    fn record_mouse_button(&mut self, button: usize, pressed: bool, coords: (i32, i32)) {
+        let timestamp = format_timestamp(Local::now());
+        let name = button_name(button);
+        let mut clicked = None;
+        let (action, mut details) = if pressed {
+            self.buttons_down.insert(button, (Instant::now(), coords));
+            let action = Action::MouseDown {
+                timestamp: timestamp.clone(),
+                button: name.clone(),
+                coords,
+            };
+            (action, format!("{} down", name))
+        } else {
+            let Some((down_at, down_coords)) = self.buttons_down.remove(&button) else {
+                return;
+            };
+            let hold_ms = down_at.elapsed().as_millis() as i64;
+            let action = Action::MouseUp {
+                timestamp: timestamp.clone(),
+                button: name.clone(),
+                coords,
+                hold_ms,
+            };
+            clicked = click(&timestamp, button, down_coords, coords, hold_ms);
+            (action, format!("{} up after {} ms", name, hold_ms))
+        };
+        self.record_action(action);
+        if let Some(click) = clicked {
+            self.record_action(click);
+        }
+        let target = pressed && self.capture_config().click_targets;
+        if let Some(element) = target.then(|| self.input.element_at(coords)).flatten() {
+            // Window titles can name people or documents.
+            let element_name = self.redactor.redact(&element.name);
+            details = format!("{} on {} {:?}", details, element.role, element_name);
+            self.record_action(Action::Custom {
+                timestamp: timestamp.clone(),
+                kind: window::TARGET_KIND.to_string(),
+                payload: serde_json::json!({
+                    "button": name,
+                    "role": element.role,
+                    "name": element_name,
+                    "app": element.app,
+                }),
+            });
+        }
+
+        let detailed_event = DetailedEvent {
+            timestamp,
+            task_name: self.task_name.clone(),
+            event_type: if pressed { "mouse_down" } else { "mouse_up" }.to_string(),
+            details,
+            mouse_x: coords.0,
+            mouse_y: coords.1,
+        };
+        if let Err(e) = self.write_detailed(&detailed_event) {
+            self.status_text = format!("Error: {}", e);
+        } else {
+            self.events_recorded.store(true, Ordering::SeqCst);
+        }
+    }
+
+    /// Appends `event` to the detailed log, timing the write for the
+    /// diagnostics panel. When the disk refuses it, the event is held (see
+    /// [`crate::spool`]) rather than failing, so only events that can't be
+    /// serialized are errors.
+    fn write_detailed(&mut self, event: &DetailedEvent) -> csv::Result<()> {
+        let started = Instant::now();
+        let event = if self.redactor.is_empty() {
+            event.clone()
+        } else {
+            DetailedEvent {
+                task_name: self.redactor.redact(&event.task_name),
+                details: self.redactor.redact(&event.details),
+                ..event.clone()
+            }
+        };
+        let holding = self.spool.is_holding();
+        let Some(writer) = self.detailed_writer.as_mut().filter(|_| !holding) else {
+            self.spool.hold_event(event, &self.config.spool);
+            return Ok(());
+        };
+        let result = writer
+            .serialize(&event)
+            .and_then(|()| writer.flush().map_err(csv::Error::from));
+        self.diagnostics.record_write(started.elapsed());
+        match result {
+            Err(e) if matches!(e.kind(), csv::ErrorKind::Io(_)) => {
+                self.detailed_writer = None;
+                self.spool.failed(&e, &self.config.spool);
+                self.spool.hold_event(event, &self.config.spool);
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    pub fn diagnostics(&self) -> DiagnosticsSnapshot {
+        self.diagnostics.snapshot()
+    }
+}
+
+/// The click made by releasing `button` at `up` after `hold_ms`, if it
+/// was quick and stayed near where it went `down`. Scroll wheel steps
+/// aren't clicks.
+fn click(
+    timestamp: &str,
+    button: usize,
+    down: (i32, i32),
+    up: (i32, i32),
+    hold_ms: i64,
+) -> Option<Action> {
+    let travel = (up.0 - down.0).abs().max((up.1 - down.1).abs());
+    let quick = hold_ms <= CLICK_MAX_HOLD_MS && travel <= CLICK_MAX_TRAVEL_PX;
+    (quick && !is_scroll(button)).then(|| Action::MouseClick {
+        timestamp: timestamp.to_string(),
+        button: button_name(button),
+        coords: up,
+    })
+}
+
+/// Whether button `index` is the scroll wheel, as on Linux.
+pub(crate) fn is_scroll(index: usize) -> bool {
+    button_name(index).starts_with("scroll_")
+}
+
+/// Name of a `MouseState::button_pressed` index. Indices are 1-based and
+/// their order differs on X11, where 2 is the middle button and 4 and 5
+/// are the scroll wheel.
+fn button_name(index: usize) -> String {
+    let names: [&str; 6] = if cfg!(target_os = "linux") {
+        ["", "left", "middle", "right", "scroll_up", "scroll_down"]
+    } else {
+        ["", "left", "right", "middle", "back", "forward"]
+    };
+    match names.get(index) {
+        Some(name) if !name.is_empty() => name.to_string(),
+        _ => format!("button{}", index),
+    }
+}
+
+/// Whether exactly the chord `hotkey` (e.g. `Ctrl+Alt+T`) is held. An empty
+/// `hotkey` is never held.
+fn chord_held(keys: &[Keycode], hotkey: &str) -> bool {
+    let hotkey = hotkey.trim();
+    if hotkey.is_empty() {
+        return false;
+    }
+    let names: Vec<String> = keys.iter().map(|k| format!("{:?}", k)).collect();
+    shortcuts::chord_name(&names).is_some_and(|chord| chord.eq_ignore_ascii_case(hotkey))
+}
+
+/// Publishes the monitor's events over LSL (see [`crate::lsl`]).
+#[cfg(feature = "lsl")]
+fn spawn_lsl(config: &LslConfig, bus: &mut EventBus<MonitorEvent>) {
+    let events = bus.subscribe(
+        "lsl",
+        DEFAULT_CAPACITY,
+        SinkPolicy::Buffer(DEFAULT_CAPACITY),
+    );
+    if let Err(e) = crate::lsl::spawn(config, events) {
+        eprintln!("LSL output unavailable: {}", e);
+    }
+}
+
+#[cfg(not(feature = "lsl"))]
+fn spawn_lsl(_config: &LslConfig, _bus: &mut EventBus<MonitorEvent>) {
+    eprintln!("LSL output needs a build with the `lsl` feature");
 }
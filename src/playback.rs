@@ -0,0 +1,153 @@
+//! Playback of a recorded session in the viewer: the mouse path and key
+//! presses animated over the session's timeline at an adjustable speed.
+//!
+//! Playback only draws what happened; it never moves the pointer or
+//! presses keys.
+
+use crate::analysis;
+use crate::types::{Action, Session};
+
+/// Playback speeds offered in the viewer, as multiples of real time.
+pub const SPEEDS: [f32; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+/// How long key presses and clicks stay listed after they happen, in
+/// session time.
+pub const RECENT_MS: i64 = 2000;
+
+#[derive(Debug, Clone)]
+pub struct Playback {
+    /// Mouse positions by milliseconds since the session started.
+    path: Vec<(i64, (i32, i32))>,
+    /// Key presses, clicks and shortcuts, described, by milliseconds since
+    /// the session started.
+    events: Vec<(i64, String)>,
+    duration_ms: i64,
+    position_ms: f64,
+    pub speed: f32,
+    pub playing: bool,
+}
+
+impl Playback {
+    /// Paused at the start of `session`, at normal speed.
+    pub fn new(session: &Session) -> Self {
+        let start = session.started_at();
+        let path: Vec<(i64, (i32, i32))> = session
+            .mouse_path
+            .iter()
+            .map(|point| {
+                let offset = start.map_or(0, |start| {
+                    (point.timestamp - start).num_milliseconds().max(0)
+                });
+                (offset, point.coords)
+            })
+            .collect();
+        let mut events: Vec<(i64, String)> = session
+            .actions
+            .iter()
+            .filter_map(|action| {
+                let description = match action {
+                    Action::KeyPress { keys, .. } if !keys.is_empty() => keys.join("+"),
+                    Action::MouseDown { button, .. } => format!("Click {}", button),
+                    Action::Shortcut { name, .. } => format!("Shortcut {}", name),
+                    _ => return None,
+                };
+                Some((
+                    analysis::offset_ms(session, action.timestamp()),
+                    description,
+                ))
+            })
+            .collect();
+        // Clock changes can put timestamps out of order.
+        events.sort_by_key(|(offset, _)| *offset);
+        let end = session
+            .end_time
+            .as_deref()
+            .map_or(0, |end| analysis::offset_ms(session, end));
+        let duration_ms = path
+            .iter()
+            .map(|(offset, _)| *offset)
+            .chain(events.iter().map(|(offset, _)| *offset))
+            .fold(end, i64::max);
+        Self {
+            path,
+            events,
+            duration_ms,
+            position_ms: 0.0,
+            speed: 1.0,
+            playing: false,
+        }
+    }
+
+    pub fn duration_ms(&self) -> i64 {
+        self.duration_ms
+    }
+
+    pub fn position_ms(&self) -> i64 {
+        self.position_ms as i64
+    }
+
+    pub fn seek(&mut self, ms: i64) {
+        self.position_ms = ms.clamp(0, self.duration_ms) as f64;
+    }
+
+    /// Plays or pauses. Playing from the end starts over.
+    pub fn toggle(&mut self) {
+        if !self.playing && self.position_ms() >= self.duration_ms {
+            self.position_ms = 0.0;
+        }
+        self.playing = !self.playing;
+    }
+
+    /// Moves on by `secs` of real time at the current speed, if playing,
+    /// and pauses at the end.
+    pub fn advance(&mut self, secs: f32) {
+        if !self.playing {
+            return;
+        }
+        self.position_ms += f64::from(secs * self.speed) * 1000.0;
+        if self.position_ms >= self.duration_ms as f64 {
+            self.position_ms = self.duration_ms as f64;
+            self.playing = false;
+        }
+    }
+
+    /// The mouse path up to the current position.
+    pub fn trail(&self) -> &[(i64, (i32, i32))] {
+        let position = self.position_ms();
+        let end = self.path.partition_point(|(offset, _)| *offset <= position);
+        &self.path[..end]
+    }
+
+    /// Where the pointer was at the current position.
+    pub fn cursor(&self) -> Option<(i32, i32)> {
+        self.trail().last().map(|(_, coords)| *coords)
+    }
+
+    /// Top-left and bottom-right corners of the whole mouse path, so it
+    /// can be scaled to fit once rather than as it grows.
+    pub fn bounds(&self) -> Option<((i32, i32), (i32, i32))> {
+        let (_, first) = *self.path.first()?;
+        Some(
+            self.path
+                .iter()
+                .fold((first, first), |(min, max), (_, (x, y))| {
+                    (
+                        (min.0.min(*x), min.1.min(*y)),
+                        (max.0.max(*x), max.1.max(*y)),
+                    )
+                }),
+        )
+    }
+
+    /// Key presses, clicks and shortcuts in the last [`RECENT_MS`] before
+    /// the current position, oldest first.
+    pub fn recent(&self) -> &[(i64, String)] {
+        let position = self.position_ms();
+        let from = self
+            .events
+            .partition_point(|(offset, _)| *offset <= position - RECENT_MS);
+        let until = self
+            .events
+            .partition_point(|(offset, _)| *offset <= position);
+        &self.events[from..until.max(from)]
+    }
+}
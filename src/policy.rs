@@ -0,0 +1,198 @@
+//! Settings enforced by an administrator on managed machines, e.g. for a
+//! study rollout that must keep privacy mode on. The policy file lives in a
+//! system-wide location users can't write to; the app only reads it, applies
+//! it over `monitor_config.json` on every load and locks the matching
+//! controls in Settings. A policy that can't be read stops the app rather
+//! than running unenforced.
+
+use crate::config::{CaptureConfig, Config, DailyExportConfig};
+use crate::consent::ConsentConfig;
+use crate::privacy::{PrivacyConfig, RedactionRule};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const POLICY_FILE: &str = "policy.json";
+
+/// Where the policy file is looked for: `/etc/desk-monitor` on Linux,
+/// `/Library/Application Support/desk-monitor` on macOS and
+/// `%ProgramData%\desk-monitor` on Windows.
+pub fn system_path() -> PathBuf {
+    let dir = if cfg!(windows) {
+        std::env::var_os("ProgramData")
+            .map_or_else(|| PathBuf::from(r"C:\ProgramData"), PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        PathBuf::from("/Library/Application Support")
+    } else {
+        PathBuf::from("/etc")
+    };
+    dir.join("desk-monitor").join(POLICY_FILE)
+}
+
+/// Each setting that's set is enforced; the rest stay the user's choice.
+/// Unknown keys are rejected, so a misspelt setting isn't silently
+/// unenforced.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Policy {
+    /// Shown in Settings, e.g. who manages the machine and why.
+    pub notice: String,
+    /// Privacy mode for keystroke exports (see `PrivacyConfig::hide_keys`).
+    pub hide_keys: Option<bool>,
+    /// Mouse positions are snapped to a grid at least this coarse.
+    pub min_mouse_grid_px: Option<u32>,
+    /// Redaction rules applied on top of the user's own.
+    pub redact: Vec<RedactionRule>,
+    pub capture: CapturePolicy,
+    /// Turns the local query API on or off.
+    pub api_enabled: Option<bool>,
+    /// Turns the IDE plugin endpoint on or off.
+    pub ide_enabled: Option<bool>,
+    /// Whether bug-report captures take screenshots.
+    pub bug_report_screenshots: Option<bool>,
+    pub daily_export_enabled: Option<bool>,
+    /// Where daily exports are written, e.g. a share collected by the study.
+    pub daily_export_directory: Option<String>,
+    /// Turns contributions to a research dataset on or off.
+    pub contribute_enabled: Option<bool>,
+    /// Where contributions are sent, e.g. the study's own server.
+    pub contribute_endpoint: Option<String>,
+    /// Turns the update check at startup on or off.
+    pub update_checks: Option<bool>,
+    /// Repository update checks use, e.g. an internal fork.
+    pub update_repository: Option<String>,
+    /// The consent screen, its text and version.
+    pub consent: Option<ConsentConfig>,
+    /// Metadata fields asked for before recording.
+    pub metadata_fields: Option<Vec<String>>,
+    /// Whether recording waits until every metadata field is filled in.
+    pub metadata_required: Option<bool>,
+    /// Where the policy was read from. `None` when there's no policy.
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
+}
+
+/// Event types that are always or never recorded.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CapturePolicy {
+    pub keyboard: Option<bool>,
+    pub mouse_moves: Option<bool>,
+    pub mouse_buttons: Option<bool>,
+    pub scrolls: Option<bool>,
+    pub focus_changes: Option<bool>,
+    pub clipboard: Option<bool>,
+    pub window_geometry: Option<bool>,
+    pub window_titles: Option<bool>,
+    pub cursor_shapes: Option<bool>,
+    pub click_targets: Option<bool>,
+}
+
+impl Policy {
+    /// Reads the policy at `path`, or `None` if there's no file there.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(e).with_context(|| format!("reading the policy {}", path.display()))
+            }
+        };
+        let mut policy: Self = serde_json::from_str(&contents)
+            .with_context(|| format!("invalid policy {}", path.display()))?;
+        policy.source = Some(path.to_path_buf());
+        Ok(Some(policy))
+    }
+
+    /// Whether the machine is managed by a policy file.
+    pub fn is_managed(&self) -> bool {
+        self.source.is_some()
+    }
+
+    /// Overrides `config`'s settings with the enforced ones, including
+    /// those in templates and profiles so selecting one can't undo them,
+    /// and keeps the policy in `config.policy` for the GUI.
+    pub fn apply(&self, config: &mut Config) {
+        self.enforce_privacy(&mut config.privacy);
+        self.capture.enforce(&mut config.capture);
+        for template in &mut config.templates {
+            if let Some(capture) = &mut template.capture {
+                self.capture.enforce(capture);
+            }
+        }
+        for profile in &mut config.profiles {
+            self.enforce_privacy(&mut profile.privacy);
+            self.capture.enforce(&mut profile.capture);
+            self.enforce_daily_export(&mut profile.daily_export);
+        }
+        self.enforce_daily_export(&mut config.daily_export);
+        set(&mut config.api.enabled, self.api_enabled);
+        set(&mut config.ide.enabled, self.ide_enabled);
+        set(
+            &mut config.bug_report.screenshots,
+            self.bug_report_screenshots,
+        );
+        set(&mut config.contribute.enabled, self.contribute_enabled);
+        set(
+            &mut config.contribute.endpoint,
+            self.contribute_endpoint.clone(),
+        );
+        set(&mut config.updates.check_at_startup, self.update_checks);
+        set(
+            &mut config.updates.repository,
+            self.update_repository.clone(),
+        );
+        set(&mut config.consent, self.consent.clone());
+        set(&mut config.metadata.fields, self.metadata_fields.clone());
+        set(&mut config.metadata.required, self.metadata_required);
+        config.policy = self.clone();
+    }
+
+    fn enforce_privacy(&self, privacy: &mut PrivacyConfig) {
+        set(&mut privacy.hide_keys, self.hide_keys);
+        if let Some(min) = self.min_mouse_grid_px {
+            privacy.mouse_grid_px = privacy.mouse_grid_px.max(min);
+        }
+        for rule in &self.redact {
+            if !privacy.redact.iter().any(|r| same_rule(r, rule)) {
+                privacy.redact.push(rule.clone());
+            }
+        }
+    }
+
+    fn enforce_daily_export(&self, daily: &mut DailyExportConfig) {
+        set(&mut daily.enabled, self.daily_export_enabled);
+        set(&mut daily.directory, self.daily_export_directory.clone());
+    }
+
+    /// Whether `rule` comes from the policy, and so can't be removed.
+    pub fn is_enforced_rule(&self, rule: &RedactionRule) -> bool {
+        self.redact.iter().any(|r| same_rule(r, rule))
+    }
+}
+
+impl CapturePolicy {
+    fn enforce(&self, capture: &mut CaptureConfig) {
+        set(&mut capture.keyboard, self.keyboard);
+        set(&mut capture.mouse_moves, self.mouse_moves);
+        set(&mut capture.mouse_buttons, self.mouse_buttons);
+        set(&mut capture.scrolls, self.scrolls);
+        set(&mut capture.focus_changes, self.focus_changes);
+        set(&mut capture.clipboard, self.clipboard);
+        set(&mut capture.window_geometry, self.window_geometry);
+        set(&mut capture.window_titles, self.window_titles);
+        set(&mut capture.cursor_shapes, self.cursor_shapes);
+        set(&mut capture.click_targets, self.click_targets);
+    }
+}
+
+fn set<T>(setting: &mut T, enforced: Option<T>) {
+    if let Some(value) = enforced {
+        *setting = value;
+    }
+}
+
+fn same_rule(a: &RedactionRule, b: &RedactionRule) -> bool {
+    a.pattern == b.pattern && a.replacement == b.replacement
+}
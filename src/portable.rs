@@ -0,0 +1,49 @@
+//! Portable mode for running from a USB stick: config and data are kept next
+//! to the executable instead of in the directory the app was started from.
+//! Every data and config file is relative to the working directory, so
+//! entering portable mode is switching to the executable's directory.
+
+use anyhow::{Context, Result};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Command-line flag that turns portable mode on for one run.
+pub const FLAG: &str = "--portable";
+/// A file by this name next to the executable turns portable mode on for
+/// every run. Its contents are ignored.
+pub const SENTINEL: &str = "portable.txt";
+
+static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+fn exe_dir() -> Result<PathBuf> {
+    let exe = env::current_exe().context("locating the desk-monitor binary")?;
+    // Resolves symlinks, so a link on the desktop still finds the stick.
+    let exe = exe.canonicalize().unwrap_or(exe);
+    exe.parent()
+        .map(Path::to_path_buf)
+        .context("the desk-monitor binary has no parent directory")
+}
+
+/// Whether `args` ask for portable mode or the sentinel file is there.
+pub fn requested(args: &[String]) -> bool {
+    args.iter().any(|arg| arg == FLAG) || exe_dir().is_ok_and(|dir| dir.join(SENTINEL).exists())
+}
+
+/// Makes the executable's directory the working directory, and so where
+/// config and data are read and written, for the rest of the run.
+pub fn enter() -> Result<&'static Path> {
+    let dir = exe_dir()?;
+    env::set_current_dir(&dir)
+        .with_context(|| format!("switching to {} for portable mode", dir.display()))?;
+    Ok(DATA_DIR.get_or_init(|| dir))
+}
+
+/// The data directory, if running in portable mode.
+pub fn data_dir() -> Option<&'static Path> {
+    DATA_DIR.get().map(PathBuf::as_path)
+}
+
+pub fn is_active() -> bool {
+    DATA_DIR.get().is_some()
+}
@@ -0,0 +1,261 @@
+//! Redaction of free text before it's written to disk. Window titles often
+//! name documents or people, so the rules apply to each title as it's
+//! captured, before a `FocusChange` or click target holds it, and again to
+//! titles, task names (including task switches) and session notes when a
+//! session is saved. Detailed events are redacted as they're written.
+
+use crate::analysis;
+use crate::config::Config;
+use crate::storage::{CsvStorage, FolderStorage, Storage, DETAILS_FILE};
+use crate::types::{format_timestamp, Action, MousePath, Session};
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use csv::{ReaderBuilder, WriterBuilder};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path, path::PathBuf};
+
+pub const REDACTED: &str = "[REDACTED]";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrivacyConfig {
+    pub redact: Vec<RedactionRule>,
+    /// Privacy mode for keystroke exports: timings only, without which keys
+    /// were pressed, and key sequences by category.
+    pub hide_keys: bool,
+    /// Snap mouse coordinates to the centre of a grid of this many pixels
+    /// as they're captured. 0 records exact positions.
+    pub mouse_grid_px: u32,
+}
+
+/// `coords` snapped to the centre of its `grid`-pixel cell, or unchanged
+/// when `grid` is 0 or 1.
+pub fn bin_coords(coords: (i32, i32), grid: u32) -> (i32, i32) {
+    if grid <= 1 {
+        return coords;
+    }
+    let grid = grid.min(i32::MAX as u32) as i32;
+    let bin = |v: i32| {
+        v.div_euclid(grid)
+            .saturating_mul(grid)
+            .saturating_add(grid / 2)
+    };
+    (bin(coords.0), bin(coords.1))
+}
+
+/// Replaces every match of `pattern` (a regular expression) with
+/// `replacement`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RedactionRule {
+    pub pattern: String,
+    pub replacement: String,
+}
+
+impl Default for RedactionRule {
+    fn default() -> Self {
+        Self {
+            pattern: String::new(),
+            replacement: REDACTED.to_string(),
+        }
+    }
+}
+
+/// Compiled redaction rules. Invalid patterns are reported and skipped.
+#[derive(Debug, Default)]
+pub struct Redactor {
+    rules: Vec<(Regex, String)>,
+}
+
+impl Redactor {
+    pub fn new(config: &PrivacyConfig) -> Self {
+        let mut rules = Vec::new();
+        for rule in config.redact.iter().filter(|r| !r.pattern.is_empty()) {
+            match Regex::new(&rule.pattern) {
+                Ok(regex) => rules.push((regex, rule.replacement.clone())),
+                Err(e) => eprintln!("Skipping redaction pattern {:?}: {}", rule.pattern, e),
+            }
+        }
+        Self { rules }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    pub fn redact(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for (regex, replacement) in &self.rules {
+            // `NoExpand` so a literal `$` in the replacement isn't treated
+            // as a capture group reference.
+            text = regex
+                .replace_all(&text, regex::NoExpand(replacement))
+                .into_owned();
+        }
+        text
+    }
+
+    /// Redacts the free-text fields of `session` in place.
+    pub fn redact_session(&self, session: &mut Session) {
+        if self.is_empty() {
+            return;
+        }
+        session.task_name = self.redact(&session.task_name);
+        if let Some(notes) = &session.notes {
+            session.notes = Some(self.redact(notes));
+        }
+        for action in &mut session.actions {
+            match action {
+                Action::TaskSwitch { from, to, .. } => {
+                    *from = self.redact(from);
+                    *to = self.redact(to);
+                }
+                Action::FocusChange {
+                    title: Some(title), ..
+                } => *title = self.redact(title),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Deletes every event of `session` recorded from `from` to `until`
+/// (inclusive) and marks the gap with a `Redacted` action. Returns how many
+/// events were deleted.
+pub fn delete_range(session: &mut Session, from: DateTime<Local>, until: DateTime<Local>) -> usize {
+    let ordered = session.ordered_actions();
+    session.actions.clear();
+    session.mouse_path = MousePath::default();
+
+    let mut deleted = 0;
+    let mut marked = false;
+    for action in ordered {
+        let at = DateTime::parse_from_rfc3339(action.timestamp())
+            .ok()
+            .map(|t| t.with_timezone(&Local));
+        if at.is_some_and(|at| at >= from && at <= until) {
+            deleted += 1;
+            continue;
+        }
+        if !marked && at.is_some_and(|at| at > until) {
+            session.actions.push(redacted(from, until));
+            marked = true;
+        }
+        match action {
+            Action::MouseMove { coords, .. } => {
+                if let Some(at) = at {
+                    session.record_mouse_move(at, coords);
+                }
+            }
+            action => session.actions.push(action),
+        }
+    }
+    if !marked {
+        session.actions.push(redacted(from, until));
+    }
+    if session.density.is_some() {
+        session.density = Some(analysis::event_density(session));
+    }
+    deleted
+}
+
+/// What [`delete_stored_range`] deleted.
+#[derive(Debug, Clone, Default)]
+pub struct RangeDeletion {
+    /// Events deleted from the session.
+    pub events: usize,
+    /// Every stored copy of the session that was rewritten: the sessions
+    /// file and the archive's week folders.
+    pub copies: Vec<PathBuf>,
+    /// Rows deleted from the latest detailed events.
+    pub detailed_events: usize,
+}
+
+/// Deletes `from` to `until` (see [`delete_range`]) from every copy of
+/// `session` stored under `config`'s data folder, matched by ID and start
+/// time, and from the latest detailed events. Exports written earlier aren't
+/// touched. Must not run while an `ActivityMonitor` holds the files open.
+pub fn delete_stored_range(
+    config: &Config,
+    session: &Session,
+    from: DateTime<Local>,
+    until: DateTime<Local>,
+) -> Result<RangeDeletion> {
+    let mut deletion = RangeDeletion::default();
+    // Sessions filed with `desk-monitor archive` are in both.
+    let archive = Path::new(&config.data_dir).join(&config.archive.directory);
+    let mut stores: Vec<(PathBuf, Box<dyn Storage>)> = Vec::new();
+    if config.sessions_path().exists() {
+        let path = config.sessions_path();
+        stores.push((path.clone(), Box::new(CsvStorage::open(path)?)));
+    }
+    if archive.is_dir() {
+        stores.push((archive.clone(), Box::new(FolderStorage::new(archive))));
+    }
+    for (path, mut storage) in stores {
+        let stored = storage.load()?.into_iter().find(|stored| {
+            stored.session_id == session.session_id && stored.start_time == session.start_time
+        });
+        let Some(mut stored) = stored else {
+            continue;
+        };
+        deletion.events = deletion.events.max(delete_range(&mut stored, from, until));
+        storage.replace(&stored)?;
+        deletion.copies.push(path);
+    }
+
+    let details = Path::new(&config.data_dir).join(DETAILS_FILE);
+    if details.exists() {
+        deletion.detailed_events = delete_detailed_rows(&details, from, until)?;
+    }
+    Ok(deletion)
+}
+
+/// Drops the rows of the detailed events file at `path` timestamped from
+/// `from` to `until`, returning how many there were. Only one session can
+/// have been recording then.
+fn delete_detailed_rows(
+    path: &Path,
+    from: DateTime<Local>,
+    until: DateTime<Local>,
+) -> Result<usize> {
+    let mut reader = ReaderBuilder::new()
+        .flexible(true)
+        .has_headers(false)
+        .from_path(path)?;
+    let mut kept = Vec::new();
+    let mut deleted = 0;
+    for (i, record) in reader.records().enumerate() {
+        let record = record?;
+        let at = record
+            .get(0)
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| t.with_timezone(&Local));
+        if i > 0 && at.is_some_and(|at| at >= from && at <= until) {
+            deleted += 1;
+        } else {
+            kept.push(record);
+        }
+    }
+    if deleted == 0 {
+        return Ok(0);
+    }
+
+    let tmp_path = path.with_extension("csv.tmp");
+    let mut writer = WriterBuilder::new().flexible(true).from_path(&tmp_path)?;
+    for record in &kept {
+        writer.write_record(record)?;
+    }
+    writer.flush()?;
+    drop(writer);
+    fs::rename(&tmp_path, path)?;
+    Ok(deleted)
+}
+
+fn redacted(from: DateTime<Local>, until: DateTime<Local>) -> Action {
+    Action::Redacted {
+        timestamp: format_timestamp(from),
+        until: format_timestamp(until),
+    }
+}
@@ -0,0 +1,113 @@
+//! Automatic checks for recordings that may be unreliable, so they can be
+//! flagged and left out of analysis.
+
+use crate::analysis;
+use crate::jiggle::{self, JiggleConfig};
+use crate::types::{Action, Session};
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QualityFlag {
+    /// Input capture failed at least once during the session.
+    CaptureFailure,
+    /// Nothing was recorded for longer than `max_gap_minutes`.
+    CaptureGap,
+    /// More events per minute than `max_events_per_minute`, e.g. a stuck
+    /// device.
+    HighEventRate,
+    /// The session ran for a minute or more without recording anything.
+    NoEvents,
+    /// Part of the session was a mouse jiggler keeping it looking active
+    /// (see [`crate::jiggle`]); only checked when detection is on.
+    MouseJiggle,
+}
+
+impl QualityFlag {
+    pub const ALL: [QualityFlag; 5] = [
+        QualityFlag::CaptureFailure,
+        QualityFlag::CaptureGap,
+        QualityFlag::HighEventRate,
+        QualityFlag::NoEvents,
+        QualityFlag::MouseJiggle,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            QualityFlag::CaptureFailure => "capture_failure",
+            QualityFlag::CaptureGap => "capture_gap",
+            QualityFlag::HighEventRate => "high_event_rate",
+            QualityFlag::NoEvents => "no_events",
+            QualityFlag::MouseJiggle => "mouse_jiggle",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|flag| flag.as_str() == s)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QualityConfig {
+    pub max_gap_minutes: u32,
+    pub max_events_per_minute: u32,
+    pub mouse_jiggle: JiggleConfig,
+}
+
+impl Default for QualityConfig {
+    fn default() -> Self {
+        Self {
+            max_gap_minutes: 30,
+            max_events_per_minute: 10_000,
+            mouse_jiggle: JiggleConfig::default(),
+        }
+    }
+}
+
+/// Runs every check on `session`. Sessions that are still being recorded
+/// (no end time) are only checked for capture failures.
+pub fn check(session: &Session, config: &QualityConfig) -> Vec<QualityFlag> {
+    let mut flags = Vec::new();
+    if session.actions.iter().any(|action| {
+        matches!(
+            action,
+            Action::CaptureInterrupted { .. } | Action::CaptureGap { .. }
+        )
+    }) {
+        flags.push(QualityFlag::CaptureFailure);
+    }
+    let (Some(start), Some(duration)) = (session.started_at(), session.duration()) else {
+        return flags;
+    };
+
+    let events = analysis::event_count(session);
+    let minutes = duration.num_seconds() as f64 / 60.0;
+    if events == 0 && minutes >= 1.0 {
+        flags.push(QualityFlag::NoEvents);
+    }
+    if events as f64 / minutes.max(1.0) > f64::from(config.max_events_per_minute) {
+        flags.push(QualityFlag::HighEventRate);
+    }
+
+    // The longest stretch without events, counting from the start and up
+    // to the end of the session.
+    let mut previous = start.timestamp_millis();
+    let mut longest_gap_ms = 0;
+    for action in session.ordered_actions() {
+        if let Ok(at) = DateTime::parse_from_rfc3339(action.timestamp()) {
+            longest_gap_ms = longest_gap_ms.max(at.timestamp_millis() - previous);
+            previous = at.timestamp_millis();
+        }
+    }
+    let end = start.timestamp_millis() + duration.num_milliseconds();
+    longest_gap_ms = longest_gap_ms.max(end - previous);
+    if events > 0 && longest_gap_ms > i64::from(config.max_gap_minutes) * 60_000 {
+        flags.push(QualityFlag::CaptureGap);
+    }
+    if !jiggle::detect(session, &config.mouse_jiggle).is_empty() {
+        flags.push(QualityFlag::MouseJiggle);
+    }
+    flags
+}
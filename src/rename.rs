@@ -0,0 +1,214 @@
+//! Renaming a task everywhere it's stored: sessions (in the sessions file
+//! and the archive's week folders), task switches recorded in them, minute
+//! rollups, the latest detailed events, templates and budgets.
+
+use crate::config::Config;
+use crate::storage::{FolderStorage, Storage, DETAILS_FILE, ROLLUPS_FILE, SESSION_HEADERS};
+use crate::types::{Action, Session};
+use anyhow::Result;
+use csv::{ReaderBuilder, WriterBuilder};
+use std::{fs, path::Path};
+
+/// What a rename changed, or would change in a dry run.
+#[derive(Debug, Clone, Default)]
+pub struct RenameReport {
+    /// `session_id (start_time)` of every renamed session.
+    pub sessions: Vec<String>,
+    pub task_switches: usize,
+    pub rollups: usize,
+    pub detailed_events: usize,
+    pub templates: Vec<String>,
+    /// Budgets moved to the new name.
+    pub budgets: usize,
+}
+
+/// Renames task `from` to `to` in the data files under `config`'s data
+/// folder and in its templates and budgets, saving the config. With
+/// `dry_run` nothing is written. Must not run while an `ActivityMonitor`
+/// holds the files open.
+pub fn rename_task(
+    from: &str,
+    to: &str,
+    dry_run: bool,
+    config: &mut Config,
+) -> Result<RenameReport> {
+    if to.trim().is_empty() {
+        anyhow::bail!("the new task name is empty");
+    }
+    let budgeted = |name: &str| config.budgets.iter().any(|b| b.task_name == name);
+    if budgeted(from) && budgeted(to) {
+        anyhow::bail!(
+            "both {:?} and {:?} have a budget; remove one under Settings first",
+            from,
+            to
+        );
+    }
+
+    let mut report = RenameReport::default();
+    let sessions_path = config.sessions_path();
+    if sessions_path.exists() {
+        rename_in_sessions(&sessions_path, from, to, dry_run, &mut report)?;
+    }
+    let archive = Path::new(&config.data_dir).join(&config.archive.directory);
+    if archive.is_dir() {
+        rename_in_archive(FolderStorage::new(archive), from, to, dry_run, &mut report)?;
+    }
+    report.rollups = rename_column(Path::new(ROLLUPS_FILE), 1, from, to, dry_run)?;
+    let details = Path::new(&config.data_dir).join(DETAILS_FILE);
+    report.detailed_events = rename_column(&details, 1, from, to, dry_run)?;
+
+    for template in &mut config.templates {
+        if template.task_name.as_deref() == Some(from) {
+            template.task_name = Some(to.to_string());
+            report.templates.push(template.name.clone());
+        }
+    }
+    for budget in &mut config.budgets {
+        if budget.task_name == from {
+            budget.task_name = to.to_string();
+            report.budgets += 1;
+        }
+    }
+    if !dry_run && (!report.templates.is_empty() || report.budgets > 0) {
+        config.save()?;
+    }
+    Ok(report)
+}
+
+fn rename_in_sessions(
+    path: &Path,
+    from: &str,
+    to: &str,
+    dry_run: bool,
+    report: &mut RenameReport,
+) -> Result<()> {
+    let mut reader = ReaderBuilder::new().flexible(true).from_path(path)?;
+    let tmp_path = path.with_extension("csv.tmp");
+    let mut writer = WriterBuilder::new().flexible(true).from_path(&tmp_path)?;
+    writer.write_record(SESSION_HEADERS)?;
+
+    let mut changed = false;
+    for record in reader.records() {
+        let record = record?;
+        // Rows that can't be parsed or don't mention the task are copied
+        // through untouched.
+        let Some(mut session) = Session::from_csv_record(&record) else {
+            writer.write_record(&record)?;
+            continue;
+        };
+        if !rename_session(&mut session, from, to, report) {
+            writer.write_record(&record)?;
+            continue;
+        }
+        writer.write_record(session.to_csv_record())?;
+        changed = true;
+    }
+    writer.flush()?;
+    drop(writer);
+
+    if dry_run || !changed {
+        fs::remove_file(&tmp_path)?;
+    } else {
+        fs::rename(&tmp_path, path)?;
+    }
+    Ok(())
+}
+
+/// Renames the task in the archive's week folders. Sessions filed there
+/// with `desk-monitor archive` are in the sessions file too, so they're
+/// only counted once.
+fn rename_in_archive(
+    mut archive: FolderStorage,
+    from: &str,
+    to: &str,
+    dry_run: bool,
+    report: &mut RenameReport,
+) -> Result<()> {
+    for mut session in archive.load()? {
+        let mut renamed = RenameReport::default();
+        if !rename_session(&mut session, from, to, &mut renamed) {
+            continue;
+        }
+        let key = format!("{} ({})", session.session_id, session.start_time);
+        if !report.sessions.contains(&key) {
+            report.sessions.push(key);
+            report.task_switches += renamed.task_switches;
+        }
+        if !dry_run {
+            archive.replace(&session)?;
+        }
+    }
+    Ok(())
+}
+
+/// Renames the task of `session` and its task switches, returning whether
+/// anything changed.
+fn rename_session(session: &mut Session, from: &str, to: &str, report: &mut RenameReport) -> bool {
+    let mut changed = false;
+    if session.task_name == from {
+        session.task_name = to.to_string();
+        report
+            .sessions
+            .push(format!("{} ({})", session.session_id, session.start_time));
+        changed = true;
+    }
+    for action in &mut session.actions {
+        if let Action::TaskSwitch {
+            from: previous,
+            to: next,
+            ..
+        } = action
+        {
+            for name in [previous, next] {
+                if name == from {
+                    *name = to.to_string();
+                    report.task_switches += 1;
+                    changed = true;
+                }
+            }
+        }
+    }
+    changed
+}
+
+/// Renames `from` to `to` in column `column` of the CSV file at `path`, if
+/// it exists, returning how many rows matched.
+fn rename_column(path: &Path, column: usize, from: &str, to: &str, dry_run: bool) -> Result<usize> {
+    if !path.exists() {
+        return Ok(0);
+    }
+    let mut reader = ReaderBuilder::new()
+        .flexible(true)
+        .has_headers(false)
+        .from_path(path)?;
+    let mut records = Vec::new();
+    let mut renamed = 0;
+    for (i, record) in reader.records().enumerate() {
+        let record = record?;
+        if i > 0 && record.get(column) == Some(from) {
+            renamed += 1;
+            records.push(
+                record
+                    .iter()
+                    .enumerate()
+                    .map(|(j, field)| if j == column { to } else { field })
+                    .collect(),
+            );
+        } else {
+            records.push(record);
+        }
+    }
+    if dry_run || renamed == 0 {
+        return Ok(renamed);
+    }
+
+    let tmp_path = path.with_extension("csv.tmp");
+    let mut writer = WriterBuilder::new().flexible(true).from_path(&tmp_path)?;
+    for record in &records {
+        writer.write_record(record)?;
+    }
+    writer.flush()?;
+    drop(writer);
+    fs::rename(&tmp_path, path)?;
+    Ok(renamed)
+}
@@ -0,0 +1,94 @@
+//! Rolling buffer of the last few seconds of input while nothing is being
+//! recorded, so "what just happened" can still be saved after the fact,
+//! e.g. the steps that led to a bug.
+//!
+//! The buffer only lives in memory; nothing reaches disk until it's saved
+//! as a session of its own.
+
+use crate::types::{format_timestamp, session_id, Action, Session};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RingBufferConfig {
+    /// How much input to keep while not recording. 0 turns the buffer off.
+    pub seconds: u64,
+    /// Chord that saves the buffer as a session, e.g. `Ctrl+Alt+B`. Empty
+    /// leaves saving to the window and the query API.
+    pub hotkey: String,
+    /// Task name saved buffers are recorded under.
+    pub task_name: String,
+}
+
+impl Default for RingBufferConfig {
+    fn default() -> Self {
+        Self {
+            seconds: 0,
+            hotkey: String::new(),
+            task_name: "Replay buffer".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RingBuffer {
+    events: VecDeque<(Instant, Action)>,
+}
+
+impl RingBuffer {
+    /// Adds `action`, seen at `at`, and drops whatever is now older than
+    /// `window`.
+    pub fn push(&mut self, at: Instant, action: Action, window: Duration) {
+        self.events.push_back((at, action));
+        self.expire(at, window);
+    }
+
+    /// Drops events older than `window` as of `now`.
+    pub fn expire(&mut self, now: Instant, window: Duration) {
+        while self
+            .events
+            .front()
+            .is_some_and(|(at, _)| now.saturating_duration_since(*at) > window)
+        {
+            self.events.pop_front();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+
+    /// Empties the buffer into a finished session for `task_name`, starting
+    /// at its oldest event and ending now. `None` when it's empty.
+    pub fn take_session(&mut self, task_name: &str) -> Option<Session> {
+        let (_, first) = self.events.front()?;
+        let started = DateTime::parse_from_rfc3339(first.timestamp())
+            .map_or_else(|_| Local::now(), |t| t.with_timezone(&Local));
+        let mut session = Session::new(task_name);
+        session.session_id = session_id(started);
+        session.start_time = format_timestamp(started);
+        for (_, action) in self.events.drain(..) {
+            match action {
+                Action::MouseMove { timestamp, coords } => {
+                    let at = DateTime::parse_from_rfc3339(&timestamp)
+                        .map_or_else(|_| Local::now(), |t| t.with_timezone(&Local));
+                    session.record_mouse_move(at, coords);
+                }
+                action => session.actions.push(action),
+            }
+        }
+        session.end_time = Some(format_timestamp(Local::now()));
+        Some(session)
+    }
+}
@@ -0,0 +1,160 @@
+use crate::config::{Config, DailyExportConfig, ScheduleConfig, ScheduleRule};
+use crate::export;
+use anyhow::Result;
+use chrono::{Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime};
+use std::{
+    fs,
+    path::PathBuf,
+    sync::mpsc::{self, Receiver},
+    thread, time,
+};
+
+const CHECK_INTERVAL: time::Duration = time::Duration::from_secs(60);
+/// How often schedule rules are checked, so automatic actions run within a
+/// few seconds of their time.
+const SCHEDULE_INTERVAL: time::Duration = time::Duration::from_secs(5);
+
+/// Sent by the schedule thread; acting on it is up to the receiver.
+#[derive(Debug, Clone)]
+pub enum ScheduleEvent {
+    /// `rule` will fire at `at`, `notify_minutes` from now.
+    Upcoming {
+        rule: ScheduleRule,
+        at: NaiveDateTime,
+    },
+    /// `rule` fired at `at`.
+    Due {
+        rule: ScheduleRule,
+        at: NaiveDateTime,
+    },
+}
+
+/// Starts the background thread that evaluates schedule rules. Like the
+/// daily export, it re-reads the config file on every check. Times that
+/// passed while the app wasn't running are not caught up on.
+pub fn spawn_schedule() -> Receiver<ScheduleEvent> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut last = Local::now().naive_local();
+        loop {
+            thread::sleep(SCHEDULE_INTERVAL);
+            let now = Local::now().naive_local();
+            let Ok(config) = Config::load() else {
+                last = now;
+                continue;
+            };
+            for event in schedule_events(&config.schedule, last, now) {
+                if sender.send(event).is_err() {
+                    return;
+                }
+            }
+            last = now;
+        }
+    });
+    receiver
+}
+
+/// Events for rules that fire, or whose prompt is due, in `(after, until]`.
+pub fn schedule_events(
+    config: &ScheduleConfig,
+    after: NaiveDateTime,
+    until: NaiveDateTime,
+) -> Vec<ScheduleEvent> {
+    if !config.enabled {
+        return Vec::new();
+    }
+    let notice = Duration::minutes(i64::from(config.notify_minutes));
+    let mut events = Vec::new();
+    for rule in &config.rules {
+        if config.notify_minutes > 0 {
+            if let Some(at) = next_firing(rule, after + notice, until + notice) {
+                events.push(ScheduleEvent::Upcoming {
+                    rule: rule.clone(),
+                    at,
+                });
+            }
+        }
+        if let Some(at) = next_firing(rule, after, until) {
+            events.push(ScheduleEvent::Due {
+                rule: rule.clone(),
+                at,
+            });
+        }
+    }
+    events
+}
+
+/// First time `rule` fires in `(after, until]`. Rules with an invalid time
+/// never fire.
+fn next_firing(
+    rule: &ScheduleRule,
+    after: NaiveDateTime,
+    until: NaiveDateTime,
+) -> Option<NaiveDateTime> {
+    let time = NaiveTime::parse_from_str(&rule.time, "%H:%M").ok()?;
+    after
+        .date()
+        .iter_days()
+        .take_while(|day| *day <= until.date())
+        .filter(|day| rule.days.contains(&day.weekday()))
+        .map(|day| day.and_time(time))
+        .find(|at| *at > after && *at <= until)
+}
+
+/// Starts the background thread that runs the daily export. The config file
+/// is re-read on every check so changes from the settings panel apply
+/// without a restart.
+pub fn spawn_daily_export() -> thread::JoinHandle<()> {
+    thread::spawn(|| loop {
+        if let Ok(config) = Config::load() {
+            if let Err(e) = run_daily_export(&config) {
+                eprintln!("Daily export failed: {}", e);
+            }
+        }
+        thread::sleep(CHECK_INTERVAL);
+    })
+}
+
+/// Exports yesterday's sessions if the configured time has passed and the
+/// export file doesn't exist yet. Returns the path written, if any.
+pub fn run_daily_export(config: &Config) -> Result<Option<PathBuf>> {
+    let daily = &config.daily_export;
+    if !daily.enabled {
+        return Ok(None);
+    }
+
+    let now = Local::now();
+    let run_at = NaiveTime::parse_from_str(&daily.time, "%H:%M")?;
+    if now.time() < run_at {
+        return Ok(None);
+    }
+
+    let day = now.date_naive() - Duration::days(1);
+    let path = export_path(daily, day);
+    if path.exists() {
+        return Ok(None);
+    }
+
+    let sessions: Vec<_> = config
+        .stored_sessions()?
+        .into_iter()
+        .filter(|session| session.started_at().map(|t| t.date_naive()) == Some(day))
+        .collect();
+
+    fs::create_dir_all(&daily.directory)?;
+    export::write_sessions(&sessions, &path, daily.format, config)?;
+    println!(
+        "✓ Exported {} sessions to {}",
+        sessions.len(),
+        path.display()
+    );
+    Ok(Some(path))
+}
+
+fn export_path(config: &DailyExportConfig, day: NaiveDate) -> PathBuf {
+    PathBuf::from(&config.directory).join(format!(
+        "sessions_{}.{}",
+        day.format("%Y-%m-%d"),
+        config.format.extension()
+    ))
+}
@@ -0,0 +1,105 @@
+//! Full-screen screenshots for bug reports, saved as PNG.
+
+use anyhow::Result;
+
+/// An RGB image, 3 bytes per pixel, rows top to bottom.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Screenshot {
+    pub width: u32,
+    pub height: u32,
+    pub rgb: Vec<u8>,
+}
+
+impl Screenshot {
+    /// A copy at most `max_width` pixels wide, shrunk by a whole factor
+    /// (nearest pixel) so text stays crisp. 0 keeps the full size.
+    pub fn downscaled(&self, max_width: u32) -> Screenshot {
+        if max_width == 0 || self.width <= max_width {
+            return self.clone();
+        }
+        let factor = self.width.div_ceil(max_width);
+        let (width, height) = (self.width / factor, self.height / factor);
+        let mut rgb = Vec::with_capacity((width * height * 3) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let at = (((y * factor) * self.width + x * factor) * 3) as usize;
+                rgb.extend_from_slice(&self.rgb[at..at + 3]);
+            }
+        }
+        Screenshot { width, height, rgb }
+    }
+
+    /// The image as a PNG file.
+    pub fn to_png(&self) -> Result<Vec<u8>> {
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.rgb)?;
+        Ok(png)
+    }
+}
+
+/// A screenshot of the whole screen, where supported.
+///
+/// On Linux this reads the X root window, which needs a 24- or 32-bit
+/// TrueColor visual (the norm).
+#[cfg(target_os = "linux")]
+pub fn capture() -> Option<Screenshot> {
+    use x11::xlib;
+
+    unsafe {
+        let dpy = xlib::XOpenDisplay(std::ptr::null());
+        if dpy.is_null() {
+            return None;
+        }
+        let root = xlib::XDefaultRootWindow(dpy);
+        let mut attributes: xlib::XWindowAttributes = std::mem::zeroed();
+        let image = if xlib::XGetWindowAttributes(dpy, root, &mut attributes) != 0 {
+            xlib::XGetImage(
+                dpy,
+                root,
+                0,
+                0,
+                attributes.width.max(0) as u32,
+                attributes.height.max(0) as u32,
+                xlib::XAllPlanes(),
+                xlib::ZPixmap,
+            )
+        } else {
+            std::ptr::null_mut()
+        };
+        let screenshot = image.as_ref().and_then(|image| {
+            let masks = [image.red_mask, image.green_mask, image.blue_mask];
+            if image.bits_per_pixel != 32 || image.data.is_null() || masks.contains(&0) {
+                return None;
+            }
+            let (width, height) = (image.width.max(0) as u32, image.height.max(0) as u32);
+            let channel = |pixel: u32, mask: std::os::raw::c_ulong| {
+                let mask = mask as u32;
+                ((pixel & mask) >> mask.trailing_zeros()) as u8
+            };
+            let mut rgb = Vec::with_capacity((width * height * 3) as usize);
+            for y in 0..height as usize {
+                let row = image.data.add(y * image.bytes_per_line as usize) as *const u32;
+                for x in 0..width as usize {
+                    let pixel = row.add(x).read_unaligned();
+                    rgb.push(channel(pixel, image.red_mask));
+                    rgb.push(channel(pixel, image.green_mask));
+                    rgb.push(channel(pixel, image.blue_mask));
+                }
+            }
+            Some(Screenshot { width, height, rgb })
+        });
+        if !image.is_null() {
+            xlib::XDestroyImage(image);
+        }
+        xlib::XCloseDisplay(dpy);
+        screenshot
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn capture() -> Option<Screenshot> {
+    None
+}
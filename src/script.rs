@@ -0,0 +1,346 @@
+//! Turns a recorded session's clicks and keystrokes into something that
+//! replays them: a Rust program driving `enigo`, or a JSON macro for other
+//! tools to play back.
+//!
+//! Mouse moves, button and key presses and releases become steps; other
+//! events (focus, breaks, custom events) are left out. Keys are named as
+//! recorded (`device_query` key names such as `LShift` or `Key1`).
+
+use crate::integrity;
+use crate::types::{Action, Session};
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset};
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptFormat {
+    /// A Rust `main` using `enigo` 0.2.
+    Enigo,
+    /// A `desk-monitor-macro` JSON document.
+    Json,
+}
+
+impl ScriptFormat {
+    /// The format for an output file, by its extension (`.rs` or `.json`).
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "rs" => Some(Self::Enigo),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScriptOptions {
+    /// Wait between steps as long as the recording did. Off, steps run
+    /// back to back.
+    pub keep_timing: bool,
+    /// Width and height of the screen the session was recorded on. When
+    /// set, positions are written as fractions of the screen so the script
+    /// works at other resolutions.
+    pub normalize: Option<(u32, u32)>,
+}
+
+impl Default for ScriptOptions {
+    fn default() -> Self {
+        Self {
+            keep_timing: true,
+            normalize: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Step {
+    Wait {
+        ms: i64,
+    },
+    /// Move the pointer to pixel `x`, `y`.
+    Move {
+        x: i32,
+        y: i32,
+    },
+    /// Move the pointer to `x`, `y` as fractions (0 to 1) of the screen's
+    /// width and height.
+    MoveScaled {
+        x: f64,
+        y: f64,
+    },
+    ButtonDown {
+        button: String,
+    },
+    ButtonUp {
+        button: String,
+    },
+    KeyDown {
+        key: String,
+    },
+    KeyUp {
+        key: String,
+    },
+}
+
+/// The steps replaying `session`.
+pub fn steps(session: &Session, options: ScriptOptions) -> Vec<Step> {
+    let mut steps = Vec::new();
+    let mut held: Vec<String> = Vec::new();
+    let mut previous: Option<DateTime<FixedOffset>> = None;
+    for action in session.ordered_actions() {
+        let mut action_steps = Vec::new();
+        match &action {
+            Action::MouseMove { coords, .. } => {
+                action_steps.push(match options.normalize {
+                    Some((width, height)) => Step::MoveScaled {
+                        x: fraction(coords.0, width),
+                        y: fraction(coords.1, height),
+                    },
+                    None => Step::Move {
+                        x: coords.0,
+                        y: coords.1,
+                    },
+                });
+            }
+            Action::MouseDown { button, .. } => action_steps.push(Step::ButtonDown {
+                button: button.clone(),
+            }),
+            Action::MouseUp { button, .. } => action_steps.push(Step::ButtonUp {
+                button: button.clone(),
+            }),
+            Action::KeyPress { keys, .. } => {
+                // Each press records every key held, so the steps are what
+                // changed since the last one.
+                for key in held.iter().filter(|key| !keys.contains(key)) {
+                    action_steps.push(Step::KeyUp { key: key.clone() });
+                }
+                for key in keys.iter().filter(|key| !held.contains(key)) {
+                    action_steps.push(Step::KeyDown { key: key.clone() });
+                }
+                held = keys.clone();
+            }
+            _ => {}
+        }
+        if action_steps.is_empty() {
+            continue;
+        }
+
+        let at = DateTime::parse_from_rfc3339(action.timestamp()).ok();
+        if let (true, Some(previous), Some(at)) = (options.keep_timing, previous, at) {
+            let ms = (at - previous).num_milliseconds();
+            if ms > 0 {
+                steps.push(Step::Wait { ms });
+            }
+        }
+        previous = at.or(previous);
+        steps.extend(action_steps);
+    }
+    steps
+}
+
+/// `value` as a fraction of `size`, to 4 decimal places.
+fn fraction(value: i32, size: u32) -> f64 {
+    (value as f64 / size.max(1) as f64 * 10_000.0).round() / 10_000.0
+}
+
+#[derive(Serialize)]
+struct Macro<'a> {
+    format: &'static str,
+    version: u32,
+    session_id: &'a str,
+    task_name: &'a str,
+    steps: Vec<Step>,
+}
+
+/// The `desk-monitor-macro` JSON document for `session`.
+pub fn to_json(session: &Session, options: ScriptOptions) -> Result<String> {
+    let document = Macro {
+        format: "desk-monitor-macro",
+        version: 1,
+        session_id: &session.session_id,
+        task_name: &session.task_name,
+        steps: steps(session, options),
+    };
+    Ok(serde_json::to_string_pretty(&document)?)
+}
+
+/// A Rust program replaying `session` with `enigo` 0.2. Keys and buttons
+/// it has no equivalent for are left as comments.
+pub fn to_enigo(session: &Session, options: ScriptOptions) -> String {
+    let steps = steps(session, options);
+    let has = |f: fn(&Step) -> bool| steps.iter().any(f);
+    let waits = has(|step| matches!(step, Step::Wait { .. }));
+    let scaled = has(|step| matches!(step, Step::MoveScaled { .. }));
+    let moves = has(|step| matches!(step, Step::Move { .. } | Step::MoveScaled { .. }));
+    let scrolls =
+        has(|step| matches!(step, Step::ButtonDown { button } if button.starts_with("scroll_")));
+    let clicks = has(|step| match step {
+        Step::ButtonDown { button } | Step::ButtonUp { button } => !button.starts_with("scroll_"),
+        _ => false,
+    });
+    let keys = has(|step| matches!(step, Step::KeyDown { .. } | Step::KeyUp { .. }));
+
+    let mut imports = Vec::new();
+    if scrolls {
+        imports.push("Axis");
+    }
+    if clicks {
+        imports.push("Button");
+    }
+    if moves {
+        imports.push("Coordinate");
+    }
+    if clicks || keys {
+        imports.push("Direction");
+    }
+    imports.push("Enigo");
+    if keys {
+        imports.extend(["Key", "Keyboard"]);
+    }
+    if moves || clicks || scrolls {
+        imports.push("Mouse");
+    }
+    imports.push("Settings");
+
+    let mut rs = format!(
+        "// Replays session {} ({:?}), recorded by desk-monitor.\n\
+         // Needs `enigo = \"0.2\"`.\n",
+        session.session_id, session.task_name
+    );
+    rs.push_str(&format!("use enigo::{{{}}};\n", imports.join(", ")));
+    if waits {
+        rs.push_str("use std::{thread, time::Duration};\n");
+    }
+    rs.push_str("\nfn main() -> Result<(), Box<dyn std::error::Error>> {\n");
+    rs.push_str("    let mut enigo = Enigo::new(&Settings::default())?;\n");
+    if scaled {
+        rs.push_str("    let (width, height) = enigo.main_display()?;\n");
+        rs.push_str(
+            "    let scaled = |x: f64, y: f64| ((x * width as f64) as i32, (y * height as f64) as i32);\n",
+        );
+    }
+    for step in &steps {
+        let line = match step {
+            Step::Wait { ms } => format!("thread::sleep(Duration::from_millis({}));", ms),
+            Step::Move { x, y } => format!("enigo.move_mouse({}, {}, Coordinate::Abs)?;", x, y),
+            Step::MoveScaled { x, y } => format!(
+                "let (x, y) = scaled({:?}, {:?});\n    enigo.move_mouse(x, y, Coordinate::Abs)?;",
+                x, y
+            ),
+            Step::ButtonDown { button } => match button.as_str() {
+                "scroll_up" => "enigo.scroll(-1, Axis::Vertical)?;".to_string(),
+                "scroll_down" => "enigo.scroll(1, Axis::Vertical)?;".to_string(),
+                _ => enigo_button(button, "Press"),
+            },
+            Step::ButtonUp { button } => match button.as_str() {
+                "scroll_up" | "scroll_down" => continue,
+                _ => enigo_button(button, "Release"),
+            },
+            Step::KeyDown { key } => enigo_key(key, "Press"),
+            Step::KeyUp { key } => enigo_key(key, "Release"),
+        };
+        rs.push_str(&format!("    {}\n", line));
+    }
+    rs.push_str("    Ok(())\n}\n");
+    rs
+}
+
+fn enigo_button(button: &str, direction: &str) -> String {
+    let variant = match button {
+        "left" => "Left",
+        "right" => "Right",
+        "middle" => "Middle",
+        "back" => "Back",
+        "forward" => "Forward",
+        _ => return format!("// {} {}: no enigo equivalent", direction, button),
+    };
+    format!(
+        "enigo.button(Button::{}, Direction::{})?;",
+        variant, direction
+    )
+}
+
+fn enigo_key(key: &str, direction: &str) -> String {
+    let named = match key {
+        "LShift" | "RShift" => Some("Shift"),
+        "LControl" | "RControl" => Some("Control"),
+        "LAlt" | "RAlt" => Some("Alt"),
+        "LMeta" | "RMeta" | "Meta" | "Command" | "RCommand" | "LOption" | "ROption" => Some("Meta"),
+        "Enter" | "NumpadEnter" => Some("Return"),
+        "Space" => Some("Space"),
+        "Backspace" => Some("Backspace"),
+        "Tab" => Some("Tab"),
+        "Escape" => Some("Escape"),
+        "Delete" => Some("Delete"),
+        "Home" => Some("Home"),
+        "End" => Some("End"),
+        "PageUp" => Some("PageUp"),
+        "PageDown" => Some("PageDown"),
+        "Up" => Some("UpArrow"),
+        "Down" => Some("DownArrow"),
+        "Left" => Some("LeftArrow"),
+        "Right" => Some("RightArrow"),
+        "CapsLock" => Some("CapsLock"),
+        "F1" | "F2" | "F3" | "F4" | "F5" | "F6" | "F7" | "F8" | "F9" | "F10" | "F11" | "F12" => {
+            Some(key)
+        }
+        _ => None,
+    };
+    if let Some(named) = named {
+        return format!("enigo.key(Key::{}, Direction::{})?;", named, direction);
+    }
+
+    let character = match key {
+        "Comma" => Some(','),
+        "Dot" => Some('.'),
+        "Slash" => Some('/'),
+        "Semicolon" => Some(';'),
+        "Apostrophe" => Some('\''),
+        "Minus" | "NumpadSubtract" => Some('-'),
+        "Equal" => Some('='),
+        "LeftBracket" => Some('['),
+        "RightBracket" => Some(']'),
+        "BackSlash" => Some('\\'),
+        "Grave" => Some('`'),
+        "NumpadAdd" => Some('+'),
+        "NumpadMultiply" => Some('*'),
+        "NumpadDivide" => Some('/'),
+        _ => {
+            let digit = key
+                .strip_prefix("Key")
+                .or_else(|| key.strip_prefix("Numpad"))
+                .unwrap_or(key);
+            let mut chars = digit.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if c.is_ascii_alphanumeric() => Some(c.to_ascii_lowercase()),
+                _ => None,
+            }
+        }
+    };
+    match character {
+        Some(c) => format!(
+            "enigo.key(Key::Unicode({:?}), Direction::{})?;",
+            c, direction
+        ),
+        None => format!("// {} {}: no enigo equivalent", direction, key),
+    }
+}
+
+/// Writes the script for `session` to `path` in `format`, along with a
+/// `.sha256` checksum manifest.
+pub fn write_script(
+    session: &Session,
+    path: impl AsRef<Path>,
+    format: ScriptFormat,
+    options: ScriptOptions,
+) -> Result<()> {
+    let script = match format {
+        ScriptFormat::Enigo => to_enigo(session, options),
+        ScriptFormat::Json => to_json(session, options)?,
+    };
+    fs::write(&path, script)?;
+    integrity::write_manifest(path)?;
+    Ok(())
+}
@@ -0,0 +1,345 @@
+//! Registers the headless monitor to start at login: a systemd user unit
+//! on Linux, a launchd agent on macOS and a logon scheduled task on
+//! Windows. A real Windows service would run outside the user's desktop
+//! session and couldn't see their input, so a scheduled task is used
+//! instead.
+//!
+//! The service runs in the directory it was installed from, so its data
+//! files end up in the same place as the GUI's.
+
+use anyhow::{bail, Context, Result};
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+
+pub const SERVICE_NAME: &str = "desk-monitor";
+
+/// Task name used by the service when none is given at install time.
+pub const DEFAULT_TASK: &str = "Background";
+
+/// What a login entry starts: this binary, in the directory it was
+/// registered from. Shared with `autostart`.
+pub(crate) struct Launch {
+    pub(crate) exe: PathBuf,
+    pub(crate) working_dir: PathBuf,
+}
+
+impl Launch {
+    pub(crate) fn current() -> Result<Self> {
+        Ok(Self {
+            exe: env::current_exe().context("locating the desk-monitor binary")?,
+            working_dir: env::current_dir()?,
+        })
+    }
+
+    /// A `cmd` line running `command` in the working directory, since
+    /// neither `Run` values nor scheduled tasks can set one.
+    #[cfg(windows)]
+    pub(crate) fn windows_command(&self, command: &str) -> String {
+        format!(
+            "cmd /c cd /d \"{}\" && {}",
+            self.working_dir.display(),
+            command
+        )
+    }
+}
+
+/// `$XDG_CONFIG_HOME`, or `~/.config`.
+#[cfg(target_os = "linux")]
+pub(crate) fn config_dir() -> Result<PathBuf> {
+    match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => Ok(PathBuf::from(dir)),
+        None => Ok(PathBuf::from(env::var_os("HOME").context("HOME is not set")?).join(".config")),
+    }
+}
+
+/// `~/Library/LaunchAgents/<label>.plist`.
+#[cfg(target_os = "macos")]
+pub(crate) fn launch_agent_path(label: &str) -> Result<PathBuf> {
+    Ok(
+        PathBuf::from(env::var_os("HOME").context("HOME is not set")?)
+            .join("Library/LaunchAgents")
+            .join(format!("{}.plist", label)),
+    )
+}
+
+/// `s` escaped for a plist string.
+#[cfg(target_os = "macos")]
+pub(crate) fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+pub(crate) fn run(command: &mut Command) -> Result<()> {
+    let status = command
+        .status()
+        .with_context(|| format!("running {:?}", command))?;
+    if !status.success() {
+        bail!("{:?} failed ({})", command, status);
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::{config_dir, run, Launch, SERVICE_NAME};
+    use anyhow::Result;
+    use std::{env, fs, path::PathBuf, process::Command};
+
+    fn unit_path() -> Result<PathBuf> {
+        Ok(config_dir()?
+            .join("systemd/user")
+            .join(format!("{}.service", SERVICE_NAME)))
+    }
+
+    fn unit(launch: &Launch, task_name: &str) -> String {
+        // User services don't inherit the desktop's DISPLAY, so capture it
+        // now. Until the X server accepts connections the monitor exits
+        // and systemd retries.
+        let display = env::var("DISPLAY").unwrap_or_else(|_| ":0".to_string());
+        format!(
+            "[Unit]\n\
+             Description=Desktop Activity Monitor\n\
+             \n\
+             [Service]\n\
+             ExecStart=\"{}\" headless \"{}\"\n\
+             WorkingDirectory={}\n\
+             Environment=DISPLAY={}\n\
+             Restart=on-failure\n\
+             RestartSec=10\n\
+             \n\
+             [Install]\n\
+             WantedBy=default.target\n",
+            launch.exe.display(),
+            task_name,
+            launch.working_dir.display(),
+            display
+        )
+    }
+
+    pub(super) fn install(launch: &Launch, task_name: &str) -> Result<()> {
+        let path = unit_path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, unit(launch, task_name))?;
+        println!("✓ Wrote {}", path.display());
+
+        run(Command::new("systemctl").args(["--user", "daemon-reload"]))?;
+        run(Command::new("systemctl").args([
+            "--user",
+            "enable",
+            "--now",
+            &format!("{}.service", SERVICE_NAME),
+        ]))
+    }
+
+    pub(super) fn uninstall() -> Result<()> {
+        let service = format!("{}.service", SERVICE_NAME);
+        run(Command::new("systemctl").args(["--user", "disable", "--now", &service]))?;
+        let path = unit_path()?;
+        if path.exists() {
+            fs::remove_file(&path)?;
+            println!("✓ Removed {}", path.display());
+        }
+        run(Command::new("systemctl").args(["--user", "daemon-reload"]))
+    }
+
+    pub(super) fn is_installed() -> bool {
+        unit_path().is_ok_and(|path| path.exists())
+    }
+
+    pub(super) fn status() -> Result<()> {
+        // `systemctl status` exits non-zero for inactive units, which is
+        // still a useful answer here.
+        Command::new("systemctl")
+            .args(["--user", "status", &format!("{}.service", SERVICE_NAME)])
+            .status()?;
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::{escape_xml as escape, launch_agent_path, run, Launch, SERVICE_NAME};
+    use anyhow::Result;
+    use std::{fs, path::PathBuf, process::Command};
+
+    const LABEL: &str = "com.desk-monitor.agent";
+
+    fn plist_path() -> Result<PathBuf> {
+        launch_agent_path(LABEL)
+    }
+
+    fn plist(launch: &Launch, task_name: &str) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{}</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>{}</string>
+        <string>headless</string>
+        <string>{}</string>
+    </array>
+    <key>WorkingDirectory</key>
+    <string>{}</string>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>KeepAlive</key>
+    <dict>
+        <key>SuccessfulExit</key>
+        <false/>
+    </dict>
+    <key>StandardErrorPath</key>
+    <string>/tmp/{}.log</string>
+</dict>
+</plist>
+"#,
+            LABEL,
+            escape(&launch.exe.to_string_lossy()),
+            escape(task_name),
+            escape(&launch.working_dir.to_string_lossy()),
+            SERVICE_NAME
+        )
+    }
+
+    pub(super) fn install(launch: &Launch, task_name: &str) -> Result<()> {
+        let path = plist_path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, plist(launch, task_name))?;
+        println!("✓ Wrote {}", path.display());
+        run(Command::new("launchctl").arg("load").arg("-w").arg(&path))
+    }
+
+    pub(super) fn uninstall() -> Result<()> {
+        let path = plist_path()?;
+        if path.exists() {
+            run(Command::new("launchctl").arg("unload").arg("-w").arg(&path))?;
+            fs::remove_file(&path)?;
+            println!("✓ Removed {}", path.display());
+        }
+        Ok(())
+    }
+
+    pub(super) fn is_installed() -> bool {
+        plist_path().is_ok_and(|path| path.exists())
+    }
+
+    pub(super) fn status() -> Result<()> {
+        if !plist_path()?.exists() {
+            println!("{} is not installed", LABEL);
+            return Ok(());
+        }
+        Command::new("launchctl").args(["list", LABEL]).status()?;
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::{run, Launch, SERVICE_NAME};
+    use anyhow::Result;
+    use std::process::Command;
+
+    pub(super) fn install(launch: &Launch, task_name: &str) -> Result<()> {
+        let action = launch.windows_command(&format!(
+            "\"{}\" headless \"{}\"",
+            launch.exe.display(),
+            task_name
+        ));
+        run(Command::new("schtasks").args([
+            "/Create",
+            "/TN",
+            SERVICE_NAME,
+            "/TR",
+            &action,
+            "/SC",
+            "ONLOGON",
+            "/RL",
+            "LIMITED",
+            "/F",
+        ]))?;
+        run(Command::new("schtasks").args(["/Run", "/TN", SERVICE_NAME]))
+    }
+
+    pub(super) fn uninstall() -> Result<()> {
+        // Ending a task kills the process, losing at most the last hour of
+        // the open session (see `headless`).
+        let _ = Command::new("schtasks")
+            .args(["/End", "/TN", SERVICE_NAME])
+            .status();
+        run(Command::new("schtasks").args(["/Delete", "/TN", SERVICE_NAME, "/F"]))
+    }
+
+    pub(super) fn is_installed() -> bool {
+        Command::new("schtasks")
+            .args(["/Query", "/TN", SERVICE_NAME])
+            .output()
+            .is_ok_and(|output| output.status.success())
+    }
+
+    pub(super) fn status() -> Result<()> {
+        Command::new("schtasks")
+            .args(["/Query", "/TN", SERVICE_NAME, "/V", "/FO", "LIST"])
+            .status()?;
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+mod platform {
+    use super::Launch;
+    use anyhow::{bail, Result};
+
+    pub(super) fn install(_launch: &Launch, _task_name: &str) -> Result<()> {
+        bail!("installing a login service isn't supported on this platform")
+    }
+
+    pub(super) fn uninstall() -> Result<()> {
+        bail!("installing a login service isn't supported on this platform")
+    }
+
+    pub(super) fn is_installed() -> bool {
+        false
+    }
+
+    pub(super) fn status() -> Result<()> {
+        bail!("installing a login service isn't supported on this platform")
+    }
+}
+
+/// Installs and starts the login service recording `task_name`. Refused
+/// while the GUI launches at login (see `autostart`), since both would
+/// record the same input.
+pub fn install(task_name: &str) -> Result<()> {
+    if crate::autostart::is_enabled() {
+        bail!(
+            "the app is set to launch at login; untick \"Launch at login\" in its Settings \
+             first, or both would record"
+        );
+    }
+    platform::install(&Launch::current()?, task_name)
+}
+
+/// Stops and removes the login service.
+pub fn uninstall() -> Result<()> {
+    platform::uninstall()
+}
+
+/// Whether the login service is installed, by this copy of the app or
+/// another.
+pub fn is_installed() -> bool {
+    platform::is_installed()
+}
+
+/// Prints the platform's view of the login service.
+pub fn status() -> Result<()> {
+    platform::status()
+}
@@ -0,0 +1,320 @@
+//! "Open in browser": serves an interactive HTML view of one session from a
+//! short-lived local web server, for screen-sharing without the app.
+
+use crate::analysis;
+use crate::config::Config;
+use crate::metrics;
+use crate::quality;
+use crate::timeline::Timeline;
+use crate::types::Session;
+use anyhow::{Context, Result};
+use chrono::Local;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long the page stays available.
+const SHARE_DURATION: Duration = Duration::from_secs(15 * 60);
+/// How long a connection may take to send its request. Requests are served
+/// one at a time, so a client that never sends one would block the rest.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// Timeline resolution: at most this many buckets across the session.
+const TIMELINE_BUCKETS: i64 = 600;
+
+/// Starts a server on a random local port that serves `session`'s page for
+/// [`SHARE_DURATION`] and opens it in the default browser. Returns the URL,
+/// which includes a random token so other local users can't guess it.
+pub fn open_in_browser(session: &Session, config: &Config) -> Result<String> {
+    let page = session_page(session, config);
+    let listener = TcpListener::bind(("127.0.0.1", 0))?;
+    listener.set_nonblocking(true)?;
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).context("generating a session page token")?;
+    let token = hex(&bytes);
+    let path = format!("/{}", token);
+    let url = format!("http://{}{}", listener.local_addr()?, path);
+
+    thread::spawn(move || {
+        let started = Instant::now();
+        while started.elapsed() < SHARE_DURATION {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    if let Err(e) = serve(stream, &path, &page) {
+                        eprintln!("Session page request failed: {}", e);
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    thread::sleep(Duration::from_millis(100));
+                }
+                Err(e) => {
+                    eprintln!("Session page server stopped: {}", e);
+                    return;
+                }
+            }
+        }
+    });
+
+    open_url(&url)?;
+    Ok(url)
+}
+
+fn serve(mut stream: TcpStream, path: &str, page: &str) -> Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let target = request_line.split_whitespace().nth(1).unwrap_or_default();
+    let (status, body) = if target == path {
+        ("200 OK", page)
+    } else {
+        ("404 Not Found", "Not found")
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\n\
+         Cache-Control: no-store\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    Ok(())
+}
+
+/// Opens `url`, or a file path, with the platform's default handler.
+pub(crate) fn open_url(url: &str) -> Result<()> {
+    let status = if cfg!(target_os = "windows") {
+        Command::new("cmd")
+            .args(["/C", "start", "", url])
+            .status()?
+    } else if cfg!(target_os = "macos") {
+        Command::new("open").arg(url).status()?
+    } else {
+        Command::new("xdg-open").arg(url).status()?
+    };
+    if !status.success() {
+        anyhow::bail!("couldn't open {}; open it by hand instead", url);
+    }
+    Ok(())
+}
+
+/// Self-contained HTML page: summary stats plus a timeline of event counts
+/// per type, bucketed so long sessions stay light, under the session's
+/// layers (markers, idle gaps, pauses and focus).
+pub fn session_page(session: &Session, config: &Config) -> String {
+    let start = session.started_at();
+    let duration_secs = session.duration().map_or(0, |d| d.num_seconds()).max(1);
+    let bucket_secs = (duration_secs + TIMELINE_BUCKETS - 1) / TIMELINE_BUCKETS;
+    let buckets = (duration_secs + bucket_secs - 1) / bucket_secs;
+
+    let mut counts: BTreeMap<String, Vec<u32>> = BTreeMap::new();
+    if let Some(start) = start {
+        for action in session.ordered_actions() {
+            let Ok(at) = chrono::DateTime::parse_from_rfc3339(action.timestamp()) else {
+                continue;
+            };
+            let offset = (at.with_timezone(&Local) - start).num_seconds();
+            let bucket = (offset / bucket_secs).clamp(0, buckets - 1) as usize;
+            counts
+                .entry(action.kind_and_payload().0)
+                .or_insert_with(|| vec![0; buckets as usize])[bucket] += 1;
+        }
+    }
+
+    let mut stats = vec![
+        ("Task", session.task_name.clone()),
+        ("Started", session.start_time.clone()),
+        ("Ended", session.end_time.clone().unwrap_or("-".to_string())),
+        (
+            "Duration",
+            session.duration().map_or("-".to_string(), |d| {
+                format!("{}m {}s", d.num_minutes(), d.num_seconds() % 60)
+            }),
+        ),
+        ("Events", analysis::event_count(session).to_string()),
+        ("Mouse moves", session.mouse_path.len().to_string()),
+        ("Mouse clicks", analysis::click_stats(session).describe()),
+        (
+            "Mouse distance",
+            analysis::format_mouse_distance(session, &config.screen),
+        ),
+    ];
+    let flags = session
+        .quality_flags
+        .clone()
+        .unwrap_or_else(|| quality::check(session, &config.quality));
+    if !flags.is_empty() {
+        let names: Vec<&str> = flags.iter().map(|flag| flag.as_str()).collect();
+        stats.push(("Quality flags", names.join(", ")));
+    }
+    let metric_values = metrics::session_metrics(config, session);
+    let mut stats: Vec<(String, String)> = stats
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value))
+        .collect();
+    stats.extend(
+        metric_values
+            .into_iter()
+            .map(|(name, value)| (name, format!("{:.2}", value))),
+    );
+    if let Some(notes) = &session.notes {
+        stats.push(("Notes".to_string(), notes.clone()));
+    }
+
+    let data = json!({
+        "title": session.task_name,
+        "start": start.map(|t| t.timestamp_millis()),
+        "bucket_secs": bucket_secs,
+        "counts": counts,
+        "layers": Timeline::new(session),
+        "stats": stats,
+    });
+    // `</` would end the script element early.
+    let data = data.to_string().replace("</", "<\\/");
+    PAGE_TEMPLATE.replace("/*DATA*/null", &data)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+const PAGE_TEMPLATE: &str = r##"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Session</title>
+<style>
+body { font-family: system-ui, sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 2em; }
+td { padding: 4px 12px; border-bottom: 1px solid #eee; vertical-align: top; }
+td:first-child { color: #666; }
+#legend label { margin-right: 1em; }
+#layers, #timeline { width: 100%; border: 1px solid #ddd; }
+#tip { position: fixed; background: #222; color: #fff; padding: 4px 8px; border-radius: 4px;
+       font-size: 12px; pointer-events: none; display: none; white-space: pre; }
+</style>
+</head>
+<body>
+<h1 id="title"></h1>
+<table id="stats"></table>
+<h2>Timeline</h2>
+<div id="legend"></div>
+<canvas id="layers" height="36"></canvas>
+<canvas id="timeline" height="300"></canvas>
+<div id="tip"></div>
+<script>
+const data = /*DATA*/null;
+const colors = ["#2b7bb9", "#e4572e", "#29bf12", "#ffc914", "#76448a", "#17bebb", "#a0a0a0", "#d45087"];
+const kinds = Object.keys(data.counts);
+const shown = new Set(kinds);
+document.getElementById("title").textContent = data.title;
+document.title = data.title;
+const stats = document.getElementById("stats");
+for (const [name, value] of data.stats) {
+  const row = stats.insertRow();
+  row.insertCell().textContent = name;
+  row.insertCell().textContent = value;
+}
+const legend = document.getElementById("legend");
+kinds.forEach((kind, i) => {
+  const label = document.createElement("label");
+  const box = document.createElement("input");
+  box.type = "checkbox";
+  box.checked = true;
+  box.onchange = () => { box.checked ? shown.add(kind) : shown.delete(kind); draw(); };
+  label.append(box, " ");
+  const swatch = document.createElement("span");
+  swatch.textContent = kind;
+  swatch.style.color = colors[i % colors.length];
+  label.append(swatch);
+  legend.append(label);
+});
+const canvas = document.getElementById("timeline");
+const buckets = kinds.length ? data.counts[kinds[0]].length : 0;
+const layerCanvas = document.getElementById("layers");
+const layerRows = ["focus", "idle", "paused"];
+const layerColors = { idle: "#a0a0a0", paused: "#e4572e" };
+const spanMs = Math.max(buckets * data.bucket_secs * 1000, data.layers.duration_ms, 1);
+function focusColor(label) {
+  let hash = 0;
+  for (const c of label) hash += c.charCodeAt(0);
+  return ["#2b7bb9", "#29bf12", "#76448a", "#17bebb"][hash % 4];
+}
+function drawLayers() {
+  layerCanvas.width = layerCanvas.clientWidth;
+  const ctx = layerCanvas.getContext("2d");
+  const rowHeight = layerCanvas.height / layerRows.length;
+  const x = ms => (ms / spanMs) * layerCanvas.width;
+  ctx.clearRect(0, 0, layerCanvas.width, layerCanvas.height);
+  for (const band of data.layers.bands) {
+    const row = layerRows.indexOf(band.layer);
+    ctx.fillStyle = band.layer === "focus" ? focusColor(band.label) : layerColors[band.layer];
+    ctx.fillRect(x(band.from_ms), row * rowHeight + 1, Math.max(x(band.until_ms) - x(band.from_ms), 1), rowHeight - 2);
+  }
+  ctx.fillStyle = "#222";
+  for (const marker of data.layers.markers) ctx.fillRect(x(marker.offset_ms), 0, 1, layerCanvas.height);
+}
+layerCanvas.onmousemove = e => {
+  const at = (e.offsetX / layerCanvas.clientWidth) * spanMs;
+  const within = (4 / layerCanvas.clientWidth) * spanMs;
+  const time = ms => new Date(data.start + ms).toLocaleTimeString();
+  const lines = data.layers.bands
+    .filter(b => b.from_ms <= at && at < b.until_ms)
+    .map(b => `${b.layer}: ${b.label} (${time(b.from_ms)} – ${time(b.until_ms)})`)
+    .concat(data.layers.markers.filter(m => Math.abs(m.offset_ms - at) <= within).map(m => `${time(m.offset_ms)} ${m.label}`));
+  if (!lines.length) { tip.style.display = "none"; return; }
+  tip.textContent = lines.join("\n");
+  tip.style.left = e.clientX + 12 + "px";
+  tip.style.top = e.clientY + 12 + "px";
+  tip.style.display = "block";
+};
+function draw() {
+  canvas.width = canvas.clientWidth;
+  const ctx = canvas.getContext("2d");
+  ctx.clearRect(0, 0, canvas.width, canvas.height);
+  const visible = kinds.filter(k => shown.has(k));
+  if (!buckets || !visible.length) return;
+  const rowHeight = canvas.height / visible.length;
+  const width = canvas.width / buckets;
+  visible.forEach((kind, row) => {
+    const counts = data.counts[kind];
+    const max = Math.max(...counts, 1);
+    ctx.fillStyle = colors[kinds.indexOf(kind) % colors.length];
+    counts.forEach((count, i) => {
+      const h = (count / max) * (rowHeight - 4);
+      ctx.fillRect(i * width, (row + 1) * rowHeight - h - 2, Math.max(width - 1, 1), h);
+    });
+    ctx.fillStyle = "#666";
+    ctx.fillText(kind, 4, row * rowHeight + 12);
+  });
+}
+const tip = document.getElementById("tip");
+canvas.onmousemove = e => {
+  if (!buckets) return;
+  const i = Math.floor((e.offsetX / canvas.clientWidth) * buckets);
+  const from = new Date(data.start + i * data.bucket_secs * 1000);
+  const to = new Date(from.getTime() + data.bucket_secs * 1000);
+  const lines = kinds.filter(k => shown.has(k)).map(k => `${k}: ${data.counts[k][i]}`);
+  tip.textContent = `${from.toLocaleTimeString()} – ${to.toLocaleTimeString()}\n` + lines.join("\n");
+  tip.style.left = e.clientX + 12 + "px";
+  tip.style.top = e.clientY + 12 + "px";
+  tip.style.display = "block";
+};
+canvas.onmouseleave = () => { tip.style.display = "none"; };
+layerCanvas.onmouseleave = canvas.onmouseleave;
+window.onresize = () => { drawLayers(); draw(); };
+drawLayers();
+draw();
+</script>
+</body>
+</html>
+"##;
@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const MODIFIERS: [(&str, &[&str]); 4] = [
+    ("Ctrl", &["LControl", "RControl"]),
+    ("Alt", &["LAlt", "RAlt"]),
+    ("Shift", &["LShift", "RShift"]),
+    ("Meta", &["Meta"]),
+];
+
+/// Maps key chords such as `Ctrl+S` to semantic actions such as `save`,
+/// with optional per-application overrides.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShortcutMap {
+    pub enabled: bool,
+    pub defaults: BTreeMap<String, String>,
+    /// Application name -> chord -> action. Checked before `defaults`.
+    pub per_app: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl Default for ShortcutMap {
+    fn default() -> Self {
+        let defaults = [
+            ("Ctrl+S", "save"),
+            ("Ctrl+Z", "undo"),
+            ("Ctrl+Y", "redo"),
+            ("Ctrl+Shift+Z", "redo"),
+            ("Ctrl+C", "copy"),
+            ("Ctrl+X", "cut"),
+            ("Ctrl+V", "paste"),
+            ("Ctrl+A", "select_all"),
+            ("Ctrl+F", "find"),
+            ("Ctrl+N", "new"),
+            ("Ctrl+O", "open"),
+            ("Ctrl+P", "print"),
+            ("Ctrl+W", "close_tab"),
+            ("Ctrl+T", "new_tab"),
+            ("Alt+Tab", "switch_window"),
+            ("Alt+F4", "close_window"),
+        ]
+        .into_iter()
+        .map(|(chord, action)| (chord.to_string(), action.to_string()))
+        .collect();
+
+        Self {
+            enabled: true,
+            defaults,
+            per_app: BTreeMap::new(),
+        }
+    }
+}
+
+impl ShortcutMap {
+    /// Looks up the semantic action for `chord`, preferring an override for
+    /// `app` when one is known.
+    pub fn resolve(&self, app: Option<&str>, chord: &str) -> Option<&str> {
+        app.and_then(|app| self.per_app.get(app))
+            .and_then(|overrides| overrides.get(chord))
+            .or_else(|| self.defaults.get(chord))
+            .map(String::as_str)
+    }
+}
+
+/// Normalizes held keys (as `Keycode` debug names) into a chord like
+/// `Ctrl+Shift+Z`. Returns `None` unless at least one modifier and exactly
+/// one other key are held.
+pub fn chord_name(keys: &[String]) -> Option<String> {
+    let mut parts: Vec<&str> = MODIFIERS
+        .iter()
+        .filter(|(_, names)| keys.iter().any(|k| names.contains(&k.as_str())))
+        .map(|(name, _)| *name)
+        .collect();
+    if parts.is_empty() {
+        return None;
+    }
+
+    let mut others = keys.iter().filter(|k| {
+        !MODIFIERS
+            .iter()
+            .any(|(_, names)| names.contains(&k.as_str()))
+    });
+    let key = others.next()?;
+    if others.next().is_some() {
+        return None;
+    }
+
+    parts.push(key.strip_prefix("Key").unwrap_or(key));
+    Some(parts.join("+"))
+}
@@ -0,0 +1,118 @@
+//! Holding writes back while the disk refuses them. When the detailed log
+//! or session storage can't be written (the disk is full, the drive went
+//! read-only or away), recording carries on: detailed events and finished
+//! sessions are kept in memory and written once writing works again, or
+//! once another location is chosen.
+//!
+//! Finished sessions are always kept. Detailed events are kept up to
+//! `SpoolConfig::max_events`, dropping the oldest beyond that; they're only
+//! the running session's log, and its events are in the session too.
+
+use crate::types::{DetailedEvent, Session};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpoolConfig {
+    /// Detailed events held at most while writes fail.
+    pub max_events: usize,
+    /// Seconds between attempts to write again.
+    pub retry_secs: u64,
+}
+
+impl Default for SpoolConfig {
+    fn default() -> Self {
+        Self {
+            max_events: 100_000,
+            retry_secs: 5,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Spool {
+    events: VecDeque<DetailedEvent>,
+    sessions: Vec<Session>,
+    /// Detailed events dropped over the cap since writing last worked.
+    dropped: u64,
+    /// Why the last write failed, while writes are held back.
+    error: Option<String>,
+    next_retry: Option<Instant>,
+}
+
+impl Spool {
+    /// Whether writes are being held back.
+    pub fn is_holding(&self) -> bool {
+        self.error.is_some()
+    }
+
+    /// Notes that a write failed with `error`: writes are held back until
+    /// the next retry works.
+    pub fn failed(&mut self, error: impl Display, config: &SpoolConfig) {
+        self.error = Some(error.to_string());
+        self.next_retry = Some(Instant::now() + Duration::from_secs(config.retry_secs.max(1)));
+    }
+
+    /// Whether it's time to try writing again.
+    pub fn retry_due(&self) -> bool {
+        self.is_holding() && self.next_retry.is_none_or(|at| Instant::now() >= at)
+    }
+
+    pub fn hold_event(&mut self, event: DetailedEvent, config: &SpoolConfig) {
+        self.events.push_back(event);
+        while self.events.len() > config.max_events {
+            self.events.pop_front();
+            self.dropped += 1;
+        }
+    }
+
+    pub fn hold_session(&mut self, session: Session) {
+        self.sessions.push(session);
+    }
+
+    /// Held detailed events, oldest first.
+    pub fn events(&self) -> impl Iterator<Item = &DetailedEvent> {
+        self.events.iter()
+    }
+
+    /// Forgets held detailed events, once written or once the log they
+    /// belong to is started over.
+    pub fn clear_events(&mut self) {
+        self.events.clear();
+        self.dropped = 0;
+    }
+
+    /// Takes the held sessions, oldest first, to write them.
+    pub fn take_sessions(&mut self) -> Vec<Session> {
+        std::mem::take(&mut self.sessions)
+    }
+
+    /// Notes that everything held was written.
+    pub fn recovered(&mut self) {
+        self.error = None;
+        self.next_retry = None;
+    }
+
+    /// What's wrong and what's held, for an error banner, while writes are
+    /// held back.
+    pub fn problem(&self) -> Option<String> {
+        let error = self.error.as_ref()?;
+        let mut problem = format!(
+            "Can't write data ({}). Holding {} events and {} sessions in memory",
+            error,
+            self.events.len(),
+            self.sessions.len()
+        );
+        if self.dropped > 0 {
+            problem.push_str(&format!(
+                "; {} older events were dropped from the detailed log",
+                self.dropped
+            ));
+        }
+        problem.push('.');
+        Some(problem)
+    }
+}
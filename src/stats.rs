@@ -0,0 +1,165 @@
+use crate::analysis;
+use crate::config::StatsConfig;
+use crate::storage::STATS_FILE;
+use crate::types::Session;
+use anyhow::Result;
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+/// Streaks, personal bests and earned badges, derived from the stored
+/// sessions and kept in [`STATS_FILE`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stats {
+    pub current_streak: u32,
+    pub longest_streak: u32,
+    /// Longest single session, in seconds.
+    pub longest_focus_secs: i64,
+    pub longest_focus_task: Option<String>,
+    /// Most tracked time in one day, in seconds.
+    pub best_day_secs: i64,
+    pub badges: Vec<Badge>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Badge {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub earned_on: NaiveDate,
+}
+
+struct BadgeRule {
+    id: &'static str,
+    name: &'static str,
+    description: &'static str,
+    earned: fn(&Stats, usize) -> bool,
+}
+
+const BADGES: &[BadgeRule] = &[
+    BadgeRule {
+        id: "first_session",
+        name: "First steps",
+        description: "Recorded a first session",
+        earned: |_, sessions| sessions > 0,
+    },
+    BadgeRule {
+        id: "streak_3",
+        name: "On a roll",
+        description: "3-day streak",
+        earned: |stats, _| stats.longest_streak >= 3,
+    },
+    BadgeRule {
+        id: "streak_7",
+        name: "Week strong",
+        description: "7-day streak",
+        earned: |stats, _| stats.longest_streak >= 7,
+    },
+    BadgeRule {
+        id: "streak_30",
+        name: "Habit formed",
+        description: "30-day streak",
+        earned: |stats, _| stats.longest_streak >= 30,
+    },
+    BadgeRule {
+        id: "deep_focus",
+        name: "Deep focus",
+        description: "A single session of an hour or more",
+        earned: |stats, _| stats.longest_focus_secs >= 3600,
+    },
+    BadgeRule {
+        id: "marathon",
+        name: "Marathon",
+        description: "4 hours tracked in one day",
+        earned: |stats, _| stats.best_day_secs >= 4 * 3600,
+    },
+    BadgeRule {
+        id: "sessions_100",
+        name: "Centurion",
+        description: "100 sessions recorded",
+        earned: |_, sessions| sessions >= 100,
+    },
+];
+
+impl Stats {
+    /// Computes stats from `sessions`. Badges already in `previous` keep
+    /// their original date and are never taken away, so compacted or
+    /// deleted history doesn't revoke them.
+    pub fn compute(
+        sessions: &[Session],
+        config: &StatsConfig,
+        today: NaiveDate,
+        previous: &Stats,
+    ) -> Self {
+        let min_secs = i64::from(config.streak_min_minutes) * 60;
+        let days = analysis::active_days(sessions, min_secs);
+
+        let mut stats = Stats {
+            current_streak: analysis::current_streak(&days, today),
+            longest_streak: analysis::longest_streak(&days).max(previous.longest_streak),
+            best_day_secs: analysis::daily_totals(sessions)
+                .iter()
+                .map(|total| total.tracked_secs)
+                .max()
+                .unwrap_or(0)
+                .max(previous.best_day_secs),
+            ..Default::default()
+        };
+
+        let longest = sessions
+            .iter()
+            .filter_map(|s| s.duration().map(|d| (d.num_seconds(), s)))
+            .max_by_key(|(secs, _)| *secs);
+        match longest {
+            Some((secs, session)) if secs > previous.longest_focus_secs => {
+                stats.longest_focus_secs = secs;
+                stats.longest_focus_task = Some(session.task_name.clone());
+            }
+            _ => {
+                stats.longest_focus_secs = previous.longest_focus_secs;
+                stats.longest_focus_task = previous.longest_focus_task.clone();
+            }
+        }
+
+        stats.badges = previous.badges.clone();
+        for rule in BADGES {
+            if stats.badges.iter().any(|b| b.id == rule.id) {
+                continue;
+            }
+            if (rule.earned)(&stats, sessions.len()) {
+                stats.badges.push(Badge {
+                    id: rule.id.to_string(),
+                    name: rule.name.to_string(),
+                    description: rule.description.to_string(),
+                    earned_on: today,
+                });
+            }
+        }
+        stats
+    }
+
+    /// Loads the stats file, falling back to empty stats if it doesn't
+    /// exist.
+    pub fn load() -> Result<Self> {
+        match fs::read_to_string(STATS_FILE) {
+            Ok(contents) => Ok(serde_json::from_str(&contents)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        fs::write(STATS_FILE, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Recomputes stats from `sessions` on top of the stored ones and saves
+/// the result.
+pub fn update(sessions: &[Session], config: &StatsConfig) -> Result<Stats> {
+    let previous = Stats::load()?;
+    let stats = Stats::compute(sessions, config, Local::now().date_naive(), &previous);
+    stats.save()?;
+    Ok(stats)
+}
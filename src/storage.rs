@@ -0,0 +1,613 @@
+use crate::export::JsonSession;
+use crate::types::Session;
+use anyhow::Result;
+use chrono::Datelike;
+use csv::{ReaderBuilder, Writer};
+use std::{
+    fs::{self, File, OpenOptions},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+pub const SESSIONS_FILE: &str = "monitoring_sessions.csv";
+pub const DETAILS_FILE: &str = "latest_session_details.csv";
+pub const ROLLUPS_FILE: &str = "activity_rollups.csv";
+pub const STATS_FILE: &str = "activity_stats.json";
+
+pub const SESSION_HEADERS: [&str; 13] = [
+    "session_id",
+    "task_name",
+    "start_time",
+    "end_time",
+    "actions",
+    "notes",
+    "mouse_sample_rate",
+    "machine_id",
+    "quality_flags",
+    "density",
+    "profile",
+    "consent",
+    "metadata",
+];
+
+/// Where an `ActivityMonitor` keeps finished sessions. The CSV backends are
+/// always built; [`JsonlStorage`] and [`SqliteStorage`] need the `jsonl` and
+/// `sqlite` features. Other backends (a database server, say) can be added
+/// outside this crate and passed to `ActivityMonitor::with_storage`.
+pub trait Storage {
+    /// Stores a finished session after the ones already stored.
+    fn append(&mut self, session: &Session) -> Result<()>;
+    /// Reads every stored session, oldest first.
+    fn load(&self) -> Result<Vec<Session>>;
+    /// Replaces the stored copy of `session`, matched by ID and start time.
+    fn replace(&mut self, session: &Session) -> Result<()>;
+    /// Deletes the stored copy of `session`, matched like [`Storage::replace`].
+    fn remove(&mut self, session: &Session) -> Result<()>;
+    /// Stores sessions in a file (or folder) of the same name under `dir`
+    /// from now on, e.g. when the disk it was on fails. Sessions stored
+    /// before stay where they were.
+    fn relocate(&mut self, dir: &Path) -> Result<()>;
+}
+
+/// `path`'s file name under `dir`.
+fn rerooted(path: &Path, dir: &Path) -> PathBuf {
+    dir.join(path.file_name().unwrap_or(path.as_os_str()))
+}
+
+/// Sessions as rows of a CSV file with [`SESSION_HEADERS`], the format of
+/// `monitoring_sessions.csv`.
+pub struct CsvStorage {
+    path: PathBuf,
+    writer: Writer<File>,
+}
+
+impl CsvStorage {
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let writer = open_sessions_writer(&path)?;
+        Ok(Self { path, writer })
+    }
+}
+
+impl Storage for CsvStorage {
+    fn append(&mut self, session: &Session) -> Result<()> {
+        self.writer.write_record(session.to_csv_record())?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    fn load(&self) -> Result<Vec<Session>> {
+        load_sessions(&self.path)
+    }
+
+    fn replace(&mut self, session: &Session) -> Result<()> {
+        replace_session(&self.path, session)?;
+        // The file was replaced, so the old handle appends to nothing.
+        self.writer = open_sessions_writer(&self.path)?;
+        Ok(())
+    }
+
+    fn remove(&mut self, session: &Session) -> Result<()> {
+        remove_session(&self.path, session)?;
+        self.writer = open_sessions_writer(&self.path)?;
+        Ok(())
+    }
+
+    fn relocate(&mut self, dir: &Path) -> Result<()> {
+        *self = Self::open(rerooted(&self.path, dir))?;
+        Ok(())
+    }
+}
+
+/// Sessions as JSON lines, one object per session in the shape of JSON
+/// exports. Needs the `jsonl` feature.
+#[cfg(feature = "jsonl")]
+pub struct JsonlStorage {
+    path: PathBuf,
+    file: File,
+}
+
+#[cfg(feature = "jsonl")]
+impl JsonlStorage {
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(Self { path, file })
+    }
+
+    /// Rewrites the file with the line of `session` replaced by
+    /// `replacement`, or dropped if that's `None`.
+    fn rewrite(&mut self, session: &Session, replacement: Option<&Session>) -> Result<()> {
+        let mut lines = Vec::new();
+        let mut found = false;
+        for line in fs::read_to_string(&self.path)?.lines() {
+            let matches = serde_json::from_str::<JsonSession>(line)
+                .map(JsonSession::into_session)
+                .is_ok_and(|stored| {
+                    stored.session_id == session.session_id
+                        && stored.start_time == session.start_time
+                });
+            if matches && !found {
+                if let Some(replacement) = replacement {
+                    lines.push(serde_json::to_string(&JsonSession::from(replacement))?);
+                }
+                found = true;
+            } else {
+                lines.push(line.to_string());
+            }
+        }
+        if !found {
+            anyhow::bail!(
+                "session {} not found in {}",
+                session.session_id,
+                self.path.display()
+            );
+        }
+
+        let tmp_path = self.path.with_extension("jsonl.tmp");
+        fs::write(&tmp_path, lines.join("\n") + "\n")?;
+        fs::rename(&tmp_path, &self.path)?;
+        self.file = OpenOptions::new().append(true).open(&self.path)?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "jsonl")]
+impl Storage for JsonlStorage {
+    fn append(&mut self, session: &Session) -> Result<()> {
+        use std::io::Write;
+
+        let line = serde_json::to_string(&JsonSession::from(session))?;
+        writeln!(self.file, "{}", line)?;
+        self.file.flush()?;
+        Ok(())
+    }
+
+    fn load(&self) -> Result<Vec<Session>> {
+        load_jsonl(&self.path)
+    }
+
+    fn replace(&mut self, session: &Session) -> Result<()> {
+        self.rewrite(session, Some(session))
+    }
+
+    fn remove(&mut self, session: &Session) -> Result<()> {
+        self.rewrite(session, None)
+    }
+
+    fn relocate(&mut self, dir: &Path) -> Result<()> {
+        *self = Self::open(rerooted(&self.path, dir))?;
+        Ok(())
+    }
+}
+
+/// Reads a JSON-lines sessions file, skipping lines that can't be parsed.
+#[cfg(feature = "jsonl")]
+pub fn load_jsonl(path: impl AsRef<Path>) -> Result<Vec<Session>> {
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .filter_map(|line| serde_json::from_str::<JsonSession>(line).ok())
+        .map(JsonSession::into_session)
+        .collect())
+}
+
+/// Sessions in a SQLite database with the schema of `.sqlite` exports
+/// ([`crate::export::SQLITE_SCHEMA`]), created on first use. Needs the
+/// `sqlite` feature.
+#[cfg(feature = "sqlite")]
+pub struct SqliteStorage {
+    path: PathBuf,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteStorage {
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let conn = rusqlite::Connection::open(&path)?;
+        let tables: i64 = conn.query_row(
+            "SELECT count(*) FROM sqlite_master WHERE name = 'sessions'",
+            [],
+            |row| row.get(0),
+        )?;
+        if tables == 0 {
+            conn.execute_batch(crate::export::SQLITE_SCHEMA)?;
+        } else {
+            let version = crate::export::sqlite_version(&conn)?;
+            if version < 3 {
+                conn.execute_batch(
+                    "ALTER TABLE sessions ADD COLUMN metadata TEXT; PRAGMA user_version = 3;",
+                )?;
+            }
+            if version < 4 {
+                conn.execute_batch(
+                    "ALTER TABLE events ADD COLUMN trial TEXT; PRAGMA user_version = 4;",
+                )?;
+            }
+        }
+        Ok(Self { path })
+    }
+
+    /// Inserts `session` as row `id` (the next free one if `None`) inside a
+    /// transaction, after deleting whatever row `id` held.
+    fn write(&self, id: Option<i64>, session: &Session) -> Result<()> {
+        use crate::export::{insert_sqlite_session, INSERT_EVENT_SQL, INSERT_SESSION_SQL};
+
+        let mut conn = rusqlite::Connection::open(&self.path)?;
+        // Rolled back if dropped before the commit.
+        let tx = conn.transaction()?;
+        let id = match id {
+            Some(id) => {
+                tx.execute("DELETE FROM events WHERE session = ?1", [id])?;
+                tx.execute("DELETE FROM sessions WHERE id = ?1", [id])?;
+                id
+            }
+            None => tx.query_row("SELECT COALESCE(MAX(id), 0) + 1 FROM sessions", [], |row| {
+                row.get(0)
+            })?,
+        };
+        {
+            let mut insert_session = tx.prepare(INSERT_SESSION_SQL)?;
+            let mut insert_event = tx.prepare(INSERT_EVENT_SQL)?;
+            insert_sqlite_session(&mut insert_session, &mut insert_event, id, session)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Row ID of the stored copy of `session`.
+    fn id(&self, session: &Session) -> Result<i64> {
+        use rusqlite::OptionalExtension;
+
+        let conn = rusqlite::Connection::open_with_flags(
+            &self.path,
+            rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY,
+        )?;
+        let id = conn
+            .query_row(
+                "SELECT id FROM sessions WHERE session_id = ?1 AND start_time = ?2 ORDER BY id",
+                [&session.session_id, &session.start_time],
+                |row| row.get(0),
+            )
+            .optional()?;
+        id.ok_or_else(|| {
+            anyhow::anyhow!(
+                "session {} not found in {}",
+                session.session_id,
+                self.path.display()
+            )
+        })
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl Storage for SqliteStorage {
+    fn append(&mut self, session: &Session) -> Result<()> {
+        self.write(None, session)
+    }
+
+    fn load(&self) -> Result<Vec<Session>> {
+        crate::export::read_sqlite(&self.path)
+    }
+
+    fn replace(&mut self, session: &Session) -> Result<()> {
+        let id = self.id(session)?;
+        self.write(Some(id), session)
+    }
+
+    fn remove(&mut self, session: &Session) -> Result<()> {
+        let id = self.id(session)?;
+        let mut conn = rusqlite::Connection::open(&self.path)?;
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM events WHERE session = ?1", [id])?;
+        tx.execute("DELETE FROM sessions WHERE id = ?1", [id])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn relocate(&mut self, dir: &Path) -> Result<()> {
+        *self = Self::open(rerooted(&self.path, dir))?;
+        Ok(())
+    }
+}
+
+/// Sessions as one sessions CSV each, in a folder per ISO week:
+/// `<root>/<year>/W<week>/<start>_<elapsed>.csv`, e.g.
+/// `data/2024/W03/2024-01-20T123456_PT25M10S.csv`. The start is local time
+/// and the elapsed time an ISO 8601 duration, so files sort by start and
+/// long sessions stand out in a listing.
+pub struct FolderStorage {
+    root: PathBuf,
+}
+
+impl FolderStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// The week folder `session` is kept in.
+    pub fn week_dir(&self, session: &Session) -> Result<PathBuf> {
+        let Some(start) = session.started_at() else {
+            anyhow::bail!("session {} has no valid start time", session.session_id);
+        };
+        let week = start.iso_week();
+        Ok(self
+            .root
+            .join(week.year().to_string())
+            .join(format!("W{:02}", week.week())))
+    }
+
+    /// Sessions files in `dir`, by name.
+    fn files(dir: &Path) -> Result<Vec<PathBuf>> {
+        let mut files: Vec<PathBuf> = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| extension(p).as_deref() == Some("csv"))
+            .collect();
+        files.sort();
+        Ok(files)
+    }
+
+    /// Week folders under the root, oldest first.
+    fn week_dirs(&self) -> Result<Vec<PathBuf>> {
+        if !self.root.exists() {
+            return Ok(Vec::new());
+        }
+        let mut dirs = Vec::new();
+        for year in fs::read_dir(&self.root)? {
+            let year = year?.path();
+            if year.is_dir() {
+                dirs.extend(
+                    fs::read_dir(&year)?
+                        .filter_map(|entry| entry.ok().map(|e| e.path()))
+                        .filter(|p| p.is_dir()),
+                );
+            }
+        }
+        dirs.sort();
+        Ok(dirs)
+    }
+
+    /// The file holding the stored copy of `session`.
+    fn file(&self, session: &Session) -> Result<PathBuf> {
+        let dir = self.week_dir(session)?;
+        let files = if dir.exists() {
+            Self::files(&dir)?
+        } else {
+            Vec::new()
+        };
+        for file in files {
+            let stored = load_sessions(&file)?.iter().any(|stored| {
+                stored.session_id == session.session_id && stored.start_time == session.start_time
+            });
+            if stored {
+                return Ok(file);
+            }
+        }
+        anyhow::bail!(
+            "session {} not found in {}",
+            session.session_id,
+            dir.display()
+        )
+    }
+}
+
+impl Storage for FolderStorage {
+    fn append(&mut self, session: &Session) -> Result<()> {
+        let dir = self.week_dir(session)?;
+        fs::create_dir_all(&dir)?;
+        let mut name = session
+            .started_at()
+            .map_or_else(String::new, |t| t.format("%Y-%m-%dT%H%M%S").to_string());
+        if let Some(elapsed) = session.duration() {
+            name.push('_');
+            name.push_str(&iso_duration(elapsed.num_seconds()));
+        }
+        let mut path = dir.join(format!("{}.csv", name));
+        // Sessions started within the same second get a file each.
+        let mut n = 1;
+        while path.exists() {
+            n += 1;
+            path = dir.join(format!("{}_{}.csv", name, n));
+        }
+        let mut writer = open_sessions_writer(&path)?;
+        writer.write_record(session.to_csv_record())?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn load(&self) -> Result<Vec<Session>> {
+        let mut sessions = Vec::new();
+        for dir in self.week_dirs()? {
+            for file in Self::files(&dir)? {
+                sessions.extend(load_sessions(&file)?);
+            }
+        }
+        Ok(sessions)
+    }
+
+    fn replace(&mut self, session: &Session) -> Result<()> {
+        replace_session(self.file(session)?, session)
+    }
+
+    /// Deletes the session's file along with it, if that leaves it empty.
+    fn remove(&mut self, session: &Session) -> Result<()> {
+        let file = self.file(session)?;
+        remove_session(&file, session)?;
+        if load_sessions(&file)?.is_empty() {
+            fs::remove_file(&file)?;
+        }
+        Ok(())
+    }
+
+    fn relocate(&mut self, dir: &Path) -> Result<()> {
+        self.root = rerooted(&self.root, dir);
+        Ok(())
+    }
+}
+
+/// `secs` as an ISO 8601 duration, e.g. `PT1H5M0S`.
+fn iso_duration(secs: i64) -> String {
+    let secs = secs.max(0);
+    let (hours, minutes) = (secs / 3600, secs % 3600 / 60);
+    let mut duration = String::from("PT");
+    if hours > 0 {
+        duration.push_str(&format!("{}H", hours));
+    }
+    if hours > 0 || minutes > 0 {
+        duration.push_str(&format!("{}M", minutes));
+    }
+    duration.push_str(&format!("{}S", secs % 60));
+    duration
+}
+
+/// Opens the sessions file for appending, writing the header row if the file
+/// is new so history is kept across runs.
+pub fn open_sessions_writer(path: impl AsRef<Path>) -> Result<Writer<File>> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let is_new = file.metadata()?.len() == 0;
+
+    let mut writer = Writer::from_writer(file);
+    if is_new {
+        writer.write_record(SESSION_HEADERS)?;
+        writer.flush()?;
+    }
+    Ok(writer)
+}
+
+/// Reads every stored session, skipping rows that can't be parsed.
+pub fn load_sessions(path: impl AsRef<Path>) -> Result<Vec<Session>> {
+    let mut reader = ReaderBuilder::new().flexible(true).from_path(path)?;
+    if reader.headers()?.get(0) != Some(SESSION_HEADERS[0]) {
+        anyhow::bail!("not a sessions file (missing session_id column)");
+    }
+
+    let mut sessions = Vec::new();
+    for record in reader.records() {
+        if let Some(session) = Session::from_csv_record(&record?) {
+            sessions.push(session);
+        }
+    }
+    Ok(sessions)
+}
+
+/// Rewrites the sessions file at `path` with the stored row of `session`
+/// (matched by ID and start time) replaced. Other rows are copied through
+/// untouched. Must not run while an `ActivityMonitor` holds the file open.
+pub fn replace_session(path: impl AsRef<Path>, session: &Session) -> Result<()> {
+    rewrite_session(path.as_ref(), session, Some(session))
+}
+
+/// Rewrites the sessions file at `path` without the stored row of
+/// `session`, like [`replace_session`].
+pub fn remove_session(path: impl AsRef<Path>, session: &Session) -> Result<()> {
+    rewrite_session(path.as_ref(), session, None)
+}
+
+/// Rewrites the sessions file at `path` with the row of `session` replaced
+/// by `replacement`, or dropped if that's `None`.
+fn rewrite_session(path: &Path, session: &Session, replacement: Option<&Session>) -> Result<()> {
+    let mut reader = ReaderBuilder::new().flexible(true).from_path(path)?;
+    let tmp_path = path.with_extension("csv.tmp");
+    let mut writer = csv::WriterBuilder::new()
+        .flexible(true)
+        .from_path(&tmp_path)?;
+    writer.write_record(SESSION_HEADERS)?;
+
+    let mut found = false;
+    for record in reader.records() {
+        let record = record?;
+        if record.get(0) == Some(session.session_id.as_str())
+            && record.get(2) == Some(session.start_time.as_str())
+        {
+            if let Some(replacement) = replacement {
+                writer.write_record(replacement.to_csv_record())?;
+            }
+            found = true;
+        } else {
+            writer.write_record(&record)?;
+        }
+    }
+    writer.flush()?;
+    drop(writer);
+
+    if !found {
+        fs::remove_file(&tmp_path)?;
+        anyhow::bail!(
+            "session {} not found in {}",
+            session.session_id,
+            path.display()
+        );
+    }
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+/// Loads sessions from an exported dataset: a sessions CSV, a JSON export, a
+/// `.jsonl` file (with the `jsonl` feature), a `.sqlite` export (with the
+/// `sqlite` feature), or a directory or ZIP archive containing any number of
+/// CSV and JSON exports, in it or in folders under it (like the week folders
+/// of [`FolderStorage`]).
+pub fn load_dataset(path: impl AsRef<Path>) -> Result<Vec<Session>> {
+    let path = path.as_ref();
+    if path.is_dir() {
+        let mut entries: Vec<_> = fs::read_dir(path)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| p.is_dir() || matches!(extension(p).as_deref(), Some("csv" | "json")))
+            .collect();
+        entries.sort();
+
+        let mut sessions = Vec::new();
+        for entry in entries {
+            match load_dataset(&entry) {
+                Ok(loaded) => sessions.extend(loaded),
+                Err(e) => eprintln!("Skipping {}: {}", entry.display(), e),
+            }
+        }
+        return Ok(sessions);
+    }
+
+    match extension(path).as_deref() {
+        Some("json") => {
+            let records: Vec<JsonSession> = serde_json::from_str(&fs::read_to_string(path)?)?;
+            Ok(records.into_iter().map(JsonSession::into_session).collect())
+        }
+        #[cfg(feature = "jsonl")]
+        Some("jsonl") => load_jsonl(path),
+        #[cfg(not(feature = "jsonl"))]
+        Some("jsonl") => {
+            anyhow::bail!("reading .jsonl files needs a build with `--features jsonl`")
+        }
+        #[cfg(feature = "sqlite")]
+        Some("sqlite" | "db") => crate::export::read_sqlite(path),
+        #[cfg(not(feature = "sqlite"))]
+        Some("sqlite" | "db") => {
+            anyhow::bail!("reading .sqlite exports needs a build with `--features sqlite`")
+        }
+        Some("xlsx") => anyhow::bail!("XLSX exports can't be read back; use a CSV or JSON export"),
+        Some("zip") => load_zip(path),
+        _ => load_sessions(path),
+    }
+}
+
+/// Extracts the ZIP archive at `path` to a scratch folder and loads that.
+fn load_zip(path: &Path) -> Result<Vec<Session>> {
+    static EXTRACTED: AtomicUsize = AtomicUsize::new(0);
+
+    let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+    let dir = std::env::temp_dir().join(format!(
+        "desk-monitor-zip-{}-{}",
+        std::process::id(),
+        EXTRACTED.fetch_add(1, Ordering::Relaxed)
+    ));
+    // Entries naming paths outside `dir` are refused.
+    let sessions = archive
+        .extract(&dir)
+        .map_err(anyhow::Error::from)
+        .and_then(|()| load_dataset(&dir));
+    let _ = fs::remove_dir_all(&dir);
+    sessions
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+}
@@ -0,0 +1,87 @@
+//! Sync markers for lining recordings up with other devices (eye trackers,
+//! EEG amplifiers) afterwards. A marker is sent to the other device at the
+//! same moment it's recorded here, and carries a counter plus the
+//! wall-clock and monotonic times it was taken at, read back to back, so
+//! either clock can be matched against the other stream.
+//!
+//! Markers are `sync` custom events in the running session.
+
+use crate::types::{Action, Session};
+use chrono::{Local, SecondsFormat};
+use serde::{Deserialize, Serialize};
+
+/// Custom event kind markers are recorded under.
+pub const EVENT_KIND: &str = "sync";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncConfig {
+    /// Chord that records a marker while recording, e.g. `Ctrl+Alt+S`.
+    /// Empty turns it off.
+    pub hotkey: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncMarker {
+    /// Counts up from 1 for the life of the monitor, across sessions, so
+    /// a marker can be told apart from its neighbours on the other device.
+    pub counter: u64,
+    /// Local time with nanoseconds.
+    pub wall_time: String,
+    /// See [`monotonic_ns`].
+    pub monotonic_ns: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+impl SyncMarker {
+    pub fn now(counter: u64, label: Option<String>) -> Self {
+        let wall_time = Local::now().to_rfc3339_opts(SecondsFormat::Nanos, false);
+        Self {
+            counter,
+            wall_time,
+            monotonic_ns: monotonic_ns(),
+            label: label
+                .map(|l| l.trim().to_string())
+                .filter(|l| !l.is_empty()),
+        }
+    }
+}
+
+/// Nanoseconds on the system's monotonic clock. On Unix that's
+/// `CLOCK_MONOTONIC`, which other acquisition software on the machine can
+/// read as well; elsewhere it counts from the first call in this process.
+#[cfg(unix)]
+pub fn monotonic_ns() -> u64 {
+    let mut time = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    unsafe {
+        libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut time);
+    }
+    time.tv_sec as u64 * 1_000_000_000 + time.tv_nsec as u64
+}
+
+#[cfg(not(unix))]
+pub fn monotonic_ns() -> u64 {
+    use std::sync::OnceLock;
+    use std::time::Instant;
+
+    static START: OnceLock<Instant> = OnceLock::new();
+    START.get_or_init(Instant::now).elapsed().as_nanos() as u64
+}
+
+/// The sync markers recorded in `session`, oldest first.
+pub fn markers(session: &Session) -> Vec<SyncMarker> {
+    session
+        .actions
+        .iter()
+        .filter_map(|action| match action {
+            Action::Custom { kind, payload, .. } if kind == EVENT_KIND => {
+                serde_json::from_value(payload.clone()).ok()
+            }
+            _ => None,
+        })
+        .collect()
+}
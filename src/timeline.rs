@@ -0,0 +1,206 @@
+//! The layers drawn along a session's time axis: markers (task switches,
+//! trial marks, sync markers, breaks taken), idle gaps, pauses and which
+//! window had focus. Built once per session and drawn by the viewer's
+//! timeline, by playback and by session pages, so they all agree on what
+//! happened when.
+//!
+//! Times are milliseconds since the session started, as in playback.
+
+use crate::analysis::{self, PAUSE_KIND, RESUME_KIND};
+use crate::sync;
+use crate::types::{Action, Session};
+use serde::Serialize;
+
+/// Gaps between events at least this long are idle.
+pub const IDLE_GAP_MS: i64 = 60_000;
+
+/// Layers of bands, in the order they're drawn from the top.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Layer {
+    /// A window had keyboard focus.
+    Focus,
+    /// Nothing was recorded for at least [`IDLE_GAP_MS`].
+    Idle,
+    /// Recording was paused.
+    Paused,
+}
+
+impl Layer {
+    pub const ALL: [Layer; 3] = [Layer::Focus, Layer::Idle, Layer::Paused];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Layer::Focus => "Focus",
+            Layer::Idle => "Idle",
+            Layer::Paused => "Paused",
+        }
+    }
+}
+
+/// A stretch of time in one layer.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Band {
+    pub layer: Layer,
+    pub from_ms: i64,
+    pub until_ms: i64,
+    pub label: String,
+}
+
+/// A moment worth pointing out.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Marker {
+    pub offset_ms: i64,
+    pub label: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Timeline {
+    /// To the session's end, or its last event if that's later.
+    pub duration_ms: i64,
+    pub markers: Vec<Marker>,
+    /// Ordered by start within each layer.
+    pub bands: Vec<Band>,
+}
+
+impl Timeline {
+    pub fn new(session: &Session) -> Self {
+        let mut timeline = Self::default();
+        let mut focus: Option<(i64, String)> = None;
+        let mut paused_at: Option<i64> = None;
+        let mut last_event: Option<i64> = None;
+        let mut idle = Vec::new();
+        let mut paused = Vec::new();
+
+        for action in session.ordered_actions() {
+            let offset = analysis::offset_ms(session, action.timestamp());
+            let resumed = matches!(&action, Action::Custom { kind, .. } if kind == RESUME_KIND);
+            // The time between a pause and its resume is a pause, not idle.
+            if let Some(last) = last_event.filter(|_| !resumed) {
+                if offset - last >= IDLE_GAP_MS {
+                    idle.push(band(Layer::Idle, last, offset, "No activity"));
+                }
+            }
+            last_event = Some(offset.max(last_event.unwrap_or(0)));
+
+            match &action {
+                Action::FocusChange {
+                    window, title, app, ..
+                } => {
+                    if let Some((from, label)) = focus.take() {
+                        timeline
+                            .bands
+                            .push(band(Layer::Focus, from, offset, &label));
+                    }
+                    let label = title
+                        .clone()
+                        .or_else(|| app.clone())
+                        .unwrap_or_else(|| format!("Window {}", window));
+                    focus = Some((offset, label));
+                }
+                Action::TaskSwitch { to, .. } => timeline.marker(offset, format!("Task {}", to)),
+                Action::BreakTaken { kind, .. } => {
+                    timeline.marker(offset, format!("Back from {}", kind.replace('_', " ")))
+                }
+                Action::Custom { kind, .. } if kind == PAUSE_KIND => {
+                    paused_at.get_or_insert(offset);
+                }
+                Action::Custom { .. } if resumed => {
+                    if let Some(from) = paused_at.take() {
+                        paused.push(band(Layer::Paused, from, offset, "Paused"));
+                    }
+                }
+                Action::Custom { kind, payload, .. } if kind == sync::EVENT_KIND => {
+                    let counter = payload.get("counter").and_then(|c| c.as_u64());
+                    let label = payload.get("label").and_then(|l| l.as_str());
+                    let text = match (counter, label) {
+                        (Some(counter), Some(label)) => format!("Sync {} ({})", counter, label),
+                        (Some(counter), None) => format!("Sync {}", counter),
+                        _ => "Sync".to_string(),
+                    };
+                    timeline.marker(offset, text);
+                }
+                Action::Custom { .. } => {
+                    if let Some((start, trial_id)) = analysis::trial_marker(&action) {
+                        let edge = if start { "starts" } else { "ends" };
+                        timeline.marker(offset, format!("Trial {} {}", trial_id, edge));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let end = session
+            .end_time
+            .as_deref()
+            .map_or(0, |end| analysis::offset_ms(session, end));
+        timeline.duration_ms = end.max(last_event.unwrap_or(0));
+        let duration = timeline.duration_ms;
+        if let Some((from, label)) = focus {
+            timeline
+                .bands
+                .push(band(Layer::Focus, from, duration, &label));
+        }
+        // A session stopped while paused was paused to the end; otherwise
+        // the time since the last event may have been idle.
+        match (paused_at, last_event) {
+            (Some(from), _) => paused.push(band(Layer::Paused, from, duration, "Paused")),
+            (None, Some(last)) if duration - last >= IDLE_GAP_MS => {
+                idle.push(band(Layer::Idle, last, duration, "No activity"))
+            }
+            _ => {}
+        }
+        timeline.bands.extend(idle);
+        timeline.bands.extend(paused);
+        // Clock changes can put timestamps out of order.
+        timeline.markers.sort_by_key(|marker| marker.offset_ms);
+        timeline
+    }
+
+    fn marker(&mut self, offset_ms: i64, label: String) {
+        self.markers.push(Marker { offset_ms, label });
+    }
+
+    pub fn bands(&self, layer: Layer) -> impl Iterator<Item = &Band> {
+        self.bands.iter().filter(move |band| band.layer == layer)
+    }
+
+    /// What was going on at `offset_ms`: the bands covering it, then the
+    /// markers within `within_ms` of it, for hover text.
+    pub fn describe_at(&self, offset_ms: i64, within_ms: i64) -> Vec<String> {
+        let bands = self
+            .bands
+            .iter()
+            .filter(|band| (band.from_ms..band.until_ms).contains(&offset_ms))
+            .map(|band| {
+                format!(
+                    "{}: {} ({} – {})",
+                    band.layer.as_str(),
+                    band.label,
+                    analysis::format_offset(band.from_ms),
+                    analysis::format_offset(band.until_ms)
+                )
+            });
+        let markers = self
+            .markers
+            .iter()
+            .filter(|marker| (marker.offset_ms - offset_ms).abs() <= within_ms)
+            .map(|marker| {
+                format!(
+                    "{} {}",
+                    analysis::format_offset(marker.offset_ms),
+                    marker.label
+                )
+            });
+        bands.chain(markers).collect()
+    }
+}
+
+fn band(layer: Layer, from_ms: i64, until_ms: i64, label: &str) -> Band {
+    Band {
+        layer,
+        from_ms,
+        until_ms,
+        label: label.to_string(),
+    }
+}
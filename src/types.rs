@@ -1,6 +1,12 @@
-use serde::Serialize;
+use crate::consent::ConsentRecord;
+use crate::quality::QualityFlag;
+use chrono::{DateTime, Duration, Local, SecondsFormat, SubsecRound};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum Action {
     MouseMove {
         timestamp: String,
@@ -10,9 +16,181 @@ pub enum Action {
         timestamp: String,
         keys: Vec<String>,
     },
+    /// Mouse `button` (`left`, `middle`, `right`, ...) went down at
+    /// `coords`.
+    MouseDown {
+        timestamp: String,
+        button: String,
+        coords: (i32, i32),
+    },
+    /// Mouse `button` came up at `coords` after being held for `hold_ms`.
+    MouseUp {
+        timestamp: String,
+        button: String,
+        coords: (i32, i32),
+        hold_ms: i64,
+    },
+    /// `button` was pressed and released at `coords` quickly and without
+    /// moving far, recorded right after the `MouseUp`. Longer or moving
+    /// presses (drags) get no click.
+    MouseClick {
+        timestamp: String,
+        button: String,
+        coords: (i32, i32),
+    },
+    /// The mouse cursor took `shape` (see `cursor::current_shape`), telling
+    /// what the pointer is over: text, a link, a window edge.
+    CursorChange { timestamp: String, shape: String },
+    /// Recording moved from task `from` to task `to` without stopping.
+    TaskSwitch {
+        timestamp: String,
+        from: String,
+        to: String,
+    },
+    /// A key chord recognized as a semantic action, e.g. `Ctrl+S` -> `save`.
+    Shortcut {
+        timestamp: String,
+        chord: String,
+        name: String,
+    },
+    /// The system clock jumped by `jump_ms` relative to monotonic time, or
+    /// the UTC offset changed (timezone/DST).
+    ClockChange {
+        timestamp: String,
+        jump_ms: i64,
+        utc_offset_secs: i32,
+    },
+    /// The active keyboard layout became `layout`. Also recorded at the
+    /// start of a session so every key press falls under a known layout.
+    LayoutChange { timestamp: String, layout: String },
+    /// Input capture failed at `timestamp` and was unavailable until
+    /// `resumed_at`, or until the session ended if that's `None`.
+    CaptureInterrupted {
+        timestamp: String,
+        reason: String,
+        resumed_at: Option<String>,
+    },
+    /// Capture didn't run for `gap_ms` from `timestamp` while recording (the
+    /// GUI stopped delivering frames, the machine was suspended), so nothing
+    /// was recorded in between.
+    CaptureGap { timestamp: String, gap_ms: i64 },
+    /// A `break` or `micro_break` reminder after `active_secs` of activity.
+    BreakReminder {
+        timestamp: String,
+        kind: String,
+        active_secs: i64,
+    },
+    /// Activity resumed after an idle gap long enough to count as a break
+    /// of `kind`; `reminded` says whether a reminder was pending.
+    BreakTaken {
+        timestamp: String,
+        kind: String,
+        idle_secs: i64,
+        reminded: bool,
+    },
+    /// Keyboard focus moved to `window` of `app` (its `WM_CLASS` class on
+    /// Linux). `bounds` is recorded when window geometry capture is on, and
+    /// `title`, after the redaction rules, when window title capture is.
+    FocusChange {
+        timestamp: String,
+        window: u64,
+        bounds: Option<WindowBounds>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        app: Option<String>,
+    },
+    /// Everything recorded from `timestamp` to `until` was deleted after the
+    /// session was saved.
+    Redacted { timestamp: String, until: String },
+    /// An event of an integration-defined `kind` (browser extension, IDE
+    /// plugin, ...), added with `ActivityMonitor::inject_event`.
+    Custom {
+        timestamp: String,
+        kind: String,
+        payload: serde_json::Value,
+    },
+}
+
+/// A window's position and size in global screen coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowBounds {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl WindowBounds {
+    /// Translates global screen coordinates into the window's own, with
+    /// `(0, 0)` at its top-left corner. `None` if the point is outside it.
+    pub fn to_local(&self, coords: (i32, i32)) -> Option<(i32, i32)> {
+        let (x, y) = (coords.0 - self.x, coords.1 - self.y);
+        let inside = (0..self.width as i32).contains(&x) && (0..self.height as i32).contains(&y);
+        inside.then_some((x, y))
+    }
+}
+
+/// Formats a recorded timestamp: RFC 3339 with milliseconds, the
+/// resolution `MousePath` stores, so events within the same second keep
+/// their order and timestamps with the same UTC offset sort as text.
+pub fn format_timestamp(time: DateTime<Local>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Millis, false)
+}
+
+/// A session ID for a session starting at `start`: a UUIDv7 (RFC 9562),
+/// whose leading 48 bits are the Unix time in milliseconds, so IDs sort by
+/// start, and whose remaining bits are random, so sessions started within
+/// the same millisecond (by the API, say) still get different ones.
+pub fn session_id(start: DateTime<Local>) -> String {
+    static FALLBACK: AtomicU64 = AtomicU64::new(0);
+
+    let mut bytes = [0u8; 16];
+    bytes[..6].copy_from_slice(&start.timestamp_millis().to_be_bytes()[2..]);
+    if getrandom::getrandom(&mut bytes[6..]).is_err() {
+        // Without an entropy source, a per-process count and the clock's
+        // nanoseconds keep IDs apart.
+        let count = FALLBACK.fetch_add(1, Ordering::Relaxed);
+        bytes[6..14].copy_from_slice(&count.to_be_bytes());
+        bytes[14..].copy_from_slice(&start.timestamp_subsec_nanos().to_be_bytes()[2..]);
+    }
+    bytes[6] = 0x70 | (bytes[6] & 0x0F);
+    bytes[8] = 0x80 | (bytes[8] & 0x3F);
+
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
 }
 
 impl Action {
+    pub fn timestamp(&self) -> &str {
+        match self {
+            Action::MouseMove { timestamp, .. }
+            | Action::KeyPress { timestamp, .. }
+            | Action::MouseDown { timestamp, .. }
+            | Action::MouseUp { timestamp, .. }
+            | Action::MouseClick { timestamp, .. }
+            | Action::CursorChange { timestamp, .. }
+            | Action::TaskSwitch { timestamp, .. }
+            | Action::Shortcut { timestamp, .. }
+            | Action::ClockChange { timestamp, .. }
+            | Action::LayoutChange { timestamp, .. }
+            | Action::CaptureInterrupted { timestamp, .. }
+            | Action::CaptureGap { timestamp, .. }
+            | Action::BreakReminder { timestamp, .. }
+            | Action::BreakTaken { timestamp, .. }
+            | Action::FocusChange { timestamp, .. }
+            | Action::Redacted { timestamp, .. }
+            | Action::Custom { timestamp, .. } => timestamp,
+        }
+    }
+
     pub fn to_csv_string(&self) -> String {
         match self {
             Action::MouseMove { timestamp, coords } => {
@@ -21,23 +199,708 @@ impl Action {
             Action::KeyPress { timestamp, keys } => {
                 format!("{{key,{},{:?}}}", timestamp, keys.join("+"))
             }
+            Action::MouseDown {
+                timestamp,
+                button,
+                coords,
+            } => {
+                format!(
+                    "{{mouse_down,{},({},{},{})}}",
+                    timestamp, button, coords.0, coords.1
+                )
+            }
+            Action::MouseUp {
+                timestamp,
+                button,
+                coords,
+                hold_ms,
+            } => {
+                format!(
+                    "{{mouse_up,{},({},{},{},{})}}",
+                    timestamp, button, coords.0, coords.1, hold_ms
+                )
+            }
+            Action::MouseClick {
+                timestamp,
+                button,
+                coords,
+            } => {
+                format!(
+                    "{{click,{},({},{},{})}}",
+                    timestamp, button, coords.0, coords.1
+                )
+            }
+            Action::CursorChange { timestamp, shape } => {
+                format!("{{cursor,{},{}}}", timestamp, shape)
+            }
+            Action::TaskSwitch {
+                timestamp,
+                from,
+                to,
+            } => {
+                let tasks = serde_json::to_string(&[from, to]).unwrap_or_default();
+                format!("{{switch,{},{}}}", timestamp, tasks)
+            }
+            Action::Shortcut {
+                timestamp,
+                chord,
+                name,
+            } => {
+                format!("{{shortcut,{},({},{})}}", timestamp, chord, name)
+            }
+            Action::ClockChange {
+                timestamp,
+                jump_ms,
+                utc_offset_secs,
+            } => {
+                format!("{{clock,{},({},{})}}", timestamp, jump_ms, utc_offset_secs)
+            }
+            Action::LayoutChange { timestamp, layout } => {
+                let layout = serde_json::to_string(layout).unwrap_or_default();
+                format!("{{layout,{},{}}}", timestamp, layout)
+            }
+            Action::CaptureInterrupted {
+                timestamp,
+                reason,
+                resumed_at,
+            } => {
+                let payload = serde_json::to_string(&(reason, resumed_at)).unwrap_or_default();
+                format!("{{interrupted,{},{}}}", timestamp, payload)
+            }
+            Action::CaptureGap { timestamp, gap_ms } => {
+                format!("{{gap,{},{}}}", timestamp, gap_ms)
+            }
+            Action::BreakReminder {
+                timestamp,
+                kind,
+                active_secs,
+            } => {
+                format!(
+                    "{{break_reminder,{},({},{})}}",
+                    timestamp, kind, active_secs
+                )
+            }
+            Action::BreakTaken {
+                timestamp,
+                kind,
+                idle_secs,
+                reminded,
+            } => {
+                format!(
+                    "{{break,{},({},{},{})}}",
+                    timestamp, kind, idle_secs, reminded
+                )
+            }
+            Action::FocusChange {
+                timestamp,
+                window,
+                bounds,
+                title,
+                app,
+            } => {
+                let fields = match bounds {
+                    Some(b) => format!("{},{},{},{},{}", window, b.x, b.y, b.width, b.height),
+                    None => window.to_string(),
+                };
+                // The title and then the app go after the fields, quoted, so
+                // commas in them are kept. A missing title is `null` when
+                // there's an app.
+                let json =
+                    |value: &Option<String>| serde_json::to_string(value).unwrap_or_default();
+                match (title, app) {
+                    (None, None) => format!("{{focus,{},({})}}", timestamp, fields),
+                    (Some(_), None) => {
+                        format!("{{focus,{},({}),{}}}", timestamp, fields, json(title))
+                    }
+                    (_, Some(_)) => format!(
+                        "{{focus,{},({}),{},{}}}",
+                        timestamp,
+                        fields,
+                        json(title),
+                        json(app)
+                    ),
+                }
+            }
+            Action::Redacted { timestamp, until } => {
+                let until = serde_json::to_string(until).unwrap_or_default();
+                format!("{{redacted,{},{}}}", timestamp, until)
+            }
+            Action::Custom {
+                timestamp,
+                kind,
+                payload,
+            } => {
+                let payload = serde_json::to_string(&(kind, payload)).unwrap_or_default();
+                format!("{{custom,{},{}}}", timestamp, payload)
+            }
+        }
+    }
+
+    /// The `kind` and `payload` parts of `to_csv_string`, for display.
+    /// Custom events show their own kind.
+    pub fn kind_and_payload(&self) -> (String, String) {
+        if let Action::Custom { kind, payload, .. } = self {
+            return (kind.clone(), payload.to_string());
+        }
+        let csv = self.to_csv_string();
+        let mut parts = csv
+            .trim_start_matches('{')
+            .trim_end_matches('}')
+            .splitn(3, ',');
+        let kind = parts.next().unwrap_or_default().to_string();
+        let payload = parts.nth(1).unwrap_or_default().to_string();
+        (kind, payload)
+    }
+
+    /// Parses the `{kind,timestamp,payload}` form produced by `to_csv_string`.
+    pub fn from_csv_string(s: &str) -> Option<Self> {
+        let inner = s.trim().strip_prefix('{')?.strip_suffix('}')?;
+        let mut parts = inner.splitn(3, ',');
+        let kind = parts.next()?;
+        let timestamp = parts.next()?.to_string();
+        let payload = parts.next()?;
+
+        match kind {
+            "mouse" => {
+                let (x, y) = payload
+                    .strip_prefix('(')?
+                    .strip_suffix(')')?
+                    .split_once(',')?;
+                Some(Action::MouseMove {
+                    timestamp,
+                    coords: (x.trim().parse().ok()?, y.trim().parse().ok()?),
+                })
+            }
+            "key" => {
+                let joined = payload.strip_prefix('"')?.strip_suffix('"')?;
+                let keys = if joined.is_empty() {
+                    Vec::new()
+                } else {
+                    joined.split('+').map(str::to_string).collect()
+                };
+                Some(Action::KeyPress { timestamp, keys })
+            }
+            "mouse_down" => {
+                let mut fields = payload.strip_prefix('(')?.strip_suffix(')')?.split(',');
+                Some(Action::MouseDown {
+                    timestamp,
+                    button: fields.next()?.to_string(),
+                    coords: (
+                        fields.next()?.trim().parse().ok()?,
+                        fields.next()?.trim().parse().ok()?,
+                    ),
+                })
+            }
+            "mouse_up" => {
+                let mut fields = payload.strip_prefix('(')?.strip_suffix(')')?.split(',');
+                Some(Action::MouseUp {
+                    timestamp,
+                    button: fields.next()?.to_string(),
+                    coords: (
+                        fields.next()?.trim().parse().ok()?,
+                        fields.next()?.trim().parse().ok()?,
+                    ),
+                    hold_ms: fields.next()?.trim().parse().ok()?,
+                })
+            }
+            "click" => {
+                let mut fields = payload.strip_prefix('(')?.strip_suffix(')')?.split(',');
+                Some(Action::MouseClick {
+                    timestamp,
+                    button: fields.next()?.to_string(),
+                    coords: (
+                        fields.next()?.trim().parse().ok()?,
+                        fields.next()?.trim().parse().ok()?,
+                    ),
+                })
+            }
+            "switch" => {
+                let tasks: [String; 2] = serde_json::from_str(payload).ok()?;
+                let [from, to] = tasks;
+                Some(Action::TaskSwitch {
+                    timestamp,
+                    from,
+                    to,
+                })
+            }
+            "cursor" => Some(Action::CursorChange {
+                timestamp,
+                shape: payload.to_string(),
+            }),
+            "shortcut" => {
+                let (chord, name) = payload
+                    .strip_prefix('(')?
+                    .strip_suffix(')')?
+                    .split_once(',')?;
+                Some(Action::Shortcut {
+                    timestamp,
+                    chord: chord.to_string(),
+                    name: name.to_string(),
+                })
+            }
+            "clock" => {
+                let (jump, offset) = payload
+                    .strip_prefix('(')?
+                    .strip_suffix(')')?
+                    .split_once(',')?;
+                Some(Action::ClockChange {
+                    timestamp,
+                    jump_ms: jump.trim().parse().ok()?,
+                    utc_offset_secs: offset.trim().parse().ok()?,
+                })
+            }
+            "layout" => Some(Action::LayoutChange {
+                timestamp,
+                layout: serde_json::from_str(payload).ok()?,
+            }),
+            "break_reminder" => {
+                let (kind, active_secs) = payload
+                    .strip_prefix('(')?
+                    .strip_suffix(')')?
+                    .split_once(',')?;
+                Some(Action::BreakReminder {
+                    timestamp,
+                    kind: kind.to_string(),
+                    active_secs: active_secs.trim().parse().ok()?,
+                })
+            }
+            "break" => {
+                let mut fields = payload.strip_prefix('(')?.strip_suffix(')')?.split(',');
+                Some(Action::BreakTaken {
+                    timestamp,
+                    kind: fields.next()?.to_string(),
+                    idle_secs: fields.next()?.trim().parse().ok()?,
+                    reminded: fields.next()?.trim().parse().ok()?,
+                })
+            }
+            "focus" => {
+                let (fields, rest) = payload.strip_prefix('(')?.split_once(')')?;
+                let (title, app) = match rest.strip_prefix(',') {
+                    Some(rest) => {
+                        let names: Vec<Option<String>> =
+                            serde_json::from_str(&format!("[{}]", rest)).ok()?;
+                        match names[..] {
+                            [ref title] => (title.clone(), None),
+                            [ref title, ref app] => (title.clone(), app.clone()),
+                            _ => return None,
+                        }
+                    }
+                    None if rest.is_empty() => (None, None),
+                    None => return None,
+                };
+                let mut fields = fields.split(',');
+                let window = fields.next()?.trim().parse().ok()?;
+                let bounds = match fields.next() {
+                    Some(x) => Some(WindowBounds {
+                        x: x.trim().parse().ok()?,
+                        y: fields.next()?.trim().parse().ok()?,
+                        width: fields.next()?.trim().parse().ok()?,
+                        height: fields.next()?.trim().parse().ok()?,
+                    }),
+                    None => None,
+                };
+                Some(Action::FocusChange {
+                    timestamp,
+                    window,
+                    bounds,
+                    title,
+                    app,
+                })
+            }
+            "redacted" => Some(Action::Redacted {
+                timestamp,
+                until: serde_json::from_str(payload).ok()?,
+            }),
+            "custom" => {
+                let (kind, payload) = serde_json::from_str(payload).ok()?;
+                Some(Action::Custom {
+                    timestamp,
+                    kind,
+                    payload,
+                })
+            }
+            "gap" => Some(Action::CaptureGap {
+                timestamp,
+                gap_ms: payload.parse().ok()?,
+            }),
+            "interrupted" => {
+                let (reason, resumed_at) = serde_json::from_str(payload).ok()?;
+                Some(Action::CaptureInterrupted {
+                    timestamp,
+                    reason,
+                    resumed_at,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Splits the `;`-joined actions column, ignoring separators inside quoted
+/// payloads (task names and key lists may contain `;`).
+fn split_actions(s: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut in_quotes = false;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            ';' if !in_quotes => {
+                parts.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+/// A single reconstructed point of a [`MousePath`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MousePoint {
+    pub timestamp: DateTime<Local>,
+    pub coords: (i32, i32),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PathStep {
+    dx: i32,
+    dy: i32,
+    dt_ms: u32,
+    // Number of non-mouse actions recorded before this point, so the
+    // original interleaving can be restored on export.
+    action_index: u32,
+}
+
+/// Delta-encoded mouse trajectory: a start point followed by coordinate and
+/// time deltas, instead of a full `Action` (with its own timestamp string)
+/// per pixel moved.
+#[derive(Debug, Clone, Default)]
+pub struct MousePath {
+    origin: Option<(DateTime<Local>, (i32, i32))>,
+    last_coords: (i32, i32),
+    last_offset_ms: i64,
+    steps: Vec<PathStep>,
+}
+
+impl MousePath {
+    pub fn push(&mut self, timestamp: DateTime<Local>, coords: (i32, i32), action_index: usize) {
+        // A whole-millisecond origin makes every reconstructed point land on
+        // the same millisecond `format_timestamp` gives other events.
+        let (origin_time, _) = *self
+            .origin
+            .get_or_insert((timestamp.trunc_subsecs(3), coords));
+        if self.steps.is_empty() {
+            self.last_coords = coords;
+        }
+
+        // Offsets are taken from the origin rather than the previous point so
+        // millisecond truncation doesn't accumulate over long sessions.
+        let offset_ms = (timestamp - origin_time)
+            .num_milliseconds()
+            .max(self.last_offset_ms);
+        self.steps.push(PathStep {
+            dx: coords.0 - self.last_coords.0,
+            dy: coords.1 - self.last_coords.1,
+            dt_ms: (offset_ms - self.last_offset_ms).min(u32::MAX as i64) as u32,
+            action_index: action_index as u32,
+        });
+        self.last_coords = coords;
+        self.last_offset_ms = offset_ms;
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Iterates over the absolute positions and timestamps of the path.
+    pub fn iter(&self) -> MousePathIter<'_> {
+        MousePathIter {
+            path: self,
+            index: 0,
+            coords: self.origin.map(|(_, c)| c).unwrap_or_default(),
+            offset_ms: 0,
         }
     }
 }
 
-#[derive(Debug)]
+pub struct MousePathIter<'a> {
+    path: &'a MousePath,
+    index: usize,
+    coords: (i32, i32),
+    offset_ms: i64,
+}
+
+impl Iterator for MousePathIter<'_> {
+    type Item = MousePoint;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (origin_time, _) = self.path.origin?;
+        let step = self.path.steps.get(self.index)?;
+        self.index += 1;
+        self.coords = (self.coords.0 + step.dx, self.coords.1 + step.dy);
+        self.offset_ms += step.dt_ms as i64;
+        Some(MousePoint {
+            timestamp: origin_time + Duration::milliseconds(self.offset_ms),
+            coords: self.coords,
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.path.steps.len() - self.index;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a> IntoIterator for &'a MousePath {
+    type Item = MousePoint;
+    type IntoIter = MousePathIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Metadata field a session's tags are kept in, comma-separated, so they
+/// go wherever metadata goes.
+pub const TAGS_FIELD: &str = "tags";
+
+#[derive(Debug, Clone)]
 pub struct Session {
     pub session_id: String,
     pub task_name: String,
     pub start_time: String,
     pub end_time: Option<String>,
+    /// Non-mouse actions; mouse moves live in `mouse_path`.
     pub actions: Vec<Action>,
+    pub mouse_path: MousePath,
+    /// Free-text answer to the "What did you accomplish?" prompt.
+    pub notes: Option<String>,
+    /// 1 in N mouse moves were kept while recording (1 = every move).
+    pub mouse_sample_rate: u32,
+    /// Machine the session was recorded on, set when datasets from several
+    /// machines are merged.
+    pub machine_id: Option<String>,
+    /// Results of the quality checks, run when the session is saved. `None`
+    /// for sessions saved before the checks existed.
+    pub quality_flags: Option<Vec<QualityFlag>>,
+    /// Events per minute over the session (see `analysis::event_density`),
+    /// computed when it's saved. `None` for sessions saved before that.
+    pub density: Option<Vec<u32>>,
+    /// Config profile active when the session started, if any.
+    pub profile: Option<String>,
+    /// The participant's consent the session was recorded under, when
+    /// consent has been given (see `consent`).
+    pub consent: Option<ConsentRecord>,
+    /// Experiment metadata, e.g. participant, condition and group (see
+    /// `config::MetadataConfig`).
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            session_id: String::new(),
+            task_name: String::new(),
+            start_time: String::new(),
+            end_time: None,
+            actions: Vec::new(),
+            mouse_path: MousePath::default(),
+            notes: None,
+            mouse_sample_rate: 1,
+            machine_id: None,
+            quality_flags: None,
+            density: None,
+            profile: None,
+            consent: None,
+            metadata: BTreeMap::new(),
+        }
+    }
 }
 
 impl Session {
+    /// Starts a new, empty session for `task_name` at the current time.
+    /// `start_time` is the readable start; the ID is only unique.
+    pub fn new(task_name: impl Into<String>) -> Self {
+        let now = Local::now();
+        Self {
+            session_id: session_id(now),
+            task_name: task_name.into(),
+            start_time: format_timestamp(now),
+            ..Default::default()
+        }
+    }
+
+    /// Tags added from the history, in the order they were added.
+    pub fn tags(&self) -> Vec<&str> {
+        self.metadata.get(TAGS_FIELD).map_or_else(Vec::new, |tags| {
+            tags.split(',')
+                .map(str::trim)
+                .filter(|tag| !tag.is_empty())
+                .collect()
+        })
+    }
+
+    /// Adds `tag`, trimmed and without commas, unless the session already
+    /// has it. Returns whether it was added.
+    pub fn add_tag(&mut self, tag: &str) -> bool {
+        let tag = tag.replace(',', " ");
+        let tag = tag.trim();
+        if tag.is_empty() || self.tags().contains(&tag) {
+            return false;
+        }
+        let mut tags = self.tags().join(",");
+        if !tags.is_empty() {
+            tags.push(',');
+        }
+        tags.push_str(tag);
+        self.metadata.insert(TAGS_FIELD.to_string(), tags);
+        true
+    }
+
+    /// Rebuilds a session from a `monitoring_sessions.csv` row. Rows written
+    /// before the `notes` column existed are accepted too.
+    pub fn from_csv_record(record: &csv::StringRecord) -> Option<Self> {
+        let mut session = Session {
+            session_id: record.get(0)?.to_string(),
+            task_name: record.get(1)?.to_string(),
+            start_time: record.get(2)?.to_string(),
+            end_time: record.get(3).filter(|s| !s.is_empty()).map(str::to_string),
+            notes: record.get(5).filter(|s| !s.is_empty()).map(str::to_string),
+            mouse_sample_rate: record.get(6).and_then(|s| s.parse().ok()).unwrap_or(1),
+            machine_id: record.get(7).filter(|s| !s.is_empty()).map(str::to_string),
+            quality_flags: record.get(8).and_then(|s| serde_json::from_str(s).ok()),
+            density: record.get(9).and_then(|s| serde_json::from_str(s).ok()),
+            profile: record.get(10).filter(|s| !s.is_empty()).map(str::to_string),
+            consent: record.get(11).and_then(|s| serde_json::from_str(s).ok()),
+            metadata: record
+                .get(12)
+                .and_then(|s| serde_json::from_str(s).ok())
+                .unwrap_or_default(),
+            ..Default::default()
+        };
+
+        for action in split_actions(record.get(4).unwrap_or_default()) {
+            match Action::from_csv_string(action) {
+                Some(Action::MouseMove { timestamp, coords }) => {
+                    if let Ok(ts) = DateTime::parse_from_rfc3339(&timestamp) {
+                        session.record_mouse_move(ts.with_timezone(&Local), coords);
+                    }
+                }
+                Some(action) => session.actions.push(action),
+                None => {}
+            }
+        }
+        Some(session)
+    }
+
+    /// Local start time, if `start_time` is valid RFC 3339.
+    pub fn started_at(&self) -> Option<DateTime<Local>> {
+        DateTime::parse_from_rfc3339(&self.start_time)
+            .ok()
+            .map(|ts| ts.with_timezone(&Local))
+    }
+
+    /// Wall-clock duration with any recorded clock jumps taken out, so an
+    /// NTP adjustment or manual clock change can't produce a negative or
+    /// inflated duration. `None` while the session is still running.
+    pub fn duration(&self) -> Option<Duration> {
+        let end = DateTime::parse_from_rfc3339(self.end_time.as_deref()?).ok()?;
+        let start = DateTime::parse_from_rfc3339(&self.start_time).ok()?;
+        let jumps: i64 = self
+            .actions
+            .iter()
+            .map(|action| match action {
+                Action::ClockChange { jump_ms, .. } => *jump_ms,
+                _ => 0,
+            })
+            .sum();
+        let duration = (end - start) - Duration::milliseconds(jumps);
+        Some(duration.max(Duration::zero()))
+    }
+
+    /// A copy holding only the events from `from` to `until` (inclusive),
+    /// with the start and end times narrowed to that range. Participant
+    /// metadata, consent and profile are kept; quality flags aren't, since
+    /// they describe the whole session.
+    pub fn slice(&self, from: DateTime<Local>, until: DateTime<Local>) -> Session {
+        let mut slice = Session {
+            session_id: self.session_id.clone(),
+            task_name: self.task_name.clone(),
+            start_time: format_timestamp(from),
+            end_time: Some(format_timestamp(until)),
+            notes: self.notes.clone(),
+            mouse_sample_rate: self.mouse_sample_rate,
+            machine_id: self.machine_id.clone(),
+            profile: self.profile.clone(),
+            consent: self.consent.clone(),
+            metadata: self.metadata.clone(),
+            ..Default::default()
+        };
+        slice.replace_actions(self.ordered_actions().into_iter().filter(|action| {
+            DateTime::parse_from_rfc3339(action.timestamp())
+                .map(|at| at.with_timezone(&Local))
+                .is_ok_and(|at| at >= from && at <= until)
+        }));
+        slice
+    }
+
+    /// Replaces every event with `actions`, given in recorded order, moving
+    /// mouse moves back into the path.
+    pub fn replace_actions(&mut self, actions: impl IntoIterator<Item = Action>) {
+        self.actions.clear();
+        self.mouse_path = Default::default();
+        for action in actions {
+            match action {
+                Action::MouseMove { timestamp, coords } => {
+                    match DateTime::parse_from_rfc3339(&timestamp) {
+                        Ok(at) => self.record_mouse_move(at.with_timezone(&Local), coords),
+                        Err(_) => self.actions.push(Action::MouseMove { timestamp, coords }),
+                    }
+                }
+                action => self.actions.push(action),
+            }
+        }
+    }
+
+    pub fn record_mouse_move(&mut self, timestamp: DateTime<Local>, coords: (i32, i32)) {
+        self.mouse_path.push(timestamp, coords, self.actions.len());
+    }
+
+    /// All actions in recording order, with mouse moves expanded from the
+    /// compressed path.
+    pub fn ordered_actions(&self) -> Vec<Action> {
+        let mut ordered = Vec::with_capacity(self.actions.len() + self.mouse_path.len());
+        let mut actions = self.actions.iter();
+        let mut taken = 0;
+        for (step, point) in self.mouse_path.steps.iter().zip(self.mouse_path.iter()) {
+            while taken < step.action_index as usize {
+                match actions.next() {
+                    Some(action) => ordered.push(action.clone()),
+                    None => break,
+                }
+                taken += 1;
+            }
+            ordered.push(Action::MouseMove {
+                timestamp: format_timestamp(point.timestamp),
+                coords: point.coords,
+            });
+        }
+        ordered.extend(actions.cloned());
+        ordered
+    }
+
     pub fn to_csv_record(&self) -> Vec<String> {
         let actions_str = self
-            .actions
+            .ordered_actions()
             .iter()
             .map(|action| action.to_csv_string())
             .collect::<Vec<_>>()
@@ -49,11 +912,32 @@ impl Session {
             self.start_time.clone(),
             self.end_time.clone().unwrap_or_default(),
             actions_str,
+            self.notes.clone().unwrap_or_default(),
+            self.mouse_sample_rate.to_string(),
+            self.machine_id.clone().unwrap_or_default(),
+            self.quality_flags
+                .as_ref()
+                .map(|flags| serde_json::to_string(flags).unwrap_or_default())
+                .unwrap_or_default(),
+            self.density
+                .as_ref()
+                .map(|density| serde_json::to_string(density).unwrap_or_default())
+                .unwrap_or_default(),
+            self.profile.clone().unwrap_or_default(),
+            self.consent
+                .as_ref()
+                .map(|consent| serde_json::to_string(consent).unwrap_or_default())
+                .unwrap_or_default(),
+            if self.metadata.is_empty() {
+                String::new()
+            } else {
+                serde_json::to_string(&self.metadata).unwrap_or_default()
+            },
         ]
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct DetailedEvent {
     pub timestamp: String,
     pub task_name: String,
@@ -61,4 +945,4 @@ pub struct DetailedEvent {
     pub details: String,
     pub mouse_x: i32,
     pub mouse_y: i32,
-} 
\ No newline at end of file
+}
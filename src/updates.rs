@@ -0,0 +1,237 @@
+//! Checking GitHub releases for a newer build, so study participants stay
+//! current. Requests go through `curl`, which ships with Linux, macOS and
+//! Windows 10 and later, rather than bundling a TLS stack.
+
+use crate::integrity;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+/// Version of this build, compared with release tags.
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Off by default, so nothing contacts GitHub unless asked to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UpdateConfig {
+    /// Check once at startup.
+    pub check_at_startup: bool,
+    /// `owner/name` of the GitHub repository releases are published to.
+    pub repository: String,
+}
+
+impl Default for UpdateConfig {
+    fn default() -> Self {
+        Self {
+            check_at_startup: false,
+            repository: "h-lakshman/desktop-app".to_string(),
+        }
+    }
+}
+
+/// A published release.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Release {
+    /// The tag without a leading `v`, e.g. `0.2.0`.
+    pub version: String,
+    pub name: String,
+    /// Release notes as written on GitHub (Markdown).
+    pub notes: String,
+    /// The release page, to download by hand.
+    pub page_url: String,
+    /// The asset built for this platform and processor, if there's one that
+    /// can replace the running binary directly (not an archive or
+    /// installer).
+    pub binary_url: Option<String>,
+    /// `<binary>.sha256` published next to it, checked before installing.
+    /// Releases without one aren't installed.
+    pub checksum_url: Option<String>,
+}
+
+impl Release {
+    /// Reads a release from the GitHub API's JSON.
+    pub fn from_github(json: &Value) -> Option<Self> {
+        let tag = json["tag_name"].as_str()?;
+        let version = tag.trim_start_matches('v').to_string();
+        let assets: Vec<(String, String)> = json["assets"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|asset| {
+                Some((
+                    asset["name"].as_str()?.to_string(),
+                    asset["browser_download_url"].as_str()?.to_string(),
+                ))
+            })
+            .collect();
+        let binary = assets.iter().find(|(name, _)| {
+            let name = name.to_ascii_lowercase();
+            name.contains(PLATFORM)
+                && ARCH_NAMES.iter().any(|arch| name.contains(arch))
+                && !ARCHIVE_SUFFIXES.iter().any(|s| name.ends_with(s))
+                && (cfg!(not(windows)) || name.ends_with(".exe"))
+        });
+        let checksum_url = binary.and_then(|(binary, _)| {
+            let checksum = format!("{}.sha256", binary);
+            assets
+                .iter()
+                .find(|(name, _)| *name == checksum)
+                .map(|(_, url)| url.clone())
+        });
+        Some(Self {
+            name: json["name"]
+                .as_str()
+                .filter(|name| !name.is_empty())
+                .unwrap_or(tag)
+                .to_string(),
+            notes: json["body"].as_str().unwrap_or_default().to_string(),
+            page_url: json["html_url"].as_str().unwrap_or_default().to_string(),
+            version,
+            binary_url: binary.map(|(_, url)| url.clone()),
+            checksum_url,
+        })
+    }
+
+    /// Whether [`install`] can apply this release here.
+    pub fn can_install(&self) -> bool {
+        self.binary_url.is_some() && self.checksum_url.is_some() && cfg!(not(windows))
+    }
+
+    /// Whether this release is newer than the running build.
+    pub fn is_newer(&self) -> bool {
+        is_newer(&self.version, CURRENT_VERSION)
+    }
+}
+
+/// Asset names ending in these can't be run as they are.
+const ARCHIVE_SUFFIXES: [&str; 7] = [".zip", ".tar.gz", ".tgz", ".dmg", ".msi", ".deb", ".sha256"];
+
+/// What platform assets are named after, e.g. `desk-monitor-linux`.
+const PLATFORM: &str = if cfg!(target_os = "linux") {
+    "linux"
+} else if cfg!(target_os = "macos") {
+    "macos"
+} else {
+    "windows"
+};
+
+/// What assets built for this processor are named with, e.g.
+/// `desk-monitor-linux-x86_64`.
+const ARCH_NAMES: &[&str] = match std::env::consts::ARCH.as_bytes() {
+    b"x86_64" => &["x86_64", "amd64", "x64"],
+    b"aarch64" => &["aarch64", "arm64"],
+    _ => &[std::env::consts::ARCH],
+};
+
+/// Compares dotted versions numerically (`0.10.0` > `0.9.1`). Anything
+/// after a `-`, like `-beta`, is ignored.
+pub fn is_newer(candidate: &str, current: &str) -> bool {
+    let parts = |version: &str| -> Vec<u64> {
+        let version = version.trim_start_matches('v');
+        let core = version.split('-').next().unwrap_or_default();
+        core.split('.').map(|p| p.parse().unwrap_or(0)).collect()
+    };
+    parts(candidate) > parts(current)
+}
+
+pub(crate) fn curl(args: &[&str]) -> Result<Vec<u8>> {
+    let output = Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--location"])
+        .args(["--max-time", "60"])
+        .args(args)
+        .output()
+        .context("running curl")?;
+    if !output.status.success() {
+        bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(output.stdout)
+}
+
+/// The latest release of `config.repository`, newer or not.
+pub fn latest_release(config: &UpdateConfig) -> Result<Release> {
+    let url = format!(
+        "https://api.github.com/repos/{}/releases/latest",
+        config.repository
+    );
+    let body = curl(&["--header", "Accept: application/vnd.github+json", &url])?;
+    let json: Value = serde_json::from_slice(&body).context("reading the release")?;
+    Release::from_github(&json).context("the latest release has no tag")
+}
+
+/// The latest release if it's newer than this build.
+pub fn check(config: &UpdateConfig) -> Result<Option<Release>> {
+    let release = latest_release(config)?;
+    Ok(release.is_newer().then_some(release))
+}
+
+/// Checks in the background, delivering at most one result.
+pub fn spawn_check(config: UpdateConfig) -> Receiver<Result<Option<Release>>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = sender.send(check(&config));
+    });
+    receiver
+}
+
+/// Installs in the background, delivering one result.
+pub fn spawn_install(release: Release) -> Receiver<Result<PathBuf>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = sender.send(install(&release));
+    });
+    receiver
+}
+
+/// Downloads the release's binary over the running one, which takes effect
+/// the next time the app starts, after checking it against its published
+/// checksum. Returns the path replaced. Where that isn't possible (no
+/// binary asset or checksum, or Windows, which locks running executables),
+/// download it from the release page instead.
+pub fn install(release: &Release) -> Result<PathBuf> {
+    let Some(url) = &release.binary_url else {
+        bail!(
+            "release {} has no binary for {} {}; download it from {}",
+            release.version,
+            PLATFORM,
+            std::env::consts::ARCH,
+            release.page_url
+        );
+    };
+    let Some(checksum_url) = &release.checksum_url else {
+        bail!(
+            "release {} has no checksum to check the download against; download it from {}",
+            release.version,
+            release.page_url
+        );
+    };
+    if cfg!(windows) {
+        bail!(
+            "updates can't be installed while running on Windows; download it from {}",
+            release.page_url
+        );
+    }
+    let exe = std::env::current_exe().context("finding the running binary")?;
+    let tmp = exe.with_extension("update");
+    curl(&["--output", &tmp.to_string_lossy(), url])?;
+    let expected = String::from_utf8_lossy(&curl(&[checksum_url])?)
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    if integrity::sha256_file(&tmp)? != expected {
+        let _ = std::fs::remove_file(&tmp);
+        bail!("the download doesn't match its published checksum");
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&tmp, std::fs::Permissions::from_mode(0o755))?;
+    }
+    // Renaming keeps the running process's file intact until it exits.
+    std::fs::rename(&tmp, &exe).with_context(|| format!("replacing {}", exe.display()))?;
+    Ok(exe)
+}
@@ -0,0 +1,817 @@
+use crate::analysis;
+use crate::config::{Config, ExportFormat};
+use crate::export;
+use crate::keystroke;
+use crate::playback::{self, Playback};
+use crate::portable;
+use crate::quality::{self, QualityFlag};
+use crate::share;
+use crate::storage;
+use crate::timeline::{Layer, Timeline};
+use crate::types::{Action, Session};
+use chrono::{DateTime, Local};
+use eframe::egui;
+use std::path::PathBuf;
+
+/// Widths of the time, type, app and task columns of the event table.
+const COLUMN_WIDTHS: [f32; 4] = [240.0, 110.0, 140.0, 160.0];
+/// Columns of the timeline's event-density histogram.
+const TIMELINE_BUCKETS: usize = 240;
+const TIMELINE_HEIGHT: f32 = 48.0;
+/// Height of each row of the layers strip (see [`layers`]).
+const LAYER_ROW_HEIGHT: f32 = 8.0;
+/// Focus bands take one of these by window, so neighbours stand apart.
+const FOCUS_COLORS: [egui::Color32; 4] = [
+    egui::Color32::from_rgb(43, 123, 185),
+    egui::Color32::from_rgb(41, 191, 18),
+    egui::Color32::from_rgb(118, 68, 138),
+    egui::Color32::from_rgb(23, 190, 187),
+];
+const PAUSED_COLOR: egui::Color32 = egui::Color32::from_rgb(228, 87, 46);
+/// Size of the density sparkline next to each session in the list.
+const SPARKLINE_SIZE: egui::Vec2 = egui::vec2(48.0, 14.0);
+const PLAYBACK_HEIGHT: f32 = 240.0;
+
+/// One row of the event table, with the app that had focus and the task
+/// that was active at the time.
+struct EventRow {
+    time: String,
+    kind: String,
+    app: String,
+    task: String,
+    details: String,
+}
+
+impl EventRow {
+    fn new(action: &Action, app: &str, task: &str) -> Self {
+        let (kind, details) = action.kind_and_payload();
+        Self {
+            time: action.timestamp().to_string(),
+            kind,
+            app: app.to_string(),
+            task: task.to_string(),
+            details,
+        }
+    }
+
+    fn matches(&self, filter: &str) -> bool {
+        [&self.time, &self.kind, &self.app, &self.task, &self.details]
+            .iter()
+            .any(|field| field.to_lowercase().contains(filter))
+    }
+}
+
+/// Which sessions the list shows, by quality flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum QualityFilter {
+    All,
+    Clean,
+    Flagged,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortColumn {
+    Time,
+    Kind,
+    App,
+    Task,
+}
+
+/// Read-only browser for an exported dataset. It never creates an
+/// `ActivityMonitor`, so nothing can be recorded on the analyst's machine.
+pub struct ViewerApp {
+    path: PathBuf,
+    config: Config,
+    sessions: Vec<Session>,
+    /// Quality flags per session: the stored ones, or checked on load for
+    /// sessions saved before flags were stored.
+    flags: Vec<Vec<QualityFlag>>,
+    /// Events per minute over each session: the stored density, or
+    /// computed on load like `flags`.
+    density: Vec<Vec<u32>>,
+    /// Busiest slice of any session, so sparklines share a scale.
+    max_density: u32,
+    quality_filter: QualityFilter,
+    selected: Option<usize>,
+    events: Vec<EventRow>,
+    /// Indices into `events` that pass the filter, in display order.
+    visible: Vec<usize>,
+    filter: String,
+    sort: SortColumn,
+    descending: bool,
+    error: Option<String>,
+    /// Result of the last "Open in browser".
+    share_status: Option<String>,
+    /// Events per timeline column across `span`.
+    timeline: Vec<u32>,
+    /// Start and end of the selected session.
+    span: Option<(DateTime<Local>, DateTime<Local>)>,
+    /// Markers, idle gaps, pauses and focus of the selected session, drawn
+    /// under the timeline and the playback slider.
+    layers: Timeline,
+    /// Range dragged on the timeline, as fractions of `span` in drag order.
+    range: Option<(f32, f32)>,
+    /// Result of the last range export or copy.
+    range_status: Option<String>,
+    /// Animated replay of the selected session.
+    playback: Option<Playback>,
+    /// Sessions ticked for "Export to .sqlite".
+    #[cfg(feature = "sqlite")]
+    export_selection: Vec<bool>,
+    #[cfg(feature = "sqlite")]
+    export_status: Option<String>,
+}
+
+impl ViewerApp {
+    pub fn new(_cc: &eframe::CreationContext<'_>, path: PathBuf) -> Self {
+        let (sessions, error) = match storage::load_dataset(&path) {
+            Ok(sessions) => (sessions, None),
+            Err(e) => (
+                Vec::new(),
+                Some(format!("Error loading {}: {}", path.display(), e)),
+            ),
+        };
+        let config = Config::load().unwrap_or_default();
+        let flags = sessions
+            .iter()
+            .map(|session| {
+                session
+                    .quality_flags
+                    .clone()
+                    .unwrap_or_else(|| quality::check(session, &config.quality))
+            })
+            .collect();
+        let density: Vec<Vec<u32>> = sessions
+            .iter()
+            .map(|session| {
+                session
+                    .density
+                    .clone()
+                    .unwrap_or_else(|| analysis::event_density(session))
+            })
+            .collect();
+        let max_density = density.iter().flatten().copied().max().unwrap_or(0);
+        Self {
+            path,
+            config,
+            flags,
+            density,
+            max_density,
+            quality_filter: QualityFilter::All,
+            #[cfg(feature = "sqlite")]
+            export_selection: vec![false; sessions.len()],
+            #[cfg(feature = "sqlite")]
+            export_status: None,
+            sessions,
+            selected: None,
+            events: Vec::new(),
+            visible: Vec::new(),
+            filter: String::new(),
+            sort: SortColumn::Time,
+            descending: false,
+            error,
+            share_status: None,
+            timeline: Vec::new(),
+            span: None,
+            layers: Timeline::default(),
+            range: None,
+            range_status: None,
+            playback: None,
+        }
+    }
+
+    /// Writes the ticked sessions, or all of them if none are ticked, to a
+    /// timestamped `.sqlite` file in the working directory.
+    #[cfg(feature = "sqlite")]
+    fn export_sqlite(&mut self) {
+        let mut sessions: Vec<&Session> = self
+            .sessions
+            .iter()
+            .zip(&self.export_selection)
+            .filter(|(_, ticked)| **ticked)
+            .map(|(session, _)| session)
+            .collect();
+        if sessions.is_empty() {
+            sessions = self.sessions.iter().collect();
+        }
+
+        let path = format!(
+            "sessions_{}.sqlite",
+            chrono::Local::now().format("%Y%m%d_%H%M%S")
+        );
+        self.export_status = Some(
+            match crate::export::write_sqlite(sessions.iter().copied(), &path) {
+                Ok(()) => format!("Exported {} sessions to {}", sessions.len(), path),
+                Err(e) => format!("Error exporting to {}: {}", path, e),
+            },
+        );
+    }
+
+    fn select(&mut self, index: usize) {
+        self.selected = Some(index);
+        let session = &self.sessions[index];
+        let mut task = session.task_name.as_str();
+        // Empty until the first focus change with an app.
+        let mut app = "";
+        self.events = session
+            .ordered_actions()
+            .iter()
+            .map(|action| {
+                match action {
+                    Action::TaskSwitch { to, .. } => task = to,
+                    Action::FocusChange { app: focused, .. } => {
+                        app = focused.as_deref().unwrap_or_default()
+                    }
+                    _ => {}
+                }
+                EventRow::new(action, app, task)
+            })
+            .collect();
+        self.refresh_events();
+        self.build_timeline();
+        self.layers = Timeline::new(&self.sessions[index]);
+        self.playback = Some(Playback::new(&self.sessions[index]));
+    }
+
+    /// Buckets the selected session's events over its span for the timeline.
+    fn build_timeline(&mut self) {
+        self.range = None;
+        self.range_status = None;
+        self.timeline = vec![0; TIMELINE_BUCKETS];
+        self.span = None;
+        let Some(session) = self.selected.map(|i| &self.sessions[i]) else {
+            return;
+        };
+        let times: Vec<DateTime<Local>> = self
+            .events
+            .iter()
+            .filter_map(|event| DateTime::parse_from_rfc3339(&event.time).ok())
+            .map(|t| t.with_timezone(&Local))
+            .collect();
+        let Some(start) = session.started_at().or(times.first().copied()) else {
+            return;
+        };
+        let end = session
+            .end_time
+            .as_deref()
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| t.with_timezone(&Local))
+            .into_iter()
+            .chain(times.iter().copied())
+            .max()
+            .filter(|end| *end > start)
+            .unwrap_or(start + chrono::Duration::seconds(1));
+
+        let span_ms = (end - start).num_milliseconds() as f64;
+        for time in times {
+            let offset = (time - start).num_milliseconds().max(0) as f64;
+            let bucket = (offset / span_ms * TIMELINE_BUCKETS as f64) as usize;
+            self.timeline[bucket.min(TIMELINE_BUCKETS - 1)] += 1;
+        }
+        self.span = Some((start, end));
+    }
+
+    /// The dragged range as times, if one of any width is selected.
+    fn selected_range(&self) -> Option<(DateTime<Local>, DateTime<Local>)> {
+        let (start, end) = self.span?;
+        let (a, b) = self.range?;
+        let (from, until) = (a.min(b), a.max(b));
+        if until - from <= f32::EPSILON {
+            return None;
+        }
+        let span_ms = (end - start).num_milliseconds() as f64;
+        let at = |fraction: f32| {
+            start + chrono::Duration::milliseconds((span_ms * f64::from(fraction)) as i64)
+        };
+        Some((at(from), at(until)))
+    }
+
+    /// Event density over the session. Dragging across it selects a range
+    /// that can be exported or summarized.
+    fn timeline(&mut self, ui: &mut egui::Ui) {
+        let Some((start, end)) = self.span else {
+            return;
+        };
+        let size = egui::vec2(ui.available_width(), TIMELINE_HEIGHT);
+        let (rect, response) = ui.allocate_exact_size(size, egui::Sense::drag());
+        let fraction = |pos: egui::Pos2| ((pos.x - rect.left()) / rect.width()).clamp(0.0, 1.0);
+        if let Some(pos) = response.interact_pointer_pos() {
+            if response.drag_started() {
+                self.range = Some((fraction(pos), fraction(pos)));
+                self.range_status = None;
+            } else if let (true, Some((from, _))) = (response.dragged(), self.range) {
+                self.range = Some((from, fraction(pos)));
+            }
+        }
+
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+        let max = self.timeline.iter().copied().max().unwrap_or(0).max(1);
+        let column = rect.width() / TIMELINE_BUCKETS as f32;
+        for (i, &count) in self.timeline.iter().enumerate() {
+            if count == 0 {
+                continue;
+            }
+            let left = rect.left() + i as f32 * column;
+            let height = rect.height() * count as f32 / max as f32;
+            painter.rect_filled(
+                egui::Rect::from_min_max(
+                    egui::pos2(left, rect.bottom() - height),
+                    egui::pos2(left + column, rect.bottom()),
+                ),
+                0.0,
+                ui.visuals().selection.bg_fill,
+            );
+        }
+        if let Some((a, b)) = self.range {
+            let x = |f: f32| rect.left() + f * rect.width();
+            painter.rect_filled(
+                egui::Rect::from_x_y_ranges(x(a.min(b))..=x(a.max(b)), rect.y_range()),
+                0.0,
+                egui::Color32::from_white_alpha(40),
+            );
+        }
+        layers(ui, &self.layers, (end - start).num_milliseconds(), None);
+
+        let mut export = None;
+        let mut copy = false;
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "{} – {}",
+                start.format("%H:%M:%S"),
+                end.format("%H:%M:%S")
+            ));
+            ui.separator();
+            match self.selected_range() {
+                Some((from, until)) => {
+                    ui.label(format!(
+                        "Selected {} – {}",
+                        from.format("%H:%M:%S"),
+                        until.format("%H:%M:%S")
+                    ));
+                    if ui.button("Export CSV").clicked() {
+                        export = Some(ExportFormat::Csv);
+                    }
+                    if ui.button("Export JSON").clicked() {
+                        export = Some(ExportFormat::Json);
+                    }
+                    copy = ui.button("Copy stats").clicked();
+                    if ui.button("Clear").clicked() {
+                        self.range = None;
+                        self.range_status = None;
+                    }
+                }
+                None => {
+                    ui.label("Drag across the timeline to select a range");
+                }
+            }
+            if let Some(status) = &self.range_status {
+                ui.label(status);
+            }
+        });
+
+        let (Some(i), Some((from, until))) = (self.selected, self.selected_range()) else {
+            return;
+        };
+        let slice = self.sessions[i].slice(from, until);
+        if let Some(format) = export {
+            let path = format!(
+                "{}_{}-{}.{}",
+                slice.session_id,
+                from.format("%H%M%S"),
+                until.format("%H%M%S"),
+                format.extension()
+            );
+            self.range_status = Some(
+                match export::write_sessions(&[slice], &path, format, &self.config) {
+                    Ok(()) => format!("Exported to {}", path),
+                    Err(e) => format!("Error exporting to {}: {}", path, e),
+                },
+            );
+        } else if copy {
+            let summary = range_summary(&slice, from, until);
+            ui.output_mut(|o| o.copied_text = summary);
+            self.range_status = Some("Copied".to_string());
+        }
+    }
+
+    /// Animates the mouse path and key presses of the selected session,
+    /// drawn to fit the area the pointer covered. Nothing is replayed for
+    /// real.
+    fn playback(&mut self, ui: &mut egui::Ui) {
+        let Some(playback) = &mut self.playback else {
+            return;
+        };
+        playback.advance(ui.input(|i| i.stable_dt));
+        if playback.playing {
+            ui.ctx().request_repaint();
+        }
+
+        ui.horizontal(|ui| {
+            let label = if playback.playing {
+                "⏸ Pause"
+            } else {
+                "▶ Play"
+            };
+            if ui.button(label).clicked() {
+                playback.toggle();
+            }
+            let mut position = playback.position_ms();
+            let slider =
+                egui::Slider::new(&mut position, 0..=playback.duration_ms()).show_value(false);
+            if ui.add(slider).changed() {
+                playback.seek(position);
+            }
+            ui.label(format!(
+                "{} / {}",
+                analysis::format_offset(position),
+                analysis::format_offset(playback.duration_ms())
+            ));
+            egui::ComboBox::from_id_source("playback_speed")
+                .selected_text(format!("{}×", playback.speed))
+                .show_ui(ui, |ui| {
+                    for speed in playback::SPEEDS {
+                        ui.selectable_value(&mut playback.speed, speed, format!("{}×", speed));
+                    }
+                });
+        });
+        layers(
+            ui,
+            &self.layers,
+            playback.duration_ms(),
+            Some(playback.position_ms()),
+        );
+
+        let size = egui::vec2(ui.available_width(), PLAYBACK_HEIGHT);
+        let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+        if let Some(((min_x, min_y), (max_x, max_y))) = playback.bounds() {
+            let area = rect.shrink(8.0);
+            let (width, height) = ((max_x - min_x).max(1) as f32, (max_y - min_y).max(1) as f32);
+            let scale = (area.width() / width).min(area.height() / height);
+            let to_screen = |(x, y): (i32, i32)| {
+                area.center()
+                    + egui::vec2(
+                        (x as f32 - (min_x + max_x) as f32 / 2.0) * scale,
+                        (y as f32 - (min_y + max_y) as f32 / 2.0) * scale,
+                    )
+            };
+            let points: Vec<egui::Pos2> = playback
+                .trail()
+                .iter()
+                .map(|(_, coords)| to_screen(*coords))
+                .collect();
+            painter.add(egui::Shape::line(
+                points,
+                egui::Stroke::new(1.5, ui.visuals().selection.bg_fill),
+            ));
+            if let Some(cursor) = playback.cursor() {
+                painter.circle_filled(to_screen(cursor), 4.0, ui.visuals().strong_text_color());
+            }
+        }
+        let mut y = rect.top() + 4.0;
+        for (offset, description) in playback.recent() {
+            painter.text(
+                egui::pos2(rect.left() + 6.0, y),
+                egui::Align2::LEFT_TOP,
+                format!("{} {}", analysis::format_offset(*offset), description),
+                egui::FontId::monospace(12.0),
+                ui.visuals().text_color(),
+            );
+            y += 14.0;
+        }
+    }
+
+    /// Re-applies the filter and sort order to `events`. Sorting is stable,
+    /// so rows with equal keys stay in recording order.
+    fn refresh_events(&mut self) {
+        let filter = self.filter.to_lowercase();
+        self.visible = (0..self.events.len())
+            .filter(|&i| filter.is_empty() || self.events[i].matches(&filter))
+            .collect();
+        let events = &self.events;
+        match self.sort {
+            SortColumn::Time => {}
+            SortColumn::Kind => self
+                .visible
+                .sort_by(|&a, &b| events[a].kind.cmp(&events[b].kind)),
+            SortColumn::App => self
+                .visible
+                .sort_by(|&a, &b| events[a].app.cmp(&events[b].app)),
+            SortColumn::Task => self
+                .visible
+                .sort_by(|&a, &b| events[a].task.cmp(&events[b].task)),
+        }
+        if self.descending {
+            self.visible.reverse();
+        }
+    }
+
+    /// Sortable, filterable event table. Only the rows in view are laid
+    /// out, so sessions with hundreds of thousands of events stay
+    /// responsive.
+    fn event_table(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            ui.label("Filter: ");
+            changed |= ui.text_edit_singleline(&mut self.filter).changed();
+            ui.label(format!(
+                "{} of {} events",
+                self.visible.len(),
+                self.events.len()
+            ));
+        });
+        ui.horizontal(|ui| {
+            for (column, title, width) in [
+                (SortColumn::Time, "Time", COLUMN_WIDTHS[0]),
+                (SortColumn::Kind, "Type", COLUMN_WIDTHS[1]),
+                (SortColumn::App, "App", COLUMN_WIDTHS[2]),
+                (SortColumn::Task, "Task", COLUMN_WIDTHS[3]),
+            ] {
+                let arrow = match (self.sort == column, self.descending) {
+                    (false, _) => "",
+                    (true, false) => " ⏶",
+                    (true, true) => " ⏷",
+                };
+                let header =
+                    egui::Button::new(egui::RichText::new(format!("{}{}", title, arrow)).strong())
+                        .frame(false);
+                if ui.add_sized([width, 0.0], header).clicked() {
+                    self.descending = self.sort == column && !self.descending;
+                    self.sort = column;
+                    changed = true;
+                }
+            }
+            ui.strong("Details");
+        });
+        if changed {
+            self.refresh_events();
+        }
+        ui.separator();
+
+        let row_height = ui.text_style_height(&egui::TextStyle::Body);
+        egui::ScrollArea::both().auto_shrink(false).show_rows(
+            ui,
+            row_height,
+            self.visible.len(),
+            |ui, rows| {
+                for row in rows {
+                    let event = &self.events[self.visible[row]];
+                    ui.horizontal(|ui| {
+                        for (text, width) in [&event.time, &event.kind, &event.app, &event.task]
+                            .into_iter()
+                            .zip(COLUMN_WIDTHS)
+                        {
+                            ui.add_sized(
+                                [width, row_height],
+                                egui::Label::new(text.as_str()).truncate(true),
+                            );
+                        }
+                        ui.label(&event.details);
+                    });
+                }
+            },
+        );
+    }
+}
+
+impl eframe::App for ViewerApp {
+    /// Same as the monitor window: not in portable mode.
+    fn persist_egui_memory(&self) -> bool {
+        !portable::is_active()
+    }
+
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        egui::TopBottomPanel::top("viewer_header").show(ctx, |ui| {
+            ui.heading("Desktop Activity Monitor — Viewer (read-only)");
+            ui.label(format!(
+                "{} sessions from {}",
+                self.sessions.len(),
+                self.path.display()
+            ));
+            if let Some(error) = &self.error {
+                ui.colored_label(egui::Color32::RED, error);
+            }
+            #[cfg(feature = "sqlite")]
+            ui.horizontal(|ui| {
+                if ui.button("Export to .sqlite").clicked() {
+                    self.export_sqlite();
+                }
+                if let Some(status) = &self.export_status {
+                    ui.label(status);
+                }
+            });
+        });
+
+        let mut clicked = None;
+        egui::SidePanel::left("viewer_sessions").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Show: ");
+                ui.radio_value(&mut self.quality_filter, QualityFilter::All, "All");
+                ui.radio_value(&mut self.quality_filter, QualityFilter::Clean, "Clean");
+                ui.radio_value(&mut self.quality_filter, QualityFilter::Flagged, "Flagged");
+            });
+            ui.separator();
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for (i, session) in self.sessions.iter().enumerate() {
+                    let flags = &self.flags[i];
+                    let shown = match self.quality_filter {
+                        QualityFilter::All => true,
+                        QualityFilter::Clean => flags.is_empty(),
+                        QualityFilter::Flagged => !flags.is_empty(),
+                    };
+                    if !shown {
+                        continue;
+                    }
+                    let label = format!("{} — {}", session.start_time, session.task_name);
+                    ui.horizontal(|ui| {
+                        #[cfg(feature = "sqlite")]
+                        ui.checkbox(&mut self.export_selection[i], "");
+                        sparkline(ui, &self.density[i], self.max_density);
+                        if ui
+                            .selectable_label(self.selected == Some(i), label)
+                            .clicked()
+                        {
+                            clicked = Some(i);
+                        }
+                        if !flags.is_empty() {
+                            ui.colored_label(egui::Color32::YELLOW, "⚠")
+                                .on_hover_text(format_flags(flags));
+                        }
+                    });
+                }
+            });
+        });
+        if let Some(i) = clicked {
+            self.select(i);
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let Some(i) = self.selected else {
+                ui.label("Select a session to view its events");
+                return;
+            };
+            let session = &self.sessions[i];
+
+            ui.heading(&session.task_name);
+            ui.label(format!("Session: {}", session.session_id));
+            ui.label(format!("Started: {}", session.start_time));
+            ui.label(format!(
+                "Ended: {}",
+                session.end_time.as_deref().unwrap_or("-")
+            ));
+            for (field, value) in &session.metadata {
+                ui.label(format!("{}: {}", field, value));
+            }
+            if let Some(consent) = &session.consent {
+                ui.label(format!(
+                    "Consent: version {} by {} at {}",
+                    consent.version, consent.participant_id, consent.accepted_at
+                ));
+            }
+            if let Some(profile) = &session.profile {
+                ui.label(format!("Profile: {}", profile));
+            }
+            if let Some(notes) = &session.notes {
+                ui.label(format!("Notes: {}", notes));
+            }
+            if !self.flags[i].is_empty() {
+                ui.colored_label(
+                    egui::Color32::YELLOW,
+                    format!("Quality: {}", format_flags(&self.flags[i])),
+                );
+            }
+            ui.horizontal(|ui| {
+                if ui.button("Open in browser").clicked() {
+                    self.share_status = Some(match share::open_in_browser(session, &self.config) {
+                        Ok(url) => format!("Serving at {}", url),
+                        Err(e) => format!("Error opening browser: {}", e),
+                    });
+                }
+                if let Some(status) = &self.share_status {
+                    ui.label(status);
+                }
+            });
+            ui.separator();
+            self.timeline(ui);
+            ui.collapsing("Playback", |ui| self.playback(ui));
+            ui.separator();
+            self.event_table(ui);
+        });
+    }
+}
+
+/// Bars of `density` scaled to `max`, by square root so quiet stretches
+/// still show next to very busy ones. Hovering shows the peak.
+fn sparkline(ui: &mut egui::Ui, density: &[u32], max: u32) {
+    let (rect, response) = ui.allocate_exact_size(SPARKLINE_SIZE, egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 1.0, ui.visuals().extreme_bg_color);
+    if density.is_empty() || max == 0 {
+        return;
+    }
+    let column = rect.width() / density.len() as f32;
+    for (i, &value) in density.iter().enumerate() {
+        if value == 0 {
+            continue;
+        }
+        let height = rect.height() * (value as f32 / max as f32).sqrt();
+        let left = rect.left() + i as f32 * column;
+        painter.rect_filled(
+            egui::Rect::from_min_max(
+                egui::pos2(left, rect.bottom() - height),
+                egui::pos2(left + column, rect.bottom()),
+            ),
+            0.0,
+            ui.visuals().selection.bg_fill,
+        );
+    }
+    let peak = density.iter().copied().max().unwrap_or(0);
+    response.on_hover_text(format!("Up to {} events a minute", peak));
+}
+
+/// The layers of `timeline` as a strip `span_ms` wide: a row of bands per
+/// [`Layer`], with markers as ticks across all of them and `position_ms`,
+/// if given, as a line. Hovering lists what was going on there.
+fn layers(ui: &mut egui::Ui, timeline: &Timeline, span_ms: i64, position_ms: Option<i64>) {
+    let size = egui::vec2(
+        ui.available_width(),
+        LAYER_ROW_HEIGHT * Layer::ALL.len() as f32,
+    );
+    let (rect, response) = ui.allocate_exact_size(size, egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 1.0, ui.visuals().extreme_bg_color);
+    let span_ms = span_ms.max(1) as f32;
+    let x = |ms: i64| rect.left() + rect.width() * (ms as f32 / span_ms).clamp(0.0, 1.0);
+
+    for (row, layer) in Layer::ALL.into_iter().enumerate() {
+        let top = rect.top() + row as f32 * LAYER_ROW_HEIGHT;
+        for band in timeline.bands(layer) {
+            let color = match layer {
+                Layer::Focus => {
+                    let hash = band.label.bytes().map(usize::from).sum::<usize>();
+                    FOCUS_COLORS[hash % FOCUS_COLORS.len()]
+                }
+                Layer::Idle => egui::Color32::GRAY,
+                Layer::Paused => PAUSED_COLOR,
+            };
+            painter.rect_filled(
+                egui::Rect::from_x_y_ranges(
+                    x(band.from_ms)..=x(band.until_ms).max(x(band.from_ms) + 1.0),
+                    top + 1.0..=top + LAYER_ROW_HEIGHT - 1.0,
+                ),
+                0.0,
+                color,
+            );
+        }
+    }
+    for marker in &timeline.markers {
+        painter.vline(
+            x(marker.offset_ms),
+            rect.y_range(),
+            egui::Stroke::new(1.0, ui.visuals().strong_text_color()),
+        );
+    }
+    if let Some(position) = position_ms {
+        painter.vline(x(position), rect.y_range(), ui.visuals().selection.stroke);
+    }
+
+    if let Some(pos) = response.hover_pos() {
+        let fraction = ((pos.x - rect.left()) / rect.width()).clamp(0.0, 1.0);
+        let at = (fraction * span_ms) as i64;
+        // Markers within a few pixels of the pointer count as under it.
+        let within = (4.0 * span_ms / rect.width()) as i64;
+        let lines = timeline.describe_at(at, within);
+        if !lines.is_empty() {
+            response.on_hover_text(lines.join("\n"));
+        }
+    }
+}
+
+/// One line of stats for a timeline range, for pasting elsewhere.
+fn range_summary(slice: &Session, from: DateTime<Local>, until: DateTime<Local>) -> String {
+    let secs = (until - from).num_seconds();
+    format!(
+        "{} {}–{} ({}m {:02}s): {} events, {} keystrokes, {} mouse moves, {} clicks, \
+         {:.0} px mouse travel",
+        slice.task_name,
+        from.format("%Y-%m-%d %H:%M:%S"),
+        until.format("%H:%M:%S"),
+        secs / 60,
+        secs % 60,
+        analysis::event_count(slice),
+        keystroke::keystrokes(slice).len(),
+        slice.mouse_path.len(),
+        analysis::click_stats(slice).describe(),
+        analysis::mouse_distance_px(slice)
+    )
+}
+
+fn format_flags(flags: &[QualityFlag]) -> String {
+    flags
+        .iter()
+        .map(|flag| flag.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
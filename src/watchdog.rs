@@ -0,0 +1,56 @@
+//! Notices when capture stops ticking while recording, e.g. because the GUI
+//! stopped delivering frames, and asks for it to be restarted.
+
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Capture silent for this long while recording counts as stalled.
+pub const STALL_AFTER: Duration = Duration::from_secs(5);
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// When capture last ticked, shared between the monitor and the watchdog.
+/// Empty while nothing is being recorded.
+#[derive(Debug, Clone, Default)]
+pub struct Heartbeat(Arc<Mutex<Option<Instant>>>);
+
+impl Heartbeat {
+    pub fn beat(&self) {
+        *self.0.lock().unwrap() = Some(Instant::now());
+    }
+
+    pub fn clear(&self) {
+        *self.0.lock().unwrap() = None;
+    }
+
+    /// Time since the last tick, or `None` while not recording.
+    pub fn silent_for(&self) -> Option<Duration> {
+        self.0.lock().unwrap().map(|last| last.elapsed())
+    }
+}
+
+/// Checks `heartbeat` every second and calls `restart` once capture has
+/// been silent for [`STALL_AFTER`], then again every [`STALL_AFTER`] until
+/// it ticks. The gap itself is recorded by the monitor when capture resumes.
+pub fn spawn(heartbeat: Heartbeat, restart: impl Fn() + Send + 'static) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut last_restart: Option<Instant> = None;
+        loop {
+            thread::sleep(CHECK_INTERVAL);
+            let Some(silent) = heartbeat.silent_for() else {
+                last_restart = None;
+                continue;
+            };
+            if silent < STALL_AFTER {
+                last_restart = None;
+                continue;
+            }
+            if last_restart.is_some_and(|t| t.elapsed() < STALL_AFTER) {
+                continue;
+            }
+            eprintln!("Capture stalled for {}s; restarting it", silent.as_secs());
+            restart();
+            last_restart = Some(Instant::now());
+        }
+    })
+}
@@ -0,0 +1,158 @@
+use crate::config::WellnessConfig;
+use std::time::{Duration, Instant};
+
+/// What the break tracker noticed on a tick.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WellnessEvent {
+    /// Continuous activity reached a reminder point.
+    Reminder {
+        kind: BreakKind,
+        active_for: Duration,
+    },
+    /// Activity resumed after an idle gap long enough to count as a break.
+    BreakTaken {
+        kind: BreakKind,
+        idle_for: Duration,
+        /// Whether a reminder of this kind was pending when the break began.
+        reminded: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakKind {
+    Break,
+    MicroBreak,
+}
+
+impl BreakKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BreakKind::Break => "break",
+            BreakKind::MicroBreak => "micro_break",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "break" => Some(BreakKind::Break),
+            "micro_break" => Some(BreakKind::MicroBreak),
+            _ => None,
+        }
+    }
+}
+
+/// Tracks continuous activity for break reminders. Any input change counts
+/// as activity; an idle gap of at least the configured length counts as a
+/// break and restarts the matching timer.
+#[derive(Debug)]
+pub struct BreakTracker {
+    last_activity: Instant,
+    break_since: Instant,
+    micro_since: Instant,
+    break_due: bool,
+    micro_due: bool,
+}
+
+impl Default for BreakTracker {
+    fn default() -> Self {
+        let now = Instant::now();
+        Self {
+            last_activity: now,
+            break_since: now,
+            micro_since: now,
+            break_due: false,
+            micro_due: false,
+        }
+    }
+}
+
+impl BreakTracker {
+    /// Restarts all timers, e.g. when a session starts.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// The reminder currently waiting for a break, if any.
+    pub fn due(&self) -> Option<BreakKind> {
+        if self.break_due {
+            Some(BreakKind::Break)
+        } else if self.micro_due {
+            Some(BreakKind::MicroBreak)
+        } else {
+            None
+        }
+    }
+
+    /// How long the user has been active without a full break.
+    pub fn active_for(&self, now: Instant) -> Duration {
+        now.duration_since(self.break_since)
+    }
+
+    pub fn tick(
+        &mut self,
+        now: Instant,
+        active: bool,
+        config: &WellnessConfig,
+    ) -> Vec<WellnessEvent> {
+        let mut events = Vec::new();
+        if !config.enabled {
+            return events;
+        }
+
+        let break_len = Duration::from_secs(config.break_minutes * 60);
+        let micro_len = Duration::from_secs(config.micro_break_secs);
+
+        if active {
+            let idle_for = now.duration_since(self.last_activity);
+            if idle_for >= break_len {
+                events.push(WellnessEvent::BreakTaken {
+                    kind: BreakKind::Break,
+                    idle_for,
+                    reminded: self.break_due,
+                });
+                self.break_since = now;
+                self.micro_since = now;
+                self.break_due = false;
+                self.micro_due = false;
+            } else if config.micro_breaks && idle_for >= micro_len {
+                events.push(WellnessEvent::BreakTaken {
+                    kind: BreakKind::MicroBreak,
+                    idle_for,
+                    reminded: self.micro_due,
+                });
+                self.micro_since = now;
+                self.micro_due = false;
+            }
+            self.last_activity = now;
+        }
+
+        // Only count time towards a reminder while the user is actually
+        // there; the idle gap is judged when activity resumes.
+        if now.duration_since(self.last_activity) >= micro_len {
+            return events;
+        }
+
+        let active_for = now.duration_since(self.break_since);
+        if !self.break_due && active_for >= Duration::from_secs(config.remind_after_minutes * 60) {
+            self.break_due = true;
+            events.push(WellnessEvent::Reminder {
+                kind: BreakKind::Break,
+                active_for,
+            });
+        }
+
+        let micro_active = now.duration_since(self.micro_since);
+        if config.micro_breaks
+            && !self.micro_due
+            && !self.break_due
+            && micro_active >= Duration::from_secs(config.micro_break_every_minutes * 60)
+        {
+            self.micro_due = true;
+            events.push(WellnessEvent::Reminder {
+                kind: BreakKind::MicroBreak,
+                active_for: micro_active,
+            });
+        }
+        events
+    }
+}
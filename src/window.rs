@@ -0,0 +1,357 @@
+use crate::types::WindowBounds;
+use serde::Serialize;
+
+/// The window that has keyboard focus. `id` is only meaningful for telling
+/// windows apart within one desktop session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FocusedWindow {
+    pub id: u64,
+    pub bounds: WindowBounds,
+}
+
+/// Custom event kind the element under a pressed mouse button is recorded
+/// under.
+pub const TARGET_KIND: &str = "click_target";
+
+/// The user interface element under the pointer: its accessibility `role`
+/// (`window`, `button`, ...), its accessible `name` and the application
+/// it belongs to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ClickTarget {
+    pub role: String,
+    pub name: String,
+    pub app: Option<String>,
+}
+
+/// Runs `f` with this thread's connection to the X display.
+#[cfg(target_os = "linux")]
+fn with_display<T>(f: impl FnOnce(*mut x11::xlib::Display) -> Option<T>) -> Option<T> {
+    use std::cell::Cell;
+    use x11::xlib;
+
+    thread_local! {
+        static DISPLAY: Cell<*mut xlib::Display> = const { Cell::new(std::ptr::null_mut()) };
+    }
+
+    DISPLAY.with(|display| unsafe {
+        if display.get().is_null() {
+            display.set(xlib::XOpenDisplay(std::ptr::null()));
+        }
+        let dpy = display.get();
+        if dpy.is_null() {
+            return None;
+        }
+        f(dpy)
+    })
+}
+
+/// Runs `f` with the display and the EWMH `_NET_ACTIVE_WINDOW` of the root
+/// window, which needs a window manager that sets it (most do).
+#[cfg(target_os = "linux")]
+fn with_active_window<T>(
+    f: impl FnOnce(*mut x11::xlib::Display, x11::xlib::Window) -> Option<T>,
+) -> Option<T> {
+    use x11::xlib;
+
+    with_display(|dpy| unsafe {
+        let root = xlib::XDefaultRootWindow(dpy);
+        let atom = xlib::XInternAtom(dpy, c"_NET_ACTIVE_WINDOW".as_ptr(), xlib::True);
+        if atom == 0 {
+            return None;
+        }
+
+        let mut actual_type = 0;
+        let mut actual_format = 0;
+        let mut items = 0;
+        let mut bytes_after = 0;
+        let mut prop: *mut u8 = std::ptr::null_mut();
+        let status = xlib::XGetWindowProperty(
+            dpy,
+            root,
+            atom,
+            0,
+            1,
+            xlib::False,
+            xlib::XA_WINDOW,
+            &mut actual_type,
+            &mut actual_format,
+            &mut items,
+            &mut bytes_after,
+            &mut prop,
+        );
+        if status != 0 || prop.is_null() {
+            return None;
+        }
+        // Format-32 properties are returned as an array of C longs.
+        let window = if actual_format == 32 && items == 1 {
+            *(prop as *const std::os::raw::c_ulong)
+        } else {
+            0
+        };
+        xlib::XFree(prop.cast());
+        if window == 0 {
+            return None;
+        }
+        f(dpy, window)
+    })
+}
+
+/// The focused window, where supported.
+///
+/// On Linux this is the EWMH `_NET_ACTIVE_WINDOW` of the root window, so it
+/// needs a window manager that sets it (most do). Bounds are the window's
+/// inner area in root (global) coordinates, without decorations.
+#[cfg(target_os = "linux")]
+pub fn focused_window() -> Option<FocusedWindow> {
+    use x11::xlib;
+
+    with_active_window(|dpy, window| unsafe {
+        let root = xlib::XDefaultRootWindow(dpy);
+        let mut attributes: xlib::XWindowAttributes = std::mem::zeroed();
+        if xlib::XGetWindowAttributes(dpy, window, &mut attributes) == 0 {
+            return None;
+        }
+        let (mut x, mut y, mut child) = (0, 0, 0);
+        if xlib::XTranslateCoordinates(dpy, window, root, 0, 0, &mut x, &mut y, &mut child) == 0 {
+            return None;
+        }
+        Some(FocusedWindow {
+            id: window,
+            bounds: WindowBounds {
+                x,
+                y,
+                width: attributes.width.max(0) as u32,
+                height: attributes.height.max(0) as u32,
+            },
+        })
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn focused_window() -> Option<FocusedWindow> {
+    None
+}
+
+/// Name of the application owning the focused window, where supported.
+///
+/// On Linux this is the class of the window's `WM_CLASS`, e.g. `firefox`
+/// or `Slack`. It's saved with focus changes and used for app usage
+/// alerts.
+#[cfg(target_os = "linux")]
+pub fn focused_app() -> Option<String> {
+    with_active_window(|dpy, window| unsafe { class_name(dpy, window) })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn focused_app() -> Option<String> {
+    None
+}
+
+/// The class of `window`'s `WM_CLASS`, if it has one.
+#[cfg(target_os = "linux")]
+unsafe fn class_name(dpy: *mut x11::xlib::Display, window: x11::xlib::Window) -> Option<String> {
+    use std::ffi::CStr;
+    use x11::xlib;
+
+    let mut hint = xlib::XClassHint {
+        res_name: std::ptr::null_mut(),
+        res_class: std::ptr::null_mut(),
+    };
+    if xlib::XGetClassHint(dpy, window, &mut hint) == 0 {
+        return None;
+    }
+    let class = (!hint.res_class.is_null()).then(|| {
+        CStr::from_ptr(hint.res_class)
+            .to_string_lossy()
+            .into_owned()
+    });
+    for ptr in [hint.res_name, hint.res_class] {
+        if !ptr.is_null() {
+            xlib::XFree(ptr.cast());
+        }
+    }
+    class
+}
+
+/// Title of the focused window, where supported.
+///
+/// On Linux this is the window's `WM_NAME`, which window managers show in
+/// its title bar.
+#[cfg(target_os = "linux")]
+pub fn focused_title() -> Option<String> {
+    with_active_window(|dpy, window| unsafe { window_name(dpy, window) })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn focused_title() -> Option<String> {
+    None
+}
+
+/// `window`'s `WM_NAME`, if it has one.
+#[cfg(target_os = "linux")]
+unsafe fn window_name(dpy: *mut x11::xlib::Display, window: x11::xlib::Window) -> Option<String> {
+    use std::ffi::CStr;
+    use x11::xlib;
+
+    let mut name: *mut std::os::raw::c_char = std::ptr::null_mut();
+    if xlib::XFetchName(dpy, window, &mut name) == 0 || name.is_null() {
+        return None;
+    }
+    let title = CStr::from_ptr(name).to_string_lossy().into_owned();
+    xlib::XFree(name.cast());
+    Some(title)
+}
+
+/// The element at `coords` in global screen coordinates, where supported.
+///
+/// On Linux the application window under the pointer is found with X11: the
+/// first window down from the root that has a `WM_CLASS`, so window manager
+/// frames are skipped. The element within it (a button, link, text field)
+/// is then read over AT-SPI from the accessible window of the same title.
+/// Apps that don't expose accessibility, or desktops without an AT-SPI bus,
+/// give the window itself, named by its title. Other platforms (UI
+/// Automation, AX) aren't supported yet.
+#[cfg(target_os = "linux")]
+pub fn element_at(coords: (i32, i32)) -> Option<ClickTarget> {
+    use x11::xlib;
+
+    let (app, title) = with_display(|dpy| unsafe {
+        let mut window = xlib::XDefaultRootWindow(dpy);
+        // Frames and toolkit windows nest, but not deeply.
+        for _ in 0..8 {
+            let (mut x, mut y, mut child) = (0, 0, 0);
+            let root = xlib::XDefaultRootWindow(dpy);
+            let translated = xlib::XTranslateCoordinates(
+                dpy, root, window, coords.0, coords.1, &mut x, &mut y, &mut child,
+            );
+            if translated == 0 || child == 0 {
+                return None;
+            }
+            window = child;
+            if let Some(app) = class_name(dpy, window) {
+                return Some((app, window_name(dpy, window).unwrap_or_default()));
+            }
+        }
+        None
+    })?;
+    let (role, name) =
+        atspi::element_at(&title, coords).unwrap_or_else(|| ("window".to_string(), title));
+    Some(ClickTarget {
+        role,
+        name,
+        app: Some(app),
+    })
+}
+
+/// Accessible elements read over AT-SPI, the D-Bus accessibility interface
+/// GTK, Qt, Firefox and Chromium implement on Linux.
+#[cfg(target_os = "linux")]
+mod atspi {
+    use atspi_proxies::accessible::AccessibleProxyBlocking;
+    use atspi_proxies::bus::BusProxyBlocking;
+    use atspi_proxies::common::{CoordType, ObjectRef};
+    use atspi_proxies::component::ComponentProxyBlocking;
+    use std::sync::OnceLock;
+    use std::time::Duration;
+    use zbus::blocking::{connection, Connection};
+
+    /// Clicks are recorded on the capture thread, so a hung app mustn't
+    /// hold it up for long.
+    const TIMEOUT: Duration = Duration::from_millis(200);
+    const NULL_PATH: &str = "/org/a11y/atspi/accessible/null";
+
+    /// The accessibility bus, connected on first use. `None` when there
+    /// isn't one, e.g. without a session bus.
+    fn bus() -> Option<&'static Connection> {
+        static BUS: OnceLock<Option<Connection>> = OnceLock::new();
+        BUS.get_or_init(|| {
+            let session = Connection::session().ok()?;
+            let address = BusProxyBlocking::new(&session).ok()?.get_address().ok()?;
+            connection::Builder::address(address.as_str())
+                .ok()?
+                .method_timeout(TIMEOUT)
+                .build()
+                .ok()
+        })
+        .as_ref()
+    }
+
+    fn accessible<'a>(
+        bus: &'a Connection,
+        object: &ObjectRef,
+    ) -> Option<AccessibleProxyBlocking<'a>> {
+        AccessibleProxyBlocking::builder(bus)
+            .destination(object.name.clone())
+            .ok()?
+            .path(object.path.clone())
+            .ok()?
+            .build()
+            .ok()
+    }
+
+    fn component<'a>(
+        bus: &'a Connection,
+        object: &ObjectRef,
+    ) -> Option<ComponentProxyBlocking<'a>> {
+        ComponentProxyBlocking::builder(bus)
+            .destination(object.name.clone())
+            .ok()?
+            .path(object.path.clone())
+            .ok()?
+            .build()
+            .ok()
+    }
+
+    /// The role and name of the deepest element at `coords` in the
+    /// accessible top-level window named `title`, if an app has one.
+    pub(super) fn element_at(title: &str, coords: (i32, i32)) -> Option<(String, String)> {
+        let bus = bus()?;
+        let registry = AccessibleProxyBlocking::builder(bus)
+            .destination("org.a11y.atspi.Registry")
+            .ok()?
+            .path("/org/a11y/atspi/accessible/root")
+            .ok()?
+            .build()
+            .ok()?;
+        let window = registry
+            .get_children()
+            .ok()?
+            .iter()
+            .filter_map(|app| accessible(bus, app)?.get_children().ok())
+            .flatten()
+            .find(|window| {
+                let named = accessible(bus, window)
+                    .and_then(|w| w.name().ok())
+                    .is_some_and(|name| name == title);
+                named
+                    && component(bus, window)
+                        .and_then(|w| w.contains(coords.0, coords.1, CoordType::Screen).ok())
+                        .unwrap_or(false)
+            })?;
+
+        let mut element = window;
+        // Each step goes one level down; real trees are much shallower.
+        for _ in 0..32 {
+            let Some(child) = component(bus, &element).and_then(|c| {
+                c.get_accessible_at_point(coords.0, coords.1, CoordType::Screen)
+                    .ok()
+            }) else {
+                break;
+            };
+            if child.path.as_str() == NULL_PATH || child == element {
+                break;
+            }
+            element = child;
+        }
+        let element = accessible(bus, &element)?;
+        Some((
+            element.get_role_name().ok()?,
+            element.name().unwrap_or_default(),
+        ))
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn element_at(_coords: (i32, i32)) -> Option<ClickTarget> {
+    None
+}
@@ -0,0 +1,267 @@
+//! Minimal XLSX (Office Open XML) writer: text and number cells with a bold
+//! header row. Enough for exports to open in Excel, LibreOffice and Google
+//! Sheets.
+
+use anyhow::{bail, Result};
+use std::fs::File;
+use std::io::{BufWriter, Seek, Write};
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+/// Excel's row limit per sheet, including the header.
+pub const MAX_ROWS: usize = 1_048_576;
+
+#[derive(Debug, Clone)]
+pub enum Cell {
+    Text(String),
+    Number(f64),
+    Empty,
+}
+
+impl From<&str> for Cell {
+    fn from(text: &str) -> Self {
+        Cell::Text(text.to_string())
+    }
+}
+
+impl From<String> for Cell {
+    fn from(text: String) -> Self {
+        Cell::Text(text)
+    }
+}
+
+impl From<f64> for Cell {
+    fn from(value: f64) -> Self {
+        Cell::Number(value)
+    }
+}
+
+impl From<i64> for Cell {
+    fn from(value: i64) -> Self {
+        Cell::Number(value as f64)
+    }
+}
+
+impl From<usize> for Cell {
+    fn from(value: usize) -> Self {
+        Cell::Number(value as f64)
+    }
+}
+
+impl<T: Into<Cell>> From<Option<T>> for Cell {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Cell::Empty, Into::into)
+    }
+}
+
+/// A sheet whose first row is a bold header.
+pub struct Sheet {
+    name: String,
+    xml: String,
+    rows: usize,
+}
+
+impl Sheet {
+    /// `name` is cleaned up to what Excel accepts: at most 31 characters and
+    /// none of `[]:*?/\`.
+    pub fn new(name: &str, header: &[&str]) -> Self {
+        let name: String = name
+            .chars()
+            .map(|c| if "[]:*?/\\".contains(c) { '_' } else { c })
+            .take(31)
+            .collect();
+        let mut sheet = Self {
+            name,
+            xml: String::new(),
+            rows: 0,
+        };
+        let header: Vec<Cell> = header.iter().map(|&h| h.into()).collect();
+        sheet.push_row_styled(&header, true);
+        sheet
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether another row fits under Excel's limit.
+    pub fn is_full(&self) -> bool {
+        self.rows >= MAX_ROWS
+    }
+
+    pub fn push_row(&mut self, cells: &[Cell]) {
+        self.push_row_styled(cells, false);
+    }
+
+    fn push_row_styled(&mut self, cells: &[Cell], bold: bool) {
+        if self.is_full() {
+            return;
+        }
+        self.rows += 1;
+        let row = self.rows;
+        self.xml.push_str(&format!("<row r=\"{}\">", row));
+        let style = if bold { " s=\"1\"" } else { "" };
+        for (col, cell) in cells.iter().enumerate() {
+            let reference = format!("{}{}", column_name(col), row);
+            match cell {
+                Cell::Text(text) if !text.is_empty() => self.xml.push_str(&format!(
+                    "<c r=\"{}\" t=\"inlineStr\"{}><is><t xml:space=\"preserve\">{}</t></is></c>",
+                    reference,
+                    style,
+                    escape(text)
+                )),
+                Cell::Number(value) if value.is_finite() => self.xml.push_str(&format!(
+                    "<c r=\"{}\"{}><v>{}</v></c>",
+                    reference, style, value
+                )),
+                Cell::Text(_) | Cell::Number(_) | Cell::Empty => {}
+            }
+        }
+        self.xml.push_str("</row>");
+    }
+}
+
+/// Writes `sheets` to an `.xlsx` workbook at `path`. Sheet names must be
+/// unique.
+pub fn write(path: impl AsRef<Path>, sheets: &[Sheet]) -> Result<()> {
+    for (i, sheet) in sheets.iter().enumerate() {
+        if sheets[..i]
+            .iter()
+            .any(|s| s.name.to_lowercase() == sheet.name.to_lowercase())
+        {
+            bail!("duplicate sheet name {:?}", sheet.name);
+        }
+    }
+
+    let mut zip = ZipWriter::new(BufWriter::new(File::create(path)?));
+    let mut content_types = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\
+         <Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">\
+         <Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>\
+         <Default Extension=\"xml\" ContentType=\"application/xml\"/>\
+         <Override PartName=\"/xl/workbook.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml\"/>\
+         <Override PartName=\"/xl/styles.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml\"/>",
+    );
+    let mut workbook = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\
+         <workbook xmlns=\"http://schemas.openxmlformats.org/spreadsheetml/2006/main\" \
+         xmlns:r=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships\"><sheets>",
+    );
+    let mut workbook_rels = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\
+         <Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">",
+    );
+    for (i, sheet) in sheets.iter().enumerate() {
+        let n = i + 1;
+        content_types.push_str(&format!(
+            "<Override PartName=\"/xl/worksheets/sheet{}.xml\" \
+             ContentType=\"application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml\"/>",
+            n
+        ));
+        workbook.push_str(&format!(
+            "<sheet name=\"{}\" sheetId=\"{}\" r:id=\"rId{}\"/>",
+            escape(&sheet.name),
+            n,
+            n
+        ));
+        workbook_rels.push_str(&format!(
+            "<Relationship Id=\"rId{}\" \
+             Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet\" \
+             Target=\"worksheets/sheet{}.xml\"/>",
+            n, n
+        ));
+    }
+    content_types.push_str("</Types>");
+    workbook.push_str("</sheets></workbook>");
+    workbook_rels.push_str(&format!(
+        "<Relationship Id=\"rId{}\" \
+         Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles\" \
+         Target=\"styles.xml\"/></Relationships>",
+        sheets.len() + 1
+    ));
+
+    add(&mut zip, "[Content_Types].xml", content_types.as_bytes())?;
+    add(
+        &mut zip,
+        "_rels/.rels",
+        b"<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\
+          <Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\
+          <Relationship Id=\"rId1\" \
+          Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument\" \
+          Target=\"xl/workbook.xml\"/></Relationships>",
+    )?;
+    add(&mut zip, "xl/workbook.xml", workbook.as_bytes())?;
+    add(
+        &mut zip,
+        "xl/_rels/workbook.xml.rels",
+        workbook_rels.as_bytes(),
+    )?;
+    add(&mut zip, "xl/styles.xml", STYLES.as_bytes())?;
+    for (i, sheet) in sheets.iter().enumerate() {
+        let xml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\
+             <worksheet xmlns=\"http://schemas.openxmlformats.org/spreadsheetml/2006/main\">\
+             <sheetData>{}</sheetData></worksheet>",
+            sheet.xml
+        );
+        add(
+            &mut zip,
+            &format!("xl/worksheets/sheet{}.xml", i + 1),
+            xml.as_bytes(),
+        )?;
+    }
+    zip.finish()?.flush()?;
+    Ok(())
+}
+
+/// Style 0 is the default, style 1 is bold.
+const STYLES: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\
+<styleSheet xmlns=\"http://schemas.openxmlformats.org/spreadsheetml/2006/main\">\
+<fonts count=\"2\"><font><sz val=\"11\"/><name val=\"Calibri\"/></font>\
+<font><b/><sz val=\"11\"/><name val=\"Calibri\"/></font></fonts>\
+<fills count=\"2\"><fill><patternFill patternType=\"none\"/></fill>\
+<fill><patternFill patternType=\"gray125\"/></fill></fills>\
+<borders count=\"1\"><border><left/><right/><top/><bottom/><diagonal/></border></borders>\
+<cellStyleXfs count=\"1\"><xf numFmtId=\"0\" fontId=\"0\" fillId=\"0\" borderId=\"0\"/></cellStyleXfs>\
+<cellXfs count=\"2\"><xf numFmtId=\"0\" fontId=\"0\" fillId=\"0\" borderId=\"0\" xfId=\"0\"/>\
+<xf numFmtId=\"0\" fontId=\"1\" fillId=\"0\" borderId=\"0\" xfId=\"0\" applyFont=\"1\"/></cellXfs>\
+</styleSheet>";
+
+/// `A`, `B`, ..., `Z`, `AA`, ... for a zero-based column index.
+fn column_name(mut col: usize) -> String {
+    let mut name = Vec::new();
+    loop {
+        name.push(b'A' + (col % 26) as u8);
+        if col < 26 {
+            break;
+        }
+        col = col / 26 - 1;
+    }
+    name.reverse();
+    String::from_utf8(name).unwrap_or_default()
+}
+
+/// Escapes XML markup and drops control characters XML can't contain.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c < ' ' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Adds a deflated entry to `zip`.
+fn add<W: Write + Seek>(zip: &mut ZipWriter<W>, name: &str, data: &[u8]) -> Result<()> {
+    zip.start_file(name, SimpleFileOptions::default())?;
+    zip.write_all(data)?;
+    Ok(())
+}
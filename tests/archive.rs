@@ -0,0 +1,37 @@
+//! Turning the archive on changes where every monitor in the working
+//! directory stores sessions, so it gets a test binary of its own.
+
+use desk_monitor::storage::SESSIONS_FILE;
+use desk_monitor::{ActivityMonitor, Config, MockInput};
+use device_query::Keycode;
+use std::{env, fs};
+
+#[test]
+fn the_archive_replaces_the_sessions_file() {
+    let cwd = env::temp_dir().join(format!("desk-monitor-archive-{}", std::process::id()));
+    fs::create_dir_all(&cwd).unwrap();
+    env::set_current_dir(&cwd).unwrap();
+    let mut config = Config::default();
+    config.archive.enabled = true;
+    config.save().unwrap();
+
+    let input = MockInput::new();
+    let mut monitor = ActivityMonitor::with_input(Box::new(input.clone())).unwrap();
+    monitor.set_task_name("archive test");
+    monitor.start_monitoring();
+    input.set_keys(vec![Keycode::F]);
+    monitor.update();
+    monitor.stop_monitoring();
+    monitor.finish_session(None);
+
+    assert!(!cwd.join(SESSIONS_FILE).exists());
+    let weeks: Vec<_> = fs::read_dir(cwd.join(&config.archive.directory))
+        .unwrap()
+        .collect();
+    assert_eq!(weeks.len(), 1);
+    let stored = monitor.stored_sessions().unwrap();
+    assert_eq!(stored.len(), 1);
+    assert_eq!(stored[0].session_id, monitor.current_session().session_id);
+    let reloaded = Config::load().unwrap().stored_sessions().unwrap();
+    assert_eq!(reloaded[0].session_id, stored[0].session_id);
+}
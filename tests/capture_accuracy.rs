@@ -0,0 +1,1452 @@
+//! Drives the capture loop from a `MockInput` with an irregular number of
+//! update ticks between input changes, checking that every change is
+//! recorded exactly once.
+
+use desk_monitor::bug_report;
+use desk_monitor::config::AppAlert;
+use desk_monitor::consent::{self, CONSENT_FILE};
+use desk_monitor::event_bus::SinkPolicy;
+use desk_monitor::integrity;
+use desk_monitor::privacy::RedactionRule;
+use desk_monitor::quality::QualityFlag;
+use desk_monitor::screenshot::Screenshot;
+use desk_monitor::storage::Storage;
+use desk_monitor::window::{self, ClickTarget, FocusedWindow};
+use desk_monitor::{
+    analysis, clipboard, keystroke, lsl, sync, Action, ActivityMonitor, MockInput, MonitorEvent,
+    Session, WindowBounds,
+};
+use device_query::Keycode;
+use std::io::Read;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::{env, fs, path::Path, thread, time::Duration};
+
+/// Update ticks between successive input changes, standing in for a GUI
+/// whose frame rate varies.
+const TICK_PATTERN: [usize; 6] = [1, 4, 2, 7, 1, 3];
+
+fn start_monitor(input: &MockInput) -> ActivityMonitor {
+    let dir = env::temp_dir().join(format!("desk-monitor-capture-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    env::set_current_dir(&dir).unwrap();
+
+    let mut monitor = ActivityMonitor::with_input(Box::new(input.clone())).unwrap();
+    monitor.set_task_name("capture test");
+    monitor.start_monitoring();
+    monitor
+}
+
+fn tick(monitor: &mut ActivityMonitor, step: usize) {
+    for _ in 0..TICK_PATTERN[step % TICK_PATTERN.len()] {
+        monitor.update();
+    }
+    if step.is_multiple_of(3) {
+        thread::sleep(Duration::from_millis(2));
+    }
+}
+
+fn recorded_keys(monitor: &ActivityMonitor) -> Vec<Vec<String>> {
+    monitor
+        .current_session()
+        .actions
+        .iter()
+        .filter_map(|action| match action {
+            Action::KeyPress { keys, .. } => Some(keys.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn idle_ticks_record_nothing() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+
+    for step in 0..50 {
+        tick(&mut monitor, step);
+    }
+
+    assert!(monitor.current_session().actions.is_empty());
+    assert!(monitor.current_session().mouse_path.is_empty());
+}
+
+#[test]
+fn key_changes_are_recorded_once_per_change() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+
+    let script = [
+        vec![Keycode::A],
+        vec![Keycode::A, Keycode::LShift],
+        vec![Keycode::LShift],
+        vec![],
+        vec![Keycode::Enter],
+        vec![],
+    ];
+    for (step, keys) in script.iter().enumerate() {
+        input.set_keys(keys.clone());
+        tick(&mut monitor, step);
+    }
+
+    let expected: Vec<Vec<String>> = script
+        .iter()
+        .map(|keys| keys.iter().map(|k| format!("{:?}", k)).collect())
+        .collect();
+    assert_eq!(recorded_keys(&monitor), expected);
+}
+
+#[test]
+fn mouse_moves_are_deduplicated_and_complete() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+
+    let script = [(10, 10), (11, 10), (11, 10), (300, -40), (0, 0), (5, 5)];
+    for (step, coords) in script.iter().enumerate() {
+        input.set_mouse_position(*coords);
+        tick(&mut monitor, step);
+    }
+
+    let recorded: Vec<_> = monitor
+        .current_session()
+        .mouse_path
+        .iter()
+        .map(|point| point.coords)
+        .collect();
+    assert_eq!(
+        recorded,
+        vec![(10, 10), (11, 10), (300, -40), (0, 0), (5, 5)]
+    );
+}
+
+#[test]
+fn interleaved_input_keeps_recording_order() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+
+    input.set_mouse_position((1, 1));
+    tick(&mut monitor, 0);
+    input.set_keys(vec![Keycode::B]);
+    tick(&mut monitor, 1);
+    input.set_mouse_position((2, 2));
+    input.set_keys(vec![]);
+    tick(&mut monitor, 2);
+
+    let kinds: Vec<&str> = monitor
+        .current_session()
+        .ordered_actions()
+        .iter()
+        .map(|action| match action {
+            Action::MouseMove { .. } => "mouse",
+            Action::KeyPress { .. } => "key",
+            _ => "other",
+        })
+        .collect();
+    assert_eq!(kinds, vec!["mouse", "key", "key", "mouse"]);
+}
+
+#[test]
+fn failed_device_is_recorded_and_reconnected() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+
+    input.set_failing(true);
+    for step in 0..10 {
+        tick(&mut monitor, step);
+    }
+    assert!(monitor.capture_problem().is_some());
+
+    input.set_failing(false);
+    input.set_keys(vec![Keycode::A]);
+    thread::sleep(Duration::from_millis(1100));
+    monitor.update();
+    assert!(monitor.capture_problem().is_none());
+
+    let interruptions: Vec<&Action> = monitor
+        .current_session()
+        .actions
+        .iter()
+        .filter(|action| matches!(action, Action::CaptureInterrupted { .. }))
+        .collect();
+    assert_eq!(interruptions.len(), 1);
+    assert!(matches!(
+        interruptions[0],
+        Action::CaptureInterrupted {
+            resumed_at: Some(_),
+            ..
+        }
+    ));
+    assert_eq!(recorded_keys(&monitor), vec![vec!["A".to_string()]]);
+
+    monitor.stop_monitoring();
+    if monitor.awaiting_notes() {
+        monitor.finish_session(None);
+    }
+    assert_eq!(
+        monitor.current_session().quality_flags,
+        Some(vec![QualityFlag::CaptureFailure])
+    );
+}
+
+#[test]
+fn subscribers_receive_every_recorded_input() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+    let events = monitor.subscribe();
+
+    for step in 0..12 {
+        if step % 2 == 0 {
+            input.set_keys(if step % 4 == 0 {
+                vec![Keycode::A]
+            } else {
+                vec![]
+            });
+        } else {
+            input.set_mouse_position((step as i32 * 7, step as i32 * 3));
+        }
+        tick(&mut monitor, step);
+    }
+    monitor.stop_monitoring();
+
+    let mut keys = Vec::new();
+    let mut mouse_moves = 0;
+    let mut stopped = false;
+    for event in events.try_iter() {
+        match event {
+            MonitorEvent::Recorded(Action::KeyPress { keys: k, .. }) => keys.push(k),
+            MonitorEvent::Recorded(Action::MouseMove { .. }) => mouse_moves += 1,
+            MonitorEvent::Stopped { .. } => stopped = true,
+            _ => {}
+        }
+    }
+    assert_eq!(keys, recorded_keys(&monitor));
+    assert_eq!(mouse_moves, monitor.current_session().mouse_path.len());
+    assert!(stopped);
+}
+
+#[test]
+fn timestamps_keep_order_within_a_second() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+
+    for step in 0..20 {
+        input.set_keys(if step % 2 == 0 {
+            vec![Keycode::A]
+        } else {
+            vec![]
+        });
+        input.set_mouse_position((step + 1, step + 1));
+        monitor.update();
+    }
+
+    let events = analysis::timed_events(monitor.current_session());
+    assert_eq!(events.len(), 40);
+    assert!(events.iter().skip(1).all(|e| e.delta_ms >= Some(0)));
+    let timestamps: Vec<&str> = events.iter().map(|e| e.action.timestamp()).collect();
+    let mut sorted = timestamps.clone();
+    sorted.sort();
+    assert_eq!(timestamps, sorted);
+}
+
+#[test]
+fn focus_changes_carry_window_bounds() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+    monitor.config_mut().capture.focus_changes = true;
+    monitor.config_mut().capture.window_geometry = true;
+    let editor = WindowBounds {
+        x: 100,
+        y: 50,
+        width: 800,
+        height: 600,
+    };
+
+    input.set_focused_window(Some(FocusedWindow {
+        id: 7,
+        bounds: editor,
+    }));
+    input.set_focused_app(Some("code"));
+    for step in 0..6 {
+        input.set_mouse_position((150 + step, 70));
+        tick(&mut monitor, step as usize);
+    }
+    // Outside the focused window.
+    input.set_mouse_position((20, 20));
+    monitor.update();
+
+    let focus: Vec<&Action> = monitor
+        .current_session()
+        .actions
+        .iter()
+        .filter(|action| matches!(action, Action::FocusChange { .. }))
+        .collect();
+    assert!(matches!(
+        focus.as_slice(),
+        [Action::FocusChange {
+            window: 7,
+            bounds: Some(bounds),
+            app: Some(app),
+            ..
+        }] if *bounds == editor && app == "code"
+    ));
+
+    let moves = analysis::window_moves(monitor.current_session());
+    let coords: Vec<(i32, i32)> = moves.iter().map(|m| m.coords).collect();
+    assert_eq!(
+        coords,
+        (0..6).map(|step| (50 + step, 20)).collect::<Vec<_>>()
+    );
+    assert!(moves.iter().all(|m| m.window == 7));
+}
+
+#[test]
+fn keystroke_timings_follow_recorded_key_sets() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+
+    // A, then B pressed before A is released, then C after a pause.
+    let script = [
+        vec![Keycode::A],
+        vec![Keycode::A, Keycode::B],
+        vec![Keycode::B],
+        vec![],
+    ];
+    for (step, keys) in script.iter().enumerate() {
+        input.set_keys(keys.clone());
+        tick(&mut monitor, step);
+        thread::sleep(Duration::from_millis(5));
+    }
+    thread::sleep(Duration::from_millis(keystroke::BURST_GAP_MS as u64 + 50));
+    input.set_keys(vec![Keycode::C]);
+    monitor.update();
+    input.set_keys(vec![]);
+    monitor.update();
+
+    let bursts = keystroke::bursts(monitor.current_session());
+    assert_eq!(bursts.len(), 2);
+    let keys: Vec<&str> = bursts[0]
+        .keystrokes
+        .iter()
+        .map(|k| k.key.as_str())
+        .collect();
+    assert_eq!(keys, ["A", "B"]);
+    assert!(bursts[0].hold_times().iter().all(|&hold| hold > 0));
+    assert!(bursts[0].down_down()[0] > 0);
+    // B went down while A was still held.
+    assert!(bursts[0].up_down()[0] < 0);
+    assert_eq!(bursts[1].keystrokes.len(), 1);
+}
+
+#[test]
+fn slow_subscribers_drop_or_buffer_without_blocking() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+    let dropping = monitor.subscribe_sink("dropping", 2, SinkPolicy::Drop);
+    let buffering = monitor.subscribe_sink("buffering", 2, SinkPolicy::Buffer(100));
+    let gone = monitor.subscribe_sink("gone", 2, SinkPolicy::Drop);
+    drop(gone);
+
+    for step in 1..=10 {
+        input.set_mouse_position((step, step));
+        monitor.update();
+    }
+
+    let health = monitor.sink_health();
+    assert_eq!(
+        health.iter().map(|h| h.name.as_str()).collect::<Vec<_>>(),
+        ["dropping", "buffering"]
+    );
+    assert_eq!(health[0].delivered, 2);
+    assert!(health[0].dropped >= 8);
+    assert!(health[0].backlogged);
+    assert_eq!((health[1].delivered, health[1].dropped), (2, 0));
+    assert_eq!(health[1].buffered as u64, health[0].dropped);
+    assert_eq!(dropping.try_iter().count(), 2);
+
+    // Once the buffering sink catches up it gets every move, in order.
+    let mut coords = Vec::new();
+    while monitor.sink_health()[1].buffered > 0 {
+        coords.extend(buffering.try_iter().filter_map(|event| match event {
+            MonitorEvent::Recorded(Action::MouseMove { coords, .. }) => Some(coords),
+            _ => None,
+        }));
+        monitor.update();
+    }
+    coords.extend(buffering.try_iter().filter_map(|event| match event {
+        MonitorEvent::Recorded(Action::MouseMove { coords, .. }) => Some(coords),
+        _ => None,
+    }));
+    assert_eq!(coords, (1..=10).map(|i| (i, i)).collect::<Vec<_>>());
+    assert_eq!(monitor.sink_health()[1].dropped, 0);
+}
+
+#[test]
+fn injected_events_are_recorded_in_order() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+    let events = monitor.subscribe();
+
+    input.set_keys(vec![Keycode::A]);
+    monitor.update();
+    let payload = serde_json::json!({ "url": "https://example.com/a;b", "tab": 3 });
+    monitor
+        .inject_event("browser_tab", payload.clone())
+        .unwrap();
+    input.set_keys(vec![]);
+    monitor.update();
+
+    let kinds: Vec<String> = monitor
+        .current_session()
+        .actions
+        .iter()
+        .map(|action| action.kind_and_payload().0)
+        .filter(|kind| kind != "layout")
+        .collect();
+    assert_eq!(kinds, ["key", "browser_tab", "key"]);
+    let injected = events.try_iter().find_map(|event| match event {
+        MonitorEvent::Recorded(action @ Action::Custom { .. }) => Some(action),
+        _ => None,
+    });
+    let Some(Action::Custom {
+        kind, payload: p, ..
+    }) = injected
+        .as_ref()
+        .and_then(|a| Action::from_csv_string(&a.to_csv_string()))
+    else {
+        panic!("custom event wasn't published or didn't round-trip");
+    };
+    assert_eq!((kind.as_str(), p), ("browser_tab", payload));
+
+    monitor.stop_monitoring();
+    assert!(monitor
+        .inject_event("browser_tab", serde_json::Value::Null)
+        .is_err());
+}
+
+#[test]
+fn mouse_coordinates_are_binned_when_configured() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+    monitor.config_mut().privacy.mouse_grid_px = 100;
+
+    let script = [(10, 10), (40, 60), (150, 20), (199, 99), (-5, -5)];
+    for (step, coords) in script.iter().enumerate() {
+        input.set_mouse_position(*coords);
+        tick(&mut monitor, step);
+    }
+
+    let recorded: Vec<_> = monitor
+        .current_session()
+        .mouse_path
+        .iter()
+        .map(|point| point.coords)
+        .collect();
+    assert_eq!(recorded, vec![(50, 50), (150, 50), (-50, -50)]);
+}
+
+#[test]
+fn discarded_session_is_not_saved() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+    let events = monitor.subscribe();
+
+    input.set_keys(vec![Keycode::P]);
+    tick(&mut monitor, 0);
+    input.set_mouse_position((40, 40));
+    tick(&mut monitor, 1);
+    monitor.discard_session();
+
+    assert!(!monitor.is_running());
+    assert!(!monitor.awaiting_notes());
+    assert!(monitor.current_session().actions.is_empty());
+    assert!(monitor.current_session().mouse_path.is_empty());
+    let events: Vec<MonitorEvent> = events.try_iter().collect();
+    assert!(events
+        .iter()
+        .any(|event| matches!(event, MonitorEvent::Discarded { .. })));
+    assert!(!events.iter().any(|event| matches!(
+        event,
+        MonitorEvent::Stopped { .. } | MonitorEvent::SessionSaved { .. }
+    )));
+}
+
+#[test]
+fn paused_sessions_record_nothing_until_resumed() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+
+    monitor.pause();
+    assert!(monitor.is_running());
+    assert!(monitor.is_paused());
+    input.set_keys(vec![Keycode::A]);
+    tick(&mut monitor, 0);
+    input.set_keys(Vec::new());
+    tick(&mut monitor, 1);
+
+    monitor.resume();
+    assert!(!monitor.is_paused());
+    input.set_keys(vec![Keycode::B]);
+    tick(&mut monitor, 2);
+
+    assert_eq!(recorded_keys(&monitor), vec![vec!["B".to_string()]]);
+    let kinds: Vec<&str> = monitor
+        .current_session()
+        .actions
+        .iter()
+        .filter_map(|action| match action {
+            Action::Custom { kind, .. } => Some(kind.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(kinds, ["pause", "resume"]);
+}
+
+#[test]
+fn live_metrics_follow_the_running_session() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+    monitor.switch_task("metrics test".to_string());
+
+    for (step, key) in [Keycode::A, Keycode::B, Keycode::C].into_iter().enumerate() {
+        input.set_keys(vec![key]);
+        tick(&mut monitor, step * 2);
+        input.set_keys(Vec::new());
+        tick(&mut monitor, step * 2 + 1);
+    }
+
+    let metrics = monitor.metrics();
+    assert_eq!(metrics.apm, 3);
+    assert!(metrics.task_secs.contains_key("metrics test"));
+    assert!(metrics.task_secs.contains_key("capture test"));
+
+    monitor.stop_monitoring();
+    assert_eq!(monitor.metrics().apm, 0);
+}
+
+#[test]
+fn input_to_the_monitor_window_is_ignored() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+
+    monitor.set_self_focus(true, true);
+    input.set_keys(vec![Keycode::S]);
+    input.set_mouse_position((10, 10));
+    tick(&mut monitor, 0);
+    input.set_keys(Vec::new());
+    tick(&mut monitor, 1);
+
+    monitor.set_self_focus(false, false);
+    input.set_keys(vec![Keycode::T]);
+    input.set_mouse_position((20, 20));
+    tick(&mut monitor, 2);
+
+    assert_eq!(recorded_keys(&monitor), vec![vec!["T".to_string()]]);
+    let moves: Vec<(i32, i32)> = monitor
+        .current_session()
+        .mouse_path
+        .iter()
+        .map(|point| point.coords)
+        .collect();
+    assert_eq!(moves, vec![(20, 20)]);
+}
+
+#[test]
+fn stalled_capture_is_recorded_as_a_gap() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+    tick(&mut monitor, 0);
+
+    thread::sleep(desk_monitor::watchdog::STALL_AFTER + Duration::from_millis(100));
+    tick(&mut monitor, 1);
+
+    let gaps: Vec<i64> = monitor
+        .current_session()
+        .actions
+        .iter()
+        .filter_map(|action| match action {
+            Action::CaptureGap { gap_ms, .. } => Some(*gap_ms),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(gaps.len(), 1);
+    assert!(gaps[0] >= 5000);
+}
+
+#[test]
+fn mouse_button_holds_are_recorded_on_release() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+    tick(&mut monitor, 0);
+
+    input.set_mouse_position((40, 50));
+    input.set_mouse_buttons(vec![false, true]);
+    tick(&mut monitor, 1);
+    thread::sleep(Duration::from_millis(50));
+    input.set_mouse_position((60, 50));
+    input.set_mouse_buttons(vec![false, false]);
+    tick(&mut monitor, 2);
+
+    let buttons: Vec<(String, (i32, i32), Option<i64>)> = monitor
+        .current_session()
+        .actions
+        .iter()
+        .filter_map(|action| match action {
+            Action::MouseDown { button, coords, .. } => Some((button.clone(), *coords, None)),
+            Action::MouseUp {
+                button,
+                coords,
+                hold_ms,
+                ..
+            } => Some((button.clone(), *coords, Some(*hold_ms))),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(buttons.len(), 2);
+    assert_eq!(buttons[0], ("left".to_string(), (40, 50), None));
+    assert_eq!(
+        (&buttons[1].0, buttons[1].1),
+        (&"left".to_string(), (60, 50))
+    );
+    assert!(buttons[1].2.is_some_and(|ms| ms >= 50));
+
+    let clicks = analysis::click_stats(monitor.current_session());
+    assert_eq!(clicks.clicks, 1);
+    assert!(clicks.avg_hold_ms.is_some_and(|ms| ms >= 50));
+}
+
+#[test]
+fn quick_presses_in_place_are_also_clicks() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+    tick(&mut monitor, 0);
+
+    input.set_mouse_position((40, 50));
+    input.set_mouse_buttons(vec![false, true]);
+    tick(&mut monitor, 1);
+    input.set_mouse_position((42, 51));
+    input.set_mouse_buttons(vec![false, false]);
+    tick(&mut monitor, 2);
+    // A drag is a press and release but not a click.
+    input.set_mouse_buttons(vec![false, true]);
+    tick(&mut monitor, 3);
+    input.set_mouse_position((140, 51));
+    input.set_mouse_buttons(vec![false, false]);
+    tick(&mut monitor, 4);
+
+    let clicks: Vec<&Action> = monitor
+        .current_session()
+        .actions
+        .iter()
+        .filter(|action| matches!(action, Action::MouseClick { .. }))
+        .collect();
+    assert_eq!(clicks.len(), 1);
+    let Action::MouseClick { button, coords, .. } = clicks[0] else {
+        unreachable!();
+    };
+    assert_eq!((button.as_str(), *coords), ("left", (42, 51)));
+    let csv = clicks[0].to_csv_string();
+    assert!(csv.starts_with("{click,"));
+    assert!(matches!(
+        Action::from_csv_string(&csv),
+        Some(Action::MouseClick {
+            coords: (42, 51),
+            ..
+        })
+    ));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn scrolls_are_toggled_apart_from_clicks() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+    monitor.config_mut().capture.scrolls = false;
+    tick(&mut monitor, 0);
+
+    // Button 4 is the scroll wheel on X11.
+    let mut step = 1;
+    for scrolls in [false, true] {
+        monitor.config_mut().capture.scrolls = scrolls;
+        for pressed in [
+            vec![false, true],
+            vec![false; 5],
+            vec![false, false, false, false, true],
+        ] {
+            input.set_mouse_buttons(pressed);
+            tick(&mut monitor, step);
+            step += 1;
+        }
+        input.set_mouse_buttons(vec![false; 5]);
+        tick(&mut monitor, step);
+        step += 1;
+    }
+
+    let pressed: Vec<&str> = monitor
+        .current_session()
+        .actions
+        .iter()
+        .filter_map(|action| match action {
+            Action::MouseDown { button, .. } => Some(button.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(pressed, ["left", "left", "scroll_up"]);
+    let clicked = monitor
+        .current_session()
+        .actions
+        .iter()
+        .filter(|action| matches!(action, Action::MouseClick { button, .. } if button == "left"))
+        .count();
+    assert_eq!(clicked, 2);
+    assert!(!monitor
+        .current_session()
+        .actions
+        .iter()
+        .any(|action| matches!(action, Action::MouseClick { button, .. } if button != "left")));
+}
+
+#[test]
+fn clipboard_copies_are_recorded_while_enabled() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+    input.copy_to_clipboard();
+    input.copy_to_clipboard();
+    tick(&mut monitor, 0);
+    monitor.config_mut().capture.clipboard = false;
+    input.copy_to_clipboard();
+    tick(&mut monitor, 1);
+    // Copies made while it was off aren't recorded once it's back on.
+    monitor.config_mut().capture.clipboard = true;
+    tick(&mut monitor, 2);
+
+    let copies = monitor
+        .current_session()
+        .actions
+        .iter()
+        .filter(
+            |action| matches!(action, Action::Custom { kind, .. } if kind == clipboard::EVENT_KIND),
+        )
+        .count();
+    assert_eq!(copies, 2);
+}
+
+#[test]
+fn cursor_shape_changes_are_recorded_under_the_pointer() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+    monitor.config_mut().capture.cursor_shapes = true;
+
+    // The shape is only read as the pointer moves or clicks.
+    input.set_cursor_shape(Some("text"));
+    tick(&mut monitor, 0);
+    let script = [(10, 10), (20, 10), (30, 10), (40, 10)];
+    let shapes = ["text", "text", "pointer", "pointer"];
+    for (step, (coords, shape)) in script.iter().zip(shapes).enumerate() {
+        input.set_cursor_shape(Some(shape));
+        input.set_mouse_position(*coords);
+        tick(&mut monitor, step);
+    }
+    input.set_mouse_buttons(vec![true]);
+    tick(&mut monitor, 4);
+
+    let recorded: Vec<&str> = monitor
+        .current_session()
+        .actions
+        .iter()
+        .filter_map(|action| match action {
+            Action::CursorChange { shape, .. } => Some(shape.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(recorded, ["text", "pointer"]);
+    let by_shape = analysis::cursor_shapes(monitor.current_session());
+    assert_eq!(by_shape.get("text"), Some(&2));
+    // Two moves and the click.
+    assert_eq!(by_shape.get("pointer"), Some(&3));
+}
+
+#[test]
+fn click_targets_are_recorded_with_presses_when_enabled() {
+    let input = MockInput::new();
+    // Sets up the working directory.
+    drop(start_monitor(&input));
+    let mut monitor = ActivityMonitor::with_input(Box::new(input.clone())).unwrap();
+    monitor.config_mut().capture.click_targets = true;
+    monitor.config_mut().privacy.redact.push(RedactionRule {
+        pattern: "(?i)invoice \\d+".to_string(),
+        replacement: "[invoice]".to_string(),
+    });
+    monitor.set_task_name("click targets");
+    monitor.start_monitoring();
+    input.set_element(Some(ClickTarget {
+        role: "window".to_string(),
+        name: "Invoice 2291 - Editor".to_string(),
+        app: Some("Editor".to_string()),
+    }));
+    tick(&mut monitor, 0);
+
+    input.set_mouse_buttons(vec![false, true]);
+    tick(&mut monitor, 1);
+    input.set_mouse_buttons(vec![false, false]);
+    tick(&mut monitor, 2);
+
+    let targets: Vec<&serde_json::Value> = monitor
+        .current_session()
+        .actions
+        .iter()
+        .filter_map(|action| match action {
+            Action::Custom { kind, payload, .. } if kind == window::TARGET_KIND => Some(payload),
+            _ => None,
+        })
+        .collect();
+    // Only the press carries a target.
+    assert_eq!(targets.len(), 1);
+    assert_eq!(targets[0]["button"], "left");
+    assert_eq!(targets[0]["role"], "window");
+    assert_eq!(targets[0]["name"], "[invoice] - Editor");
+    assert_eq!(targets[0]["app"], "Editor");
+}
+
+#[test]
+fn window_titles_are_redacted_before_they_are_stored() {
+    let input = MockInput::new();
+    // Sets up the working directory.
+    drop(start_monitor(&input));
+    let mut monitor = ActivityMonitor::with_input(Box::new(input.clone())).unwrap();
+    monitor.config_mut().capture.focus_changes = true;
+    monitor.config_mut().capture.window_titles = true;
+    monitor.config_mut().privacy.redact.push(RedactionRule {
+        pattern: "(?i)invoice \\d+".to_string(),
+        replacement: "[invoice]".to_string(),
+    });
+    monitor.set_task_name("window titles");
+    monitor.start_monitoring();
+    input.set_focused_window(Some(FocusedWindow {
+        id: 7,
+        bounds: WindowBounds {
+            x: 0,
+            y: 0,
+            width: 800,
+            height: 600,
+        },
+    }));
+    input.set_focused_title(Some("Invoice 2291 - Mail, Inbox"));
+    tick(&mut monitor, 0);
+
+    let titles: Vec<Option<&str>> = monitor
+        .current_session()
+        .actions
+        .iter()
+        .filter_map(|action| match action {
+            Action::FocusChange { title, .. } => Some(title.as_deref()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(titles, vec![Some("[invoice] - Mail, Inbox")]);
+
+    let csv = monitor.current_session().to_csv_record().join(",");
+    assert!(csv.contains("[invoice] - Mail, Inbox"), "{}", csv);
+    assert!(!csv.contains("2291"), "{}", csv);
+    let focus = monitor
+        .current_session()
+        .actions
+        .iter()
+        .find(|action| matches!(action, Action::FocusChange { .. }))
+        .unwrap();
+    match Action::from_csv_string(&focus.to_csv_string()) {
+        Some(Action::FocusChange { title, .. }) => {
+            assert_eq!(title.as_deref(), Some("[invoice] - Mail, Inbox"))
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn shortcuts_use_the_focused_apps_overrides() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+    monitor
+        .config_mut()
+        .shortcuts
+        .per_app
+        .entry("Editor".to_string())
+        .or_default()
+        .insert("Ctrl+S".to_string(), "save_draft".to_string());
+
+    let mut step = 0;
+    for app in ["Editor", "Browser"] {
+        input.set_focused_app(Some(app));
+        input.set_keys(vec![Keycode::LControl, Keycode::S]);
+        tick(&mut monitor, step);
+        input.set_keys(Vec::new());
+        tick(&mut monitor, step + 1);
+        step += 2;
+    }
+
+    let names: Vec<&str> = monitor
+        .current_session()
+        .actions
+        .iter()
+        .filter_map(|action| match action {
+            Action::Shortcut { name, .. } => Some(name.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(names, ["save_draft", "save"]);
+}
+
+#[test]
+fn app_alerts_fire_once_per_continuous_stretch() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+    monitor.config_mut().app_alerts = vec![AppAlert {
+        name: "Chat".to_string(),
+        apps: vec!["slack".to_string(), " Discord".to_string()],
+        minutes: 0,
+    }];
+
+    let focus = [
+        Some("Slack"),
+        Some("discord"),
+        Some("firefox"),
+        None,
+        Some("Slack"),
+    ];
+    let mut banners = Vec::new();
+    for app in focus {
+        input.set_focused_app(app);
+        monitor.update();
+        banners.push(monitor.app_alert().is_some());
+        thread::sleep(Duration::from_millis(260));
+    }
+
+    let alerts: Vec<serde_json::Value> = monitor
+        .current_session()
+        .actions
+        .iter()
+        .filter_map(|action| match action {
+            Action::Custom { kind, payload, .. } if kind == "app_alert" => Some(payload.clone()),
+            _ => None,
+        })
+        .collect();
+    // Slack to Discord is one stretch in the same alert.
+    assert_eq!(alerts.len(), 2);
+    assert!(alerts
+        .iter()
+        .all(|alert| alert["alert"] == "Chat" && alert["app"] == "Slack"));
+    assert_eq!(banners, [true, true, false, false, true]);
+}
+
+#[test]
+fn handles_drive_the_monitor_from_other_threads() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+    let handle = monitor.handle();
+
+    let caller = thread::spawn(move || {
+        let before = handle.live_state().unwrap();
+        handle.switch_task("from another thread").unwrap();
+        handle
+            .inject_event("tray", serde_json::json!({ "clicked": true }))
+            .unwrap();
+        handle.stop().unwrap();
+        let after = handle.live_state().unwrap();
+        let late = handle.inject_event("tray", serde_json::Value::Null);
+        (before, after, late)
+    });
+    while !caller.is_finished() {
+        monitor.update();
+        thread::sleep(Duration::from_millis(1));
+    }
+    let (before, after, late) = caller.join().unwrap();
+
+    assert!(before.running);
+    assert_eq!(before.task_name, "capture test");
+    assert!(!after.running);
+    assert_eq!(after.task_name, "from another thread");
+    assert!(late.is_err());
+    assert!(monitor
+        .current_session()
+        .actions
+        .iter()
+        .any(|action| matches!(action, Action::Custom { kind, .. } if kind == "tray")));
+}
+
+#[test]
+fn handle_calls_time_out_without_updates() {
+    let input = MockInput::new();
+    let monitor = start_monitor(&input);
+    let handle = monitor.handle();
+    assert!(handle.live_state().is_err());
+    drop(monitor);
+    assert!(handle.stop().is_err());
+}
+
+#[test]
+fn saved_sessions_carry_their_event_density() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+    for step in 0..6 {
+        input.set_mouse_position((10 * step as i32, 20));
+        tick(&mut monitor, step);
+    }
+    thread::sleep(Duration::from_millis(50));
+    monitor.stop_monitoring();
+    assert_eq!(monitor.current_session().density, None);
+    monitor.finish_session(None);
+
+    let density = monitor.current_session().density.clone().unwrap();
+    assert_eq!(density.len(), analysis::DENSITY_BUCKETS);
+    // Everything was recorded before the pause at the end.
+    assert!(density[0] > 0);
+    assert_eq!(density[analysis::DENSITY_BUCKETS - 1], 0);
+}
+
+/// Storage that fails like a full disk while `full` is set.
+#[derive(Clone, Default)]
+struct FlakyStorage {
+    full: Arc<AtomicBool>,
+    sessions: Arc<Mutex<Vec<Session>>>,
+}
+
+impl Storage for FlakyStorage {
+    fn append(&mut self, session: &Session) -> anyhow::Result<()> {
+        if self.full.load(Ordering::SeqCst) {
+            anyhow::bail!("No space left on device");
+        }
+        self.sessions.lock().unwrap().push(session.clone());
+        Ok(())
+    }
+
+    fn load(&self) -> anyhow::Result<Vec<Session>> {
+        Ok(self.sessions.lock().unwrap().clone())
+    }
+
+    fn replace(&mut self, _session: &Session) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn remove(&mut self, _session: &Session) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn relocate(&mut self, _dir: &Path) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn sessions_are_held_while_storage_fails() {
+    let input = MockInput::new();
+    // Sets up the working directory.
+    drop(start_monitor(&input));
+    let storage = FlakyStorage::default();
+    let mut monitor =
+        ActivityMonitor::with_storage(Box::new(input.clone()), Box::new(storage.clone())).unwrap();
+    let events = monitor.subscribe();
+    monitor.set_task_name("full disk");
+    monitor.start_monitoring();
+    input.set_keys(vec![Keycode::F]);
+    tick(&mut monitor, 0);
+
+    storage.full.store(true, Ordering::SeqCst);
+    monitor.stop_monitoring();
+    monitor.finish_session(None);
+    let problem = monitor.write_problem().unwrap();
+    assert!(problem.contains("No space left on device"), "{}", problem);
+    assert!(problem.contains("1 sessions"), "{}", problem);
+
+    // Still full: nothing changes.
+    monitor.retry_writes();
+    assert!(monitor.write_problem().is_some());
+    assert!(storage.sessions.lock().unwrap().is_empty());
+
+    storage.full.store(false, Ordering::SeqCst);
+    monitor.retry_writes();
+    assert_eq!(monitor.write_problem(), None);
+    let stored = storage.sessions.lock().unwrap().clone();
+    assert_eq!(stored.len(), 1);
+    assert_eq!(stored[0].session_id, monitor.current_session().session_id);
+    assert_eq!(
+        stored[0].actions.len(),
+        monitor.current_session().actions.len()
+    );
+    let saved: Vec<MonitorEvent> = events
+        .try_iter()
+        .filter(|event| matches!(event, MonitorEvent::SessionSaved { .. }))
+        .collect();
+    assert_eq!(saved.len(), 1);
+}
+
+#[test]
+fn sessions_record_the_profile_they_started_under() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+    assert_eq!(monitor.current_session().profile, None);
+
+    let config = monitor.config_mut();
+    config.save_profile("Personal");
+    config.capture.mouse_moves = false;
+    config.save_profile("Study");
+    assert!(monitor.switch_profile("Holiday").is_err());
+    monitor.switch_profile("Personal").unwrap();
+    assert!(monitor.config().capture.mouse_moves);
+    assert_eq!(monitor.config().active_profile, "Personal");
+
+    // Sessions are labelled when they start, not when they're saved.
+    monitor.stop_monitoring();
+    monitor.finish_session(None);
+    assert_eq!(monitor.current_session().profile, None);
+
+    monitor.start_monitoring();
+    assert_eq!(
+        monitor.current_session().profile.as_deref(),
+        Some("Personal")
+    );
+    monitor.stop_monitoring();
+    monitor.finish_session(None);
+}
+
+#[test]
+fn recording_waits_for_consent_and_sessions_carry_it() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+    monitor.stop_monitoring();
+    monitor.finish_session(None);
+    assert_eq!(monitor.current_session().consent, None);
+
+    monitor.config_mut().consent.required = true;
+    assert!(monitor.needs_consent());
+    monitor.start_monitoring();
+    assert!(!monitor.is_running());
+
+    monitor.accept_consent(" P-017 ").unwrap();
+    assert!(!monitor.needs_consent());
+    monitor.start_monitoring();
+    assert!(monitor.is_running());
+    let consent = monitor.current_session().consent.clone().unwrap();
+    assert_eq!(consent.participant_id, "P-017");
+    assert_eq!(consent.version, "1");
+    assert_eq!(consent::load(CONSENT_FILE).unwrap().last(), Some(&consent));
+    monitor.stop_monitoring();
+    monitor.finish_session(None);
+
+    // A new version of the text asks again.
+    monitor.config_mut().consent.version = "2".to_string();
+    assert!(monitor.needs_consent());
+}
+
+#[test]
+fn sessions_carry_the_metadata_entered_before_them() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+    monitor.stop_monitoring();
+    monitor.finish_session(None);
+
+    let metadata = &mut monitor.config_mut().metadata;
+    metadata.fields = vec!["participant_id".to_string(), "condition".to_string()];
+    metadata.required = true;
+    metadata
+        .values
+        .insert("participant_id".to_string(), " P-017 ".to_string());
+    monitor.start_monitoring();
+    assert!(!monitor.is_running());
+    assert!(monitor.status().contains("condition"));
+
+    monitor
+        .config_mut()
+        .metadata
+        .values
+        .insert("condition".to_string(), "B".to_string());
+    monitor.start_monitoring();
+    monitor.switch_task("second task".to_string());
+    let metadata = &monitor.current_session().metadata;
+    assert_eq!(
+        metadata.get("participant_id").map(String::as_str),
+        Some("P-017")
+    );
+    assert_eq!(metadata.get("condition").map(String::as_str), Some("B"));
+    monitor.stop_monitoring();
+    monitor.finish_session(None);
+}
+
+#[test]
+fn trials_started_by_hotkey_stop_and_export_themselves() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+    monitor.stop_monitoring();
+    monitor.finish_session(None);
+
+    let config = monitor.config_mut();
+    config.prompt_session_notes = true;
+    config.trials.duration_secs = 0;
+    config.trials.hotkey = "Ctrl+Alt+T".to_string();
+    config.trials.directory = "trial-exports".to_string();
+
+    // A zero-length trial starts and runs out on the same update.
+    input.set_keys(vec![Keycode::LControl, Keycode::LAlt, Keycode::T]);
+    monitor.update();
+    assert!(!monitor.is_running());
+    assert!(!monitor.awaiting_notes());
+    assert_eq!(monitor.trial_remaining(), None);
+    let session = monitor.current_session();
+    assert!(session
+        .actions
+        .iter()
+        .any(|action| matches!(action, Action::Custom { kind, .. } if kind == "trial")));
+    assert!(!session
+        .actions
+        .iter()
+        .any(|action| matches!(action, Action::KeyPress { .. })));
+    let export = Path::new("trial-exports").join(format!("trial_{}.csv", session.session_id));
+    assert!(export.exists());
+
+    // Holding the chord doesn't start another.
+    monitor.update();
+    assert!(!monitor.is_running());
+}
+
+#[test]
+fn events_are_tagged_with_the_trial_marked_around_them() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+    monitor.config_mut().trials.mark_hotkey = "Ctrl+Alt+M".to_string();
+    let press = |monitor: &mut ActivityMonitor, keys: Vec<Keycode>| {
+        input.set_keys(keys);
+        monitor.update();
+        input.set_keys(Vec::new());
+        monitor.update();
+    };
+
+    press(&mut monitor, vec![Keycode::A]);
+    assert_eq!(monitor.mark_trial_start(None).unwrap(), "1");
+    press(&mut monitor, vec![Keycode::B]);
+    // Starting a trial ends the open one.
+    assert_eq!(monitor.mark_trial_start(Some(" B2 ".into())).unwrap(), "B2");
+    press(&mut monitor, vec![Keycode::D]);
+    press(
+        &mut monitor,
+        vec![Keycode::LControl, Keycode::LAlt, Keycode::M],
+    );
+    assert_eq!(monitor.open_trial(), None);
+    press(&mut monitor, vec![Keycode::C]);
+    monitor.stop_monitoring();
+    monitor.finish_session(None);
+
+    let session = monitor.current_session();
+    let tagged: Vec<(String, Option<String>)> = analysis::timed_events(session)
+        .into_iter()
+        .filter_map(|event| match event.action {
+            Action::KeyPress { keys, .. } if !keys.is_empty() => {
+                Some((keys.join("+"), event.trial_id))
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        tagged,
+        [
+            ("A".to_string(), None),
+            ("B".to_string(), Some("1".to_string())),
+            ("D".to_string(), Some("B2".to_string())),
+            ("C".to_string(), None),
+        ]
+    );
+
+    let trials = analysis::trial_summaries(session);
+    let ids: Vec<&str> = trials.iter().map(|t| t.trial_id.as_str()).collect();
+    assert_eq!(ids, ["1", "B2"]);
+    // A press and its release each.
+    assert!(trials.iter().all(|t| t.key_presses == 2 && t.end.is_some()));
+}
+
+#[test]
+fn sync_markers_count_up_across_sessions() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+    monitor.config_mut().sync.hotkey = "Ctrl+Alt+S".to_string();
+
+    let first = monitor.sync_marker(Some(" block1 ".to_string())).unwrap();
+    assert_eq!(first.counter, 1);
+    assert_eq!(first.label.as_deref(), Some("block1"));
+    input.set_keys(vec![Keycode::LControl, Keycode::LAlt, Keycode::S]);
+    monitor.update();
+    assert!(recorded_keys(&monitor).is_empty());
+    monitor.stop_monitoring();
+    monitor.finish_session(None);
+
+    let markers = sync::markers(monitor.current_session());
+    assert_eq!(markers.len(), 2);
+    assert_eq!(markers[0], first);
+    assert_eq!(markers[1].counter, 2);
+    assert_eq!(markers[1].label, None);
+    assert!(markers[1].monotonic_ns >= first.monotonic_ns);
+    assert!(monitor.sync_marker(None).is_err());
+
+    monitor.start_monitoring();
+    assert_eq!(monitor.sync_marker(None).unwrap().counter, 3);
+    monitor.stop_monitoring();
+    monitor.finish_session(None);
+}
+
+#[test]
+fn lsl_samples_carry_events_as_json_at_their_recording_time() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+    let events = monitor.subscribe();
+    input.set_keys(vec![Keycode::A]);
+    monitor.update();
+    thread::sleep(Duration::from_millis(5));
+    monitor.switch_task("lsl test".to_string());
+    monitor.stop_monitoring();
+    monitor.finish_session(None);
+
+    let samples: Vec<lsl::Sample> = events
+        .try_iter()
+        .filter_map(|event| lsl::sample(&event))
+        .collect();
+    let values: Vec<serde_json::Value> = samples
+        .iter()
+        .map(|sample| serde_json::from_str(&sample.json).unwrap())
+        .collect();
+    let types: Vec<&str> = values
+        .iter()
+        .map(|value| value["type"].as_str().unwrap())
+        .collect();
+    assert!(types.ends_with(&["task_switch", "session_stopped"]));
+
+    let key = types.iter().position(|t| *t == "key_press").unwrap();
+    assert_eq!(values[key]["keys"], serde_json::json!(["A"]));
+    // Stamped when the key went down, not when the sample was made.
+    let pressed_at = values[key]["timestamp"].as_str().unwrap();
+    assert_eq!(
+        samples[key].recorded_at,
+        chrono::DateTime::parse_from_rfc3339(pressed_at).unwrap()
+    );
+}
+
+#[test]
+fn input_before_recording_is_saved_from_the_ring_buffer() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+    monitor.stop_monitoring();
+    monitor.finish_session(None);
+    let config = monitor.config_mut();
+    config.ring_buffer.seconds = 60;
+    config.ring_buffer.hotkey = "Ctrl+Alt+B".to_string();
+
+    input.set_keys(vec![Keycode::A]);
+    monitor.update();
+    input.set_keys(Vec::new());
+    monitor.update();
+    input.set_mouse_position((10, 20));
+    input.set_mouse_buttons(vec![false, true]);
+    monitor.update();
+    input.set_mouse_buttons(vec![false, false]);
+    monitor.update();
+    input.set_keys(vec![Keycode::LControl, Keycode::LAlt, Keycode::B]);
+    monitor.update();
+
+    assert!(!monitor.is_running());
+    let session = monitor.current_session();
+    assert_eq!(session.task_name, "Replay buffer");
+    assert!(session.end_time.is_some());
+    assert_eq!(session.mouse_path.len(), 1);
+    let keys: Vec<Vec<String>> = session
+        .actions
+        .iter()
+        .filter_map(|action| match action {
+            Action::KeyPress { keys, .. } => Some(keys.clone()),
+            _ => None,
+        })
+        .collect();
+    // The hotkey saved the buffer instead of being buffered.
+    assert_eq!(keys, [vec!["A".to_string()], Vec::new()]);
+    assert!(session
+        .actions
+        .iter()
+        .any(|action| matches!(action, Action::MouseUp { button, .. } if button == "left")));
+
+    // Saving empties the buffer.
+    assert!(monitor.save_ring_buffer().is_err());
+}
+
+#[test]
+fn bug_reports_bundle_screenshots_and_a_timeline() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+    monitor.stop_monitoring();
+    monitor.finish_session(None);
+    let config = monitor.config_mut();
+    config.prompt_session_notes = true;
+    config.bug_report.directory = "bug-report-exports".to_string();
+    input.set_screenshot(Some(Screenshot {
+        width: 2,
+        height: 2,
+        rgb: vec![255; 12],
+    }));
+    input.set_focused_window(Some(FocusedWindow {
+        id: 7,
+        bounds: WindowBounds {
+            x: 0,
+            y: 0,
+            width: 800,
+            height: 600,
+        },
+    }));
+
+    monitor.start_bug_report();
+    assert!(monitor.bug_report_remaining().is_some());
+    input.set_mouse_buttons(vec![false, true]);
+    monitor.update();
+    monitor.stop_monitoring();
+    assert!(!monitor.awaiting_notes());
+    assert_eq!(monitor.bug_report_remaining(), None);
+
+    let session = monitor.current_session();
+    let bundle =
+        Path::new("bug-report-exports").join(format!("bug_report_{}.zip", session.session_id));
+    assert_eq!(
+        integrity::verify(&bundle).unwrap()[0].status,
+        integrity::VerifyStatus::Ok
+    );
+    let mut zip = zip::ZipArchive::new(fs::File::open(&bundle).unwrap()).unwrap();
+    let mut png = Vec::new();
+    zip.by_name("screenshots/0001.png")
+        .unwrap()
+        .read_to_end(&mut png)
+        .unwrap();
+    assert!(png.starts_with(b"\x89PNG"));
+    let mut timeline = String::new();
+    zip.by_name("timeline.md")
+        .unwrap()
+        .read_to_string(&mut timeline)
+        .unwrap();
+    assert!(timeline.contains("Focused window 7 at (0, 0) 800x600"));
+    assert!(timeline.contains("Clicked left at (0, 0)"));
+    assert!(timeline.contains("screenshots/0001.png"));
+}
+
+#[test]
+fn bug_reports_can_leave_screenshots_out() {
+    let input = MockInput::new();
+    let mut monitor = start_monitor(&input);
+    monitor.stop_monitoring();
+    monitor.finish_session(None);
+    let config = monitor.config_mut();
+    config.bug_report.screenshots = false;
+    config.bug_report.directory = "bug-report-exports".to_string();
+    input.set_screenshot(Some(Screenshot {
+        width: 2,
+        height: 2,
+        rgb: vec![255; 12],
+    }));
+
+    monitor.start_bug_report();
+    assert!(monitor.status().ends_with("without screenshots"));
+    monitor.update();
+    assert!(!monitor.current_session().actions.iter().any(
+        |action| matches!(action, Action::Custom { kind, .. } if kind == bug_report::SCREENSHOT_KIND)
+    ));
+    monitor.stop_monitoring();
+}
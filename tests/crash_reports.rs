@@ -0,0 +1,91 @@
+//! The crash hook is process-wide and writes to the working directory, so
+//! it gets a test binary of its own.
+
+use desk_monitor::crash;
+use desk_monitor::privacy::RedactionRule;
+use desk_monitor::{ActivityMonitor, Config, MockInput};
+use std::path::Path;
+use std::sync::Once;
+use std::{env, fs, thread};
+
+/// Installs the hook once for the whole binary, in a fresh working
+/// directory.
+fn install() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let dir = env::temp_dir().join(format!("desk-monitor-crash-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        env::set_current_dir(&dir).unwrap();
+
+        let mut config = Config::default();
+        config.privacy.redact.push(RedactionRule {
+            pattern: "secret-pattern".to_string(),
+            ..Default::default()
+        });
+        config.contribute.endpoint = "https://research.example/upload".to_string();
+        crash::install(&config);
+    });
+}
+
+/// Reports in [`crash::DIRECTORY`] that mention `text`.
+fn reports_mentioning(text: &str) -> usize {
+    fs::read_dir(crash::DIRECTORY)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| fs::read_to_string(entry.ok()?.path()).ok())
+                .filter(|report| report.contains(text))
+                .count()
+        })
+        .unwrap_or(0)
+}
+
+#[test]
+fn panics_leave_a_report_that_is_offered_once() {
+    install();
+    crash::record_status("Started monitoring task: Payroll review", "Payroll review");
+
+    let reports = Path::new(crash::DIRECTORY);
+    assert_eq!(crash::unseen(reports), None);
+    let crashed = thread::Builder::new()
+        .name("capture".to_string())
+        .spawn(|| panic!("device vanished"))
+        .unwrap()
+        .join();
+    assert!(crashed.is_err());
+
+    let report = crash::unseen(reports).unwrap();
+    let text = fs::read_to_string(&report).unwrap();
+    assert!(text.contains("Thread: capture\n"));
+    assert!(text.contains("Panic: device vanished\n"));
+    assert!(text.contains("Location: tests/crash_reports.rs:"));
+    assert!(text.contains("Last status: Started monitoring task: [task]\n"));
+    assert!(text.contains("privacy: redaction_rules=1 hide_keys=false mouse_grid_px=0\n"));
+    assert!(text.contains("\nBacktrace:\n"));
+    for private in ["Payroll", "secret-pattern", "research.example"] {
+        assert!(!text.contains(private), "{} is in the report", private);
+    }
+
+    crash::mark_seen(reports, &report).unwrap();
+    assert_eq!(crash::unseen(reports), None);
+}
+
+#[test]
+fn caught_device_panics_leave_no_report() {
+    install();
+    let input = MockInput::new();
+    let mut monitor = ActivityMonitor::with_input(Box::new(input.clone())).unwrap();
+    monitor.config_mut().ring_buffer.seconds = 30;
+    input.set_failing(true);
+    // Idle frames read into the ring buffer.
+    for _ in 0..5 {
+        monitor.update();
+    }
+    // Recording frames read and try to reconnect.
+    monitor.set_task_name("crash test");
+    monitor.start_monitoring();
+    for _ in 0..5 {
+        monitor.update();
+    }
+    assert!(monitor.capture_problem().is_some());
+    assert_eq!(reports_mentioning("mock input device unavailable"), 0);
+}
@@ -0,0 +1,26 @@
+
+PRAGMA user_version = 4;
+CREATE TABLE sessions (
+    id INTEGER PRIMARY KEY,
+    session_id TEXT NOT NULL,
+    task_name TEXT NOT NULL,
+    start_time TEXT NOT NULL,
+    end_time TEXT,
+    duration_secs INTEGER,
+    notes TEXT,
+    mouse_sample_rate INTEGER NOT NULL,
+    machine_id TEXT,
+    metadata TEXT
+);
+CREATE TABLE events (
+    session INTEGER NOT NULL REFERENCES sessions(id),
+    seq INTEGER NOT NULL,
+    type TEXT NOT NULL,
+    timestamp TEXT NOT NULL,
+    x INTEGER,
+    y INTEGER,
+    data TEXT NOT NULL,
+    trial TEXT,
+    PRIMARY KEY (session, seq)
+);
+CREATE INDEX events_by_type ON events(type, timestamp);
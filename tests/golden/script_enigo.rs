@@ -0,0 +1,22 @@
+// Replays session 20240120_123456 ("Writing \"Email\"; draft"), recorded by desk-monitor.
+// Needs `enigo = "0.2"`.
+use enigo::{Button, Coordinate, Direction, Enigo, Key, Keyboard, Mouse, Settings};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut enigo = Enigo::new(&Settings::default())?;
+    let (width, height) = enigo.main_display()?;
+    let scaled = |x: f64, y: f64| ((x * width as f64) as i32, (y * height as f64) as i32);
+    let (x, y) = scaled(0.0521, 0.1852);
+    enigo.move_mouse(x, y, Coordinate::Abs)?;
+    let (x, y) = scaled(0.0536, 0.1815);
+    enigo.move_mouse(x, y, Coordinate::Abs)?;
+    enigo.button(Button::Left, Direction::Press)?;
+    enigo.button(Button::Left, Direction::Release)?;
+    enigo.key(Key::Shift, Direction::Press)?;
+    enigo.key(Key::Unicode('h'), Direction::Press)?;
+    enigo.key(Key::Shift, Direction::Release)?;
+    enigo.key(Key::Unicode('h'), Direction::Release)?;
+    let (x, y) = scaled(0.0469, 0.1667);
+    enigo.move_mouse(x, y, Coordinate::Abs)?;
+    Ok(())
+}
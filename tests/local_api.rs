@@ -0,0 +1,59 @@
+//! The local HTTP endpoints listen on fixed ports, so they get a test binary
+//! of their own.
+
+use desk_monitor::api;
+use desk_monitor::storage::{CsvStorage, SESSIONS_FILE};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::{env, fs};
+
+fn status(port: u16, request: &str) -> String {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    stream.write_all(request.as_bytes()).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response.lines().next().unwrap_or_default().to_string()
+}
+
+#[test]
+fn web_pages_are_refused() {
+    let dir = env::temp_dir().join(format!("desk-monitor-api-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    env::set_current_dir(&dir).unwrap();
+    CsvStorage::open(SESSIONS_FILE).unwrap();
+    let port = 47000 + (std::process::id() % 1000) as u16;
+    api::spawn(port, None).unwrap();
+
+    let local = format!("GET /sessions HTTP/1.1\r\nHost: 127.0.0.1:{}\r\n\r\n", port);
+    assert_eq!(status(port, &local), "HTTP/1.1 200 OK");
+    let named = format!("GET /sessions HTTP/1.1\r\nHost: localhost:{}\r\n\r\n", port);
+    assert_eq!(status(port, &named), "HTTP/1.1 200 OK");
+
+    // A rebound DNS name reaches the port with the page's host.
+    let rebound = format!(
+        "GET /sessions HTTP/1.1\r\nHost: evil.example:{}\r\n\r\n",
+        port
+    );
+    assert_eq!(status(port, &rebound), "HTTP/1.1 403 Forbidden");
+    let hostless = "GET /sessions HTTP/1.1\r\n\r\n";
+    assert_eq!(status(port, hostless), "HTTP/1.1 403 Forbidden");
+    // A no-cors POST from a page carries its origin.
+    let posted = format!(
+        "POST /trial HTTP/1.1\r\nHost: 127.0.0.1:{}\r\nOrigin: https://evil.example\r\n\r\n",
+        port
+    );
+    assert_eq!(status(port, &posted), "HTTP/1.1 403 Forbidden");
+
+    assert!(!api::is_local_request(
+        port,
+        "GET",
+        Some("127.0.0.1:1"),
+        None
+    ));
+    assert!(api::is_local_request(
+        port,
+        "GET",
+        Some(&format!("127.0.0.1:{}", port)),
+        Some("https://evil.example")
+    ));
+}
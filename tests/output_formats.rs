@@ -0,0 +1,920 @@
+//! Golden-file tests for everything the monitor writes for other programs to
+//! read. Each output is compared byte for byte with `tests/golden/`, so a
+//! format change fails here until the golden file is updated on purpose:
+//!
+//! ```bash
+//! UPDATE_GOLDEN=1 cargo test --test output_formats
+//! ```
+
+use chrono::{DateTime, Local, NaiveDate, Weekday};
+use desk_monitor::analysis;
+use desk_monitor::anonymize::Pseudonymizer;
+use desk_monitor::billing;
+use desk_monitor::compaction;
+use desk_monitor::config::{BillingConfig, CalendarConfig, RoundingMode};
+use desk_monitor::consent::ConsentRecord;
+use desk_monitor::contribute::{self, ContributeConfig};
+use desk_monitor::dedup::DedupReport;
+use desk_monitor::diff;
+use desk_monitor::export;
+use desk_monitor::jiggle;
+use desk_monitor::merge;
+use desk_monitor::metrics;
+use desk_monitor::playback::Playback;
+use desk_monitor::quality::{self, QualityFlag};
+use desk_monitor::script::{self, ScriptOptions};
+use desk_monitor::storage::{self, SESSION_HEADERS};
+use desk_monitor::timeline::{Band, Layer, Marker, Timeline};
+use desk_monitor::types;
+use desk_monitor::{Action, Config, DetailedEvent, ExportFormat, Session, WindowBounds};
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+
+fn assert_golden(name: &str, actual: &[u8]) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(name);
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = fs::read(&path).unwrap_or_else(|_| {
+        panic!(
+            "{} is missing; run with UPDATE_GOLDEN=1 to create it",
+            path.display()
+        )
+    });
+    assert!(
+        expected == actual,
+        "{} changed; if that's intended, run with UPDATE_GOLDEN=1 and commit the \
+         new file\n--- expected\n{}\n--- actual\n{}",
+        name,
+        String::from_utf8_lossy(&expected),
+        String::from_utf8_lossy(actual)
+    );
+}
+
+/// A scratch directory per test, since tests run in parallel.
+fn out_dir(test: &str) -> PathBuf {
+    let dir = env::temp_dir()
+        .join(format!("desk-monitor-golden-{}", std::process::id()))
+        .join(test);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn at(timestamp: &str) -> DateTime<Local> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .unwrap()
+        .with_timezone(&Local)
+}
+
+/// Two sessions from different machines covering every action type. Mouse
+/// moves are rendered in local time, so the tests run in UTC.
+fn fixture() -> Vec<Session> {
+    env::set_var("TZ", "UTC");
+
+    let mut writing = Session {
+        session_id: "20240120_123456".to_string(),
+        task_name: "Writing \"Email\"; draft".to_string(),
+        start_time: "2024-01-20T12:34:56.120+00:00".to_string(),
+        end_time: Some("2024-01-20T12:36:10.004+00:00".to_string()),
+        notes: Some("Drafted the reply, sent it".to_string()),
+        machine_id: Some("laptop".to_string()),
+        quality_flags: Some(Vec::new()),
+        ..Default::default()
+    };
+    let ts = |s: &str| format!("2024-01-20T12:{}+00:00", s);
+    writing.actions.push(Action::LayoutChange {
+        timestamp: ts("34:56.120"),
+        layout: "us".to_string(),
+    });
+    writing.record_mouse_move(at(&ts("34:57.031")), (100, 200));
+    writing.actions.push(Action::CursorChange {
+        timestamp: ts("34:57.040"),
+        shape: "pointer".to_string(),
+    });
+    writing.record_mouse_move(at(&ts("34:57.048")), (103, 196));
+    writing.actions.push(Action::MouseDown {
+        timestamp: ts("34:57.060"),
+        button: "left".to_string(),
+        coords: (103, 196),
+    });
+    writing.actions.push(Action::MouseUp {
+        timestamp: ts("34:57.090"),
+        button: "left".to_string(),
+        coords: (103, 196),
+        hold_ms: 30,
+    });
+    writing.actions.push(Action::KeyPress {
+        timestamp: ts("34:57.100"),
+        keys: vec!["LShift".to_string()],
+    });
+    writing.actions.push(Action::KeyPress {
+        timestamp: ts("34:57.180"),
+        keys: vec!["LShift".to_string(), "H".to_string()],
+    });
+    writing.actions.push(Action::KeyPress {
+        timestamp: ts("34:57.260"),
+        keys: vec!["H".to_string()],
+    });
+    writing.actions.push(Action::KeyPress {
+        timestamp: ts("34:57.300"),
+        keys: Vec::new(),
+    });
+    writing.actions.push(Action::Shortcut {
+        timestamp: ts("34:58.000"),
+        chord: "Ctrl+S".to_string(),
+        name: "save".to_string(),
+    });
+    writing.actions.push(Action::ClockChange {
+        timestamp: ts("35:00.000"),
+        jump_ms: 3000,
+        utc_offset_secs: 0,
+    });
+    writing.actions.push(Action::FocusChange {
+        timestamp: ts("35:01.500"),
+        window: 41943047,
+        bounds: Some(WindowBounds {
+            x: 10,
+            y: 20,
+            width: 800,
+            height: 600,
+        }),
+        title: None,
+        app: Some("firefox".to_string()),
+    });
+    writing.actions.push(Action::CaptureInterrupted {
+        timestamp: ts("35:02.000"),
+        reason: "device lost".to_string(),
+        resumed_at: Some(ts("35:04.000")),
+    });
+    writing.actions.push(Action::CaptureGap {
+        timestamp: ts("35:10.000"),
+        gap_ms: 6500,
+    });
+    writing.actions.push(Action::BreakReminder {
+        timestamp: ts("35:30.000"),
+        kind: "micro_break".to_string(),
+        active_secs: 1200,
+    });
+    writing.actions.push(Action::BreakTaken {
+        timestamp: ts("35:50.000"),
+        kind: "micro_break".to_string(),
+        idle_secs: 20,
+        reminded: true,
+    });
+    writing.actions.push(Action::Redacted {
+        timestamp: ts("35:52.000"),
+        until: ts("35:54.000"),
+    });
+    writing.actions.push(Action::Custom {
+        timestamp: ts("35:55.000"),
+        kind: "ide".to_string(),
+        payload: serde_json::json!({ "event": "file_save", "path": "src/main.rs" }),
+    });
+    writing.actions.push(Action::TaskSwitch {
+        timestamp: ts("36:00.000"),
+        from: "Writing".to_string(),
+        to: "Review".to_string(),
+    });
+    writing.record_mouse_move(at(&ts("36:01.000")), (90, 180));
+    writing.density = Some(analysis::event_density(&writing));
+    writing.metadata = [("participant_id", "P-017"), ("condition", "B")]
+        .into_iter()
+        .map(|(field, value)| (field.to_string(), value.to_string()))
+        .collect();
+    writing.consent = Some(ConsentRecord {
+        version: "1".to_string(),
+        text_sha256: "9f2c".to_string(),
+        accepted_at: "2024-01-20T12:30:00.000+00:00".to_string(),
+        participant_id: "P-017".to_string(),
+    });
+
+    let review = Session {
+        session_id: "20240121_090000".to_string(),
+        task_name: "Review".to_string(),
+        start_time: "2024-01-21T09:00:00.000+00:00".to_string(),
+        end_time: Some("2024-01-21T09:30:00.000+00:00".to_string()),
+        mouse_sample_rate: 4,
+        machine_id: Some("desktop".to_string()),
+        quality_flags: Some(vec![QualityFlag::NoEvents]),
+        profile: Some("Study".to_string()),
+        ..Default::default()
+    };
+    vec![writing, review]
+}
+
+#[test]
+fn sessions_csv() {
+    let sessions = fixture();
+    let path = out_dir("sessions_csv").join("sessions.csv");
+    export::write_sessions(&sessions, &path, ExportFormat::Csv, &Config::default()).unwrap();
+
+    let csv = fs::read_to_string(&path).unwrap();
+    assert_eq!(csv.lines().next(), Some(SESSION_HEADERS.join(",").as_str()));
+    assert_golden("sessions.csv", csv.as_bytes());
+    assert_golden(
+        "sessions.csv.sha256",
+        &fs::read(path.with_extension("csv.sha256")).unwrap(),
+    );
+}
+
+#[test]
+fn sessions_json() {
+    let sessions = fixture();
+    let path = out_dir("sessions_json").join("sessions.json");
+    export::write_sessions(&sessions, &path, ExportFormat::Json, &Config::default()).unwrap();
+    assert_golden("sessions.json", &fs::read(&path).unwrap());
+}
+
+#[test]
+fn sessions_xlsx() {
+    let sessions = fixture();
+    let path = out_dir("sessions_xlsx").join("sessions.xlsx");
+    export::write_sessions(&sessions, &path, ExportFormat::Xlsx, &Config::default()).unwrap();
+    assert_golden("sessions.xlsx", &fs::read(&path).unwrap());
+}
+
+#[test]
+fn session_report_markdown() {
+    let sessions = fixture();
+    let report = export::session_markdown(&sessions[0], &Config::default());
+    assert_golden("latest_session_summary.md", report.as_bytes());
+}
+
+#[test]
+fn detailed_events_csv() {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer
+        .serialize(DetailedEvent {
+            timestamp: "2024-01-20T12:34:57.100+00:00".to_string(),
+            task_name: "Writing".to_string(),
+            event_type: "keyboard".to_string(),
+            details: "[\"LShift\"]".to_string(),
+            mouse_x: 103,
+            mouse_y: 196,
+        })
+        .unwrap();
+    assert_golden("latest_session_details.csv", &writer.into_inner().unwrap());
+}
+
+#[test]
+fn minute_rollups_csv() {
+    let sessions = fixture();
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for rollup in compaction::minute_rollups(&sessions[0]) {
+        writer.serialize(rollup).unwrap();
+    }
+    assert_golden("activity_rollups.csv", &writer.into_inner().unwrap());
+}
+
+#[test]
+fn keystroke_dynamics_csv() {
+    let sessions = fixture();
+    let dir = out_dir("keystroke_dynamics_csv");
+    export::write_keystroke_dynamics(&sessions, dir.join("keys.csv"), false).unwrap();
+    export::write_keystroke_dynamics(&sessions, dir.join("hidden.csv"), true).unwrap();
+    assert_golden("keystrokes.csv", &fs::read(dir.join("keys.csv")).unwrap());
+    assert_golden(
+        "keystrokes_hidden.csv",
+        &fs::read(dir.join("hidden.csv")).unwrap(),
+    );
+}
+
+#[test]
+fn key_ngram_frequencies() {
+    let mut sessions = fixture();
+    // "the the" typed on a German layout, one key at a time.
+    let review = &mut sessions[1];
+    review.actions.push(Action::LayoutChange {
+        timestamp: "2024-01-21T09:00:00.000+00:00".to_string(),
+        layout: "de".to_string(),
+    });
+    for (i, key) in ["T", "H", "E", "Space", "T", "H", "E"].iter().enumerate() {
+        for (offset_ms, keys) in [(0, vec![key.to_string()]), (50, Vec::new())] {
+            review.actions.push(Action::KeyPress {
+                timestamp: format!("2024-01-21T09:00:01.{:03}+00:00", i * 100 + offset_ms),
+                keys,
+            });
+        }
+    }
+
+    let dir = out_dir("key_ngram_frequencies");
+    assert_eq!(
+        export::write_key_ngrams(&sessions, dir.join("ngrams.csv"), false).unwrap(),
+        9
+    );
+    export::write_key_ngrams(&sessions, dir.join("categories.csv"), true).unwrap();
+    assert_golden("key_ngrams.csv", &fs::read(dir.join("ngrams.csv")).unwrap());
+    assert_golden(
+        "key_ngrams_categories.csv",
+        &fs::read(dir.join("categories.csv")).unwrap(),
+    );
+}
+
+#[test]
+fn merged_dataset() {
+    let sessions = fixture();
+    let dir = out_dir("merged_dataset");
+    merge::write_merged(&sessions, &dir, ExportFormat::Csv, &Config::default()).unwrap();
+    assert_golden(
+        "daily_by_machine.csv",
+        &fs::read(dir.join("daily_by_machine.csv")).unwrap(),
+    );
+    assert_golden("report.md", &fs::read(dir.join("report.md")).unwrap());
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn sqlite_schema() {
+    assert_golden("schema.sql", export::SQLITE_SCHEMA.as_bytes());
+}
+
+#[cfg(feature = "polars")]
+#[test]
+fn sessions_as_dataframes() {
+    use desk_monitor::dataframe;
+    use desk_monitor::storage::{CsvStorage, Storage};
+
+    let sessions = fixture();
+    let frame = sessions[0].to_dataframe().unwrap();
+    let columns: Vec<&str> = frame
+        .get_column_names()
+        .iter()
+        .map(|c| c.as_str())
+        .collect();
+    assert_eq!(columns, dataframe::COLUMNS);
+    assert_eq!(frame.height(), sessions[0].ordered_actions().len());
+    let kinds = frame.column("kind").unwrap().str().unwrap();
+    let payloads = frame.column("payload").unwrap().str().unwrap();
+    let offsets = frame.column("offset_ms").unwrap().i64().unwrap();
+    assert_eq!(
+        (kinds.get(0), payloads.get(0), offsets.get(0)),
+        (Some("layout"), Some("\"us\""), Some(0))
+    );
+    assert_eq!(
+        (kinds.get(1), payloads.get(1), offsets.get(1)),
+        (Some("mouse"), Some("(100,200)"), Some(911))
+    );
+
+    let mut storage =
+        CsvStorage::open(out_dir("sessions_as_dataframes").join("sessions.csv")).unwrap();
+    for session in &sessions {
+        storage.append(session).unwrap();
+    }
+    let all = dataframe::query_dataframe(&storage).unwrap();
+    assert_eq!(all.height(), frame.height());
+    // The review session recorded nothing, so adds no rows.
+    let ids = all.column("session_id").unwrap().str().unwrap();
+    assert!(ids
+        .into_iter()
+        .all(|id| id == Some(sessions[0].session_id.as_str())));
+}
+
+#[test]
+fn timeline_range_export() {
+    let sessions = fixture();
+    let slice = sessions[0].slice(
+        at("2024-01-20T12:34:57.048+00:00"),
+        at("2024-01-20T12:35:00.000+00:00"),
+    );
+    // Participant details go with the range.
+    assert_eq!(slice.metadata, sessions[0].metadata);
+    assert_eq!(
+        slice.consent.as_ref().map(|c| c.participant_id.as_str()),
+        Some("P-017")
+    );
+    let path = out_dir("timeline_range_export").join("range.csv");
+    export::write_sessions(&[slice], &path, ExportFormat::Csv, &Config::default()).unwrap();
+    assert_golden("range.csv", &fs::read(&path).unwrap());
+}
+
+#[test]
+fn automation_scripts() {
+    let sessions = fixture();
+    let json = script::to_json(&sessions[0], ScriptOptions::default()).unwrap();
+    assert_golden("script.json", json.as_bytes());
+    let options = ScriptOptions {
+        keep_timing: false,
+        normalize: Some((1920, 1080)),
+    };
+    assert_golden(
+        "script_enigo.rs",
+        script::to_enigo(&sessions[0], options).as_bytes(),
+    );
+}
+
+#[test]
+fn session_diff_report() {
+    let a = fixture().remove(0);
+    let mut b = fixture().remove(0);
+    // B clicks further right, skips the save, focuses a bigger window and
+    // ends with an extra right click.
+    if let Action::MouseDown { coords, .. } = &mut b.actions[2] {
+        *coords = (160, 196);
+    }
+    assert!(matches!(b.actions.remove(8), Action::Shortcut { .. }));
+    if let Action::FocusChange {
+        bounds: Some(bounds),
+        ..
+    } = &mut b.actions[9]
+    {
+        bounds.width = 1024;
+        bounds.height = 768;
+    }
+    b.actions.push(Action::MouseDown {
+        timestamp: "2024-01-20T12:36:02.000+00:00".to_string(),
+        button: "right".to_string(),
+        coords: (400, 300),
+    });
+
+    let comparison = diff::compare(&a, &b);
+    assert_golden("session_diff.md", comparison.to_markdown(&a, &b).as_bytes());
+    assert_eq!(diff::compare(&a, &a).divergences().count(), 0);
+}
+
+#[test]
+fn merge_folds_duplicates_together() {
+    let dir = out_dir("merge_folds_duplicates_together");
+    let first = dir.join("first.csv");
+    export::write_sessions(&fixture(), &first, ExportFormat::Csv, &Config::default()).unwrap();
+
+    // A second copy of the first session with one event repeated and one
+    // it was missing.
+    let mut copy = fixture().remove(0);
+    copy.actions.push(Action::Shortcut {
+        timestamp: "2024-01-20T12:34:58.000+00:00".to_string(),
+        chord: "Ctrl+S".to_string(),
+        name: "save".to_string(),
+    });
+    copy.actions.push(Action::KeyPress {
+        timestamp: "2024-01-20T12:36:05.000+00:00".to_string(),
+        keys: vec!["Return".to_string()],
+    });
+    copy.end_time = Some("2024-01-20T12:36:20.000+00:00".to_string());
+    let second = dir.join("second.csv");
+    export::write_sessions(&[copy], &second, ExportFormat::Csv, &Config::default()).unwrap();
+
+    let sources = [first, second].map(|path| merge::MergeSource {
+        machine_id: "unused".to_string(),
+        path,
+    });
+    let (sessions, report) = merge::merge(&sources).unwrap();
+    assert_eq!(sessions.len(), 2);
+    assert_eq!(
+        report,
+        DedupReport {
+            duplicate_sessions: 1,
+            reconciled_sessions: 1,
+            duplicate_events: 1,
+        }
+    );
+    let path = dir.join("deduplicated.csv");
+    export::write_sessions(&sessions, &path, ExportFormat::Csv, &Config::default()).unwrap();
+    assert_golden("deduplicated.csv", &fs::read(&path).unwrap());
+}
+
+#[test]
+fn invoice_csv_and_html() {
+    let sessions = fixture();
+    let day = at("2024-01-20T12:00:00+00:00").date_naive();
+    let config = Config::default();
+    let invoice = billing::invoice(
+        &sessions,
+        &sessions[0].task_name,
+        day,
+        day,
+        80.0,
+        &config.billing,
+    );
+    let path = out_dir("invoice_csv_and_html").join("invoice.csv");
+    billing::write_csv(&invoice, &path).unwrap();
+    assert_golden("invoice.csv", &fs::read(&path).unwrap());
+    assert_golden("invoice.html", billing::to_html(&invoice).as_bytes());
+}
+
+#[test]
+fn invoice_with_rounding_rules() {
+    let sessions = fixture();
+    let day = at("2024-01-21T12:00:00+00:00").date_naive();
+    let billing = BillingConfig {
+        round_to_minutes: 15,
+        rounding: RoundingMode::Up,
+        minimum_minutes: 45,
+        ..Default::default()
+    };
+    let invoice = billing::invoice(&sessions, "Review", day, day, 80.0, &billing);
+    let path = out_dir("invoice_with_rounding_rules").join("invoice_rounded.csv");
+    billing::write_csv(&invoice, &path).unwrap();
+    assert_golden("invoice_rounded.csv", &fs::read(&path).unwrap());
+}
+
+#[test]
+fn anonymized_sessions_and_mapping() {
+    let mut pseudonymizer = Pseudonymizer::new("golden salt");
+    let mut sessions = fixture();
+    sessions[0].actions.push(Action::FocusChange {
+        timestamp: "2024-01-20T12:35:01.800+00:00".to_string(),
+        window: 41943048,
+        bounds: None,
+        title: Some("Inbox - jane@example.com".to_string()),
+        app: Some("Thunderbird".to_string()),
+    });
+    let sessions: Vec<Session> = sessions
+        .into_iter()
+        .map(|session| pseudonymizer.anonymize(session))
+        .collect();
+    // The "Review" switch target and the second session's task share a
+    // pseudonym.
+    assert_eq!(
+        pseudonymizer.pseudonym("task", "Review"),
+        sessions[1].task_name
+    );
+
+    let dir = out_dir("anonymized_sessions_and_mapping");
+    let path = dir.join("anonymized.csv");
+    export::write_sessions(&sessions, &path, ExportFormat::Csv, &Config::default()).unwrap();
+    assert_golden("anonymized.csv", &fs::read(&path).unwrap());
+    let mapping = dir.join("anonymization_map.csv");
+    pseudonymizer.write_mapping(&mapping).unwrap();
+    assert_golden("anonymization_map.csv", &fs::read(&mapping).unwrap());
+}
+
+#[test]
+fn weekly_totals_follow_the_configured_week() {
+    let sessions = fixture();
+    let week = |date: &str| chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+    let [saturday, sunday] =
+        [&sessions[0], &sessions[1]].map(|s| s.duration().unwrap().num_seconds());
+
+    // Monday to Friday: the weekend falls in one week, all outside it.
+    let weeks = analysis::weekly_totals(&sessions, &CalendarConfig::default());
+    assert_eq!(weeks.len(), 1);
+    assert_eq!(weeks[0].week_start, week("2024-01-15"));
+    assert_eq!(weeks[0].tracked_secs, saturday + sunday);
+    assert_eq!(weeks[0].off_day_secs, saturday + sunday);
+    assert_eq!(weeks[0].workdays_tracked, 0);
+
+    // Sunday to Thursday: Saturday ends one week and Sunday is a workday
+    // starting the next.
+    let calendar = CalendarConfig {
+        first_day_of_week: Weekday::Sun,
+        workweek: vec![
+            Weekday::Sun,
+            Weekday::Mon,
+            Weekday::Tue,
+            Weekday::Wed,
+            Weekday::Thu,
+        ],
+    };
+    let weeks = analysis::weekly_totals(&sessions, &calendar);
+    assert_eq!(weeks.len(), 2);
+    assert_eq!(weeks[0].week_start, week("2024-01-14"));
+    assert_eq!(weeks[0].off_day_secs, saturday);
+    assert_eq!(weeks[1].week_start, week("2024-01-21"));
+    assert_eq!(weeks[1].tracked_secs, sunday);
+    assert_eq!(weeks[1].off_day_secs, 0);
+    assert_eq!(weeks[1].workdays_tracked, 1);
+}
+
+#[test]
+fn mouse_jiggling_is_reported_as_pseudo_idle() {
+    let start = at("2024-01-22T09:00:00+00:00");
+    let mut session = Session {
+        session_id: "20240122_090000".to_string(),
+        task_name: "Reports".to_string(),
+        start_time: "2024-01-22T09:00:00.000+00:00".to_string(),
+        end_time: Some("2024-01-22T09:20:00.000+00:00".to_string()),
+        ..Default::default()
+    };
+    // A jiggler nudging the pointer a pixel and back every 30s for 10
+    // minutes, then someone typing and moving the mouse for real.
+    for nudge in 0..=20 {
+        let at = start + chrono::Duration::seconds(30 * nudge);
+        session.record_mouse_move(at, (500, 400));
+        session.record_mouse_move(at + chrono::Duration::milliseconds(100), (501, 400));
+    }
+    session.actions.push(Action::KeyPress {
+        timestamp: "2024-01-22T09:10:30.000+00:00".to_string(),
+        keys: vec!["A".to_string()],
+    });
+    // Resting a hand on the mouse: tiny but irregular moves.
+    for secs in [700, 703, 760, 761, 900, 1150] {
+        session.record_mouse_move(start + chrono::Duration::seconds(secs), (640, 360));
+        session.record_mouse_move(
+            start + chrono::Duration::seconds(secs) + chrono::Duration::milliseconds(100),
+            (641, 361),
+        );
+    }
+
+    let mut config = Config::default();
+    assert!(jiggle::detect(&session, &config.quality.mouse_jiggle).is_empty());
+    assert!(quality::check(&session, &config.quality).is_empty());
+
+    config.quality.mouse_jiggle.enabled = true;
+    let periods = jiggle::detect(&session, &config.quality.mouse_jiggle);
+    assert_eq!(periods.len(), 1);
+    assert_eq!(periods[0].start, start);
+    assert_eq!(periods[0].moves, 42);
+    assert_eq!(
+        jiggle::pseudo_idle_secs(&session, &config.quality.mouse_jiggle),
+        600
+    );
+    assert_eq!(
+        quality::check(&session, &config.quality),
+        vec![QualityFlag::MouseJiggle]
+    );
+    assert!(export::session_markdown(&session, &config)
+        .contains("- Pseudo-idle (mouse jiggle): 10m 0s\n"));
+}
+
+#[test]
+fn playback_follows_the_recording_at_any_speed() {
+    let session = fixture().remove(0);
+    let mut playback = Playback::new(&session);
+    assert_eq!(playback.duration_ms(), 73_884);
+    assert_eq!(playback.cursor(), None);
+    assert_eq!(playback.bounds(), Some(((90, 180), (103, 200))));
+
+    playback.seek(1000);
+    assert_eq!(playback.trail().len(), 2);
+    assert_eq!(playback.cursor(), Some((103, 196)));
+    assert_eq!(
+        playback.recent(),
+        [(940, "Click left".to_string()), (980, "LShift".to_string())]
+    );
+
+    // Nothing moves while paused; at 4x, a quarter second is a second.
+    playback.advance(1.0);
+    assert_eq!(playback.position_ms(), 1000);
+    playback.speed = 4.0;
+    playback.toggle();
+    playback.advance(0.25);
+    assert_eq!(playback.position_ms(), 2000);
+    let recent: Vec<&str> = playback.recent().iter().map(|(_, e)| e.as_str()).collect();
+    assert_eq!(
+        recent,
+        ["Click left", "LShift", "LShift+H", "H", "Shortcut save"]
+    );
+    playback.advance(0.5);
+    assert!(playback.recent().is_empty());
+
+    playback.advance(100.0);
+    assert_eq!(playback.position_ms(), 73_884);
+    assert!(!playback.playing);
+    assert_eq!(playback.cursor(), Some((90, 180)));
+
+    // Playing again from the end starts over.
+    playback.toggle();
+    assert!(playback.playing);
+    assert_eq!(playback.position_ms(), 0);
+}
+
+#[test]
+fn contributions_total_whole_days_since_the_last_one() {
+    let sessions = fixture();
+    let mut config = ContributeConfig {
+        enabled: true,
+        endpoint: "https://example.org/contribute".to_string(),
+        interval_days: 2,
+        directory: out_dir("contributions").to_string_lossy().into_owned(),
+    };
+    let day = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+
+    // Today isn't over, so the 21st waits for the next day.
+    let contribution = contribute::pending(&config, &sessions, day("2024-01-21")).unwrap();
+    assert_eq!(contribution.sessions, 1);
+    let contribution = contribute::pending(&config, &sessions, day("2024-01-22")).unwrap();
+    assert_eq!(
+        (contribution.from, contribution.until),
+        (day("2024-01-20"), day("2024-01-21"))
+    );
+    assert_eq!(contribution.sessions, 2);
+    assert_eq!(contribution.tracked_minutes, 31);
+    assert_eq!(contribution.keystrokes, 2);
+    assert_eq!(contribution.clicks, 1);
+    assert_eq!(contribution.mouse_moves, 3);
+    assert_eq!(contribution.mouse_distance_px, 26);
+    let json = contribution.to_json().unwrap();
+    for private in ["Email", "Review", "laptop", "P-017", "12:34"] {
+        assert!(!json.contains(private), "{} was sent", private);
+    }
+
+    // The next one covers the days after the last one sent, once there
+    // are enough of them with sessions.
+    fs::write(
+        Path::new(&config.directory).join("contribution_2024-01-21.json"),
+        json,
+    )
+    .unwrap();
+    assert_eq!(contribute::last_sent(&config), Some(day("2024-01-21")));
+    assert_eq!(
+        contribute::pending(&config, &sessions, day("2024-01-23")),
+        None
+    );
+    assert_eq!(
+        contribute::pending(&config, &sessions, day("2024-01-25")),
+        None
+    );
+
+    config.enabled = false;
+    assert_eq!(
+        contribute::pending(&config, &sessions, day("2024-01-22")),
+        None
+    );
+}
+
+#[test]
+fn session_ids_are_unique_within_a_millisecond_and_sort_by_start() {
+    let start: DateTime<Local> = DateTime::parse_from_rfc3339("2024-01-20T12:34:56.120+00:00")
+        .unwrap()
+        .into();
+    let (a, b) = (types::session_id(start), types::session_id(start));
+    assert_ne!(a, b);
+    for id in [&a, &b] {
+        assert_eq!(id.len(), 36);
+        assert!(id.starts_with("018d26dd-f1f8-7"), "{}", id);
+        assert!(
+            matches!(id.as_bytes()[19], b'8' | b'9' | b'a' | b'b'),
+            "{}",
+            id
+        );
+    }
+
+    let later = types::session_id(start + chrono::Duration::milliseconds(1));
+    assert!(later > a && later > b);
+}
+
+#[test]
+fn timeline_layers_markers_idle_gaps_pauses_and_focus() {
+    let ts = |s: &str| format!("2024-01-20T12:{}+00:00", s);
+    let custom = |at: &str, kind: &str, payload| Action::Custom {
+        timestamp: ts(at),
+        kind: kind.to_string(),
+        payload,
+    };
+    let mut session = Session {
+        start_time: ts("00:00.000"),
+        end_time: Some(ts("06:40.000")),
+        ..Default::default()
+    };
+    session.actions = vec![
+        Action::FocusChange {
+            timestamp: ts("00:00.000"),
+            window: 1,
+            bounds: None,
+            title: None,
+            app: None,
+        },
+        Action::TaskSwitch {
+            timestamp: ts("00:10.000"),
+            from: "Writing".to_string(),
+            to: "Review".to_string(),
+        },
+        custom("00:20.000", "pause", serde_json::json!({})),
+        custom(
+            "03:20.000",
+            "resume",
+            serde_json::json!({ "paused_secs": 180 }),
+        ),
+        Action::KeyPress {
+            timestamp: ts("03:21.000"),
+            keys: vec!["A".to_string()],
+        },
+        custom(
+            "05:00.000",
+            "sync",
+            serde_json::json!({ "counter": 1, "label": "eeg" }),
+        ),
+        Action::FocusChange {
+            timestamp: ts("05:01.000"),
+            window: 2,
+            bounds: None,
+            title: None,
+            app: Some("firefox".to_string()),
+        },
+    ];
+
+    let timeline = Timeline::new(&session);
+    assert_eq!(timeline.duration_ms, 400_000);
+    let marker = |offset_ms, label: &str| Marker {
+        offset_ms,
+        label: label.to_string(),
+    };
+    assert_eq!(
+        timeline.markers,
+        [
+            marker(10_000, "Task Review"),
+            marker(300_000, "Sync 1 (eeg)")
+        ]
+    );
+    let band = |layer, from_ms, until_ms, label: &str| Band {
+        layer,
+        from_ms,
+        until_ms,
+        label: label.to_string(),
+    };
+    // Time paused isn't idle, though nothing was recorded.
+    assert_eq!(
+        timeline.bands,
+        [
+            band(Layer::Focus, 0, 301_000, "Window 1"),
+            band(Layer::Focus, 301_000, 400_000, "firefox"),
+            band(Layer::Idle, 201_000, 300_000, "No activity"),
+            band(Layer::Idle, 301_000, 400_000, "No activity"),
+            band(Layer::Paused, 20_000, 200_000, "Paused"),
+        ]
+    );
+    assert_eq!(
+        timeline.describe_at(250_000, 0),
+        [
+            "Focus: Window 1 (+0:00.000 – +5:01.000)",
+            "Idle: No activity (+3:21.000 – +5:00.000)"
+        ]
+    );
+    assert_eq!(
+        timeline.describe_at(300_500, 1000),
+        [
+            "Focus: Window 1 (+0:00.000 – +5:01.000)",
+            "+5:00.000 Sync 1 (eeg)"
+        ]
+    );
+}
+
+#[test]
+fn tags_are_kept_in_metadata() {
+    let mut session = Session::default();
+    assert!(session.tags().is_empty());
+    assert!(session.add_tag(" review "));
+    assert!(session.add_tag("client, urgent"));
+    assert!(!session.add_tag("review"));
+    assert!(!session.add_tag(" , "));
+    assert_eq!(session.tags(), ["review", "client  urgent"]);
+    assert_eq!(session.metadata[types::TAGS_FIELD], "review,client  urgent");
+}
+
+#[test]
+fn click_metrics() {
+    let session = &fixture()[0];
+    let metric = |expression: &str| metrics::DerivedMetric {
+        name: "metric".to_string(),
+        expression: expression.to_string(),
+    };
+    let vars = metrics::session_variables(session, &Default::default());
+    let clicks = analysis::click_stats(session).clicks as f64;
+    assert!(clicks > 0.0);
+    assert_eq!(vars["clicks"], clicks);
+    let values = metrics::evaluate(&[metric("clicks / keystrokes")], &vars);
+    assert_eq!(values["metric"], clicks / vars["keystrokes"]);
+
+    // Per minute, they add up to the session's.
+    let per_minute: f64 = compaction::minute_rollups(session)
+        .iter()
+        .map(|rollup| metrics::minute_variables(rollup, &Default::default())["clicks"])
+        .sum();
+    assert_eq!(per_minute, clicks);
+}
+
+#[test]
+fn focused_time_per_app() {
+    let mut sessions = fixture();
+    // Without a title, the app is still kept.
+    sessions[0].actions.push(Action::FocusChange {
+        timestamp: "2024-01-20T12:35:05.000+00:00".to_string(),
+        window: 41943049,
+        bounds: None,
+        title: None,
+        app: Some("Slack, Inc.".to_string()),
+    });
+    let path = out_dir("focused_time_per_app").join("sessions.csv");
+    export::write_sessions(&sessions, &path, ExportFormat::Csv, &Config::default()).unwrap();
+    let focus = |session: &Session| {
+        session
+            .actions
+            .iter()
+            .filter(|action| matches!(action, Action::FocusChange { .. }))
+            .map(|action| format!("{:?}", action))
+            .collect::<Vec<_>>()
+    };
+    let reloaded = storage::load_sessions(&path).unwrap();
+    let original = sessions
+        .iter()
+        .find(|s| s.session_id == "20240120_123456")
+        .unwrap();
+    let reloaded = reloaded
+        .iter()
+        .find(|s| s.session_id == original.session_id)
+        .unwrap();
+    assert_eq!(focus(reloaded), focus(original));
+
+    // Each app counts until the next focus change or the session end.
+    let apps: Vec<_> = analysis::app_times(&sessions)
+        .into_iter()
+        .map(|app| (app.app, app.focused_secs))
+        .collect();
+    assert_eq!(
+        apps,
+        [("Slack, Inc.".to_string(), 65), ("firefox".to_string(), 3)]
+    );
+}
@@ -0,0 +1,83 @@
+//! Admin policy files: what they enforce and what they reject. These read
+//! policies from a temporary directory, not the system-wide location.
+
+use desk_monitor::config::SessionTemplate;
+use desk_monitor::policy::Policy;
+use desk_monitor::Config;
+use std::{env, fs};
+
+#[test]
+fn policies_enforce_settings_through_templates_and_profiles() {
+    let dir = env::temp_dir().join(format!("desk-monitor-policy-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("policy.json");
+    assert!(Policy::load(&path).unwrap().is_none());
+
+    fs::write(
+        &path,
+        r#"{
+            "notice": "Managed for the typing study",
+            "hide_keys": true,
+            "min_mouse_grid_px": 50,
+            "redact": [{ "pattern": "(?i)patient \\w+" }],
+            "capture": { "keyboard": false },
+            "bug_report_screenshots": false,
+            "contribute_enabled": true,
+            "contribute_endpoint": "https://study.example/stats",
+            "daily_export_directory": "/srv/study"
+        }"#,
+    )
+    .unwrap();
+    let policy = Policy::load(&path).unwrap().unwrap();
+
+    let mut config = Config::default();
+    config.templates.push(SessionTemplate {
+        name: "Typing".to_string(),
+        capture: Some(Default::default()),
+        ..Default::default()
+    });
+    config.save_profile("Open");
+    config.privacy.mouse_grid_px = 200;
+    config.save_profile("Coarse");
+    policy.apply(&mut config);
+    policy.apply(&mut config);
+
+    assert!(config.policy.is_managed());
+    assert!(config.privacy.hide_keys);
+    // A coarser grid than required is kept.
+    assert_eq!(config.privacy.mouse_grid_px, 200);
+    assert_eq!(config.privacy.redact.len(), 1);
+    assert!(config.policy.is_enforced_rule(&config.privacy.redact[0]));
+    assert!(!config.capture.keyboard);
+    assert!(config.capture.mouse_moves);
+    assert_eq!(config.daily_export.directory, "/srv/study");
+    assert!(!config.bug_report.screenshots);
+    assert!(config.contribute.enabled);
+    assert_eq!(config.contribute.endpoint, "https://study.example/stats");
+    assert!(config
+        .templates
+        .iter()
+        .filter_map(|t| t.capture.as_ref())
+        .all(|capture| !capture.keyboard));
+
+    // Switching profile can't bring back what the policy turned off.
+    config.capture.keyboard = true;
+    config.privacy.hide_keys = false;
+    config.switch_profile("Open").unwrap();
+    assert!(!config.capture.keyboard);
+    assert!(config.privacy.hide_keys);
+    assert_eq!(config.privacy.mouse_grid_px, 50);
+
+    // The policy is never written to the user's config.
+    let saved = serde_json::to_value(&config).unwrap();
+    assert!(saved.get("policy").is_none());
+}
+
+#[test]
+fn policies_with_unknown_settings_are_rejected() {
+    let dir = env::temp_dir().join(format!("desk-monitor-policy-bad-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("policy.json");
+    fs::write(&path, r#"{ "hide_key": true }"#).unwrap();
+    assert!(Policy::load(&path).is_err());
+}
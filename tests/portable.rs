@@ -0,0 +1,31 @@
+//! Portable mode switches the process's working directory, so it gets a test
+//! binary of its own.
+
+use desk_monitor::portable;
+use std::{env, fs};
+
+#[test]
+fn portable_mode_keeps_data_next_to_the_executable() {
+    let exe_dir = env::current_exe()
+        .unwrap()
+        .canonicalize()
+        .unwrap()
+        .parent()
+        .unwrap()
+        .to_path_buf();
+    let sentinel = exe_dir.join(portable::SENTINEL);
+    let _ = fs::remove_file(&sentinel);
+
+    assert!(!portable::requested(&[]));
+    assert!(portable::requested(&["--portable".to_string()]));
+    fs::write(&sentinel, "").unwrap();
+    assert!(portable::requested(&[]));
+    fs::remove_file(&sentinel).unwrap();
+
+    assert!(!portable::is_active());
+    env::set_current_dir(env::temp_dir()).unwrap();
+    let dir = portable::enter().unwrap();
+    assert_eq!(dir, exe_dir);
+    assert_eq!(env::current_dir().unwrap().canonicalize().unwrap(), exe_dir);
+    assert_eq!(portable::data_dir(), Some(exe_dir.as_path()));
+}
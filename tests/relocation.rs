@@ -0,0 +1,44 @@
+//! Relocating saves the new folder in the settings, which every monitor in
+//! the working directory then writes to, so it gets a test binary of its
+//! own.
+
+use desk_monitor::storage::{self, DETAILS_FILE, SESSIONS_FILE};
+use desk_monitor::{ActivityMonitor, Config, MockInput};
+use device_query::Keycode;
+use std::{env, fs};
+
+#[test]
+fn relocating_moves_data_but_not_the_working_directory() {
+    let cwd = env::temp_dir().join(format!("desk-monitor-relocation-{}", std::process::id()));
+    fs::create_dir_all(&cwd).unwrap();
+    env::set_current_dir(&cwd).unwrap();
+    let input = MockInput::new();
+    let mut monitor = ActivityMonitor::with_input(Box::new(input.clone())).unwrap();
+    monitor.set_task_name("relocation test");
+    monitor.start_monitoring();
+
+    let dir = cwd.join("relocated");
+    monitor.relocate(&dir).unwrap();
+    assert_eq!(env::current_dir().unwrap(), cwd);
+
+    input.set_keys(vec![Keycode::F]);
+    monitor.update();
+    monitor.stop_monitoring();
+    monitor.finish_session(None);
+    let stored = storage::load_sessions(dir.join(SESSIONS_FILE)).unwrap();
+    assert!(stored
+        .iter()
+        .any(|s| s.session_id == monitor.current_session().session_id));
+    let details = fs::read_to_string(dir.join(DETAILS_FILE)).unwrap();
+    assert!(details.contains("keyboard"), "{}", details);
+    assert!(dir.join("latest_session_summary.md").exists());
+
+    // The next launch, and everything reading sessions, follow.
+    let config = Config::load().unwrap();
+    assert_eq!(config.sessions_path(), dir.join(SESSIONS_FILE));
+    drop(monitor);
+    fs::remove_file(cwd.join(DETAILS_FILE)).unwrap();
+    let restarted = ActivityMonitor::with_input(Box::new(input)).unwrap();
+    assert_eq!(restarted.data_dir(), dir);
+    assert!(!cwd.join(DETAILS_FILE).exists());
+}
@@ -0,0 +1,63 @@
+//! Renaming a task saves budgets and templates in the settings of the
+//! working directory, so it gets a test binary of its own.
+
+use desk_monitor::config::TaskBudget;
+use desk_monitor::rename;
+use desk_monitor::storage::{self, CsvStorage, FolderStorage, Storage};
+use desk_monitor::{Action, Config, Session};
+use std::{env, fs};
+
+#[test]
+fn renaming_reaches_archived_copies_and_budgets() {
+    let cwd = env::temp_dir().join(format!("desk-monitor-rename-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&cwd);
+    fs::create_dir_all(&cwd).unwrap();
+    env::set_current_dir(&cwd).unwrap();
+
+    let mut config = Config::default();
+    config.budgets.push(TaskBudget {
+        task_name: "proj-x".to_string(),
+        planned_hours: 10.0,
+    });
+    let mut session = Session {
+        session_id: "20240120_100000".to_string(),
+        task_name: "proj-x".to_string(),
+        start_time: "2024-01-20T10:00:00.000+00:00".to_string(),
+        end_time: Some("2024-01-20T10:30:00.000+00:00".to_string()),
+        ..Default::default()
+    };
+    session.actions.push(Action::TaskSwitch {
+        timestamp: "2024-01-20T10:10:00.000+00:00".to_string(),
+        from: "proj-x".to_string(),
+        to: "email".to_string(),
+    });
+    // Filed into the week folders with `desk-monitor archive`, so in both.
+    CsvStorage::open(config.sessions_path())
+        .unwrap()
+        .append(&session)
+        .unwrap();
+    let mut archive = FolderStorage::new(&config.archive.directory);
+    archive.append(&session).unwrap();
+
+    let report = rename::rename_task("proj-x", "Project X", true, &mut config.clone()).unwrap();
+    assert_eq!(report.sessions.len(), 1);
+    assert_eq!(report.task_switches, 1);
+    assert_eq!(report.budgets, 1);
+    assert_eq!(archive.load().unwrap()[0].task_name, "proj-x");
+
+    rename::rename_task("proj-x", "Project X", false, &mut config).unwrap();
+    for stored in [
+        storage::load_sessions(config.sessions_path()).unwrap(),
+        archive.load().unwrap(),
+    ] {
+        assert_eq!(stored[0].task_name, "Project X");
+    }
+    assert_eq!(Config::load().unwrap().budgets[0].task_name, "Project X");
+
+    // Two budgets for one task would be ambiguous.
+    config.budgets.push(TaskBudget {
+        task_name: "email".to_string(),
+        planned_hours: 1.0,
+    });
+    assert!(rename::rename_task("Project X", "email", false, &mut config).is_err());
+}
@@ -0,0 +1,222 @@
+//! Every storage backend built into this configuration must append, load,
+//! replace and remove sessions the same way, and edits must reach every
+//! stored copy.
+
+use chrono::{DateTime, Local};
+use desk_monitor::storage::{self, CsvStorage, FolderStorage, Storage, DETAILS_FILE};
+use desk_monitor::{export, privacy, Action, Config, ExportFormat, Session};
+use std::io::Write;
+use std::path::PathBuf;
+use std::{env, fs};
+
+fn scratch(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("desk-monitor-storage-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    let _ = fs::remove_file(&path);
+    path
+}
+
+fn session(id: &str, start: &str, task: &str) -> Session {
+    let mut session = Session {
+        session_id: id.to_string(),
+        task_name: task.to_string(),
+        start_time: start.to_string(),
+        end_time: Some(start.to_string()),
+        quality_flags: Some(Vec::new()),
+        ..Default::default()
+    };
+    session.actions.push(Action::KeyPress {
+        timestamp: start.to_string(),
+        keys: vec!["A".to_string()],
+    });
+    session
+}
+
+fn check(mut storage: impl Storage) {
+    let mut first = session("20240120_100000", "2024-01-20T10:00:00.000+00:00", "One");
+    first
+        .metadata
+        .insert("participant_id".to_string(), "P-017".to_string());
+    // Back-to-back sessions can share an ID; the start time tells them apart.
+    let second = session("20240120_100000", "2024-01-20T10:00:30.000+00:00", "Two");
+    storage.append(&first).unwrap();
+    storage.append(&second).unwrap();
+
+    let mut edited = session("20240120_100000", "2024-01-20T10:00:30.000+00:00", "Two");
+    edited.notes = Some("edited".to_string());
+    edited.actions.clear();
+    storage.replace(&edited).unwrap();
+    storage
+        .append(&session(
+            "20240120_110000",
+            "2024-01-20T11:00:00.000+00:00",
+            "Three",
+        ))
+        .unwrap();
+
+    let loaded = storage.load().unwrap();
+    let summary: Vec<_> = loaded
+        .iter()
+        .map(|s| (s.task_name.as_str(), s.notes.as_deref(), s.actions.len()))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("One", None, 1),
+            ("Two", Some("edited"), 0),
+            ("Three", None, 1)
+        ]
+    );
+
+    assert_eq!(loaded[0].metadata, first.metadata);
+    assert!(loaded[1].metadata.is_empty());
+
+    let missing = session("20240121_000000", "2024-01-21T00:00:00.000+00:00", "Gone");
+    assert!(storage.replace(&missing).is_err());
+
+    storage.remove(&loaded[1]).unwrap();
+    let left: Vec<String> = storage
+        .load()
+        .unwrap()
+        .into_iter()
+        .map(|s| s.task_name)
+        .collect();
+    assert_eq!(left, ["One", "Three"]);
+    assert!(storage.remove(&missing).is_err());
+}
+
+#[test]
+fn csv_storage() {
+    check(CsvStorage::open(scratch("sessions.csv")).unwrap());
+}
+
+#[test]
+fn folder_storage() {
+    let root = scratch("data");
+    let _ = fs::remove_dir_all(&root);
+    check(FolderStorage::new(&root));
+
+    // 2024-01-20 falls in ISO week 3, whatever the local time zone.
+    let week: Vec<String> = fs::read_dir(root.join("2024").join("W03"))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    // The removed session's file went with it.
+    assert_eq!(week.len(), 2);
+    assert!(week.iter().all(|name| name.ends_with("_PT0S.csv")));
+}
+
+#[cfg(feature = "jsonl")]
+#[test]
+fn jsonl_storage() {
+    check(desk_monitor::storage::JsonlStorage::open(scratch("sessions.jsonl")).unwrap());
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn sqlite_storage() {
+    check(desk_monitor::storage::SqliteStorage::open(scratch("sessions.sqlite")).unwrap());
+}
+
+#[test]
+fn deleted_ranges_leave_every_stored_copy() {
+    let dir = scratch("delete-range");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let config = Config {
+        data_dir: dir.to_string_lossy().into_owned(),
+        ..Default::default()
+    };
+
+    let mut recorded = session("20240120_100000", "2024-01-20T10:00:00.000+00:00", "One");
+    recorded.end_time = Some("2024-01-20T10:03:00.000+00:00".to_string());
+    for minute in 1..=2 {
+        recorded.actions.push(Action::KeyPress {
+            timestamp: format!("2024-01-20T10:0{}:00.000+00:00", minute),
+            keys: vec!["B".to_string()],
+        });
+    }
+    // Filed into the week folders with `desk-monitor archive`, so in both.
+    CsvStorage::open(config.sessions_path())
+        .unwrap()
+        .append(&recorded)
+        .unwrap();
+    FolderStorage::new(dir.join(&config.archive.directory))
+        .append(&recorded)
+        .unwrap();
+    fs::write(
+        dir.join(DETAILS_FILE),
+        "timestamp,task_name,event_type,details,mouse_x,mouse_y\n\
+         2024-01-20T10:00:00.000+00:00,One,Key Press,A,0,0\n\
+         2024-01-20T10:01:00.000+00:00,One,Key Press,B,0,0\n\
+         2024-01-20T10:02:00.000+00:00,One,Key Press,B,0,0\n",
+    )
+    .unwrap();
+
+    let at = |time: &str| {
+        DateTime::parse_from_rfc3339(time)
+            .unwrap()
+            .with_timezone(&Local)
+    };
+    let deletion = privacy::delete_stored_range(
+        &config,
+        &recorded,
+        at("2024-01-20T10:00:30+00:00"),
+        at("2024-01-20T10:01:30+00:00"),
+    )
+    .unwrap();
+    assert_eq!(deletion.events, 1);
+    assert_eq!(deletion.copies.len(), 2);
+    assert_eq!(deletion.detailed_events, 1);
+
+    let copies = [
+        storage::load_sessions(config.sessions_path()).unwrap(),
+        FolderStorage::new(dir.join(&config.archive.directory))
+            .load()
+            .unwrap(),
+    ];
+    for stored in copies {
+        let keys: Vec<_> = stored[0]
+            .actions
+            .iter()
+            .filter(|a| matches!(a, Action::KeyPress { .. }))
+            .collect();
+        assert_eq!(keys.len(), 2);
+        assert!(stored[0]
+            .actions
+            .iter()
+            .any(|a| matches!(a, Action::Redacted { .. })));
+    }
+    let details = fs::read_to_string(dir.join(DETAILS_FILE)).unwrap();
+    assert!(!details.contains("10:01:00"), "{}", details);
+    assert_eq!(details.lines().count(), 3);
+}
+
+#[test]
+fn zip_archives_load_like_folders() {
+    let dir = scratch("zipped");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let one = session("20240120_100000", "2024-01-20T10:00:00.000+00:00", "One");
+    let two = session("20240121_100000", "2024-01-21T10:00:00.000+00:00", "Two");
+    let config = Config::default();
+    export::write_sessions(&[one], dir.join("one.csv"), ExportFormat::Csv, &config).unwrap();
+    export::write_sessions(&[two], dir.join("two.json"), ExportFormat::Json, &config).unwrap();
+
+    let path = scratch("sessions.zip");
+    let mut zip = zip::ZipWriter::new(fs::File::create(&path).unwrap());
+    let options = zip::write::SimpleFileOptions::default();
+    for (name, file) in [("one.csv", "one.csv"), ("exports/two.json", "two.json")] {
+        zip.start_file(name, options).unwrap();
+        zip.write_all(&fs::read(dir.join(file)).unwrap()).unwrap();
+    }
+    zip.finish().unwrap();
+
+    let tasks: Vec<_> = storage::load_dataset(&path)
+        .unwrap()
+        .into_iter()
+        .map(|s| s.task_name)
+        .collect();
+    assert_eq!(tasks, ["Two", "One"]);
+}
@@ -0,0 +1,90 @@
+//! Reading GitHub release metadata for the update checker. Nothing here
+//! touches the network.
+
+use desk_monitor::updates::{self, Release};
+use serde_json::json;
+
+#[test]
+fn versions_compare_numerically() {
+    assert!(updates::is_newer("0.10.0", "0.9.1"));
+    assert!(updates::is_newer("v1.0", "0.9.9"));
+    assert!(updates::is_newer("1.0.1", "1.0"));
+    assert!(!updates::is_newer("1.0.0", "1.0.0"));
+    assert!(!updates::is_newer("1.0.0-beta", "1.0.0"));
+    assert!(!updates::is_newer("0.1.0", "0.2.0"));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn releases_pick_this_platforms_binary_and_checksum() {
+    let asset = |name: &str| {
+        json!({
+            "name": name,
+            "browser_download_url": format!("https://example.com/v9.0.0/{}", name),
+        })
+    };
+    let (this, other) = match std::env::consts::ARCH {
+        "aarch64" => ("arm64", "x86_64"),
+        _ => ("x86_64", "aarch64"),
+    };
+    let binary = format!("desk-monitor-linux-{}", this);
+    let json = json!({
+        "tag_name": "v9.0.0",
+        "name": "",
+        "body": "- Weekly reports\n- Fewer crashes",
+        "html_url": "https://example.com/releases/v9.0.0",
+        "assets": [
+            asset("desk-monitor-linux.tar.gz"),
+            asset("desk-monitor-macos-x86_64"),
+            asset(&format!("desk-monitor-linux-{}", other)),
+            asset(&format!("desk-monitor-linux-{}.sha256", other)),
+            asset(&binary),
+            asset(&format!("{}.sha256", binary)),
+        ],
+    });
+
+    let release = Release::from_github(&json).unwrap();
+    assert_eq!(
+        release,
+        Release {
+            version: "9.0.0".to_string(),
+            name: "v9.0.0".to_string(),
+            notes: "- Weekly reports\n- Fewer crashes".to_string(),
+            page_url: "https://example.com/releases/v9.0.0".to_string(),
+            binary_url: Some(format!("https://example.com/v9.0.0/{}", binary)),
+            checksum_url: Some(format!("https://example.com/v9.0.0/{}.sha256", binary)),
+        }
+    );
+    assert!(release.is_newer());
+    assert!(release.can_install());
+
+    // Only an archive: linked, not installed.
+    let json = json!({
+        "tag_name": "0.0.1",
+        "html_url": "https://example.com/releases/0.0.1",
+        "assets": [asset("desk-monitor-linux.tar.gz")],
+    });
+    let release = Release::from_github(&json).unwrap();
+    assert!(!release.is_newer());
+    assert_eq!(release.binary_url, None);
+    assert!(!release.can_install());
+    assert!(updates::install(&release).is_err());
+
+    // A binary without a checksum isn't installed either, nor one built
+    // for another processor.
+    let json = json!({
+        "tag_name": "v9.0.0",
+        "html_url": "https://example.com/releases/v9.0.0",
+        "assets": [asset(&binary)],
+    });
+    let release = Release::from_github(&json).unwrap();
+    assert!(release.binary_url.is_some());
+    assert_eq!(release.checksum_url, None);
+    assert!(!release.can_install());
+    assert!(updates::install(&release).is_err());
+    let json = json!({
+        "tag_name": "v9.0.0",
+        "assets": [asset(&format!("desk-monitor-linux-{}", other))],
+    });
+    assert_eq!(Release::from_github(&json).unwrap().binary_url, None);
+}